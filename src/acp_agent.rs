@@ -3,11 +3,10 @@ use agent_client_protocol::{
     ClientCapabilities, Error, ForkSessionRequest, ForkSessionResponse, Implementation,
     InitializeRequest, InitializeResponse, ListSessionsRequest, ListSessionsResponse,
     LoadSessionRequest, LoadSessionResponse, McpCapabilities, NewSessionRequest,
-    NewSessionResponse, PromptCapabilities, PromptRequest, PromptResponse, ProtocolVersion,
-    ResumeSessionRequest, ResumeSessionResponse, SessionCapabilities, SessionForkCapabilities,
-    SessionListCapabilities, SessionResumeCapabilities, SetSessionConfigOptionRequest,
-    SetSessionConfigOptionResponse, SetSessionModeRequest, SetSessionModeResponse,
-    SetSessionModelRequest, SetSessionModelResponse,
+    NewSessionResponse, PromptRequest, PromptResponse, ProtocolVersion, ResumeSessionRequest,
+    ResumeSessionResponse, SessionCapabilities, SessionForkCapabilities, SessionListCapabilities,
+    SessionResumeCapabilities, SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+    SetSessionModeRequest, SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
 };
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
@@ -49,7 +48,7 @@ impl Agent for AcpAgent {
 
         let load_session = self.driver.supports_load_session();
         let mut agent_capabilities = AgentCapabilities::new()
-            .prompt_capabilities(PromptCapabilities::new().embedded_context(true).image(true))
+            .prompt_capabilities(self.driver.prompt_capabilities())
             .mcp_capabilities(McpCapabilities::new().http(true))
             .load_session(load_session);
 
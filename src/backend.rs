@@ -2,16 +2,20 @@ use agent_client_protocol::{AuthMethod, SessionId};
 use agent_client_protocol::{
     AuthenticateRequest, AuthenticateResponse, CancelNotification, Error, ForkSessionRequest,
     ForkSessionResponse, ListSessionsRequest, ListSessionsResponse, NewSessionRequest,
-    NewSessionResponse, PromptRequest, PromptResponse, ResumeSessionRequest, ResumeSessionResponse,
-    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
-    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
+    NewSessionResponse, PromptCapabilities, PromptRequest, PromptResponse, ResumeSessionRequest,
+    ResumeSessionResponse, SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+    SetSessionModeRequest, SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BackendKind {
     Codex,
+    /// Codex runtime against a local OpenAI-compatible server (see `local_backend`).
+    Local,
     ClaudeCode,
     Gemini,
+    /// CLI agent described by a TOML spec (see `custom_backend`).
+    Custom,
     Multi,
 }
 
@@ -25,6 +29,8 @@ pub struct WorkOrchestrationProfile {
     pub preempt_on_new_prompt: bool,
     pub supports_live_plan_updates: bool,
     pub supports_live_tool_calls: bool,
+    /// Output reaches the client as it is produced rather than once the turn ends.
+    pub streams_message_chunks: bool,
     pub operator_hint: &'static str,
 }
 
@@ -47,8 +53,10 @@ impl WorkOrchestrationProfile {
     pub const fn bridge_summary(self) -> &'static str {
         if self.supports_live_plan_updates && self.supports_live_tool_calls {
             "live ACP plan/tool updates available"
+        } else if self.streams_message_chunks {
+            "streamed ACP message/thought chunks, without plan/tool updates"
         } else {
-            "single ACP message chunk only"
+            "backend-specific (follows the active backend)"
         }
     }
 
@@ -73,8 +81,10 @@ impl BackendKind {
     pub fn parse(s: &str) -> Option<Self> {
         match s {
             "codex" => Some(Self::Codex),
+            "local" | "ollama" => Some(Self::Local),
             "claude-code" | "claude" => Some(Self::ClaudeCode),
             "gemini" | "gemini-cli" => Some(Self::Gemini),
+            "custom" => Some(Self::Custom),
             "multi" | "all" => Some(Self::Multi),
             _ => None,
         }
@@ -83,12 +93,35 @@ impl BackendKind {
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Codex => "codex",
+            Self::Local => "local",
             Self::ClaudeCode => "claude-code",
             Self::Gemini => "gemini",
+            Self::Custom => "custom",
             Self::Multi => "multi",
         }
     }
 
+    fn session_index_prefix(self) -> &'static str {
+        match self {
+            // Local sessions are Codex threads and share their index entries.
+            Self::Codex | Self::Local => "codex",
+            Self::ClaudeCode => "claude",
+            Self::Gemini => "gemini",
+            Self::Custom => "custom",
+            Self::Multi => "multi",
+        }
+    }
+
+    /// Key under which a session of this backend is registered in `GlobalSessionIndex`.
+    pub fn session_index_key(self, session_id: &SessionId) -> String {
+        format!("{}:{}", self.session_index_prefix(), session_id.0)
+    }
+
+    /// Key under which the backend CLI's own session id for `session_id` is stored.
+    pub fn native_session_index_key(self, session_id: &SessionId) -> String {
+        format!("{}-native:{}", self.session_index_prefix(), session_id.0)
+    }
+
     pub const fn work_orchestration_profile(self) -> WorkOrchestrationProfile {
         match self {
             Self::Codex => WorkOrchestrationProfile {
@@ -100,29 +133,56 @@ impl BackendKind {
                 preempt_on_new_prompt: true,
                 supports_live_plan_updates: true,
                 supports_live_tool_calls: true,
+                streams_message_chunks: true,
                 operator_hint: "Use for tool-heavy or concurrent turns; ACP can keep plan/tool state live.",
             },
+            Self::Local => WorkOrchestrationProfile {
+                display_name: "Local model (Codex runtime)",
+                evidence_summary: "Codex runs against a local OpenAI-compatible endpoint (Ollama by default): sessions, approvals, tool calls, and live plan updates without leaving the machine.",
+                task_orchestration: "sequential",
+                task_monitoring: "auto",
+                vector_checks: true,
+                preempt_on_new_prompt: true,
+                supports_live_plan_updates: true,
+                supports_live_tool_calls: true,
+                streams_message_chunks: true,
+                operator_hint: "Local models are slower and weaker at tool use; keep turns small and pick the model from the `model` option.",
+            },
             Self::ClaudeCode => WorkOrchestrationProfile {
                 display_name: "Claude Code",
-                evidence_summary: "`claude --print --cwd <cwd> <prompt>` returns one ACP text stream; live tool/approval bridging is not wired yet.",
+                evidence_summary: "`claude --print --output-format stream-json` is translated into live ACP message, thought, tool call, and TodoWrite plan updates; tool permissions are bridged to ACP request_permission.",
                 task_orchestration: "sequential",
                 task_monitoring: "status-only",
                 vector_checks: false,
                 preempt_on_new_prompt: false,
-                supports_live_plan_updates: false,
-                supports_live_tool_calls: false,
+                supports_live_plan_updates: true,
+                supports_live_tool_calls: true,
+                streams_message_chunks: true,
                 operator_hint: "Keep one bounded goal per turn, externalize Goal/Rubric/Next Action in the prompt, and use /status between iterations.",
             },
             Self::Gemini => WorkOrchestrationProfile {
                 display_name: "Gemini CLI",
-                evidence_summary: "`gemini --output-format text --approval-mode plan --prompt` returns one ACP text stream; live tool/approval bridging is not wired yet.",
+                evidence_summary: "`gemini --output-format stream-json --approval-mode <session mode> --prompt` is translated into live ACP message, tool call, and write_todos plan updates; approvals follow the session's approval mode.",
+                task_orchestration: "sequential",
+                task_monitoring: "status-only",
+                vector_checks: false,
+                preempt_on_new_prompt: false,
+                supports_live_plan_updates: true,
+                supports_live_tool_calls: true,
+                streams_message_chunks: true,
+                operator_hint: "Keep one bounded goal per turn, ask Gemini to echo Goal/Rubric/Next Action, and use /status between iterations.",
+            },
+            Self::Custom => WorkOrchestrationProfile {
+                display_name: "Custom CLI",
+                evidence_summary: "A TOML spec describes the CLI invocation; its text or JSON-lines output is streamed as ACP message and thought chunks, without tool, plan, or approval bridging.",
                 task_orchestration: "sequential",
                 task_monitoring: "status-only",
                 vector_checks: false,
                 preempt_on_new_prompt: false,
                 supports_live_plan_updates: false,
                 supports_live_tool_calls: false,
-                operator_hint: "Keep one bounded goal per turn, ask Gemini to echo Goal/Rubric/Next Action, and use /status between iterations.",
+                streams_message_chunks: true,
+                operator_hint: "Keep one bounded goal per turn, restate Goal/Rubric/Next Action in the prompt, and use /status between iterations.",
             },
             Self::Multi => WorkOrchestrationProfile {
                 display_name: "Unified ACP Router",
@@ -133,6 +193,7 @@ impl BackendKind {
                 preempt_on_new_prompt: false,
                 supports_live_plan_updates: false,
                 supports_live_tool_calls: false,
+                streams_message_chunks: false,
                 operator_hint: "Switch backends explicitly when the task shape changes so ACP can show the correct work-orchestration profile.",
             },
         }
@@ -155,6 +216,11 @@ pub trait BackendDriver {
         false
     }
 
+    /// Prompt content accepted besides text and resource links.
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        PromptCapabilities::new().embedded_context(true)
+    }
+
     fn auth_methods(&self) -> Vec<AuthMethod>;
 
     async fn authenticate(
@@ -319,7 +385,7 @@ impl BackendDriver for UnsupportedBackendDriver {
 
 #[cfg(test)]
 mod tests {
-    use super::BackendKind;
+    use super::{BackendKind, SessionId};
 
     #[test]
     fn codex_profile_uses_live_acp_defaults() {
@@ -335,16 +401,65 @@ mod tests {
     }
 
     #[test]
-    fn claude_and_gemini_profiles_are_sequential_summary_only() {
-        for backend in [BackendKind::ClaudeCode, BackendKind::Gemini] {
-            let profile = backend.work_orchestration_profile();
+    fn cli_profiles_are_sequential_with_live_stream_updates() {
+        for kind in [BackendKind::ClaudeCode, BackendKind::Gemini] {
+            let profile = kind.work_orchestration_profile();
 
             assert_eq!(profile.task_orchestration, "sequential");
             assert_eq!(profile.task_monitoring, "status-only");
             assert_eq!(profile.vector_checks_value(), "off");
             assert_eq!(profile.preempt_value(), "off");
-            assert_eq!(profile.bridge_summary(), "single ACP message chunk only");
+            assert!(profile.supports_live_plan_updates);
+            assert!(profile.supports_live_tool_calls);
+            assert_eq!(
+                profile.bridge_summary(),
+                "live ACP plan/tool updates available"
+            );
             assert!(profile.render_summary().contains("Goal/Rubric/Next Action"));
         }
     }
+
+    #[test]
+    fn custom_backend_is_selectable_and_text_only() {
+        assert_eq!(BackendKind::parse("custom"), Some(BackendKind::Custom));
+        let profile = BackendKind::Custom.work_orchestration_profile();
+
+        assert_eq!(profile.task_orchestration, "sequential");
+        assert!(profile.streams_message_chunks);
+        assert_eq!(
+            profile.bridge_summary(),
+            "streamed ACP message/thought chunks, without plan/tool updates"
+        );
+    }
+
+    #[test]
+    fn local_backend_reuses_codex_sessions() {
+        assert_eq!(BackendKind::parse("ollama"), Some(BackendKind::Local));
+        assert_eq!(BackendKind::Local.as_str(), "local");
+        assert_eq!(
+            BackendKind::Local.session_index_key(&SessionId::new("thread-1")),
+            "codex:thread-1"
+        );
+        assert_eq!(
+            BackendKind::ClaudeCode.native_session_index_key(&SessionId::new("claude:1")),
+            "claude-native:claude:1"
+        );
+        assert_eq!(
+            BackendKind::Local
+                .work_orchestration_profile()
+                .bridge_summary(),
+            "live ACP plan/tool updates available"
+        );
+    }
+
+    #[test]
+    fn multi_profile_defers_bridge_to_active_backend() {
+        let profile = BackendKind::Multi.work_orchestration_profile();
+
+        assert_eq!(profile.task_orchestration, "backend-specific");
+        assert_eq!(
+            profile.bridge_summary(),
+            "backend-specific (follows the active backend)"
+        );
+    }
 }
@@ -0,0 +1,557 @@
+//! Typed schema of the canonical session log.
+//!
+//! Every line of `canonical.jsonl` is a [`CanonicalLine`]: a fixed envelope plus a
+//! [`CanonicalEvent`], serialized as `"kind": "<name>", "data": {..}`. Writers build events
+//! from this enum instead of ad-hoc JSON, and `xsfire-camp log schema` prints the JSON Schema
+//! derived from it.
+//!
+//! Lines written by older versions are rewritten by [`upgrade_line`] before they are parsed.
+//! To change the layout of an event, bump [`CANONICAL_SCHEMA_VERSION`] and add the matching
+//! step to `upgrade_line`.
+
+use heck::ToSnakeCase;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::thread::RuntimeDiagnosticsSnapshot;
+
+/// Version written to new lines.
+///
+/// - 1: untyped `kind`/`data` pairs.
+/// - 2: typed events; bridge tool statuses use the ACP spelling (`in_progress`) instead of
+///   the Rust debug one (`InProgress`).
+pub const CANONICAL_SCHEMA_VERSION: u32 = 2;
+
+/// One line of the canonical log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CanonicalLine {
+    pub schema_version: u32,
+    /// Unix time in milliseconds.
+    pub ts_ms: u64,
+    /// Backend-independent session id (directory name under `ACP_HOME/sessions`).
+    pub global_session_id: String,
+    pub backend: String,
+    /// Session id the ACP client sees.
+    pub acp_session_id: String,
+    /// Session id of the backend (for example the Codex thread id).
+    pub backend_session_id: String,
+    #[serde(flatten)]
+    pub event: CanonicalEvent,
+}
+
+/// Everything the agent records about a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", content = "data")]
+pub enum CanonicalEvent {
+    /// A user prompt as received from the client.
+    #[serde(rename = "acp.prompt")]
+    Prompt(PromptSummary),
+    /// User text sent to the client (for example replayed history).
+    #[serde(rename = "acp.user_message_chunk")]
+    UserMessageChunk(TextChunk),
+    /// Agent reply text, in the chunks sent to the client.
+    #[serde(rename = "acp.agent_message_chunk")]
+    AgentMessageChunk(TextChunk),
+    /// Agent reasoning text.
+    #[serde(rename = "acp.agent_thought_chunk")]
+    AgentThoughtChunk(TextChunk),
+    /// An ACP `ToolCall` as sent to the client (camelCase, payloads may be capped).
+    #[serde(rename = "acp.tool_call")]
+    ToolCall(Value),
+    /// An ACP `ToolCallUpdate` as sent to the client.
+    #[serde(rename = "acp.tool_call_update")]
+    ToolCallUpdate(Value),
+    /// The current plan.
+    #[serde(rename = "acp.plan")]
+    Plan(PlanUpdate),
+    /// A plan step was added, removed or changed status.
+    #[serde(rename = "acp.plan.step_transition")]
+    PlanStepTransition(PlanStepTransition),
+    /// A permission request sent to the client.
+    #[serde(rename = "acp.request_permission")]
+    RequestPermission(PermissionRequest),
+    /// The client's answer to a permission request.
+    #[serde(rename = "acp.request_permission_response")]
+    RequestPermissionResponse(PermissionResponse),
+    /// Approval mode of a CLI session (Gemini).
+    #[serde(rename = "acp.session_mode")]
+    SessionMode(ModeSetting),
+    /// Periodic runtime counters of a Codex thread.
+    #[serde(rename = "acp.runtime_diagnostics")]
+    RuntimeDiagnostics(RuntimeDiagnosticsSnapshot),
+    /// A Codex turn started producing events.
+    #[serde(rename = "acp.bridge.run_started")]
+    BridgeRunStarted(SubmissionRef),
+    /// Codex asked for a tool call.
+    #[serde(rename = "acp.bridge.tool_call_received")]
+    BridgeToolCallReceived(BridgeToolCall),
+    /// A tool call started executing.
+    #[serde(rename = "acp.bridge.tool_exec_started")]
+    BridgeToolExecStarted(BridgeToolCall),
+    /// A tool call finished executing.
+    #[serde(rename = "acp.bridge.tool_exec_finished")]
+    BridgeToolExecFinished(BridgeToolExecFinished),
+    /// A tool result was reported to the client.
+    #[serde(rename = "acp.bridge.tool_result_sent")]
+    BridgeToolResultSent(BridgeToolResultSent),
+    /// The model produced output again after a tool result.
+    #[serde(rename = "acp.bridge.model_resumed")]
+    BridgeModelResumed(SubmissionRef),
+    /// A turn ended.
+    #[serde(rename = "acp.bridge.final_emitted")]
+    BridgeFinalEmitted(BridgeFinalEmitted),
+    /// Running turns were cancelled because a new prompt arrived.
+    #[serde(rename = "acp.bridge.preempt_before_prompt")]
+    BridgePreemptBeforePrompt(BridgePreempt),
+    /// Context optimization mode changed.
+    #[serde(rename = "acp.context_opt.mode")]
+    ContextOptMode(ModeSetting),
+    /// Auto-compact threshold changed.
+    #[serde(rename = "acp.context_opt.trigger_percent")]
+    ContextOptTriggerPercent(TriggerPercent),
+    /// Rough token estimate of a prompt.
+    #[serde(rename = "acp.context_opt.prompt_estimate")]
+    ContextOptPromptEstimate(PromptEstimate),
+    /// A prompt was projected to cross the auto-compact threshold, and what was done about it.
+    #[serde(rename = "acp.context_opt.budget")]
+    ContextOptBudget(ContextBudgetDecision),
+    /// Token usage reported by Codex after a turn.
+    #[serde(rename = "acp.context_opt.token_usage")]
+    ContextOptTokenUsage(TokenUsage),
+    /// An automatic compaction was submitted.
+    #[serde(rename = "acp.context_opt.auto_compact_triggered")]
+    ContextOptAutoCompactTriggered(AutoCompactTriggered),
+    /// An automatic compaction finished.
+    #[serde(rename = "acp.context_opt.auto_compact_completed")]
+    ContextOptAutoCompactCompleted(SubmissionRef),
+    /// An automatic compaction could not be submitted.
+    #[serde(rename = "acp.context_opt.auto_compact_error")]
+    ContextOptAutoCompactError(AutoCompactError),
+    /// Task orchestration mode changed.
+    #[serde(rename = "acp.task_monitoring.orchestration_mode")]
+    TaskOrchestrationMode(ModeSetting),
+    /// Task monitoring mode changed.
+    #[serde(rename = "acp.task_monitoring.mode")]
+    TaskMonitoringMode(ModeSetting),
+    /// Progress vector checks were turned on or off.
+    #[serde(rename = "acp.task_monitoring.vector_checks")]
+    TaskVectorChecks(Toggle),
+    /// Preempting running turns on a new prompt was turned on or off.
+    #[serde(rename = "acp.task_monitoring.preempt_on_new_prompt")]
+    TaskPreemptOnNewPrompt(Toggle),
+    /// A Codex beta feature was turned on or off.
+    #[serde(rename = "acp.beta_feature")]
+    BetaFeature(BetaFeature),
+    /// An operation was submitted to Codex.
+    #[serde(rename = "backend.codex.submit")]
+    CodexSubmit(CodexSubmit),
+    /// A client-provided MCP server could not be attached.
+    #[serde(rename = "mcp.server_skipped")]
+    McpServerSkipped(McpServerSkipped),
+    /// An approval request was answered by a local approval rule.
+    #[serde(rename = "acp.approval_policy.decision")]
+    ApprovalPolicyDecision(ApprovalPolicyDecision),
+}
+
+impl CanonicalEvent {
+    /// Parse the `kind`/`data` pair of an (upgraded) log line.
+    pub fn from_parts(kind: &str, data: Value) -> serde_json::Result<Self> {
+        serde_json::from_value(serde_json::json!({ "kind": kind, "data": data }))
+    }
+
+    pub fn prompt_text(text: impl Into<String>) -> Self {
+        Self::Prompt(PromptSummary {
+            text: text.into(),
+            block_count: 1,
+            ..PromptSummary::default()
+        })
+    }
+
+    pub fn user_message(text: impl Into<String>) -> Self {
+        Self::UserMessageChunk(TextChunk { text: text.into() })
+    }
+
+    pub fn agent_message(text: impl Into<String>) -> Self {
+        Self::AgentMessageChunk(TextChunk { text: text.into() })
+    }
+
+    pub fn agent_thought(text: impl Into<String>) -> Self {
+        Self::AgentThoughtChunk(TextChunk { text: text.into() })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TextChunk {
+    pub text: String,
+}
+
+/// Prompt summary. Embedded resources only carry their text when
+/// `ACP_LOG_EMBEDDED_CONTEXT=1`; CLI backends only record `text`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PromptSummary {
+    pub text: String,
+    #[serde(default)]
+    pub block_count: usize,
+    #[serde(default)]
+    pub resource_links: Vec<ResourceLinkSummary>,
+    #[serde(default)]
+    pub embedded_text_resources: Vec<EmbeddedTextSummary>,
+    #[serde(default)]
+    pub image_count: usize,
+    #[serde(default)]
+    pub audio_count: usize,
+    #[serde(default)]
+    pub other_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceLinkSummary {
+    pub name: String,
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddedTextSummary {
+    pub uri: String,
+    pub text_len: usize,
+    /// Whether `text` holds the resource contents.
+    pub included: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlanUpdate {
+    pub items: Vec<PlanItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlanItem {
+    pub step: String,
+    pub status: PlanStepStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStepStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PlanStepTransition {
+    pub step: String,
+    /// Status before the update; `None` when the step was added.
+    pub from: Option<PlanStepStatus>,
+    /// Status after the update; `None` when the step was removed.
+    pub to: Option<PlanStepStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PermissionRequest {
+    /// ACP `ToolCallUpdate` the client is asked about.
+    pub tool_call: Value,
+    /// ACP `PermissionOption`s offered to the client.
+    pub options: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PermissionResponse {
+    /// ACP `RequestPermissionOutcome`.
+    pub outcome: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ModeSetting {
+    pub mode: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Toggle {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TriggerPercent {
+    pub trigger_percent: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BetaFeature {
+    pub feature: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SubmissionRef {
+    pub submission_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgeToolCall {
+    pub submission_id: String,
+    pub tool_call_id: String,
+    pub tool_kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgeToolExecFinished {
+    pub submission_id: String,
+    pub tool_call_id: String,
+    pub tool_kind: String,
+    /// ACP tool call status (`completed`, `failed`, ...).
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgeToolResultSent {
+    pub submission_id: String,
+    pub tool_call_id: String,
+    /// ACP tool call status (`completed`, `failed`, ...).
+    pub status: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgeFinalEmitted {
+    pub submission_id: String,
+    pub stop_reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgePreempt {
+    pub active_submission_ids: Vec<String>,
+}
+
+/// Prompt token estimate; image cost comes from the image dimensions when they can be read, audio
+/// cost is an assumption.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PromptEstimate {
+    pub text_tokens: i64,
+    pub embedded_context_tokens: i64,
+    pub resource_link_tokens: i64,
+    pub image_tokens_assumed: i64,
+    pub audio_tokens_assumed: i64,
+    pub total_tokens: i64,
+    /// Counter used for text (`o200k_base`, `cl100k_base` or `heuristic`); absent in older logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TokenUsage {
+    pub submission_id: String,
+    pub total_tokens: i64,
+    pub context_window: Option<i64>,
+    pub used_percent: Option<i64>,
+    /// Context optimization mode at the time.
+    pub mode: String,
+    pub trigger_percent: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ContextBudgetDecision {
+    /// Context tokens reported after the previous turn.
+    pub current_tokens: i64,
+    pub prompt_tokens: i64,
+    pub projected_tokens: i64,
+    pub context_window: i64,
+    pub projected_percent: i64,
+    pub trigger_percent: i64,
+    /// Context optimization mode at the time.
+    pub mode: String,
+    /// `compact_first`, `submit_after_compact`, `send`, `trim`, `drop` or `cancel`.
+    pub decision: String,
+    /// URIs of the embedded resources that were trimmed or dropped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_submission_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AutoCompactTriggered {
+    pub source_submission_id: String,
+    pub compact_submission_id: String,
+    pub total_tokens: i64,
+    pub context_window: Option<i64>,
+    pub used_percent: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AutoCompactError {
+    pub source_submission_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CodexSubmit {
+    pub submission_id: String,
+    /// Coarse operation kind (`user_input`, `review`, `compact`, ...).
+    pub op_kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct McpServerSkipped {
+    pub name: String,
+    pub transport: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalPolicyDecision {
+    pub call_id: String,
+    /// `exec`, `patch` or `mcp`.
+    pub request: String,
+    /// `allow`, `ask` or `deny`.
+    pub decision: String,
+    pub rule: String,
+    /// Rules file the rule was read from.
+    pub source: String,
+}
+
+/// Rewrite a raw log line of any older schema version into the current layout.
+pub fn upgrade_line(mut line: Value) -> Value {
+    let Some(object) = line.as_object_mut() else {
+        return line;
+    };
+    let version = object
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(1);
+    if version < 2 {
+        upgrade_v1(object);
+    }
+    object.insert(
+        "schema_version".to_string(),
+        Value::from(CANONICAL_SCHEMA_VERSION),
+    );
+    line
+}
+
+/// v1 wrote bridge tool statuses with `{:?}` (`InProgress`).
+fn upgrade_v1(line: &mut serde_json::Map<String, Value>) {
+    let kind = line.get("kind").and_then(Value::as_str).unwrap_or_default();
+    if !matches!(
+        kind,
+        "acp.bridge.tool_exec_finished" | "acp.bridge.tool_result_sent"
+    ) {
+        return;
+    }
+    if let Some(status) = line.get_mut("data").and_then(|data| data.get_mut("status"))
+        && let Some(debug) = status.as_str()
+    {
+        *status = Value::String(debug.to_snake_case());
+    }
+}
+
+/// JSON Schema of [`CanonicalLine`].
+pub fn json_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(CanonicalLine)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Lines as written by schema version 1, one per event kind.
+    const RECORDED_V1_LOG: &str = include_str!("./testdata/canonical_v1.jsonl");
+
+    /// Position of the variant; the exhaustive match makes new variants show up here.
+    fn variant_index(event: &CanonicalEvent) -> usize {
+        match event {
+            CanonicalEvent::Prompt(_) => 0,
+            CanonicalEvent::UserMessageChunk(_) => 1,
+            CanonicalEvent::AgentMessageChunk(_) => 2,
+            CanonicalEvent::AgentThoughtChunk(_) => 3,
+            CanonicalEvent::ToolCall(_) => 4,
+            CanonicalEvent::ToolCallUpdate(_) => 5,
+            CanonicalEvent::Plan(_) => 6,
+            CanonicalEvent::RequestPermission(_) => 7,
+            CanonicalEvent::RequestPermissionResponse(_) => 8,
+            CanonicalEvent::SessionMode(_) => 9,
+            CanonicalEvent::RuntimeDiagnostics(_) => 10,
+            CanonicalEvent::BridgeRunStarted(_) => 11,
+            CanonicalEvent::BridgeToolCallReceived(_) => 12,
+            CanonicalEvent::BridgeToolExecStarted(_) => 13,
+            CanonicalEvent::BridgeToolExecFinished(_) => 14,
+            CanonicalEvent::BridgeToolResultSent(_) => 15,
+            CanonicalEvent::BridgeModelResumed(_) => 16,
+            CanonicalEvent::BridgeFinalEmitted(_) => 17,
+            CanonicalEvent::BridgePreemptBeforePrompt(_) => 18,
+            CanonicalEvent::ContextOptMode(_) => 19,
+            CanonicalEvent::ContextOptTriggerPercent(_) => 20,
+            CanonicalEvent::ContextOptPromptEstimate(_) => 21,
+            CanonicalEvent::ContextOptTokenUsage(_) => 22,
+            CanonicalEvent::ContextOptAutoCompactTriggered(_) => 23,
+            CanonicalEvent::ContextOptAutoCompactCompleted(_) => 24,
+            CanonicalEvent::ContextOptAutoCompactError(_) => 25,
+            CanonicalEvent::TaskOrchestrationMode(_) => 26,
+            CanonicalEvent::TaskMonitoringMode(_) => 27,
+            CanonicalEvent::TaskVectorChecks(_) => 28,
+            CanonicalEvent::TaskPreemptOnNewPrompt(_) => 29,
+            CanonicalEvent::BetaFeature(_) => 30,
+            CanonicalEvent::CodexSubmit(_) => 31,
+            CanonicalEvent::McpServerSkipped(_) => 32,
+            CanonicalEvent::ApprovalPolicyDecision(_) => 33,
+            CanonicalEvent::PlanStepTransition(_) => 34,
+            CanonicalEvent::ContextOptBudget(_) => 35,
+        }
+    }
+    const VARIANT_COUNT: usize = 36;
+    /// Kinds added after schema version 1, which the v1 recording cannot contain.
+    const ADDED_AFTER_V1: usize = 3;
+
+    #[test]
+    fn recorded_v1_log_parses_after_upgrade() {
+        let schema = json_schema().to_string();
+        let mut seen = BTreeSet::new();
+        for (index, raw) in RECORDED_V1_LOG.lines().enumerate() {
+            let value = serde_json::from_str::<Value>(raw).unwrap();
+            let kind = value["kind"].as_str().unwrap().to_string();
+            let line = serde_json::from_value::<CanonicalLine>(upgrade_line(value))
+                .unwrap_or_else(|e| panic!("line {} does not parse: {e}\n{raw}", index + 1));
+            assert_eq!(line.schema_version, CANONICAL_SCHEMA_VERSION);
+            assert!(
+                schema.contains(&format!("\"{kind}\"")),
+                "{kind} not in schema"
+            );
+            if let CanonicalEvent::BridgeToolExecFinished(finished) = &line.event {
+                assert_eq!(finished.status, "completed");
+            }
+            seen.insert(variant_index(&line.event));
+        }
+        assert_eq!(
+            seen.len(),
+            VARIANT_COUNT - ADDED_AFTER_V1,
+            "the recording must contain every v1 event kind"
+        );
+    }
+
+    #[test]
+    fn events_round_trip_through_kind_and_data() {
+        let event = CanonicalEvent::Plan(PlanUpdate {
+            items: vec![PlanItem {
+                step: "write tests".to_string(),
+                status: PlanStepStatus::InProgress,
+            }],
+            explanation: None,
+        });
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["kind"], "acp.plan");
+        assert_eq!(value["data"]["items"][0]["status"], "in_progress");
+        let parsed = CanonicalEvent::from_parts("acp.plan", value["data"].clone()).unwrap();
+        assert_eq!(parsed, event);
+        assert!(CanonicalEvent::from_parts("acp.plan", serde_json::json!({})).is_err());
+        assert!(CanonicalEvent::from_parts("acp.unknown", Value::Null).is_err());
+    }
+}
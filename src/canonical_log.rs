@@ -0,0 +1,409 @@
+//! Segmented storage and retention for the canonical session log.
+//!
+//! The active segment of a session is always `canonical.jsonl`. Once it would grow past the
+//! segment limit it is renamed to the next `canonical.NNNNNN.jsonl` and, unless disabled,
+//! gzip-compressed to `canonical.NNNNNN.jsonl.gz`. [`read_lines`] returns the closed segments in
+//! order followed by the active one, so readers never deal with segments themselves.
+//!
+//! Retention runs once at startup ([`apply_retention`]) and removes whole session directories
+//! that are older than the configured age or, oldest first, until the store fits the configured
+//! size. Sessions containing a `pinned` file are always kept.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use tracing::warn;
+
+/// File name of the segment currently being appended to.
+pub const ACTIVE_SEGMENT: &str = "canonical.jsonl";
+
+/// Marker file that exempts a session directory from retention.
+pub const PINNED_MARKER: &str = "pinned";
+
+const SEGMENT_BYTES_ENV: &str = "ACP_LOG_SEGMENT_BYTES";
+const COMPRESS_ENV: &str = "ACP_LOG_COMPRESS";
+const RETENTION_DAYS_ENV: &str = "ACP_LOG_RETENTION_DAYS";
+const RETENTION_MAX_BYTES_ENV: &str = "ACP_LOG_RETENTION_MAX_BYTES";
+
+const DEFAULT_SEGMENT_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Sessions written within this window are never pruned; another process may still own them.
+const ACTIVE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+}
+
+/// When the active segment is closed and what happens to closed segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Size limit of one segment; `0` never rotates.
+    pub max_segment_bytes: u64,
+    /// Gzip closed segments.
+    pub compress: bool,
+}
+
+impl RotationPolicy {
+    /// `ACP_LOG_SEGMENT_BYTES` (default 8 MiB) and `ACP_LOG_COMPRESS` (default on).
+    pub fn from_env() -> Self {
+        Self {
+            max_segment_bytes: env_u64(SEGMENT_BYTES_ENV).unwrap_or(DEFAULT_SEGMENT_BYTES),
+            compress: !std::env::var(COMPRESS_ENV)
+                .is_ok_and(|v| v == "0" || v.eq_ignore_ascii_case("false")),
+        }
+    }
+}
+
+/// Limits applied to `ACP_HOME/sessions` at startup; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// `ACP_LOG_RETENTION_DAYS` and `ACP_LOG_RETENTION_MAX_BYTES` (unset or `0` keeps everything).
+    pub fn from_env() -> Self {
+        Self {
+            max_age: env_u64(RETENTION_DAYS_ENV)
+                .filter(|days| *days > 0)
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            max_total_bytes: env_u64(RETENTION_MAX_BYTES_ENV).filter(|bytes| *bytes > 0),
+        }
+    }
+}
+
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("canonical.{seq:06}.jsonl"))
+}
+
+/// `(sequence, compressed)` of a closed segment file name.
+fn parse_segment_name(name: &str) -> Option<(u64, bool)> {
+    let rest = name.strip_prefix("canonical.")?;
+    let (seq, compressed) = match rest.strip_suffix(".jsonl.gz") {
+        Some(seq) => (seq, true),
+        None => (rest.strip_suffix(".jsonl")?, false),
+    };
+    if seq.len() < 6 || !seq.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((seq.parse().ok()?, compressed))
+}
+
+/// Closed segments of the log in `dir`, oldest first.
+///
+/// If a crash left both the plain and the compressed copy of a segment, the plain one wins.
+pub fn closed_segments(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut segments = BTreeMap::<u64, PathBuf>::new();
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let Some((seq, compressed)) = name.to_str().and_then(parse_segment_name) else {
+            continue;
+        };
+        if compressed {
+            segments.entry(seq).or_insert_with(|| entry.path());
+        } else {
+            segments.insert(seq, entry.path());
+        }
+    }
+    segments.into_values().collect()
+}
+
+/// Every line of the log in `dir`, across closed segments and the active one.
+pub fn read_lines(dir: &Path) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for path in closed_segments(dir)
+        .into_iter()
+        .chain([dir.join(ACTIVE_SEGMENT)])
+    {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        for line in BufReader::new(reader).lines() {
+            lines.push(line?);
+        }
+    }
+    Ok(lines)
+}
+
+fn compress_segment(path: &Path) -> io::Result<()> {
+    let compressed = path.with_extension("jsonl.gz");
+    let tmp = path.with_extension("jsonl.gz.tmp");
+    let mut encoder = GzEncoder::new(File::create(&tmp)?, Compression::fast());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&tmp, &compressed)?;
+    fs::remove_file(path)
+}
+
+fn open_active(dir: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(ACTIVE_SEGMENT))
+}
+
+/// Append handle for the log in one session directory.
+pub struct SegmentWriter {
+    dir: PathBuf,
+    file: File,
+    len: u64,
+    next_seq: u64,
+    policy: RotationPolicy,
+}
+
+impl SegmentWriter {
+    pub fn open(dir: &Path, policy: RotationPolicy) -> io::Result<Self> {
+        let file = open_active(dir)?;
+        let len = file.metadata()?.len();
+        let next_seq = closed_segments(dir)
+            .last()
+            .and_then(|path| parse_segment_name(path.file_name()?.to_str()?))
+            .map_or(1, |(seq, _)| seq + 1);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            len,
+            next_seq,
+            policy,
+        })
+    }
+
+    /// Append one line, closing the active segment first if the line would not fit.
+    pub fn append_line(&mut self, line: &str) -> io::Result<()> {
+        let needed = line.len() as u64 + 1;
+        let limit = self.policy.max_segment_bytes;
+        if limit > 0
+            && self.len > 0
+            && self.len + needed > limit
+            && let Err(e) = self.rotate()
+        {
+            warn!(
+                "Failed to rotate canonical log in {}: {e}",
+                self.dir.display()
+            );
+        }
+        writeln!(self.file, "{line}")?;
+        self.len += needed;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let closed = segment_path(&self.dir, self.next_seq);
+        fs::rename(self.dir.join(ACTIVE_SEGMENT), &closed)?;
+        self.next_seq += 1;
+        self.file = open_active(&self.dir)?;
+        self.len = 0;
+        if self.policy.compress
+            && let Err(e) = compress_segment(&closed)
+        {
+            // The plain segment is still readable; only the space saving is lost.
+            warn!("Failed to compress {}: {e}", closed.display());
+        }
+        Ok(())
+    }
+}
+
+struct SessionUsage {
+    path: PathBuf,
+    bytes: u64,
+    last_write: SystemTime,
+}
+
+fn add_dir_usage(path: &Path, bytes: &mut u64, last_write: &mut SystemTime) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            add_dir_usage(&entry.path(), bytes, last_write)?;
+        } else {
+            *bytes += metadata.len();
+            if let Ok(modified) = metadata.modified() {
+                *last_write = (*last_write).max(modified);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove session directories under `sessions_dir` that violate `policy`.
+///
+/// Expired sessions are removed first-come; if the store is still too large, the least
+/// recently written sessions go next. Returns the removed global session ids.
+pub fn apply_retention(
+    sessions_dir: &Path,
+    policy: RetentionPolicy,
+    now: SystemTime,
+) -> Vec<String> {
+    if policy == RetentionPolicy::default() {
+        return Vec::new();
+    }
+    let Ok(entries) = fs::read_dir(sessions_dir) else {
+        return Vec::new();
+    };
+    let mut total = 0u64;
+    let mut candidates = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let mut bytes = 0;
+        let mut last_write = SystemTime::UNIX_EPOCH;
+        if let Err(e) = add_dir_usage(&path, &mut bytes, &mut last_write) {
+            warn!("Failed to scan session directory {}: {e}", path.display());
+            continue;
+        }
+        total += bytes;
+        let age = now.duration_since(last_write).unwrap_or_default();
+        if age >= ACTIVE_GRACE && !path.join(PINNED_MARKER).exists() {
+            candidates.push(SessionUsage {
+                path,
+                bytes,
+                last_write,
+            });
+        }
+    }
+    candidates.sort_by_key(|session| session.last_write);
+
+    let mut removed = Vec::new();
+    for session in candidates {
+        let age = now.duration_since(session.last_write).unwrap_or_default();
+        let expired = policy.max_age.is_some_and(|max| age > max);
+        let over_budget = policy.max_total_bytes.is_some_and(|max| total > max);
+        if !expired && !over_budget {
+            continue;
+        }
+        match fs::remove_dir_all(&session.path) {
+            Ok(()) => {
+                total = total.saturating_sub(session.bytes);
+                if let Some(id) = session.path.file_name().and_then(|name| name.to_str()) {
+                    removed.push(id.to_string());
+                }
+            }
+            Err(e) => warn!(
+                "Failed to remove session directory {}: {e}",
+                session.path.display()
+            ),
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("acp-canonical-log-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotates_compresses_and_reads_across_segments() {
+        let dir = temp_dir();
+        let policy = RotationPolicy {
+            max_segment_bytes: 20,
+            compress: true,
+        };
+        let mut writer = SegmentWriter::open(&dir, policy).unwrap();
+        for i in 0..5 {
+            writer.append_line(&format!("line-{i}-0123456")).unwrap();
+        }
+        drop(writer);
+
+        assert!(dir.join("canonical.000001.jsonl.gz").exists());
+        assert!(!dir.join("canonical.000001.jsonl").exists());
+        assert_eq!(closed_segments(&dir).len(), 4);
+
+        // Reopening continues the sequence instead of overwriting closed segments.
+        let mut writer = SegmentWriter::open(&dir, policy).unwrap();
+        writer.append_line("line-5-0123456").unwrap();
+        assert!(dir.join("canonical.000005.jsonl.gz").exists());
+
+        let lines = read_lines(&dir).unwrap();
+        let expected = (0..6)
+            .map(|i| format!("line-{i}-0123456"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, expected);
+
+        drop(fs::remove_dir_all(&dir));
+    }
+
+    #[test]
+    fn segment_names_are_parsed_strictly() {
+        assert_eq!(
+            parse_segment_name("canonical.000012.jsonl"),
+            Some((12, false))
+        );
+        assert_eq!(
+            parse_segment_name("canonical.000012.jsonl.gz"),
+            Some((12, true))
+        );
+        assert_eq!(parse_segment_name(ACTIVE_SEGMENT), None);
+        assert_eq!(parse_segment_name("canonical.000012.jsonl.gz.tmp"), None);
+        assert_eq!(parse_segment_name("canonical.12.jsonl"), None);
+    }
+
+    #[test]
+    fn retention_prunes_old_and_oversized_sessions_but_keeps_pinned() {
+        let sessions = temp_dir();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let write_session = |id: &str, age: Duration, bytes: usize, pinned: bool| {
+            let dir = sessions.join(id);
+            fs::create_dir_all(&dir).unwrap();
+            let file = File::create(dir.join(ACTIVE_SEGMENT)).unwrap();
+            file.set_len(bytes as u64).unwrap();
+            file.set_modified(now - age).unwrap();
+            if pinned {
+                File::create(dir.join(PINNED_MARKER))
+                    .unwrap()
+                    .set_modified(now - age)
+                    .unwrap();
+            }
+        };
+        write_session("expired", day * 40, 10, false);
+        write_session("expired-pinned", day * 40, 10, true);
+        write_session("older", day * 3, 100, false);
+        write_session("newer", day * 2, 100, false);
+        write_session("active", Duration::from_secs(60), 100, false);
+
+        let mut removed = apply_retention(
+            &sessions,
+            RetentionPolicy {
+                max_age: Some(day * 30),
+                max_total_bytes: Some(250),
+            },
+            now,
+        );
+        removed.sort();
+        assert_eq!(removed, vec!["expired", "older"]);
+        for kept in ["expired-pinned", "newer", "active"] {
+            assert!(sessions.join(kept).exists(), "{kept} should be kept");
+        }
+
+        assert!(apply_retention(&sessions, RetentionPolicy::default(), now).is_empty());
+        drop(fs::remove_dir_all(&sessions));
+    }
+}
@@ -1,26 +1,45 @@
 use agent_client_protocol::{
-    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, Error,
-    ListSessionsRequest, ListSessionsResponse, LoadSessionRequest, LoadSessionResponse,
-    NewSessionRequest, NewSessionResponse, PromptRequest, PromptResponse, SessionConfigOption,
-    SessionConfigOptionCategory, SessionConfigSelectOption, SessionId, SessionInfo,
-    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
-    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse, StopReason,
+    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, ContentBlock,
+    ContentChunk, Error, ForkSessionRequest, ForkSessionResponse, ListSessionsRequest,
+    ListSessionsResponse, LoadSessionRequest, LoadSessionResponse, McpServer, NewSessionRequest,
+    NewSessionResponse, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptCapabilities,
+    PromptRequest, PromptResponse, ResumeSessionRequest, ResumeSessionResponse,
+    SessionConfigOption, SessionConfigOptionCategory, SessionConfigSelectOption, SessionId,
+    SessionUpdate, SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+    SetSessionModeRequest, SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
+    StopReason, TextContent, ToolCall, ToolCallLocation, ToolCallStatus, ToolCallUpdate,
+    ToolCallUpdateFields, ToolKind,
 };
+use serde_json::Value;
 use std::sync::{Arc, Mutex};
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     process::{Command as StdCommand, Stdio},
     rc::Rc,
 };
-use tokio::{io::AsyncReadExt, process::Command as TokioCommand, sync::watch};
-use tracing::{debug, info};
+use tokio::{net::TcpStream, process::Command as TokioCommand, sync::watch};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::{
     backend::{BackendDriver, BackendKind},
-    cli_common::{prompt_blocks_to_text, send_agent_text},
+    canonical_event::CanonicalEvent,
+    claude_permission::{
+        PermissionCheck, PermissionDecision, PermissionListener, permission_options,
+        permission_prompt_tool, reply_check,
+    },
+    cli_common::{
+        CliCommand, CliSession, CommandRunResult, StreamTranslator, TRANSCRIPT_TURNS,
+        cancel_cli_prompt, init_session_store, list_cli_sessions, parse_cli_command,
+        prompt_blocks_to_text, prompt_blocks_with_attachments, replay_turns, request_permission,
+        restore_turns, run_streaming_cli, send_agent_text, stored_global_id, transcript_prompt,
+        turns_from_canonical,
+    },
+    mcp_config::{McpConfigFile, McpConfigFormat},
+    multi_backend::with_context_handoff,
+    prompt_attachments::PromptAttachments,
     session_store::{GlobalSessionIndex, SessionStore},
 };
 
@@ -30,25 +49,301 @@ struct ClaudeAuthStatus {
     logged_in: bool,
 }
 
+/// What the Claude CLI prints to stderr when `--resume` names a session it no longer has.
+const STALE_RESUME_STDERR: &str = "No conversation found with session ID";
+
 struct ClaudeSession {
     cwd: PathBuf,
     model: Option<String>,
     history: Vec<(String, String)>,
+    /// Claude CLI session id used with `--resume` on later turns.
+    native_session_id: Option<String>,
+    /// Set on forked sessions until the first turn creates their own CLI session.
+    fork_pending: bool,
+    /// [`PermissionCheck::session_key`]s the user approved with "Always" for this session.
+    always_allowed: HashSet<String>,
     session_store: Option<SessionStore>,
+    /// Client-provided MCP servers, passed with `--mcp-config`.
+    mcp_config: Option<Value>,
     active_prompt: Option<watch::Sender<bool>>,
 }
 
-struct CommandRunResult {
-    stdout: String,
-    exit_code: Option<i32>,
-    cancelled: bool,
+impl CliSession for ClaudeSession {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn active_prompt(&self) -> Option<&watch::Sender<bool>> {
+        self.active_prompt.as_ref()
+    }
+}
+
+/// A session as recorded in its canonical log and the session index.
+struct PersistedSession {
+    turns: Vec<(String, String)>,
+    history: Vec<(String, String)>,
+    model: Option<String>,
+    native_session_id: Option<String>,
+}
+
+/// One `claude --print` invocation.
+struct ClaudeInvocation {
+    cwd: PathBuf,
+    model: Option<String>,
+    prompt: String,
+    mcp_config: Option<PathBuf>,
+    /// Directory holding this turn's attachments, passed with `--add-dir`.
+    attachment_dir: Option<PathBuf>,
+    resume_session_id: Option<String>,
+    fork_session: bool,
+}
+
+/// Incremental translator for `claude --print --output-format stream-json` events.
+///
+/// Each stdout line is one JSON event; assistant text, thinking, tool use/results and
+/// `TodoWrite` plans are mapped onto ACP session updates as soon as they arrive.
+#[derive(Default)]
+struct ClaudeStreamTranslator {
+    output_text: String,
+    session_id: Option<String>,
+    error: Option<String>,
+    plan_tool_ids: HashSet<String>,
+}
+
+impl StreamTranslator for ClaudeStreamTranslator {
+    fn handle_line(&mut self, line: &str) -> Vec<SessionUpdate> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Vec::new();
+        }
+        let Ok(event) = serde_json::from_str::<Value>(trimmed) else {
+            // Wrappers or older CLIs may still print plain text; surface it verbatim.
+            return vec![self.push_text(line, "\n")];
+        };
+
+        // `system/init` and `result` events carry the CLI session id used for `--resume`.
+        if let Some(session_id) = event.get("session_id").and_then(Value::as_str) {
+            self.session_id = Some(session_id.to_string());
+        }
+
+        match event.get("type").and_then(Value::as_str) {
+            Some("assistant") => Self::content_blocks(&event)
+                .iter()
+                .filter_map(|block| self.assistant_block(block))
+                .collect(),
+            Some("user") => Self::content_blocks(&event)
+                .iter()
+                .filter_map(|block| self.tool_result_block(block))
+                .collect(),
+            Some("result") => self.result_event(&event),
+            _ => Vec::new(),
+        }
+    }
+
+    fn output_text(&self) -> &str {
+        &self.output_text
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl ClaudeStreamTranslator {
+    fn content_blocks(event: &Value) -> Vec<Value> {
+        event
+            .pointer("/message/content")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn push_text(&mut self, text: &str, separator: &str) -> SessionUpdate {
+        let chunk = if self.output_text.is_empty() {
+            text.to_string()
+        } else {
+            format!("{separator}{text}")
+        };
+        self.output_text.push_str(&chunk);
+        SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(TextContent::new(
+            chunk,
+        ))))
+    }
+
+    fn assistant_block(&mut self, block: &Value) -> Option<SessionUpdate> {
+        match block.get("type").and_then(Value::as_str)? {
+            "text" => {
+                let text = block.get("text").and_then(Value::as_str)?;
+                (!text.is_empty()).then(|| self.push_text(text, "\n\n"))
+            }
+            "thinking" => {
+                let thinking = block.get("thinking").and_then(Value::as_str)?;
+                (!thinking.is_empty()).then(|| {
+                    SessionUpdate::AgentThoughtChunk(ContentChunk::new(ContentBlock::Text(
+                        TextContent::new(thinking),
+                    )))
+                })
+            }
+            "tool_use" => {
+                let id = block.get("id").and_then(Value::as_str)?;
+                let name = block.get("name").and_then(Value::as_str).unwrap_or("tool");
+                let input = block.get("input").cloned().unwrap_or(Value::Null);
+                if name == "TodoWrite"
+                    && let Some(plan) = todo_write_plan(&input)
+                {
+                    self.plan_tool_ids.insert(id.to_string());
+                    return Some(SessionUpdate::Plan(plan));
+                }
+                Some(SessionUpdate::ToolCall(claude_tool_call(id, name, input)))
+            }
+            _ => None,
+        }
+    }
+
+    fn tool_result_block(&mut self, block: &Value) -> Option<SessionUpdate> {
+        if block.get("type").and_then(Value::as_str)? != "tool_result" {
+            return None;
+        }
+        let id = block.get("tool_use_id").and_then(Value::as_str)?;
+        if self.plan_tool_ids.remove(id) {
+            return None;
+        }
+        let is_error = block
+            .get("is_error")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let raw_output = block.get("content").cloned().unwrap_or(Value::Null);
+        let text = tool_result_text(&raw_output);
+
+        let mut fields = ToolCallUpdateFields::new()
+            .status(if is_error {
+                ToolCallStatus::Failed
+            } else {
+                ToolCallStatus::Completed
+            })
+            .raw_output(raw_output);
+        if !text.is_empty() {
+            fields = fields.content(vec![text.into()]);
+        }
+        Some(SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+            id.to_string(),
+            fields,
+        )))
+    }
+
+    fn result_event(&mut self, event: &Value) -> Vec<SessionUpdate> {
+        let result_text = event.get("result").and_then(Value::as_str).unwrap_or("");
+        if event
+            .get("is_error")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            let subtype = event
+                .get("subtype")
+                .and_then(Value::as_str)
+                .unwrap_or("error");
+            self.error = Some(if result_text.is_empty() {
+                subtype.to_string()
+            } else {
+                format!("{subtype}: {result_text}")
+            });
+            return Vec::new();
+        }
+        // Only fall back to the summary when no assistant text was streamed.
+        if self.output_text.is_empty() && !result_text.is_empty() {
+            return vec![self.push_text(result_text, "\n\n")];
+        }
+        Vec::new()
+    }
+}
+
+fn claude_tool_call(id: &str, name: &str, input: Value) -> ToolCall {
+    let field = |key: &str| input.get(key).and_then(Value::as_str).map(str::to_string);
+    let path = field("file_path")
+        .or_else(|| field("notebook_path"))
+        .or_else(|| field("path"));
+
+    let (kind, title) = match name {
+        "Bash" => (
+            ToolKind::Execute,
+            field("command")
+                .map(|command| format!("`{command}`"))
+                .unwrap_or_else(|| "Run command".to_string()),
+        ),
+        "Read" => (
+            ToolKind::Read,
+            format!("Read {}", path.as_deref().unwrap_or("file")),
+        ),
+        "Write" | "Edit" | "MultiEdit" | "NotebookEdit" => (
+            ToolKind::Edit,
+            format!("{name} {}", path.as_deref().unwrap_or("file")),
+        ),
+        "Grep" | "Glob" => (
+            ToolKind::Search,
+            format!(
+                "{name} {}",
+                field("pattern").unwrap_or_else(|| "files".to_string())
+            ),
+        ),
+        "WebFetch" => (
+            ToolKind::Fetch,
+            format!("Fetch {}", field("url").unwrap_or_default()),
+        ),
+        "WebSearch" => (
+            ToolKind::Fetch,
+            format!("Web search: {}", field("query").unwrap_or_default()),
+        ),
+        "Task" => (
+            ToolKind::Think,
+            field("description").unwrap_or_else(|| "Task".to_string()),
+        ),
+        other => (ToolKind::Other, other.to_string()),
+    };
+
+    let mut tool_call = ToolCall::new(id.to_string(), title)
+        .kind(kind)
+        .status(ToolCallStatus::InProgress)
+        .raw_input(input);
+    if let Some(path) = path {
+        tool_call = tool_call.locations(vec![ToolCallLocation::new(PathBuf::from(path))]);
+    }
+    tool_call
+}
+
+fn todo_write_plan(input: &Value) -> Option<Plan> {
+    let todos = input.get("todos")?.as_array()?;
+    let entries = todos
+        .iter()
+        .filter_map(|todo| {
+            let content = todo.get("content").and_then(Value::as_str)?;
+            let status = match todo.get("status").and_then(Value::as_str) {
+                Some("completed") => PlanEntryStatus::Completed,
+                Some("in_progress") => PlanEntryStatus::InProgress,
+                _ => PlanEntryStatus::Pending,
+            };
+            Some(PlanEntry::new(content, PlanEntryPriority::Medium, status))
+        })
+        .collect::<Vec<_>>();
+    Some(Plan::new(entries))
+}
+
+fn tool_result_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
 }
 
 /// Claude Code backend driver (shells out to the `claude` CLI).
 ///
-/// This is intentionally minimal:
 /// - `new_session` creates an in-memory session ID
-/// - `prompt` runs `claude --print` and streams the response as a single ACP message chunk
+/// - `prompt` runs `claude --print --output-format stream-json` and translates each event
+///   into live ACP message, thought, tool call and plan updates
 pub struct ClaudeCodeDriver {
     sessions: Rc<RefCell<HashMap<SessionId, ClaudeSession>>>,
     global_session_index: Option<Arc<Mutex<GlobalSessionIndex>>>,
@@ -75,6 +370,15 @@ impl ClaudeCodeDriver {
             .unwrap_or_default()
     }
 
+    /// `acp` (default) routes Claude's permission checks to the ACP client; `cli` leaves
+    /// them to Claude's own settings (e.g. `--permission-mode` in `XSFIRE_CLAUDE_ARGS`).
+    fn bridge_permissions() -> bool {
+        !matches!(
+            std::env::var("XSFIRE_CLAUDE_PERMISSIONS").as_deref(),
+            Ok("cli")
+        )
+    }
+
     fn default_model() -> Option<String> {
         std::env::var("XSFIRE_CLAUDE_MODEL").ok()
     }
@@ -164,132 +468,217 @@ impl ClaudeCodeDriver {
         ]
     }
 
+    /// Run one `claude --print` turn, returning the CLI session id for later `--resume`.
     async fn run_claude(
         &self,
-        cwd: PathBuf,
-        model: Option<String>,
-        prompt: String,
-        mut cancel_rx: watch::Receiver<bool>,
-    ) -> Result<CommandRunResult, Error> {
+        session_id: &SessionId,
+        session_store: Option<SessionStore>,
+        invocation: ClaudeInvocation,
+        cancel_rx: watch::Receiver<bool>,
+    ) -> Result<(CommandRunResult, Option<String>), Error> {
+        let ClaudeInvocation {
+            cwd,
+            model,
+            prompt,
+            mcp_config,
+            attachment_dir,
+            resume_session_id,
+            fork_session,
+        } = invocation;
         let bin = Self::bin();
         let bin_display = bin.clone();
         let extra_args = Self::extra_args();
+        let permissions = if Self::bridge_permissions() {
+            self.permission_listener().await
+        } else {
+            None
+        };
 
         let mut cmd = TokioCommand::new(&bin);
         cmd.arg("--print");
+        let mut mcp_configs = Vec::new();
+        if let Some(path) = mcp_config {
+            mcp_configs.push(path.display().to_string());
+        }
+        if let Some((_, permission_config)) = &permissions {
+            mcp_configs.push(permission_config.path().display().to_string());
+        }
+        if !mcp_configs.is_empty() {
+            // `--mcp-config` is variadic; the flags below keep it from swallowing the prompt.
+            cmd.arg("--mcp-config");
+            cmd.args(mcp_configs);
+        }
+        // Also variadic, so it stays ahead of the flags below as well.
+        if let Some(dir) = attachment_dir {
+            cmd.arg("--add-dir");
+            cmd.arg(dir);
+        }
+        // `stream-json` requires `--verbose` when combined with `--print`.
+        cmd.arg("--output-format");
+        cmd.arg("stream-json");
+        cmd.arg("--verbose");
         cmd.arg("--cwd");
         cmd.arg(&cwd);
         if let Some(model) = model {
             cmd.arg("--model");
             cmd.arg(model);
         }
+        if let Some(resume_session_id) = resume_session_id {
+            cmd.arg("--resume");
+            cmd.arg(resume_session_id);
+            if fork_session {
+                cmd.arg("--fork-session");
+            }
+        }
+        if permissions.is_some() {
+            cmd.arg("--permission-prompt-tool");
+            cmd.arg(permission_prompt_tool());
+        }
         cmd.args(extra_args);
         cmd.arg(prompt);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
-        let mut child = cmd.spawn().map_err(|e| {
+        let child = cmd.spawn().map_err(|e| {
             Error::invalid_params().data(format!(
                 "failed to execute Claude CLI ({bin_display}). Install it or set XSFIRE_CLAUDE_BIN. Error: {e}"
             ))
         })?;
 
-        let mut stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| Error::internal_error().data("Claude CLI stdout pipe missing"))?;
-        let mut stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| Error::internal_error().data("Claude CLI stderr pipe missing"))?;
-
-        let stdout_task = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            stdout
-                .read_to_end(&mut buf)
-                .await
-                .map(|_| buf)
-                .map_err(|e| Error::internal_error().data(e.to_string()))
-        });
-        let stderr_task = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            stderr
-                .read_to_end(&mut buf)
-                .await
-                .map(|_| buf)
-                .map_err(|e| Error::internal_error().data(e.to_string()))
-        });
-
-        let (status, cancelled) = tokio::select! {
-            result = child.wait() => (
-                result.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                false,
-            ),
-            changed = cancel_rx.changed() => {
-                if changed.is_ok() && *cancel_rx.borrow() {
-                    drop(child.start_kill());
-                    (
-                        child.wait().await.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                        true,
-                    )
-                } else {
-                    (
-                        child.wait().await.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                        false,
-                    )
+        // Claude blocks on each permission check, so they are answered one at a time while
+        // stdout keeps streaming and cancellation stays responsive.
+        let permission_checks = async {
+            let Some((listener, _)) = &permissions else {
+                return;
+            };
+            loop {
+                match listener.accept().await {
+                    Ok(stream) => {
+                        self.answer_permission_check(
+                            session_id,
+                            session_store.as_ref(),
+                            listener,
+                            stream,
+                        )
+                        .await;
+                    }
+                    Err(err) => warn!("Failed to accept Claude permission check: {err}"),
                 }
             }
         };
+        let mut translator = ClaudeStreamTranslator::default();
+        let output = run_streaming_cli(
+            "Claude CLI",
+            child,
+            session_id,
+            session_store.as_ref(),
+            &mut translator,
+            permission_checks,
+            cancel_rx,
+        )
+        .await?;
+        Ok((output, translator.session_id))
+    }
 
-        let stdout_bytes = stdout_task
-            .await
-            .map_err(|e| Error::internal_error().data(e.to_string()))??;
-        let stderr_bytes = stderr_task
-            .await
-            .map_err(|e| Error::internal_error().data(e.to_string()))??;
-        let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
-        let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
-
-        if !cancelled && !status.success() {
-            return Err(Error::internal_error().data(format!(
-                "Claude CLI failed (exit {:?}). stderr:\n{stderr}",
-                status.code()
-            )));
+    /// Bind the loopback listener for Claude's permission relay and write the MCP config
+    /// pointing at it, or fall back to the CLI's own permission handling when that is not
+    /// possible.
+    async fn permission_listener(&self) -> Option<(PermissionListener, McpConfigFile)> {
+        let exe = match std::env::current_exe() {
+            Ok(exe) => exe.to_string_lossy().to_string(),
+            Err(err) => {
+                warn!("Cannot locate xsfire-camp for the Claude permission bridge: {err}");
+                return None;
+            }
+        };
+        let listener = match PermissionListener::bind().await {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("Failed to start the Claude permission bridge: {err}");
+                return None;
+            }
+        };
+        match listener.mcp_config_file(&exe) {
+            Ok(config) => Some((listener, config)),
+            Err(err) => {
+                warn!("Failed to write the Claude permission bridge config: {err}");
+                None
+            }
         }
+    }
 
-        Ok(CommandRunResult {
-            stdout,
-            exit_code: status.code(),
-            cancelled,
-        })
+    async fn answer_permission_check(
+        &self,
+        session_id: &SessionId,
+        session_store: Option<&SessionStore>,
+        listener: &PermissionListener,
+        mut stream: TcpStream,
+    ) {
+        let check = match listener.read_check(&mut stream).await {
+            Ok(check) => check,
+            Err(err) => {
+                warn!("Ignoring Claude permission check: {err}");
+                return;
+            }
+        };
+        let decision = self
+            .decide_permission(session_id, session_store, &check)
+            .await;
+        if let Err(err) = reply_check(&mut stream, &check, &decision).await {
+            warn!("Failed to answer Claude permission check: {err}");
+        }
     }
-}
 
-enum ClaudeCommand {
-    Help,
-    Status,
-    Reset,
-    SetModel(String),
-}
+    async fn decide_permission(
+        &self,
+        session_id: &SessionId,
+        session_store: Option<&SessionStore>,
+        check: &PermissionCheck,
+    ) -> PermissionDecision {
+        let always_allowed = self
+            .sessions
+            .borrow()
+            .get(session_id)
+            .is_some_and(|session| session.always_allowed.contains(&check.session_key()));
+        if always_allowed {
+            return PermissionDecision::Allow { always: true };
+        }
 
-fn parse_claude_command(input: &str) -> Option<ClaudeCommand> {
-    let trimmed = input.trim();
-    if trimmed == "/help" {
-        return Some(ClaudeCommand::Help);
-    }
-    if trimmed == "/status" {
-        return Some(ClaudeCommand::Status);
-    }
-    if trimmed == "/reset" {
-        return Some(ClaudeCommand::Reset);
-    }
-    if let Some(value) = trimmed.strip_prefix("/model ") {
-        let model = value.trim();
-        if !model.is_empty() {
-            return Some(ClaudeCommand::SetModel(model.to_string()));
+        let tool_call_id = check
+            .tool_use_id
+            .clone()
+            .unwrap_or_else(|| format!("permission-{}", Uuid::new_v4()));
+        let call = claude_tool_call(&tool_call_id, &check.tool_name, check.input.clone());
+        let tool_call = ToolCallUpdate::new(
+            tool_call_id,
+            ToolCallUpdateFields::new()
+                .kind(call.kind)
+                .status(ToolCallStatus::Pending)
+                .title(call.title)
+                .raw_input(check.input.clone())
+                .locations(if call.locations.is_empty() {
+                    None
+                } else {
+                    Some(call.locations)
+                }),
+        );
+
+        let decision =
+            match request_permission(session_id, session_store, tool_call, permission_options())
+                .await
+            {
+                Ok(response) => PermissionDecision::from_outcome(&response.outcome),
+                Err(err) => {
+                    PermissionDecision::Deny(format!("Could not ask the ACP client: {err}"))
+                }
+            };
+        if decision == (PermissionDecision::Allow { always: true })
+            && let Some(session) = self.sessions.borrow_mut().get_mut(session_id)
+        {
+            session.always_allowed.insert(check.session_key());
         }
+        decision
     }
-    None
 }
 
 #[async_trait::async_trait(?Send)]
@@ -298,6 +687,23 @@ impl BackendDriver for ClaudeCodeDriver {
         BackendKind::ClaudeCode
     }
 
+    fn supports_load_session(&self) -> bool {
+        true
+    }
+
+    fn supports_fork_session(&self) -> bool {
+        true
+    }
+
+    fn supports_resume_session(&self) -> bool {
+        true
+    }
+
+    /// Images and blob resources are forwarded as files (see `prompt_attachments`).
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        PromptCapabilities::new().embedded_context(true).image(true)
+    }
+
     fn auth_methods(&self) -> Vec<AuthMethod> {
         vec![AuthMethod::new(
             Self::AUTH_METHOD_ID,
@@ -318,7 +724,19 @@ impl BackendDriver for ClaudeCodeDriver {
     async fn new_session(&self, request: NewSessionRequest) -> Result<NewSessionResponse, Error> {
         let session_id = SessionId::new(format!("claude:{}", Uuid::new_v4()));
         let cwd = request.cwd;
-        let session_store = self.init_session_store(&session_id, &cwd);
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::ClaudeCode,
+            BackendKind::ClaudeCode.as_str(),
+            &session_id,
+            &cwd,
+        );
+        let mcp_config = Self::write_mcp_config(
+            &session_id,
+            session_store.as_ref(),
+            &request.mcp_servers,
+            &cwd,
+        );
 
         self.sessions.borrow_mut().insert(
             session_id.clone(),
@@ -326,7 +744,11 @@ impl BackendDriver for ClaudeCodeDriver {
                 cwd,
                 model: Self::default_model(),
                 history: Vec::new(),
+                native_session_id: None,
+                fork_pending: false,
+                always_allowed: HashSet::new(),
                 session_store,
+                mcp_config,
                 active_prompt: None,
             },
         );
@@ -342,26 +764,128 @@ impl BackendDriver for ClaudeCodeDriver {
 
     async fn load_session(
         &self,
-        _request: LoadSessionRequest,
+        request: LoadSessionRequest,
     ) -> Result<LoadSessionResponse, Error> {
-        Err(Error::invalid_params().data(
-            "load_session is not supported for --backend=claude-code yet (sessions are in-memory).",
-        ))
+        let LoadSessionRequest {
+            session_id,
+            cwd,
+            mcp_servers,
+            ..
+        } = request;
+        let (turns, model) = self.restore_session(&session_id, cwd, &mcp_servers)?;
+        info!(
+            "Loaded Claude session {session_id:?} ({} persisted turns)",
+            turns.len()
+        );
+        replay_turns(&session_id, &turns).await;
+        Ok(LoadSessionResponse::new().config_options(Self::config_options(model)))
+    }
+
+    async fn fork_session(
+        &self,
+        request: ForkSessionRequest,
+    ) -> Result<ForkSessionResponse, Error> {
+        let ForkSessionRequest {
+            session_id: source_id,
+            cwd,
+            mcp_servers,
+            ..
+        } = request;
+        // A source that is not loaded is only read; the fork must not register it.
+        let in_memory = self.sessions.borrow().get(&source_id).map(|source| {
+            (
+                source.history.clone(),
+                source.model.clone(),
+                source.native_session_id.clone(),
+            )
+        });
+        let (history, model, native_session_id) = match in_memory {
+            Some(source) => source,
+            None => {
+                let persisted = self.read_persisted_session(&source_id)?;
+                (
+                    persisted.history,
+                    persisted.model,
+                    persisted.native_session_id,
+                )
+            }
+        };
+
+        let fork_id = SessionId::new(format!("claude:{}", Uuid::new_v4()));
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::ClaudeCode,
+            BackendKind::ClaudeCode.as_str(),
+            &fork_id,
+            &cwd,
+        );
+        let mcp_config =
+            Self::write_mcp_config(&fork_id, session_store.as_ref(), &mcp_servers, &cwd);
+        // Seed the fork's own log so it can be loaded even before its first turn.
+        if let Some(store) = &session_store {
+            for (user, assistant) in &history {
+                store.log(CanonicalEvent::prompt_text(user.as_str()));
+                store.log(CanonicalEvent::agent_message(assistant.as_str()));
+            }
+        }
+        self.sessions.borrow_mut().insert(
+            fork_id.clone(),
+            ClaudeSession {
+                cwd,
+                model: model.clone(),
+                history,
+                fork_pending: native_session_id.is_some(),
+                native_session_id,
+                always_allowed: HashSet::new(),
+                session_store,
+                mcp_config,
+                active_prompt: None,
+            },
+        );
+
+        info!("Forked Claude session {source_id:?} into {fork_id:?}");
+        Ok(ForkSessionResponse::new(fork_id).config_options(Self::config_options(model)))
+    }
+
+    async fn resume_session(
+        &self,
+        request: ResumeSessionRequest,
+    ) -> Result<ResumeSessionResponse, Error> {
+        let ResumeSessionRequest {
+            session_id,
+            cwd,
+            mcp_servers,
+            ..
+        } = request;
+        let in_memory_model = {
+            let mut sessions = self.sessions.borrow_mut();
+            sessions.get_mut(&session_id).map(|session| {
+                session.mcp_config = Self::write_mcp_config(
+                    &session_id,
+                    session.session_store.as_ref(),
+                    &mcp_servers,
+                    &session.cwd,
+                );
+                session.model.clone()
+            })
+        };
+        let model = match in_memory_model {
+            Some(model) => model,
+            None => self.restore_session(&session_id, cwd, &mcp_servers)?.1,
+        };
+        Ok(ResumeSessionResponse::new().config_options(Self::config_options(model)))
     }
 
     async fn list_sessions(
         &self,
-        _request: ListSessionsRequest,
+        request: ListSessionsRequest,
     ) -> Result<ListSessionsResponse, Error> {
-        let sessions = self
-            .sessions
-            .borrow()
-            .iter()
-            .map(|(id, s)| {
-                SessionInfo::new(id.clone(), s.cwd.clone()).title("Claude Code (in-memory)")
-            })
-            .collect::<Vec<_>>();
-        Ok(ListSessionsResponse::new(sessions))
+        Ok(list_cli_sessions(
+            BackendKind::ClaudeCode.as_str(),
+            &self.sessions.borrow(),
+            request.cwd.as_deref(),
+            "Claude Code (in-memory)",
+        ))
     }
 
     async fn prompt(&self, request: PromptRequest) -> Result<PromptResponse, Error> {
@@ -377,27 +901,30 @@ impl BackendDriver for ClaudeCodeDriver {
             if let Some(session) = sessions.get(&session_id)
                 && let Some(store) = &session.session_store
             {
-                store.log("acp.prompt", serde_json::json!({ "text": user_text }));
+                store.log(CanonicalEvent::prompt_text(user_text.as_str()));
             }
         }
 
-        if let Some(command) = parse_claude_command(&user_text) {
+        if let Some(command) = parse_cli_command(&user_text) {
             let message = {
                 let mut sessions = self.sessions.borrow_mut();
                 let Some(session) = sessions.get_mut(&session_id) else {
                     return Err(Error::resource_not_found(None));
                 };
                 match command {
-                    ClaudeCommand::Help => Self::help_text(),
-                    ClaudeCommand::Status => {
+                    CliCommand::Help => Self::help_text(),
+                    CliCommand::Status => {
                         let model = session.model.as_deref().unwrap_or("default");
                         Self::status_text(model, session.history.len())
                     }
-                    ClaudeCommand::Reset => {
+                    CliCommand::Reset => {
                         session.history.clear();
+                        session.native_session_id = None;
+                        session.fork_pending = false;
+                        self.remember_native_session_id(&session_id, None);
                         "Claude session history has been reset.".to_string()
                     }
-                    ClaudeCommand::SetModel(model) => {
+                    CliCommand::SetModel(model) => {
                         let normalized = if model == "default" {
                             None
                         } else {
@@ -414,17 +941,14 @@ impl BackendDriver for ClaudeCodeDriver {
                 if let Some(session) = sessions.get(&session_id)
                     && let Some(store) = &session.session_store
                 {
-                    store.log(
-                        "acp.agent_message_chunk",
-                        serde_json::json!({ "text": message }),
-                    );
+                    store.log(CanonicalEvent::agent_message(message.as_str()));
                 }
             }
             send_agent_text(&session_id, message).await;
             return Ok(PromptResponse::new(StopReason::EndTurn));
         }
 
-        let (cwd, model, full_prompt, cancel_rx) = {
+        let (invocation, session_store, cancel_rx, turn_files) = {
             let mut sessions = self.sessions.borrow_mut();
             let Some(session) = sessions.get_mut(&session_id) else {
                 return Err(Error::resource_not_found(None));
@@ -436,38 +960,80 @@ impl BackendDriver for ClaudeCodeDriver {
                 )));
             }
 
-            // Keep a short running transcript to preserve basic continuity.
-            // If needed, users can override this behavior by embedding their own context in the prompt.
-            let mut full_prompt = String::new();
-            for (user, assistant) in session.history.iter().rev().take(6).rev() {
-                full_prompt.push_str("User:\n");
-                full_prompt.push_str(user);
-                full_prompt.push_str("\n\nAssistant:\n");
-                full_prompt.push_str(assistant);
-                full_prompt.push_str("\n\n");
-            }
-            full_prompt.push_str("User:\n");
-            full_prompt.push_str(&user_text);
+            // Claude reads attached images and files from disk with its Read tool.
+            let attachments = PromptAttachments::write(&session_id, &request.prompt);
+            let cli_text = match &attachments {
+                Some(attachments) => {
+                    prompt_blocks_with_attachments(&request.prompt, attachments, |attachment| {
+                        format!(
+                            "[attached {}: {} (read this file to view it)]",
+                            attachment.mime_type,
+                            attachment.path.display()
+                        )
+                    })
+                }
+                None => user_text.clone(),
+            };
+            // Claude keeps its own conversation (including tool history) per CLI session, so
+            // later turns resume it; the transcript is only a fallback when no id is known.
+            let prompt = match &session.native_session_id {
+                Some(_) => cli_text,
+                None => transcript_prompt(&session.history, &cli_text, TRANSCRIPT_TURNS),
+            };
+            let prompt = with_context_handoff(&request, prompt);
+            let mcp_config = McpConfigFile::write(session.mcp_config.as_ref());
+            let invocation = ClaudeInvocation {
+                cwd: session.cwd.clone(),
+                model: session.model.clone(),
+                prompt,
+                mcp_config: mcp_config
+                    .as_ref()
+                    .map(|config| config.path().to_path_buf()),
+                attachment_dir: attachments
+                    .as_ref()
+                    .map(|attachments| attachments.dir().to_path_buf()),
+                resume_session_id: session.native_session_id.clone(),
+                fork_session: session.fork_pending,
+            };
 
             let (cancel_tx, cancel_rx) = watch::channel(false);
             session.active_prompt = Some(cancel_tx);
 
             (
-                session.cwd.clone(),
-                session.model.clone(),
-                full_prompt,
+                invocation,
+                session.session_store.clone(),
                 cancel_rx,
+                (attachments, mcp_config),
             )
         };
 
-        let output = self.run_claude(cwd, model, full_prompt, cancel_rx).await;
+        let resumed = invocation.resume_session_id.is_some();
+        let output = self
+            .run_claude(&session_id, session_store, invocation, cancel_rx)
+            .await;
+        // The turn is over; remove its attachments and MCP config.
+        drop(turn_files);
+        // `Some(None)` forgets a CLI session that no longer exists; `None` leaves it as is.
+        let native_update = match &output {
+            Ok((_, native_session_id)) => native_session_id.clone().map(Some),
+            Err(err) if resumed && Self::is_stale_resume_error(err) => {
+                warn!("Claude CLI session for {session_id} is gone; falling back to transcript");
+                Some(None)
+            }
+            Err(_) => None,
+        };
         {
             let mut sessions = self.sessions.borrow_mut();
             if let Some(session) = sessions.get_mut(&session_id) {
                 session.active_prompt = None;
+                if let Some(native_session_id) = native_update {
+                    self.remember_native_session_id(&session_id, native_session_id.as_deref());
+                    session.native_session_id = native_session_id;
+                    session.fork_pending = false;
+                }
             }
         }
-        let output = output?;
+        let (output, _) = output?;
         if output.cancelled {
             debug!(
                 "Claude prompt cancelled for session {} (exit {:?})",
@@ -476,19 +1042,8 @@ impl BackendDriver for ClaudeCodeDriver {
             return Ok(PromptResponse::new(StopReason::Cancelled));
         }
 
-        let output_text = output.stdout.trim_end_matches('\n').to_string();
-        {
-            let sessions = self.sessions.borrow();
-            if let Some(session) = sessions.get(&session_id)
-                && let Some(store) = &session.session_store
-            {
-                store.log(
-                    "acp.agent_message_chunk",
-                    serde_json::json!({ "text": output_text }),
-                );
-            }
-        }
-        send_agent_text(&session_id, &output_text).await;
+        // Updates were already streamed (and logged) while the CLI was running.
+        let output_text = output.output_text.trim_end_matches('\n').to_string();
 
         {
             let mut sessions = self.sessions.borrow_mut();
@@ -502,14 +1057,7 @@ impl BackendDriver for ClaudeCodeDriver {
     }
 
     async fn cancel(&self, args: CancelNotification) -> Result<(), Error> {
-        let sessions = self.sessions.borrow();
-        let Some(session) = sessions.get(&args.session_id) else {
-            return Err(Error::resource_not_found(None));
-        };
-        if let Some(cancel_tx) = &session.active_prompt {
-            let _ = cancel_tx.send(true);
-        }
-        Ok(())
+        cancel_cli_prompt(&self.sessions.borrow(), &args.session_id)
     }
 
     async fn set_session_mode(
@@ -563,29 +1111,122 @@ impl BackendDriver for ClaudeCodeDriver {
 }
 
 impl ClaudeCodeDriver {
-    fn init_session_store(&self, session_id: &SessionId, cwd: &Path) -> Option<SessionStore> {
+    fn stored_native_session_id(&self, session_id: &SessionId) -> Option<String> {
         let idx = self.global_session_index.as_ref()?;
-        let global_id = idx
-            .lock()
+        idx.lock()
             .ok()
-            .and_then(|mut i| i.get_or_create(&format!("claude:{}", session_id.0)))?;
-        SessionStore::init(
-            global_id,
-            "claude-code",
-            session_id.0.to_string(),
-            session_id.0.to_string(),
-            Some(cwd),
-        )
+            .and_then(|i| i.get(&BackendKind::ClaudeCode.native_session_index_key(session_id)))
+    }
+
+    fn remember_native_session_id(&self, session_id: &SessionId, native_session_id: Option<&str>) {
+        let Some(idx) = self.global_session_index.as_ref() else {
+            return;
+        };
+        let Ok(mut idx) = idx.lock() else {
+            return;
+        };
+        let key = BackendKind::ClaudeCode.native_session_index_key(session_id);
+        match native_session_id {
+            Some(native_session_id) => idx.insert(&key, native_session_id),
+            None => idx.remove(&key),
+        }
+    }
+
+    /// `--resume` of a CLI session that no longer exists; the CLI prints
+    /// [`STALE_RESUME_STDERR`] and exits with status 1 before streaming anything.
+    fn is_stale_resume_error(err: &Error) -> bool {
+        err.data
+            .as_ref()
+            .and_then(Value::as_str)
+            .is_some_and(|data| {
+                data.starts_with("Claude CLI failed (exit Some(1))")
+                    && data.contains(STALE_RESUME_STDERR)
+            })
+    }
+
+    /// History, model and Claude CLI session id of a persisted session, without loading it.
+    fn read_persisted_session(&self, session_id: &SessionId) -> Result<PersistedSession, Error> {
+        let Some(global_id) = stored_global_id(
+            self.global_session_index.as_deref(),
+            BackendKind::ClaudeCode,
+            session_id,
+        ) else {
+            return Err(Error::resource_not_found(Some(session_id.to_string())));
+        };
+        let turns = turns_from_canonical(&SessionStore::read_canonical(&global_id));
+        let (history, model) = restore_turns(&turns, Self::default_model());
+        Ok(PersistedSession {
+            turns,
+            history,
+            model,
+            native_session_id: self.stored_native_session_id(session_id),
+        })
+    }
+
+    /// Rebuild a persisted session (history, model and Claude CLI session id) into memory.
+    /// Returns the persisted turns and the restored model.
+    fn restore_session(
+        &self,
+        session_id: &SessionId,
+        cwd: PathBuf,
+        mcp_servers: &[McpServer],
+    ) -> Result<(Vec<(String, String)>, Option<String>), Error> {
+        let PersistedSession {
+            turns,
+            history,
+            model,
+            native_session_id,
+        } = self.read_persisted_session(session_id)?;
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::ClaudeCode,
+            BackendKind::ClaudeCode.as_str(),
+            session_id,
+            &cwd,
+        );
+        let mcp_config =
+            Self::write_mcp_config(session_id, session_store.as_ref(), mcp_servers, &cwd);
+        self.sessions.borrow_mut().insert(
+            session_id.clone(),
+            ClaudeSession {
+                cwd,
+                model: model.clone(),
+                history,
+                native_session_id,
+                fork_pending: false,
+                always_allowed: HashSet::new(),
+                session_store,
+                mcp_config,
+                active_prompt: None,
+            },
+        );
+        Ok((turns, model))
+    }
+
+    fn write_mcp_config(
+        session_id: &SessionId,
+        session_store: Option<&SessionStore>,
+        mcp_servers: &[McpServer],
+        cwd: &Path,
+    ) -> Option<Value> {
+        McpConfigFormat::Claude.session_config(session_id, session_store, mcp_servers, cwd, None)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ClaudeCodeDriver;
+    use super::{ClaudeCodeDriver, ClaudeStreamTranslator};
     use crate::backend::BackendDriver;
+    use crate::canonical_event::CanonicalEvent;
+    use crate::claude_permission::{PermissionCheck, PermissionDecision};
+    use crate::cli_common::StreamTranslator;
+    use crate::session_store::SessionStore;
     use agent_client_protocol::{
-        AuthenticateRequest, CancelNotification, NewSessionRequest, PromptRequest,
-        SessionConfigKind, SetSessionConfigOptionRequest, SetSessionModelRequest, StopReason,
+        AuthenticateRequest, CancelNotification, ForkSessionRequest, ListSessionsRequest,
+        LoadSessionRequest, McpServer, McpServerStdio, NewSessionRequest, PlanEntryStatus,
+        PromptRequest, ResumeSessionRequest, SessionConfigKind, SessionUpdate,
+        SetSessionConfigOptionRequest, SetSessionModelRequest, StopReason, ToolCallStatus,
+        ToolKind,
     };
     use std::{
         fs,
@@ -632,6 +1273,65 @@ mod tests {
         }
     }
 
+    /// Fake CLI that records its arguments and reports a fixed CLI session id; resuming
+    /// `stale-native` fails the way the real CLI does for a deleted session.
+    #[cfg(unix)]
+    fn install_recording_claude_bin() -> (TempEnvVar, PathBuf) {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xsfire-claude-resume-{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        let script_path = temp_dir.join("claude");
+        let args_path = temp_dir.join("args.log");
+        let script = format!(
+            r#"#!/bin/sh
+printf '%s\n' "$*" >> '{}'
+case "$*" in
+  *"--resume stale-native"*)
+    printf '%s\n' 'No conversation found with session ID: stale-native' >&2
+    exit 1
+    ;;
+esac
+printf '%s\n' '{{"type":"system","subtype":"init","session_id":"native-1"}}'
+printf '%s\n' '{{"type":"assistant","message":{{"content":[{{"type":"text","text":"ok"}}]}}}}'
+printf '%s\n' '{{"type":"result","subtype":"success","is_error":false,"result":"ok","session_id":"native-1"}}'
+"#,
+            args_path.display()
+        );
+        fs::write(&script_path, script).unwrap();
+        let mut permissions = fs::metadata(&script_path).unwrap().permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(&script_path, permissions).unwrap();
+
+        let previous = std::env::var("XSFIRE_CLAUDE_BIN").ok();
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            std::env::set_var("XSFIRE_CLAUDE_BIN", &script_path);
+        }
+        (
+            TempEnvVar {
+                key: "XSFIRE_CLAUDE_BIN",
+                previous,
+                temp_dir,
+            },
+            args_path,
+        )
+    }
+
+    fn install_temp_acp_home() -> TempEnvVar {
+        let temp_dir = std::env::temp_dir().join(format!("xsfire-claude-acp-{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        let previous = std::env::var("ACP_HOME").ok();
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            std::env::set_var("ACP_HOME", &temp_dir);
+        }
+        TempEnvVar {
+            key: "ACP_HOME",
+            previous,
+            temp_dir,
+        }
+    }
+
     fn write_fake_claude_script(temp_dir: &Path) -> PathBuf {
         #[cfg(windows)]
         let script_path = temp_dir.join("claude.cmd");
@@ -848,7 +1548,7 @@ printf '%s\n' 'fake claude output'
         assert!(help.contains("Claude commands:"));
         assert!(help.contains("Work orchestration profile: Claude Code"));
         assert!(help.contains("Goal/Rubric/Next Action"));
-        assert!(help.contains("single ACP message chunk only"));
+        assert!(help.contains("live ACP plan/tool updates available"));
     }
 
     #[test]
@@ -862,4 +1562,422 @@ printf '%s\n' 'fake claude output'
         assert!(status.contains("- preempt_on_new_prompt: off"));
         assert!(status.contains("- work_orchestration_sequence: R->P->M->W->A"));
     }
+
+    #[test]
+    fn stream_translator_maps_claude_events_to_acp_updates() {
+        let mut translator = ClaudeStreamTranslator::default();
+
+        let init = translator.handle_line(
+            r#"{"type":"system","subtype":"init","session_id":"5f1c0d2e","tools":[]}"#,
+        );
+        assert!(init.is_empty());
+        assert_eq!(translator.session_id.as_deref(), Some("5f1c0d2e"));
+
+        let opening = translator.handle_line(
+            r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"check the tree"},{"type":"text","text":"Looking"}]}}"#,
+        );
+        assert!(matches!(
+            opening.as_slice(),
+            [
+                SessionUpdate::AgentThoughtChunk(_),
+                SessionUpdate::AgentMessageChunk(_)
+            ]
+        ));
+
+        let tool = translator.handle_line(
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"ls"}}]}}"#,
+        );
+        match tool.as_slice() {
+            [SessionUpdate::ToolCall(call)] => {
+                assert_eq!(call.tool_call_id.0.as_ref(), "toolu_1");
+                assert_eq!(call.kind, ToolKind::Execute);
+                assert_eq!(call.status, ToolCallStatus::InProgress);
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+
+        let result = translator.handle_line(
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"Cargo.toml","is_error":false}]}}"#,
+        );
+        match result.as_slice() {
+            [SessionUpdate::ToolCallUpdate(update)] => {
+                assert_eq!(update.tool_call_id.0.as_ref(), "toolu_1");
+                assert_eq!(update.fields.status, Some(ToolCallStatus::Completed));
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+
+        translator.handle_line(
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}"#,
+        );
+        let summary = translator.handle_line(
+            r#"{"type":"result","subtype":"success","is_error":false,"result":"Done"}"#,
+        );
+        assert!(summary.is_empty());
+        assert_eq!(translator.output_text, "Looking\n\nDone");
+        assert!(translator.error.is_none());
+    }
+
+    #[test]
+    fn stream_translator_turns_todo_write_into_plan() {
+        let mut translator = ClaudeStreamTranslator::default();
+
+        let plan = translator.handle_line(
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_todo","name":"TodoWrite","input":{"todos":[{"content":"Read code","status":"completed","activeForm":"Reading code"},{"content":"Write fix","status":"in_progress","activeForm":"Writing fix"}]}}]}}"#,
+        );
+        match plan.as_slice() {
+            [SessionUpdate::Plan(plan)] => {
+                assert_eq!(plan.entries.len(), 2);
+                assert_eq!(plan.entries[0].status, PlanEntryStatus::Completed);
+                assert_eq!(plan.entries[1].status, PlanEntryStatus::InProgress);
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+
+        let todo_result = translator.handle_line(
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_todo","content":"ok"}]}}"#,
+        );
+        assert!(todo_result.is_empty());
+
+        let failure = translator.handle_line(
+            r#"{"type":"result","subtype":"error_during_execution","is_error":true,"result":"boom"}"#,
+        );
+        assert!(failure.is_empty());
+        assert_eq!(
+            translator.error.as_deref(),
+            Some("error_during_execution: boom")
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn load_session_restores_persisted_history_and_lists_by_cwd() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+        let cwd = std::env::current_dir().unwrap();
+
+        let session_id = {
+            let driver = ClaudeCodeDriver::new();
+            let session = driver
+                .new_session(NewSessionRequest::new(cwd.clone()))
+                .await
+                .unwrap();
+            let sessions = driver.sessions.borrow();
+            let store = sessions
+                .get(&session.session_id)
+                .and_then(|s| s.session_store.clone())
+                .expect("ACP_HOME should enable the canonical log");
+            for event in [
+                CanonicalEvent::prompt_text("explain the build"),
+                CanonicalEvent::agent_message("It uses cargo."),
+                CanonicalEvent::prompt_text("/model opus"),
+                CanonicalEvent::agent_message("Claude model set to `opus`."),
+            ] {
+                store.log(event);
+            }
+            session.session_id
+        };
+
+        // A fresh driver simulates an editor restart.
+        let driver = ClaudeCodeDriver::new();
+        assert!(driver.supports_load_session());
+
+        let listed = driver
+            .list_sessions(ListSessionsRequest::new().cwd(cwd.clone()))
+            .await
+            .unwrap();
+        assert_eq!(listed.sessions.len(), 1);
+        assert_eq!(listed.sessions[0].session_id, session_id);
+        assert_eq!(
+            listed.sessions[0].title.as_deref(),
+            Some("explain the build")
+        );
+        let other_cwd = driver
+            .list_sessions(ListSessionsRequest::new().cwd(cwd.join("elsewhere")))
+            .await
+            .unwrap();
+        assert!(other_cwd.sessions.is_empty());
+
+        driver
+            .load_session(LoadSessionRequest::new(session_id.clone(), cwd))
+            .await
+            .unwrap();
+        let sessions = driver.sessions.borrow();
+        let session = sessions.get(&session_id).unwrap();
+        assert_eq!(
+            session.history,
+            vec![(
+                "explain the build".to_string(),
+                "It uses cargo.".to_string()
+            )]
+        );
+        assert_eq!(session.model.as_deref(), Some("opus"));
+        assert!(session.session_store.is_some());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn load_session_rejects_unknown_session() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+
+        let driver = ClaudeCodeDriver::new();
+        let cwd = std::env::current_dir().unwrap();
+        let result = driver
+            .load_session(LoadSessionRequest::new("claude:missing", cwd))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "current_thread")]
+    async fn prompts_resume_the_native_claude_session_and_forks_from_it() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+        let (_bin, args_path) = install_recording_claude_bin();
+        let cwd = std::env::current_dir().unwrap();
+        let recorded_args = || {
+            fs::read_to_string(&args_path)
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let session_id = {
+            let driver = ClaudeCodeDriver::new();
+            assert!(driver.supports_fork_session());
+            assert!(driver.supports_resume_session());
+            let session_id = driver
+                .new_session(NewSessionRequest::new(cwd.clone()))
+                .await
+                .unwrap()
+                .session_id;
+
+            for text in ["hello", "again"] {
+                driver
+                    .prompt(PromptRequest::new(session_id.clone(), vec![text.into()]))
+                    .await
+                    .unwrap();
+            }
+            let args = recorded_args();
+            assert!(!args[0].contains("--resume"));
+            assert!(args[0].ends_with(" hello"));
+            assert!(args[1].contains("--resume native-1"));
+            // The CLI session already holds the history, so only the new turn is sent.
+            assert!(args[1].ends_with(" again"));
+            assert!(!args[1].contains("User:"));
+
+            let fork_id = driver
+                .fork_session(ForkSessionRequest::new(session_id.clone(), cwd.clone()))
+                .await
+                .unwrap()
+                .session_id;
+            assert_ne!(fork_id, session_id);
+            driver
+                .prompt(PromptRequest::new(fork_id.clone(), vec!["branch".into()]))
+                .await
+                .unwrap();
+            assert!(recorded_args()[2].contains("--resume native-1 --fork-session"));
+            let sessions = driver.sessions.borrow();
+            let fork = sessions.get(&fork_id).unwrap();
+            assert_eq!(fork.history.len(), 3);
+            assert!(!fork.fork_pending);
+            session_id
+        };
+
+        // Forking a session that is not loaded reads its log without loading it.
+        let driver = ClaudeCodeDriver::new();
+        let fork_id = driver
+            .fork_session(ForkSessionRequest::new(session_id.clone(), cwd.clone()))
+            .await
+            .unwrap()
+            .session_id;
+        {
+            let sessions = driver.sessions.borrow();
+            assert!(!sessions.contains_key(&session_id));
+            let fork = sessions.get(&fork_id).unwrap();
+            assert_eq!(fork.history.len(), 2);
+            assert_eq!(fork.native_session_id.as_deref(), Some("native-1"));
+        }
+
+        // After a restart the CLI session id comes back from the session index.
+        let driver = ClaudeCodeDriver::new();
+        driver
+            .resume_session(ResumeSessionRequest::new(session_id.clone(), cwd))
+            .await
+            .unwrap();
+        let sessions = driver.sessions.borrow();
+        let session = sessions.get(&session_id).unwrap();
+        assert_eq!(session.native_session_id.as_deref(), Some("native-1"));
+        assert_eq!(session.history.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "current_thread")]
+    #[allow(clippy::await_holding_lock)]
+    async fn a_deleted_claude_session_falls_back_to_the_transcript() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+        let (_bin, args_path) = install_recording_claude_bin();
+        let cwd = std::env::current_dir().unwrap();
+
+        let driver = ClaudeCodeDriver::new();
+        let session_id = driver
+            .new_session(NewSessionRequest::new(cwd))
+            .await
+            .unwrap()
+            .session_id;
+        driver
+            .sessions
+            .borrow_mut()
+            .get_mut(&session_id)
+            .unwrap()
+            .native_session_id = Some("stale-native".to_string());
+
+        let err = driver
+            .prompt(PromptRequest::new(session_id.clone(), vec!["hello".into()]))
+            .await
+            .unwrap_err();
+        assert!(ClaudeCodeDriver::is_stale_resume_error(&err), "{err:?}");
+        assert_eq!(
+            driver.sessions.borrow()[&session_id].native_session_id,
+            None
+        );
+
+        driver
+            .prompt(PromptRequest::new(session_id.clone(), vec!["again".into()]))
+            .await
+            .unwrap();
+        let args = fs::read_to_string(&args_path).unwrap();
+        assert!(!args.lines().nth(1).unwrap().contains("--resume"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "current_thread")]
+    async fn client_mcp_servers_are_passed_to_every_claude_run() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+        let (_bin, args_path) = install_recording_claude_bin();
+        let cwd = std::env::current_dir().unwrap();
+
+        let driver = ClaudeCodeDriver::new();
+        let session_id = driver
+            .new_session(
+                NewSessionRequest::new(cwd).mcp_servers(vec![McpServer::Stdio(
+                    McpServerStdio::new("files", "/usr/bin/files-mcp"),
+                )]),
+            )
+            .await
+            .unwrap()
+            .session_id;
+        let config = driver.sessions.borrow()[&session_id]
+            .mcp_config
+            .clone()
+            .unwrap();
+        assert_eq!(config["mcpServers"]["files"]["type"], "stdio");
+        assert_eq!(
+            config["mcpServers"]["files"]["command"],
+            "/usr/bin/files-mcp"
+        );
+
+        for text in ["one", "two"] {
+            driver
+                .prompt(PromptRequest::new(session_id.clone(), vec![text.into()]))
+                .await
+                .unwrap();
+        }
+        let args = fs::read_to_string(&args_path).unwrap();
+        assert_eq!(args.lines().count(), 2);
+        for line in args.lines() {
+            let config_path = line
+                .split_once("--mcp-config ")
+                .and_then(|(_, rest)| rest.split_whitespace().next())
+                .unwrap_or_else(|| panic!("{line}"));
+            // Each run writes its own config file and removes it afterwards.
+            assert!(!Path::new(config_path).exists(), "{config_path}");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn permission_checks_are_logged_and_always_approvals_stick() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _acp_home = install_temp_acp_home();
+        let driver = ClaudeCodeDriver::new();
+        let cwd = std::env::current_dir().unwrap();
+        let session_id = driver
+            .new_session(NewSessionRequest::new(cwd))
+            .await
+            .unwrap()
+            .session_id;
+        let store = driver
+            .sessions
+            .borrow()
+            .get(&session_id)
+            .and_then(|session| session.session_store.clone())
+            .unwrap();
+        let check: PermissionCheck = serde_json::from_value(serde_json::json!({
+            "token": "t",
+            "tool_name": "Bash",
+            "input": { "command": "cargo test" },
+            "tool_use_id": "toolu_1",
+        }))
+        .unwrap();
+
+        // Without a connected ACP client the check is denied rather than silently allowed.
+        let decision = driver
+            .decide_permission(&session_id, Some(&store), &check)
+            .await;
+        assert!(matches!(decision, PermissionDecision::Deny(_)));
+        let kinds = SessionStore::read_canonical(store.global_session_id())
+            .into_iter()
+            .map(|record| record.kind)
+            .collect::<Vec<_>>();
+        assert!(kinds.contains(&"acp.request_permission".to_string()));
+
+        driver
+            .sessions
+            .borrow_mut()
+            .get_mut(&session_id)
+            .unwrap()
+            .always_allowed
+            .insert(check.session_key());
+        assert_eq!(
+            driver
+                .decide_permission(&session_id, Some(&store), &check)
+                .await,
+            PermissionDecision::Allow { always: true }
+        );
+
+        // "Always" covers the approved command only; another Bash command is asked again.
+        let other: PermissionCheck = serde_json::from_value(serde_json::json!({
+            "token": "t",
+            "tool_name": "Bash",
+            "input": { "command": "rm -rf target" },
+            "tool_use_id": "toolu_2",
+        }))
+        .unwrap();
+        assert!(matches!(
+            driver
+                .decide_permission(&session_id, Some(&store), &other)
+                .await,
+            PermissionDecision::Deny(_)
+        ));
+    }
 }
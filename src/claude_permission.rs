@@ -0,0 +1,430 @@
+//! Permission prompt bridge for the Claude Code backend.
+//!
+//! Claude's `--permission-prompt-tool` delegates each tool permission check to an MCP tool.
+//! The driver registers a tiny stdio MCP server (this binary, run as
+//! `xsfire-camp claude-permission-mcp`) which relays every check over a loopback socket
+//! back to the running bridge, where it is surfaced as an ACP `request_permission`.
+
+use agent_client_protocol::{
+    PermissionOption, PermissionOptionKind, RequestPermissionOutcome, SelectedPermissionOutcome,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{io, net::SocketAddr};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use uuid::Uuid;
+
+use crate::mcp_config::McpConfigFile;
+
+/// Subcommand that runs the stdio MCP permission server.
+pub const PERMISSION_MCP_SUBCOMMAND: &str = "claude-permission-mcp";
+
+const MCP_SERVER_NAME: &str = "xsfire_permission";
+const MCP_TOOL_NAME: &str = "approve";
+const ADDR_ENV: &str = "XSFIRE_PERMISSION_ADDR";
+const TOKEN_ENV: &str = "XSFIRE_PERMISSION_TOKEN";
+const DEFAULT_MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Fully qualified tool name passed to `claude --permission-prompt-tool`.
+pub(crate) fn permission_prompt_tool() -> String {
+    format!("mcp__{MCP_SERVER_NAME}__{MCP_TOOL_NAME}")
+}
+
+/// One permission check relayed from the MCP server to the bridge.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PermissionCheck {
+    token: String,
+    pub tool_name: String,
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default)]
+    pub tool_use_id: Option<String>,
+}
+
+impl PermissionCheck {
+    /// Key under which an "Always" approval is remembered for the session.
+    ///
+    /// Like Codex's `approved-for-session`, an approval covers the same call only: `Bash` is
+    /// keyed on its whitespace-normalized command, edit tools on the file they touch, and any
+    /// other tool on its full input.
+    pub fn session_key(&self) -> String {
+        let field = |name: &str| self.input.get(name).and_then(Value::as_str);
+        let target = match self.tool_name.as_str() {
+            "Bash" => field("command")
+                .map(|command| command.split_whitespace().collect::<Vec<_>>().join(" ")),
+            "Write" | "Edit" | "MultiEdit" => field("file_path").map(str::to_string),
+            "NotebookEdit" => field("notebook_path").map(str::to_string),
+            _ => None,
+        };
+        let target = target.unwrap_or_else(|| self.input.to_string());
+        format!("{}:{target}", self.tool_name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PermissionDecision {
+    Allow { always: bool },
+    Deny(String),
+}
+
+impl PermissionDecision {
+    pub fn from_outcome(outcome: &RequestPermissionOutcome) -> Self {
+        match outcome {
+            RequestPermissionOutcome::Selected(SelectedPermissionOutcome { option_id, .. }) => {
+                match option_id.0.as_ref() {
+                    "approved-for-session" => Self::Allow { always: true },
+                    "approved" => Self::Allow { always: false },
+                    _ => Self::Deny("The user rejected this tool call.".to_string()),
+                }
+            }
+            RequestPermissionOutcome::Cancelled | _ => {
+                Self::Deny("The permission request was cancelled.".to_string())
+            }
+        }
+    }
+
+    /// Payload expected by Claude from a permission prompt tool.
+    pub fn claude_response(&self, input: &Value) -> Value {
+        match self {
+            Self::Allow { .. } => json!({ "behavior": "allow", "updatedInput": input }),
+            Self::Deny(message) => json!({ "behavior": "deny", "message": message }),
+        }
+    }
+}
+
+/// Options offered to the ACP client, mirroring Codex exec approvals.
+pub(crate) fn permission_options() -> Vec<PermissionOption> {
+    vec![
+        PermissionOption::new(
+            "approved-for-session",
+            "Always",
+            PermissionOptionKind::AllowAlways,
+        ),
+        PermissionOption::new("approved", "Allow once", PermissionOptionKind::AllowOnce),
+        PermissionOption::new("abort", "Reject", PermissionOptionKind::RejectOnce),
+    ]
+}
+
+/// Loopback listener owned by one `claude` invocation.
+pub(crate) struct PermissionListener {
+    listener: TcpListener,
+    addr: SocketAddr,
+    token: String,
+}
+
+impl PermissionListener {
+    pub async fn bind() -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        Ok(Self {
+            listener,
+            addr,
+            token: Uuid::new_v4().to_string(),
+        })
+    }
+
+    /// `--mcp-config` JSON that makes Claude spawn the relay pointed at this listener.
+    pub fn mcp_config(&self, exe: &str) -> Value {
+        json!({
+            "mcpServers": {
+                MCP_SERVER_NAME: {
+                    "command": exe,
+                    "args": [PERMISSION_MCP_SUBCOMMAND],
+                    "env": {
+                        ADDR_ENV: self.addr.to_string(),
+                        TOKEN_ENV: self.token,
+                    },
+                },
+            },
+        })
+    }
+
+    /// [`Self::mcp_config`] written to a private file, so the token never appears on the
+    /// `claude` command line where other local users could read it.
+    pub fn mcp_config_file(&self, exe: &str) -> io::Result<McpConfigFile> {
+        McpConfigFile::create(&self.mcp_config(exe))
+    }
+
+    pub async fn accept(&self) -> io::Result<TcpStream> {
+        self.listener.accept().await.map(|(stream, _)| stream)
+    }
+
+    /// Read the check sent on an accepted connection, rejecting unknown callers.
+    pub async fn read_check(&self, stream: &mut TcpStream) -> io::Result<PermissionCheck> {
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).await?;
+        let check: PermissionCheck = serde_json::from_str(line.trim())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if check.token != self.token {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "permission check with an unknown token",
+            ));
+        }
+        Ok(check)
+    }
+}
+
+pub(crate) async fn reply_check(
+    stream: &mut TcpStream,
+    check: &PermissionCheck,
+    decision: &PermissionDecision,
+) -> io::Result<()> {
+    let mut reply = decision.claude_response(&check.input).to_string();
+    reply.push('\n');
+    stream.write_all(reply.as_bytes()).await?;
+    stream.flush().await
+}
+
+/// Run the stdio MCP server that Claude spawns for `--permission-prompt-tool`.
+pub async fn run_mcp_server() -> io::Result<()> {
+    let addr = std::env::var(ADDR_ENV).unwrap_or_default();
+    let token = std::env::var(TOKEN_ENV).unwrap_or_default();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        let Ok(message) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        if let Some(response) = handle_mcp_message(&message, &addr, &token).await {
+            let mut response = response.to_string();
+            response.push('\n');
+            stdout.write_all(response.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_mcp_message(message: &Value, addr: &str, token: &str) -> Option<Value> {
+    // Notifications (no id) never get a response.
+    let id = message.get("id")?.clone();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match message.get("method").and_then(Value::as_str) {
+        Some("initialize") => json!({
+            "protocolVersion": params
+                .get("protocolVersion")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_MCP_PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": MCP_SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+        }),
+        Some("ping") => json!({}),
+        Some("tools/list") => json!({
+            "tools": [{
+                "name": MCP_TOOL_NAME,
+                "description": "Ask the ACP client whether a Claude Code tool call may run.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "tool_name": { "type": "string" },
+                        "input": { "type": "object" },
+                        "tool_use_id": { "type": "string" },
+                    },
+                    "required": ["tool_name", "input"],
+                },
+            }],
+        }),
+        Some("tools/call") => {
+            let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+            let text = match relay_check(addr, token, &arguments).await {
+                Ok(reply) => reply,
+                Err(err) => PermissionDecision::Deny(format!(
+                    "xsfire-camp permission bridge unavailable: {err}"
+                ))
+                .claude_response(&Value::Null)
+                .to_string(),
+            };
+            json!({ "content": [{ "type": "text", "text": text }] })
+        }
+        _ => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": "method not found" },
+            }));
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+async fn relay_check(addr: &str, token: &str, arguments: &Value) -> io::Result<String> {
+    let check = PermissionCheck {
+        token: token.to_string(),
+        tool_name: arguments
+            .get("tool_name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        input: arguments.get("input").cloned().unwrap_or(Value::Null),
+        tool_use_id: arguments
+            .get("tool_use_id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    };
+    let mut request = serde_json::to_string(&check).map_err(io::Error::other)?;
+    request.push('\n');
+
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).await?;
+    if reply.trim().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "bridge closed the connection without a decision",
+        ));
+    }
+    Ok(reply.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PermissionCheck, PermissionDecision, PermissionListener, handle_mcp_message, reply_check,
+    };
+    use agent_client_protocol::{RequestPermissionOutcome, SelectedPermissionOutcome};
+    use serde_json::{Value, json};
+
+    #[test]
+    fn maps_permission_outcomes_to_claude_decisions() {
+        let selected = |id: &str| {
+            RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(id.to_string()))
+        };
+        assert_eq!(
+            PermissionDecision::from_outcome(&selected("approved-for-session")),
+            PermissionDecision::Allow { always: true }
+        );
+        assert_eq!(
+            PermissionDecision::from_outcome(&selected("approved")),
+            PermissionDecision::Allow { always: false }
+        );
+        assert!(matches!(
+            PermissionDecision::from_outcome(&selected("abort")),
+            PermissionDecision::Deny(_)
+        ));
+        assert!(matches!(
+            PermissionDecision::from_outcome(&RequestPermissionOutcome::Cancelled),
+            PermissionDecision::Deny(_)
+        ));
+
+        let input = json!({ "command": "ls" });
+        assert_eq!(
+            PermissionDecision::Allow { always: false }.claude_response(&input),
+            json!({ "behavior": "allow", "updatedInput": { "command": "ls" } })
+        );
+    }
+
+    #[test]
+    fn session_keys_cover_one_command_or_file() {
+        let check = |tool_name: &str, input: Value| -> PermissionCheck {
+            serde_json::from_value(json!({ "token": "t", "tool_name": tool_name, "input": input }))
+                .unwrap()
+        };
+        assert_eq!(
+            check(
+                "Bash",
+                json!({ "command": "cargo  test\n", "description": "Run tests" })
+            )
+            .session_key(),
+            check("Bash", json!({ "command": "cargo test" })).session_key()
+        );
+        assert_ne!(
+            check("Bash", json!({ "command": "cargo test" })).session_key(),
+            check("Bash", json!({ "command": "cargo publish" })).session_key()
+        );
+        assert_eq!(
+            check(
+                "Edit",
+                json!({ "file_path": "/repo/a.rs", "old_string": "x" })
+            )
+            .session_key(),
+            "Edit:/repo/a.rs"
+        );
+        assert_ne!(
+            check("Write", json!({ "file_path": "/repo/a.rs" })).session_key(),
+            check("Write", json!({ "file_path": "/repo/b.rs" })).session_key()
+        );
+        assert_ne!(
+            check("WebFetch", json!({ "url": "https://a.example" })).session_key(),
+            check("WebFetch", json!({ "url": "https://b.example" })).session_key()
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn mcp_tool_call_relays_checks_to_the_listener() {
+        let listener = PermissionListener::bind().await.unwrap();
+        let config = listener.mcp_config("/usr/bin/xsfire-camp");
+        let env = &config["mcpServers"]["xsfire_permission"]["env"];
+        let addr = env["XSFIRE_PERMISSION_ADDR"].as_str().unwrap().to_string();
+        let token = env["XSFIRE_PERMISSION_TOKEN"].as_str().unwrap().to_string();
+
+        let tools = handle_mcp_message(
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+            &addr,
+            &token,
+        )
+        .await
+        .unwrap();
+        assert_eq!(tools["result"]["tools"][0]["name"], "approve");
+        assert!(
+            handle_mcp_message(
+                &json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+                &addr,
+                &token,
+            )
+            .await
+            .is_none()
+        );
+
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {
+                "name": "approve",
+                "arguments": { "tool_name": "Bash", "input": { "command": "ls" }, "tool_use_id": "toolu_1" },
+            },
+        });
+        let bridge = async {
+            let mut stream = listener.accept().await.unwrap();
+            let check = listener.read_check(&mut stream).await.unwrap();
+            assert_eq!(check.tool_name, "Bash");
+            assert_eq!(check.tool_use_id.as_deref(), Some("toolu_1"));
+            reply_check(
+                &mut stream,
+                &check,
+                &PermissionDecision::Allow { always: false },
+            )
+            .await
+            .unwrap();
+        };
+        let (response, ()) = tokio::join!(handle_mcp_message(&call, &addr, &token), bridge);
+        let text = response.unwrap()["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let decision: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(decision["behavior"], "allow");
+        assert_eq!(decision["updatedInput"]["command"], "ls");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn mcp_tool_call_denies_when_the_bridge_is_unreachable() {
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": { "arguments": { "tool_name": "Bash", "input": {} } },
+        });
+        let response = handle_mcp_message(&call, "127.0.0.1:1", "token")
+            .await
+            .unwrap();
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("\"behavior\":\"deny\""));
+    }
+}
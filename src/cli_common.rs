@@ -1,17 +1,67 @@
 use agent_client_protocol::{
-    Client, ContentBlock, ContentChunk, EmbeddedResource, EmbeddedResourceResource, ResourceLink,
-    SessionId, SessionNotification, SessionUpdate, TextContent, TextResourceContents,
+    Client, ContentBlock, ContentChunk, EmbeddedResource, EmbeddedResourceResource, Error,
+    ListSessionsResponse, PermissionOption, RequestPermissionRequest, RequestPermissionResponse,
+    ResourceLink, SessionId, SessionInfo, SessionNotification, SessionUpdate, TextContent,
+    TextResourceContents, ToolCallUpdate,
+};
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::Path,
+    pin::pin,
+    sync::Mutex,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Child,
+    sync::watch,
 };
 use tracing::error;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-    ACP_CLIENT, link_paths::normalize_outgoing_local_markdown_links, resolve_session_alias,
+    ACP_CLIENT,
+    backend::BackendKind,
+    canonical_event::{
+        CanonicalEvent, PermissionRequest, PermissionResponse, PlanItem, PlanStepStatus, PlanUpdate,
+    },
+    link_paths::normalize_outgoing_local_markdown_links,
+    prompt_attachments::{Attachment, PromptAttachments},
+    resolve_session_alias,
+    session_store::{CanonicalRecord, GlobalSessionIndex, SessionStore},
 };
 
+const SESSION_TITLE_MAX_GRAPHEMES: usize = 120;
+
+/// Turns re-sent by [`transcript_prompt`] for the built-in CLI drivers.
+pub const TRANSCRIPT_TURNS: usize = 6;
+
 pub fn prompt_blocks_to_text(blocks: &[ContentBlock]) -> String {
+    render_prompt_blocks(blocks, |_| None)
+}
+
+/// [`prompt_blocks_to_text`] for a CLI run: blocks written to `attachments` are replaced by
+/// `mention(attachment)` instead of a placeholder.
+pub(crate) fn prompt_blocks_with_attachments(
+    blocks: &[ContentBlock],
+    attachments: &PromptAttachments,
+    mention: impl Fn(&Attachment) -> String,
+) -> String {
+    render_prompt_blocks(blocks, |index| attachments.get(index).map(&mention))
+}
+
+fn render_prompt_blocks(
+    blocks: &[ContentBlock],
+    attachment: impl Fn(usize) -> Option<String>,
+) -> String {
     let mut parts: Vec<String> = Vec::new();
 
-    for block in blocks {
+    for (index, block) in blocks.iter().enumerate() {
+        if let Some(mention) = attachment(index) {
+            parts.push(mention);
+            continue;
+        }
         match block {
             ContentBlock::Text(t) => parts.push(t.text.clone()),
             ContentBlock::ResourceLink(ResourceLink { name, uri, .. }) => {
@@ -47,21 +97,598 @@ pub fn prompt_blocks_to_text(blocks: &[ContentBlock]) -> String {
     parts.join("\n")
 }
 
+/// Stitch the last `max_turns` turns of `history` in front of the new prompt for CLIs that
+/// cannot resume their own conversation. Without history the prompt is sent as-is.
+pub fn transcript_prompt(
+    history: &[(String, String)],
+    user_text: &str,
+    max_turns: usize,
+) -> String {
+    if history.is_empty() || max_turns == 0 {
+        return user_text.to_string();
+    }
+    let mut prompt = String::new();
+    for (user, assistant) in history.iter().rev().take(max_turns).rev() {
+        prompt.push_str("User:\n");
+        prompt.push_str(user);
+        prompt.push_str("\n\nAssistant:\n");
+        prompt.push_str(assistant);
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("User:\n");
+    prompt.push_str(user_text);
+    prompt
+}
+
 pub async fn send_agent_text(session_id: &SessionId, text: impl Into<String>) {
+    let text = normalize_outgoing_local_markdown_links(&text.into());
+    send_session_update(
+        session_id,
+        SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(TextContent::new(
+            text,
+        )))),
+    )
+    .await;
+}
+
+pub async fn send_session_update(session_id: &SessionId, update: SessionUpdate) {
     let Some(client) = ACP_CLIENT.get() else {
         return;
     };
     let routed_session_id = resolve_session_alias(session_id);
-    let text = normalize_outgoing_local_markdown_links(&text.into());
-
-    let update = SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(
-        TextContent::new(text),
-    )));
 
     if let Err(err) = client
         .session_notification(SessionNotification::new(routed_session_id, update))
         .await
     {
-        error!("Failed to send agent text: {err:?}");
+        error!("Failed to send session update: {err:?}");
+    }
+}
+
+/// Mirror a streamed CLI update into the canonical log using the same event kinds as the
+/// Codex thread, so every backend produces a comparable timeline.
+pub fn log_session_update(store: Option<&SessionStore>, update: &SessionUpdate) {
+    let Some(store) = store else {
+        return;
+    };
+    match update {
+        SessionUpdate::AgentMessageChunk(chunk) => {
+            if let ContentBlock::Text(text) = &chunk.content {
+                store.log(CanonicalEvent::agent_message(text.text.as_str()));
+            }
+        }
+        SessionUpdate::AgentThoughtChunk(chunk) => {
+            if let ContentBlock::Text(text) = &chunk.content {
+                store.log(CanonicalEvent::agent_thought(text.text.as_str()));
+            }
+        }
+        SessionUpdate::ToolCall(tool_call) => {
+            let value = serde_json::to_value(tool_call)
+                .unwrap_or_else(|_| json!({ "debug": format!("{tool_call:?}") }));
+            store.log(CanonicalEvent::ToolCall(value));
+        }
+        SessionUpdate::ToolCallUpdate(update) => {
+            let value = serde_json::to_value(update)
+                .unwrap_or_else(|_| json!({ "debug": format!("{update:?}") }));
+            store.log(CanonicalEvent::ToolCallUpdate(value));
+        }
+        SessionUpdate::Plan(plan) => {
+            let items = plan
+                .entries
+                .iter()
+                .map(|entry| PlanItem {
+                    step: entry.content.clone(),
+                    // Both sides use the ACP spelling, so the status converts through serde.
+                    status: serde_json::to_value(&entry.status)
+                        .and_then(serde_json::from_value)
+                        .unwrap_or(PlanStepStatus::Pending),
+                })
+                .collect();
+            store.log(CanonicalEvent::Plan(PlanUpdate {
+                items,
+                explanation: None,
+            }));
+        }
+        _ => {}
+    }
+}
+
+/// Log and forward one streamed update for a CLI-backed session.
+///
+/// Agent message text gets the same local link normalization as the Codex thread applies.
+pub async fn emit_session_update(
+    session_id: &SessionId,
+    store: Option<&SessionStore>,
+    mut update: SessionUpdate,
+) {
+    if let SessionUpdate::AgentMessageChunk(ContentChunk {
+        content: ContentBlock::Text(text),
+        ..
+    }) = &mut update
+    {
+        text.text = normalize_outgoing_local_markdown_links(&text.text);
+    }
+    log_session_update(store, &update);
+    send_session_update(session_id, update).await;
+}
+
+/// Ask the ACP client to approve a CLI tool call, logging the request and the outcome as
+/// `acp.request_permission` / `acp.request_permission_response` like the Codex thread does.
+pub async fn request_permission(
+    session_id: &SessionId,
+    store: Option<&SessionStore>,
+    tool_call: ToolCallUpdate,
+    options: Vec<PermissionOption>,
+) -> Result<RequestPermissionResponse, Error> {
+    if let Some(store) = store {
+        store.log(CanonicalEvent::RequestPermission(PermissionRequest {
+            tool_call: serde_json::to_value(&tool_call)
+                .unwrap_or_else(|_| json!({"debug": format!("{tool_call:?}")})),
+            options: serde_json::to_value(&options)
+                .unwrap_or_else(|_| json!({"debug": format!("{options:?}")})),
+        }));
+    }
+    let Some(client) = ACP_CLIENT.get() else {
+        return Err(Error::internal_error().data("ACP client is not connected"));
+    };
+
+    let response = client
+        .request_permission(RequestPermissionRequest::new(
+            resolve_session_alias(session_id),
+            tool_call,
+            options,
+        ))
+        .await?;
+
+    if let Some(store) = store {
+        store.log(CanonicalEvent::RequestPermissionResponse(
+            PermissionResponse {
+                outcome: serde_json::to_value(&response.outcome)
+                    .unwrap_or_else(|_| json!({"debug": format!("{:?}", response.outcome)})),
+            },
+        ));
+    }
+    Ok(response)
+}
+
+/// Slash commands handled by the CLI-backed drivers themselves.
+pub(crate) enum CliCommand {
+    Help,
+    Status,
+    Reset,
+    SetModel(String),
+}
+
+pub(crate) fn parse_cli_command(input: &str) -> Option<CliCommand> {
+    let trimmed = input.trim();
+    if trimmed == "/help" {
+        return Some(CliCommand::Help);
+    }
+    if trimmed == "/status" {
+        return Some(CliCommand::Status);
+    }
+    if trimmed == "/reset" {
+        return Some(CliCommand::Reset);
+    }
+    if let Some(value) = trimmed.strip_prefix("/model ") {
+        let model = value.trim();
+        if !model.is_empty() {
+            return Some(CliCommand::SetModel(model.to_string()));
+        }
+    }
+    None
+}
+
+/// Outcome of one [`run_streaming_cli`] run.
+pub(crate) struct CommandRunResult {
+    pub output_text: String,
+    pub exit_code: Option<i32>,
+    pub cancelled: bool,
+}
+
+/// Backend-specific translation of a CLI's stdout into ACP session updates.
+pub(crate) trait StreamTranslator {
+    fn handle_line(&mut self, line: &str) -> Vec<SessionUpdate>;
+
+    /// Agent message text streamed so far.
+    fn output_text(&self) -> &str;
+
+    /// Error the CLI reported inside its output, which fails the run.
+    fn error(&self) -> Option<&str>;
+}
+
+/// Stream a spawned CLI's stdout through `translator` until it exits or `cancel_rx` fires.
+///
+/// `side_task` runs next to the output stream (Claude answers permission checks there) and
+/// is dropped with the run. A failed exit status or a stream error is returned with stderr.
+pub(crate) async fn run_streaming_cli(
+    name: &str,
+    mut child: Child,
+    session_id: &SessionId,
+    session_store: Option<&SessionStore>,
+    translator: &mut impl StreamTranslator,
+    side_task: impl Future<Output = ()>,
+    mut cancel_rx: watch::Receiver<bool>,
+) -> Result<CommandRunResult, Error> {
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| Error::internal_error().data(format!("{name} stdout pipe missing")))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| Error::internal_error().data(format!("{name} stderr pipe missing")))?;
+
+    let stderr_task = tokio::spawn(async move {
+        let mut buf = Vec::new();
+        stderr
+            .read_to_end(&mut buf)
+            .await
+            .map(|_| buf)
+            .map_err(|e| Error::internal_error().data(e.to_string()))
+    });
+
+    let mut lines = BufReader::new(stdout).lines();
+    let mut side_task = pin!(side_task);
+    let mut side_open = true;
+    let mut cancel_open = true;
+    let mut cancelled = false;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line.map_err(|e| Error::internal_error().data(e.to_string()))? else {
+                    break;
+                };
+                for update in translator.handle_line(&line) {
+                    emit_session_update(session_id, session_store, update).await;
+                }
+            }
+            () = &mut side_task, if side_open => {
+                side_open = false;
+            }
+            changed = cancel_rx.changed(), if cancel_open => {
+                if changed.is_err() {
+                    cancel_open = false;
+                } else if *cancel_rx.borrow() {
+                    drop(child.start_kill());
+                    cancelled = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| Error::internal_error().data(e.to_string()))?;
+    let result = CommandRunResult {
+        output_text: translator.output_text().to_string(),
+        exit_code: status.code(),
+        cancelled,
+    };
+    if cancelled {
+        return Ok(result);
+    }
+
+    let stderr_bytes = stderr_task
+        .await
+        .map_err(|e| Error::internal_error().data(e.to_string()))??;
+    let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
+
+    if !status.success() {
+        return Err(Error::internal_error().data(format!(
+            "{name} failed (exit {:?}). stderr:\n{stderr}",
+            status.code()
+        )));
+    }
+    if let Some(message) = translator.error() {
+        return Err(Error::internal_error().data(format!("{name} reported an error: {message}")));
+    }
+    Ok(result)
+}
+
+/// Rebuild `(user prompt, agent reply)` turns from a CLI session's canonical log.
+///
+/// Every `acp.prompt` opens a turn and the `acp.agent_message_chunk` events that follow
+/// are concatenated into its reply, mirroring what the driver streamed live.
+pub fn turns_from_canonical(records: &[CanonicalRecord]) -> Vec<(String, String)> {
+    let mut turns: Vec<(String, String)> = Vec::new();
+    for record in records {
+        match record.event() {
+            Some(CanonicalEvent::Prompt(prompt)) => turns.push((prompt.text, String::new())),
+            Some(CanonicalEvent::AgentMessageChunk(chunk)) => {
+                if let Some((_, reply)) = turns.last_mut() {
+                    reply.push_str(&chunk.text);
+                }
+            }
+            _ => {}
+        }
+    }
+    for (_, reply) in &mut turns {
+        let trimmed_len = reply.trim_end_matches('\n').len();
+        reply.truncate(trimmed_len);
+    }
+    turns
+}
+
+/// Rebuild prompt history and the selected model from persisted turns, honoring the
+/// slash commands (`/reset`, `/model`) that were issued along the way.
+pub(crate) fn restore_turns(
+    turns: &[(String, String)],
+    default_model: Option<String>,
+) -> (Vec<(String, String)>, Option<String>) {
+    let mut history = Vec::new();
+    let mut model = default_model;
+    for (user, assistant) in turns {
+        match parse_cli_command(user) {
+            Some(CliCommand::Reset) => history.clear(),
+            Some(CliCommand::SetModel(next)) => {
+                model = (next != "default").then_some(next);
+            }
+            Some(_) => {}
+            None => history.push((user.clone(), assistant.clone())),
+        }
+    }
+    (history, model)
+}
+
+/// Replay persisted turns to the client as user/agent message chunks (used by `load_session`).
+pub async fn replay_turns(session_id: &SessionId, turns: &[(String, String)]) {
+    for (user, agent) in turns {
+        send_session_update(
+            session_id,
+            SessionUpdate::UserMessageChunk(ContentChunk::new(ContentBlock::Text(
+                TextContent::new(user.clone()),
+            ))),
+        )
+        .await;
+        if !agent.is_empty() {
+            send_agent_text(session_id, agent.clone()).await;
+        }
+    }
+}
+
+/// Persisted sessions for a CLI backend, newest first, optionally filtered by `cwd`.
+///
+/// Titles come from the first non-command prompt in each canonical log.
+pub fn stored_session_infos(backend: &str, cwd: Option<&Path>) -> Vec<SessionInfo> {
+    let mut sessions = SessionStore::list_stored(backend)
+        .into_iter()
+        .filter_map(|stored| {
+            let session_cwd = stored.cwd?;
+            if cwd.is_some_and(|filter| filter != session_cwd) {
+                return None;
+            }
+            let records = SessionStore::read_canonical(&stored.global_session_id);
+            let last_ts_ms = records
+                .last()
+                .map_or(stored.created_at_ms, |record| record.ts_ms);
+            let title = turns_from_canonical(&records)
+                .into_iter()
+                .find(|(user, _)| !user.trim_start().starts_with('/'))
+                .and_then(|(user, _)| session_title(&user));
+            Some((
+                last_ts_ms,
+                SessionInfo::new(SessionId::new(stored.acp_session_id), session_cwd).title(title),
+            ))
+        })
+        .collect::<Vec<_>>();
+    sessions.sort_by(|a, b| b.0.cmp(&a.0));
+    sessions.into_iter().map(|(_, info)| info).collect()
+}
+
+/// Session state shared by the CLI drivers' session maps.
+/// Per-session state the CLI drivers share for listing and cancellation.
+pub(crate) trait CliSession {
+    fn cwd(&self) -> &Path;
+
+    /// Sender that cancels the running prompt, if any.
+    fn active_prompt(&self) -> Option<&watch::Sender<bool>>;
+}
+
+/// Global id of a persisted `backend` session.
+pub(crate) fn stored_global_id(
+    index: Option<&Mutex<GlobalSessionIndex>>,
+    backend: BackendKind,
+    session_id: &SessionId,
+) -> Option<String> {
+    index?
+        .lock()
+        .ok()
+        .and_then(|index| index.get(&backend.session_index_key(session_id)))
+}
+
+/// Open (or create) the canonical log of a `backend` session; `log_backend` is the backend
+/// name recorded in the log.
+pub(crate) fn init_session_store(
+    index: Option<&Mutex<GlobalSessionIndex>>,
+    backend: BackendKind,
+    log_backend: &str,
+    session_id: &SessionId,
+    cwd: &Path,
+) -> Option<SessionStore> {
+    let global_id = index?
+        .lock()
+        .ok()
+        .and_then(|mut index| index.get_or_create(&backend.session_index_key(session_id)))?;
+    SessionStore::init(
+        global_id,
+        log_backend,
+        session_id.0.to_string(),
+        session_id.0.to_string(),
+        Some(cwd),
+    )
+}
+
+/// Persisted sessions logged as `log_backend`, then the in-memory ones without a canonical
+/// log (e.g. no `ACP_HOME`), titled `in_memory_title`.
+pub(crate) fn list_cli_sessions<S: CliSession>(
+    log_backend: &str,
+    sessions: &HashMap<SessionId, S>,
+    cwd: Option<&Path>,
+    in_memory_title: &str,
+) -> ListSessionsResponse {
+    let mut infos = stored_session_infos(log_backend, cwd);
+    let mut seen = infos
+        .iter()
+        .map(|session| session.session_id.clone())
+        .collect::<HashSet<_>>();
+    for (id, session) in sessions {
+        if cwd.is_some_and(|filter| filter != session.cwd()) || !seen.insert(id.clone()) {
+            continue;
+        }
+        infos
+            .push(SessionInfo::new(id.clone(), session.cwd().to_path_buf()).title(in_memory_title));
+    }
+    ListSessionsResponse::new(infos)
+}
+
+/// Signal the running prompt of `session_id` to stop.
+pub(crate) fn cancel_cli_prompt<S: CliSession>(
+    sessions: &HashMap<SessionId, S>,
+    session_id: &SessionId,
+) -> Result<(), Error> {
+    let Some(session) = sessions.get(session_id) else {
+        return Err(Error::resource_not_found(None));
+    };
+    if let Some(cancel_tx) = session.active_prompt() {
+        let _ = cancel_tx.send(true);
+    }
+    Ok(())
+}
+
+pub fn session_title(message: &str) -> Option<String> {
+    let normalized = message.replace(['\r', '\n'], " ");
+    let trimmed = normalized.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed
+        .grapheme_indices(true)
+        .nth(SESSION_TITLE_MAX_GRAPHEMES)
+        .is_none()
+    {
+        return Some(trimmed.to_string());
+    }
+    let cut = trimmed
+        .grapheme_indices(true)
+        .nth(SESSION_TITLE_MAX_GRAPHEMES - 3)
+        .map_or(trimmed.len(), |(byte_index, _)| byte_index);
+    Some(format!("{}...", &trimmed[..cut]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        prompt_blocks_to_text, prompt_blocks_with_attachments, restore_turns, session_title,
+        transcript_prompt, turns_from_canonical,
+    };
+    use crate::{prompt_attachments::PromptAttachments, session_store::CanonicalRecord};
+    use agent_client_protocol::{ContentBlock, ImageContent, SessionId};
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn record(kind: &str, text: &str) -> CanonicalRecord {
+        CanonicalRecord {
+            ts_ms: 0,
+            kind: kind.to_string(),
+            data: json!({ "text": text }),
+        }
+    }
+
+    #[test]
+    fn restore_turns_replays_reset_and_model_commands() {
+        let turns = vec![
+            ("old question".to_string(), "old answer".to_string()),
+            ("/reset".to_string(), "reset".to_string()),
+            ("/model gemini-2.5-flash".to_string(), "set".to_string()),
+            ("/status".to_string(), "status".to_string()),
+            ("new question".to_string(), "new answer".to_string()),
+        ];
+
+        let (history, model) = restore_turns(&turns, Some("default-model".to_string()));
+        assert_eq!(
+            history,
+            vec![("new question".to_string(), "new answer".to_string())]
+        );
+        assert_eq!(model.as_deref(), Some("gemini-2.5-flash"));
+        assert_eq!(
+            restore_turns(&turns[..1], Some("default-model".to_string())).1,
+            Some("default-model".to_string())
+        );
+    }
+
+    #[test]
+    fn rebuilds_turns_from_prompt_and_message_chunks() {
+        let records = vec![
+            record("acp.agent_message_chunk", "orphan"),
+            record("acp.prompt", "first"),
+            record("acp.agent_message_chunk", "Hel"),
+            record("acp.agent_thought_chunk", "hmm"),
+            record("acp.agent_message_chunk", "lo\n"),
+            record("acp.prompt", "second"),
+        ];
+
+        assert_eq!(
+            turns_from_canonical(&records),
+            vec![
+                ("first".to_string(), "Hello".to_string()),
+                ("second".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn transcript_prompt_keeps_only_the_latest_turns() {
+        assert_eq!(transcript_prompt(&[], "hi", 6), "hi");
+        let history = vec![
+            ("one".to_string(), "1".to_string()),
+            ("two".to_string(), "2".to_string()),
+        ];
+        assert_eq!(
+            transcript_prompt(&history, "three", 1),
+            "User:\ntwo\n\nAssistant:\n2\n\nUser:\nthree"
+        );
+        assert_eq!(transcript_prompt(&history, "three", 0), "three");
+    }
+
+    #[test]
+    fn attachments_replace_image_placeholders() {
+        let blocks = vec![
+            ContentBlock::from("what is this?"),
+            ContentBlock::Image(ImageContent::new(STANDARD.encode(b"gif"), "image/gif")),
+        ];
+        assert_eq!(
+            prompt_blocks_to_text(&blocks),
+            "what is this?\n[image omitted]"
+        );
+
+        let session_id = SessionId::new(format!("test-{}", Uuid::new_v4()));
+        let attachments = PromptAttachments::write(&session_id, &blocks).unwrap();
+        let text = prompt_blocks_with_attachments(&blocks, &attachments, |attachment| {
+            format!("@{}", attachment.path.display())
+        });
+        assert_eq!(
+            text,
+            format!(
+                "what is this?\n@{}",
+                attachments.dir().join("2-image.gif").display()
+            )
+        );
+    }
+
+    #[test]
+    fn session_title_flattens_and_truncates() {
+        assert_eq!(session_title("  \n "), None);
+        assert_eq!(
+            session_title("fix\nthe bug").as_deref(),
+            Some("fix the bug")
+        );
+        let long = "a".repeat(200);
+        let title = session_title(&long).unwrap();
+        assert_eq!(title.len(), 120);
+        assert!(title.ends_with("..."));
     }
 }
@@ -2,8 +2,8 @@ use agent_client_protocol::{
     AuthMethod, AuthMethodId, AuthenticateRequest, AuthenticateResponse, CancelNotification,
     ClientCapabilities, CreateTerminalRequest, Error, ForkSessionRequest, ForkSessionResponse,
     KillTerminalCommandRequest, ListSessionsRequest, ListSessionsResponse, LoadSessionRequest,
-    LoadSessionResponse, McpServer, McpServerHttp, McpServerStdio, NewSessionRequest,
-    NewSessionResponse, PromptRequest, PromptResponse, ReleaseTerminalRequest,
+    LoadSessionResponse, McpServer, McpServerHttp, McpServerSse, McpServerStdio, NewSessionRequest,
+    NewSessionResponse, PromptCapabilities, PromptRequest, PromptResponse, ReleaseTerminalRequest,
     ResumeSessionRequest, ResumeSessionResponse, SessionId, SessionInfo,
     SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
     SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse, TerminalOutputRequest,
@@ -33,19 +33,23 @@ use std::{
     path::PathBuf,
     rc::Rc,
     sync::{Arc, Mutex},
+    time::Duration,
 };
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
 use crate::{
     acp_create_terminal, acp_kill_terminal_command, acp_release_terminal, acp_terminal_output,
-    acp_wait_for_terminal_exit,
+    acp_wait_for_terminal_exit, acp_write_terminal,
     backend::{BackendDriver, BackendKind},
     local_spawner::{AcpFs, LocalSpawner},
+    mcp_config::{SkippedMcpServer, report_skipped_mcp_servers},
+    mcp_sse_bridge,
+    pty_terminal::PtyTerminal,
     resolve_session_alias,
     session_store::{GlobalSessionIndex, SessionStore},
-    thread::Thread,
+    thread::{ModelsManagerImpl, Thread},
 };
 
 /// Codex backend driver for ACP.
@@ -65,6 +69,8 @@ pub struct CodexDriver {
     sessions: Rc<RefCell<HashMap<SessionId, Rc<Thread>>>>,
     /// Session working directories for filesystem sandboxing
     session_roots: Arc<Mutex<HashMap<SessionId, PathBuf>>>,
+    /// Model catalog override (defaults to the thread manager's `ModelsManager`)
+    models_manager: Option<Arc<dyn ModelsManagerImpl>>,
     /// Optional global canonical session store, for cross-backend continuity.
     ///
     /// If `ACP_HOME` (or `$HOME`) can't be resolved, this stays disabled.
@@ -77,6 +83,10 @@ const XSFIRE_CODEX_OPEN_BROWSER_ENV_VAR: &str = "XSFIRE_CODEX_OPEN_BROWSER";
 const ACP_TERMINAL_OUTPUT_BYTE_LIMIT_DEFAULT: u64 = 32 * 1024;
 const ACP_TERMINAL_OUTPUT_BYTE_LIMIT_MIN: u64 = 4 * 1024;
 const ACP_TERMINAL_OUTPUT_BYTE_LIMIT_MAX: u64 = 512 * 1024;
+/// Pause after forwarding stdin so the next output read reflects it.
+const ACP_TERMINAL_WRITE_SETTLE: Duration = Duration::from_millis(100);
+const PTY_YIELD_TIME_MIN_MS: u64 = 250;
+const PTY_YIELD_TIME_MAX_MS: u64 = 30_000;
 
 #[derive(Clone, Debug)]
 struct AcpTerminalHandle {
@@ -84,10 +94,28 @@ struct AcpTerminalHandle {
     terminal_id: String,
 }
 
+impl AcpTerminalHandle {
+    fn terminal_id(&self) -> agent_client_protocol::TerminalId {
+        agent_client_protocol::TerminalId::new(self.terminal_id.clone())
+    }
+}
+
+/// A command started by the delegate that is still running.
+#[derive(Clone)]
+enum ExecProcess {
+    /// Runs in an ACP client terminal.
+    Acp(AcpTerminalHandle),
+    /// Runs in a local PTY; `mirror` is the ACP terminal that displays it.
+    Pty {
+        pty: Arc<PtyTerminal>,
+        mirror: AcpTerminalHandle,
+    },
+}
+
 struct AcpUnifiedExecDelegate {
     session_id: SessionId,
     client_capabilities: Arc<Mutex<ClientCapabilities>>,
-    terminals: Arc<Mutex<HashMap<String, AcpTerminalHandle>>>,
+    processes: Arc<Mutex<HashMap<String, ExecProcess>>>,
 }
 
 impl AcpUnifiedExecDelegate {
@@ -95,7 +123,7 @@ impl AcpUnifiedExecDelegate {
         Self {
             session_id,
             client_capabilities,
-            terminals: Arc::default(),
+            processes: Arc::default(),
         }
     }
 
@@ -107,6 +135,19 @@ impl AcpUnifiedExecDelegate {
         self.client_capabilities.lock().unwrap().terminal
     }
 
+    /// Clients advertise `_meta.terminal_write` when they accept `terminal/write` requests.
+    fn supports_terminal_write(&self) -> bool {
+        self.client_capabilities
+            .lock()
+            .unwrap()
+            .meta
+            .as_ref()
+            .is_some_and(|v| {
+                v.get("terminal_write")
+                    .is_some_and(|v| v.as_bool().unwrap_or_default())
+            })
+    }
+
     fn output_byte_limit(max_output_tokens: Option<usize>) -> u64 {
         max_output_tokens
             .map(|tokens| tokens.saturating_mul(4) as u64)
@@ -117,6 +158,10 @@ impl AcpUnifiedExecDelegate {
             )
     }
 
+    fn pty_yield_time(yield_time_ms: u64) -> Duration {
+        Duration::from_millis(yield_time_ms.clamp(PTY_YIELD_TIME_MIN_MS, PTY_YIELD_TIME_MAX_MS))
+    }
+
     fn exit_code_from_terminal_status(
         exit_status: &Option<agent_client_protocol::TerminalExitStatus>,
     ) -> Option<i32> {
@@ -133,7 +178,7 @@ impl AcpUnifiedExecDelegate {
         context: &UnifiedExecContext,
         terminal_id: &str,
         output: String,
-        exit_status: &Option<agent_client_protocol::TerminalExitStatus>,
+        exit_code: Option<i32>,
         still_running: bool,
     ) -> UnifiedExecResponse {
         let raw_output = output.as_bytes().to_vec();
@@ -144,7 +189,7 @@ impl AcpUnifiedExecDelegate {
             output,
             raw_output,
             process_id: still_running.then(|| request_process_id.to_string()),
-            exit_code: Self::exit_code_from_terminal_status(exit_status),
+            exit_code,
             original_token_count: None,
             terminal_id: Some(terminal_id.to_string()),
             session_command: Some(request_command.to_vec()),
@@ -153,6 +198,112 @@ impl AcpUnifiedExecDelegate {
                 .or_else(|| Some(context.cwd().to_path_buf())),
         }
     }
+
+    async fn release_terminal(handle: &AcpTerminalHandle) -> Result<(), UnifiedExecError> {
+        acp_release_terminal(ReleaseTerminalRequest::new(
+            handle.session_id.clone(),
+            handle.terminal_id(),
+        ))
+        .await
+        .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Run an interactive command in a local PTY and show it through a mirroring ACP terminal.
+    async fn exec_in_pty(
+        &self,
+        request: ExecCommandRequest,
+        context: &UnifiedExecContext,
+    ) -> Result<UnifiedExecResponse, UnifiedExecError> {
+        let cwd = request
+            .workdir
+            .clone()
+            .unwrap_or_else(|| context.cwd().clone());
+        let pty = PtyTerminal::spawn(&request.command, &cwd)
+            .await
+            .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
+        let create_request = match pty.mirror_command() {
+            Ok((exe, args)) => CreateTerminalRequest::new(self.routed_session_id(), exe)
+                .args(args)
+                .cwd(Some(cwd))
+                .output_byte_limit(Self::output_byte_limit(request.max_output_tokens)),
+            Err(err) => {
+                pty.terminate();
+                return Err(UnifiedExecError::create_process(err.to_string()));
+            }
+        };
+        let create_response = match acp_create_terminal(create_request).await {
+            Ok(response) => response,
+            Err(err) => {
+                pty.terminate();
+                return Err(UnifiedExecError::create_process(err));
+            }
+        };
+        let mirror = AcpTerminalHandle {
+            session_id: self.routed_session_id(),
+            terminal_id: create_response.terminal_id.0.as_ref().to_string(),
+        };
+        self.pty_response(
+            &request.process_id,
+            &request.command,
+            &request.workdir,
+            context,
+            Arc::new(pty),
+            mirror,
+            request.yield_time_ms,
+            request.max_output_tokens,
+        )
+        .await
+    }
+
+    /// Collect PTY output for one exec/write call and track or retire the process.
+    #[allow(clippy::too_many_arguments)]
+    async fn pty_response(
+        &self,
+        process_id: &str,
+        command: &[String],
+        workdir: &Option<PathBuf>,
+        context: &UnifiedExecContext,
+        pty: Arc<PtyTerminal>,
+        mirror: AcpTerminalHandle,
+        yield_time_ms: u64,
+        max_output_tokens: Option<usize>,
+    ) -> Result<UnifiedExecResponse, UnifiedExecError> {
+        let limit =
+            usize::try_from(Self::output_byte_limit(max_output_tokens)).unwrap_or(usize::MAX);
+        let (output, exit_code) = pty.read(Self::pty_yield_time(yield_time_ms), limit).await;
+        let still_running = exit_code.is_none();
+        if still_running {
+            self.processes.lock().unwrap().insert(
+                process_id.to_string(),
+                ExecProcess::Pty {
+                    pty,
+                    mirror: mirror.clone(),
+                },
+            );
+        } else {
+            self.processes.lock().unwrap().remove(process_id);
+            // The mirror exits on its own once it has shown the exit marker.
+            acp_wait_for_terminal_exit(WaitForTerminalExitRequest::new(
+                mirror.session_id.clone(),
+                mirror.terminal_id(),
+            ))
+            .await
+            .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
+            Self::release_terminal(&mirror).await?;
+        }
+
+        Ok(Self::unified_exec_response(
+            process_id,
+            command,
+            workdir,
+            context,
+            &mirror.terminal_id,
+            output,
+            exit_code,
+            still_running,
+        ))
+    }
 }
 
 #[async_trait::async_trait]
@@ -170,6 +321,11 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
         let Some((command, args)) = request.command.split_first() else {
             return Err(UnifiedExecError::MissingCommandLine);
         };
+        // ACP terminals have no stdin of their own; interactive commands need either the
+        // client's write extension or a local PTY.
+        if request.tty && !self.supports_terminal_write() {
+            return self.exec_in_pty(request, context).await;
+        }
 
         let session_id = self.routed_session_id();
         let create_request = CreateTerminalRequest::new(session_id.clone(), command.clone())
@@ -188,21 +344,17 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
         .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
 
         let still_running = output_response.exit_status.is_none();
+        let handle = AcpTerminalHandle {
+            session_id,
+            terminal_id: terminal_id.clone(),
+        };
         if still_running {
-            self.terminals.lock().unwrap().insert(
-                request.process_id.clone(),
-                AcpTerminalHandle {
-                    session_id,
-                    terminal_id: terminal_id.clone(),
-                },
-            );
+            self.processes
+                .lock()
+                .unwrap()
+                .insert(request.process_id.clone(), ExecProcess::Acp(handle));
         } else {
-            acp_release_terminal(ReleaseTerminalRequest::new(
-                session_id,
-                create_response.terminal_id,
-            ))
-            .await
-            .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
+            Self::release_terminal(&handle).await?;
         }
 
         Ok(Self::unified_exec_response(
@@ -212,7 +364,7 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
             context,
             &terminal_id,
             output_response.output,
-            &output_response.exit_status,
+            Self::exit_code_from_terminal_status(&output_response.exit_status),
             still_running,
         ))
     }
@@ -222,11 +374,8 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
         request: WriteStdinRequest,
         context: &UnifiedExecContext,
     ) -> Result<UnifiedExecResponse, UnifiedExecError> {
-        if !request.input.is_empty() {
-            return Err(UnifiedExecError::StdinClosed);
-        }
-        let handle = self
-            .terminals
+        let process = self
+            .processes
             .lock()
             .unwrap()
             .get(&request.process_id)
@@ -234,22 +383,58 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
             .ok_or_else(|| UnifiedExecError::UnknownProcessId {
                 process_id: request.process_id.clone(),
             })?;
+        let handle = match process {
+            ExecProcess::Pty { pty, mirror } => {
+                if !request.input.is_empty() {
+                    pty.write(request.input.as_bytes())
+                        .await
+                        .map_err(|_| UnifiedExecError::WriteToStdin)?;
+                }
+                return self
+                    .pty_response(
+                        &request.process_id,
+                        &[],
+                        &None,
+                        context,
+                        pty,
+                        mirror,
+                        request.yield_time_ms,
+                        request.max_output_tokens,
+                    )
+                    .await;
+            }
+            ExecProcess::Acp(handle) => handle,
+        };
+
+        if !request.input.is_empty() {
+            if !self.supports_terminal_write() {
+                return Err(UnifiedExecError::StdinClosed);
+            }
+            acp_write_terminal(
+                handle.session_id.clone(),
+                handle.terminal_id(),
+                request.input.clone(),
+            )
+            .await
+            .map_err(|err| {
+                warn!("terminal/write failed for {}: {err}", handle.terminal_id);
+                UnifiedExecError::WriteToStdin
+            })?;
+            // Give the process a moment to react so the output below reflects the input.
+            tokio::time::sleep(ACP_TERMINAL_WRITE_SETTLE).await;
+        }
+
         let output_response = acp_terminal_output(TerminalOutputRequest::new(
             handle.session_id.clone(),
-            agent_client_protocol::TerminalId::new(handle.terminal_id.clone()),
+            handle.terminal_id(),
         ))
         .await
         .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
 
         let still_running = output_response.exit_status.is_none();
         if !still_running {
-            self.terminals.lock().unwrap().remove(&request.process_id);
-            acp_release_terminal(ReleaseTerminalRequest::new(
-                handle.session_id.clone(),
-                agent_client_protocol::TerminalId::new(handle.terminal_id.clone()),
-            ))
-            .await
-            .map_err(|err| UnifiedExecError::create_process(err.to_string()))?;
+            self.processes.lock().unwrap().remove(&request.process_id);
+            Self::release_terminal(&handle).await?;
         }
 
         Ok(Self::unified_exec_response(
@@ -259,21 +444,27 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
             context,
             &handle.terminal_id,
             output_response.output,
-            &output_response.exit_status,
+            Self::exit_code_from_terminal_status(&output_response.exit_status),
             still_running,
         ))
     }
 
     async fn terminate_all_processes(&self) -> Result<(), UnifiedExecError> {
         let handles = {
-            let mut terminals = self.terminals.lock().unwrap();
-            terminals
+            let mut processes = self.processes.lock().unwrap();
+            processes
                 .drain()
-                .map(|(_, handle)| handle)
+                .map(|(_, process)| match process {
+                    ExecProcess::Acp(handle) => handle,
+                    ExecProcess::Pty { pty, mirror } => {
+                        pty.terminate();
+                        mirror
+                    }
+                })
                 .collect::<Vec<_>>()
         };
         for handle in handles {
-            let terminal_id = agent_client_protocol::TerminalId::new(handle.terminal_id.clone());
+            let terminal_id = handle.terminal_id();
             acp_kill_terminal_command(KillTerminalCommandRequest::new(
                 handle.session_id.clone(),
                 terminal_id.clone(),
@@ -294,6 +485,100 @@ impl UnifiedExecDelegate for AcpUnifiedExecDelegate {
     }
 }
 
+fn mcp_server_config(transport: McpServerTransportConfig) -> McpServerConfig {
+    McpServerConfig {
+        transport,
+        enabled: true,
+        startup_timeout_sec: None,
+        tool_timeout_sec: None,
+        disabled_tools: None,
+        enabled_tools: None,
+        disabled_reason: None,
+    }
+}
+
+/// Translate the MCP servers passed by the ACP client into codex-rs configs. SSE servers
+/// run through the `mcp-sse-bridge` stdio shim since codex-rs has no SSE client.
+fn client_mcp_server_configs(
+    mcp_servers: Vec<McpServer>,
+    cwd: &PathBuf,
+) -> (HashMap<String, McpServerConfig>, Vec<SkippedMcpServer>) {
+    let mut configs = HashMap::new();
+    let mut skipped = Vec::new();
+    for mcp_server in mcp_servers {
+        match mcp_server {
+            McpServer::Sse(McpServerSse {
+                name, url, headers, ..
+            }) => {
+                let headers = headers
+                    .into_iter()
+                    .map(|h| (h.name, h.value))
+                    .collect::<Vec<_>>();
+                match mcp_sse_bridge::bridge_command(&url, &headers) {
+                    Ok((command, args, env)) => {
+                        configs.insert(
+                            name,
+                            mcp_server_config(McpServerTransportConfig::Stdio {
+                                command,
+                                args,
+                                env: (!env.is_empty()).then_some(env),
+                                env_vars: vec![],
+                                cwd: Some(cwd.clone()),
+                            }),
+                        );
+                    }
+                    Err(err) => skipped.push(SkippedMcpServer {
+                        name,
+                        transport: "sse",
+                        reason: format!("cannot start the SSE bridge: {err}"),
+                    }),
+                }
+            }
+            McpServer::Http(McpServerHttp {
+                name, url, headers, ..
+            }) => {
+                configs.insert(
+                    name,
+                    mcp_server_config(McpServerTransportConfig::StreamableHttp {
+                        url,
+                        bearer_token_env_var: None,
+                        http_headers: if headers.is_empty() {
+                            None
+                        } else {
+                            Some(headers.into_iter().map(|h| (h.name, h.value)).collect())
+                        },
+                        env_http_headers: None,
+                    }),
+                );
+            }
+            McpServer::Stdio(McpServerStdio {
+                name,
+                command,
+                args,
+                env,
+                ..
+            }) => {
+                configs.insert(
+                    name,
+                    mcp_server_config(McpServerTransportConfig::Stdio {
+                        command: command.display().to_string(),
+                        args,
+                        env: if env.is_empty() {
+                            None
+                        } else {
+                            Some(env.into_iter().map(|env| (env.name, env.value)).collect())
+                        },
+                        env_vars: vec![],
+                        cwd: Some(cwd.clone()),
+                    }),
+                );
+            }
+            other => skipped.push(SkippedMcpServer::unsupported(&other, "codex")),
+        }
+    }
+    (configs, skipped)
+}
+
 impl CodexDriver {
     /// Create a new `CodexDriver` with the given configuration.
     pub fn new(config: Config, client_capabilities: Arc<Mutex<ClientCapabilities>>) -> Self {
@@ -337,10 +622,17 @@ impl CodexDriver {
             thread_manager,
             sessions: Rc::default(),
             session_roots,
+            models_manager: None,
             global_session_index,
         }
     }
 
+    /// Serve the `model` options from another catalog, e.g. the models a local server exposes.
+    pub fn with_models_manager(mut self, models_manager: Arc<dyn ModelsManagerImpl>) -> Self {
+        self.models_manager = Some(models_manager);
+        self
+    }
+
     fn session_id_from_thread_id(thread_id: ThreadId) -> SessionId {
         SessionId::new(thread_id.to_string())
     }
@@ -373,82 +665,22 @@ impl CodexDriver {
         &self,
         cwd: &PathBuf,
         mcp_servers: Vec<McpServer>,
-    ) -> Result<Config, Error> {
+    ) -> Result<(Config, Vec<SkippedMcpServer>), Error> {
         let mut config = self.config.clone();
         config.include_apply_patch_tool = true;
         config.cwd.clone_from(cwd);
 
         // Propagate any client-provided MCP servers that codex-rs supports.
         let mut new_mcp_servers = config.mcp_servers.get().clone();
-        for mcp_server in mcp_servers {
-            match mcp_server {
-                // Not supported in codex
-                McpServer::Sse(..) => {}
-                McpServer::Http(McpServerHttp {
-                    name, url, headers, ..
-                }) => {
-                    new_mcp_servers.insert(
-                        name,
-                        McpServerConfig {
-                            transport: McpServerTransportConfig::StreamableHttp {
-                                url,
-                                bearer_token_env_var: None,
-                                http_headers: if headers.is_empty() {
-                                    None
-                                } else {
-                                    Some(headers.into_iter().map(|h| (h.name, h.value)).collect())
-                                },
-                                env_http_headers: None,
-                            },
-                            enabled: true,
-                            startup_timeout_sec: None,
-                            tool_timeout_sec: None,
-                            disabled_tools: None,
-                            enabled_tools: None,
-                            disabled_reason: None,
-                        },
-                    );
-                }
-                McpServer::Stdio(McpServerStdio {
-                    name,
-                    command,
-                    args,
-                    env,
-                    ..
-                }) => {
-                    new_mcp_servers.insert(
-                        name,
-                        McpServerConfig {
-                            transport: McpServerTransportConfig::Stdio {
-                                command: command.display().to_string(),
-                                args,
-                                env: if env.is_empty() {
-                                    None
-                                } else {
-                                    Some(env.into_iter().map(|env| (env.name, env.value)).collect())
-                                },
-                                env_vars: vec![],
-                                cwd: Some(cwd.clone()),
-                            },
-                            enabled: true,
-                            startup_timeout_sec: None,
-                            tool_timeout_sec: None,
-                            disabled_tools: None,
-                            enabled_tools: None,
-                            disabled_reason: None,
-                        },
-                    );
-                }
-                _ => {}
-            }
-        }
+        let (client_mcp_servers, skipped) = client_mcp_server_configs(mcp_servers, cwd);
+        new_mcp_servers.extend(client_mcp_servers);
 
         config
             .mcp_servers
             .set(new_mcp_servers)
             .map_err(|e| anyhow::anyhow!(e))?;
 
-        Ok(config)
+        Ok((config, skipped))
     }
 
     async fn resolve_rollout_path(&self, session_id: &SessionId) -> Result<PathBuf, Error> {
@@ -481,6 +713,7 @@ impl CodexDriver {
         config: Config,
         new_thread: NewThread,
         replay_history: Option<Vec<RolloutItem>>,
+        skipped_mcp_servers: Vec<SkippedMcpServer>,
     ) -> Result<LoadSessionResponse, Error> {
         let NewThread {
             thread_id,
@@ -493,14 +726,19 @@ impl CodexDriver {
             .unwrap()
             .insert(session_id.clone(), config.cwd.clone());
 
+        let session_store = self.build_session_store(&session_id, &config, &thread_id);
+        report_skipped_mcp_servers(&session_id, session_store.as_ref(), skipped_mcp_servers);
+
         let thread = Rc::new(Thread::new(
             session_id.clone(),
             thread,
             self.auth_manager.clone(),
-            self.thread_manager.get_models_manager(),
+            self.models_manager.clone().unwrap_or_else(|| {
+                self.thread_manager.get_models_manager() as Arc<dyn ModelsManagerImpl>
+            }),
             self.client_capabilities.clone(),
             config.clone(),
-            self.build_session_store(&session_id, &config, &thread_id),
+            session_store,
         ));
 
         if let Some(history) = replay_history.filter(|history| !history.is_empty()) {
@@ -519,6 +757,10 @@ impl BackendDriver for CodexDriver {
         BackendKind::Codex
     }
 
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        PromptCapabilities::new().embedded_context(true).image(true)
+    }
+
     fn supports_load_session(&self) -> bool {
         true
     }
@@ -625,7 +867,7 @@ impl BackendDriver for CodexDriver {
         } = request;
         info!("Creating new session with cwd: {}", cwd.display());
 
-        let config = self.build_session_config(&cwd, mcp_servers)?;
+        let (config, skipped_mcp_servers) = self.build_session_config(&cwd, mcp_servers)?;
         let num_mcp_servers = config.mcp_servers.len();
 
         let new_thread = Box::pin(self.thread_manager.start_thread(config.clone()))
@@ -633,7 +875,13 @@ impl BackendDriver for CodexDriver {
             .map_err(|_e| Error::internal_error())?;
         let session_id = Self::session_id_from_thread_id(new_thread.thread_id);
         let load = self
-            .register_thread(session_id.clone(), config, new_thread, None)
+            .register_thread(
+                session_id.clone(),
+                config,
+                new_thread,
+                None,
+                skipped_mcp_servers,
+            )
             .await?;
 
         debug!("Created new session with {} MCP servers", num_mcp_servers);
@@ -672,7 +920,7 @@ impl BackendDriver for CodexDriver {
             InitialHistory::New => Vec::new(),
         };
 
-        let config = self.build_session_config(&cwd, mcp_servers)?;
+        let (config, skipped_mcp_servers) = self.build_session_config(&cwd, mcp_servers)?;
 
         let new_thread = Box::pin(self.thread_manager.resume_thread_from_rollout(
             config.clone(),
@@ -682,8 +930,14 @@ impl BackendDriver for CodexDriver {
         .await
         .map_err(|e| Error::internal_error().data(e.to_string()))?;
 
-        self.register_thread(session_id, config, new_thread, Some(rollout_items))
-            .await
+        self.register_thread(
+            session_id,
+            config,
+            new_thread,
+            Some(rollout_items),
+            skipped_mcp_servers,
+        )
+        .await
     }
 
     async fn fork_session(
@@ -699,7 +953,7 @@ impl BackendDriver for CodexDriver {
             ..
         } = request;
         let rollout_path = self.resolve_rollout_path(&session_id).await?;
-        let config = self.build_session_config(&cwd, mcp_servers)?;
+        let (config, skipped_mcp_servers) = self.build_session_config(&cwd, mcp_servers)?;
         let new_thread = Box::pin(self.thread_manager.fork_thread(
             usize::MAX,
             config.clone(),
@@ -710,7 +964,13 @@ impl BackendDriver for CodexDriver {
 
         let forked_session_id = Self::session_id_from_thread_id(new_thread.thread_id);
         let load = self
-            .register_thread(forked_session_id.clone(), config, new_thread, None)
+            .register_thread(
+                forked_session_id.clone(),
+                config,
+                new_thread,
+                None,
+                skipped_mcp_servers,
+            )
             .await?;
 
         Ok(ForkSessionResponse::new(forked_session_id)
@@ -733,7 +993,7 @@ impl BackendDriver for CodexDriver {
             ..
         } = request;
         let rollout_path = self.resolve_rollout_path(&session_id).await?;
-        let config = self.build_session_config(&cwd, mcp_servers)?;
+        let (config, skipped_mcp_servers) = self.build_session_config(&cwd, mcp_servers)?;
         let new_thread = Box::pin(self.thread_manager.resume_thread_from_rollout(
             config.clone(),
             rollout_path,
@@ -743,7 +1003,7 @@ impl BackendDriver for CodexDriver {
         .map_err(|e| Error::internal_error().data(e.to_string()))?;
 
         let load = self
-            .register_thread(session_id, config, new_thread, None)
+            .register_thread(session_id, config, new_thread, None, skipped_mcp_servers)
             .await?;
 
         Ok(ResumeSessionResponse::new()
@@ -1003,7 +1263,10 @@ fn format_session_title(message: &str) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::is_truthy_env_value;
+    use super::{client_mcp_server_configs, is_truthy_env_value};
+    use agent_client_protocol::{HttpHeader, McpServer, McpServerSse};
+    use codex_core::config::types::McpServerTransportConfig;
+    use std::path::PathBuf;
 
     #[test]
     fn parses_truthy_env_values() {
@@ -1018,4 +1281,45 @@ mod tests {
             assert!(!is_truthy_env_value(value), "{value} should be false");
         }
     }
+
+    #[test]
+    fn sse_mcp_servers_run_through_the_stdio_bridge() {
+        let cwd = PathBuf::from("/work");
+        let (configs, skipped) = client_mcp_server_configs(
+            vec![McpServer::Sse(
+                McpServerSse::new("docs", "http://127.0.0.1:9/sse")
+                    .headers(vec![HttpHeader::new("x-api-key", "secret")]),
+            )],
+            &cwd,
+        );
+
+        assert!(skipped.is_empty(), "{skipped:?}");
+        let McpServerTransportConfig::Stdio {
+            args,
+            env,
+            cwd: server_cwd,
+            ..
+        } = &configs["docs"].transport
+        else {
+            panic!(
+                "expected a stdio bridge, got {:?}",
+                configs["docs"].transport
+            );
+        };
+        assert_eq!(
+            args,
+            &vec![
+                crate::mcp_sse_bridge::MCP_SSE_BRIDGE_SUBCOMMAND.to_string(),
+                "http://127.0.0.1:9/sse".to_string(),
+            ]
+        );
+        let headers = env
+            .as_ref()
+            .and_then(|env| env.get("XSFIRE_MCP_SSE_HEADERS"));
+        assert_eq!(
+            headers.map(String::as_str),
+            Some(r#"{"x-api-key":"secret"}"#)
+        );
+        assert_eq!(server_cwd.as_ref(), Some(&cwd));
+    }
 }
@@ -0,0 +1,992 @@
+//! Config-described CLI backend (`--backend=custom`).
+//!
+//! A TOML spec declares how to invoke an arbitrary CLI agent (aider, opencode, qwen-code,
+//! in-house tools, ...) and how to read its output, so teams can plug it into ACP without
+//! writing another driver. The spec is read from `XSFIRE_CUSTOM_BACKEND_SPEC`, falling back
+//! to `ACP_HOME/backends/custom.toml`:
+//!
+//! ```toml
+//! id = "aider"
+//! name = "Aider"
+//! bin = "aider"
+//! args = ["--no-auto-commits", "--yes-always"]
+//! prompt_args = ["--message", "{prompt}"]
+//! model_args = ["--model", "{model}"]
+//!
+//! [auth]
+//! command = ["aider", "--version"]
+//! hint = "Install aider with `pipx install aider-chat`."
+//!
+//! [output]
+//! format = "text"
+//!
+//! [[models]]
+//! id = "sonnet"
+//! name = "Claude Sonnet"
+//! ```
+
+use agent_client_protocol::{
+    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, ContentBlock,
+    ContentChunk, Error, ListSessionsRequest, ListSessionsResponse, LoadSessionRequest,
+    LoadSessionResponse, NewSessionRequest, NewSessionResponse, PromptRequest, PromptResponse,
+    SessionConfigOption, SessionConfigOptionCategory, SessionConfigSelectOption, SessionId,
+    SessionUpdate, SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+    SetSessionModeRequest, SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
+    StopReason, TextContent,
+};
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command as StdCommand, Stdio},
+    rc::Rc,
+};
+use tokio::{process::Command as TokioCommand, sync::watch};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::{
+    backend::{BackendDriver, BackendKind},
+    canonical_event::CanonicalEvent,
+    cli_common::{
+        CliCommand, CliSession, CommandRunResult, StreamTranslator, TRANSCRIPT_TURNS,
+        cancel_cli_prompt, init_session_store, list_cli_sessions, parse_cli_command,
+        prompt_blocks_to_text, replay_turns, restore_turns, run_streaming_cli, send_agent_text,
+        stored_global_id, transcript_prompt, turns_from_canonical,
+    },
+    session_store::{AcpHome, GlobalSessionIndex, SessionStore},
+};
+
+/// Declarative description of a CLI agent.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomBackendSpec {
+    /// Short identifier used in session ids and as the canonical log backend name.
+    pub id: String,
+    /// Display name shown in ACP (defaults to `id`).
+    #[serde(default)]
+    pub name: Option<String>,
+    pub bin: String,
+    /// Arguments passed on every run, before the templated ones.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Argument template carrying the prompt; must contain `{prompt}`.
+    #[serde(default = "default_prompt_args")]
+    pub prompt_args: Vec<String>,
+    /// Argument template used only when a model is selected (`{model}`).
+    #[serde(default)]
+    pub model_args: Vec<String>,
+    /// Argument template for CLIs that take the workspace as a flag (`{cwd}`); the process
+    /// always runs with the session cwd as its working directory.
+    #[serde(default)]
+    pub cwd_args: Vec<String>,
+    /// Prior turns stitched into each prompt (`0` sends the prompt alone).
+    #[serde(default = "default_history_turns")]
+    pub history_turns: usize,
+    #[serde(default)]
+    pub auth: Option<CustomAuthSpec>,
+    #[serde(default)]
+    pub output: CustomOutputSpec,
+    #[serde(default)]
+    pub models: Vec<CustomModelSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomAuthSpec {
+    /// Command run by ACP `authenticate`; a zero exit status means ready.
+    pub command: Vec<String>,
+    /// Extra guidance appended to authentication errors.
+    #[serde(default)]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomOutputSpec {
+    #[serde(default)]
+    pub format: CustomOutputFormat,
+    /// JSON pointer to assistant text in each `json-lines` event.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// JSON pointer to reasoning text, streamed as thought chunks.
+    #[serde(default)]
+    pub thought: Option<String>,
+    /// JSON pointer to an error message; its presence fails the prompt.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CustomOutputFormat {
+    /// Every stdout line is agent text.
+    #[default]
+    Text,
+    /// Every stdout line is a JSON event read through the `[output]` pointers.
+    JsonLines,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomModelSpec {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+fn default_prompt_args() -> Vec<String> {
+    vec!["{prompt}".to_string()]
+}
+
+fn default_history_turns() -> usize {
+    TRANSCRIPT_TURNS
+}
+
+impl CustomBackendSpec {
+    const SPEC_ENV: &'static str = "XSFIRE_CUSTOM_BACKEND_SPEC";
+
+    /// Load the spec named by `XSFIRE_CUSTOM_BACKEND_SPEC` (or `ACP_HOME/backends/custom.toml`).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path = match std::env::var(Self::SPEC_ENV) {
+            Ok(path) if !path.trim().is_empty() => PathBuf::from(path),
+            _ => AcpHome::resolve()
+                .map(|home| home.join("backends").join("custom.toml"))
+                .with_context(|| {
+                    format!(
+                        "--backend=custom needs a spec: set {} to a TOML file",
+                        Self::SPEC_ENV
+                    )
+                })?,
+        };
+        Self::load(&path)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read custom backend spec {}", path.display()))?;
+        Self::parse(&data)
+            .with_context(|| format!("invalid custom backend spec {}", path.display()))
+    }
+
+    pub fn parse(data: &str) -> anyhow::Result<Self> {
+        let spec: Self = toml::from_str(data)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!(
+                "`id` must be a non-empty identifier of ASCII letters, digits, `-` or `_` (got `{}`)",
+                self.id
+            );
+        }
+        // Every name or alias of a built-in backend, so `/backend <id>` and the session index
+        // never mistake one for the other.
+        if let Some(backend) = BackendKind::parse(&self.id.to_ascii_lowercase()) {
+            bail!(
+                "`id` `{}` is reserved for the built-in `{}` backend",
+                self.id,
+                backend.as_str()
+            );
+        }
+        if self.bin.trim().is_empty() {
+            bail!("`bin` must not be empty");
+        }
+        if !self.prompt_args.iter().any(|arg| arg.contains("{prompt}")) {
+            bail!("`prompt_args` must contain a `{{prompt}}` placeholder");
+        }
+        if let Some(auth) = &self.auth
+            && auth.command.is_empty()
+        {
+            bail!("`auth.command` must not be empty");
+        }
+        if self.output.format == CustomOutputFormat::JsonLines && self.output.text.is_none() {
+            bail!("`output.text` is required for the `json-lines` format");
+        }
+        for (field, pointer) in [
+            ("output.text", &self.output.text),
+            ("output.thought", &self.output.thought),
+            ("output.error", &self.output.error),
+        ] {
+            if let Some(pointer) = pointer
+                && !pointer.starts_with('/')
+            {
+                bail!("`{field}` must be a JSON pointer such as `/text` (got `{pointer}`)");
+            }
+        }
+        Ok(())
+    }
+
+    fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+
+    /// Full argument list for one run, with `{prompt}`, `{model}` and `{cwd}` expanded.
+    fn command_args(&self, cwd: &Path, model: Option<&str>, prompt: &str) -> Vec<String> {
+        let cwd = cwd.display().to_string();
+        let mut args = self.args.clone();
+        args.extend(expand_args(&self.cwd_args, &[("{cwd}", &cwd)]));
+        if let Some(model) = model {
+            args.extend(expand_args(&self.model_args, &[("{model}", model)]));
+        }
+        args.extend(expand_args(
+            &self.prompt_args,
+            &[("{prompt}", prompt), ("{cwd}", &cwd)],
+        ));
+        args
+    }
+}
+
+/// Expand placeholders in one left-to-right pass, so text inside an inserted value (a prompt
+/// mentioning `{cwd}`, say) is never expanded again.
+fn expand_args(template: &[String], vars: &[(&str, &str)]) -> Vec<String> {
+    template
+        .iter()
+        .map(|arg| {
+            let mut expanded = String::with_capacity(arg.len());
+            let mut rest = arg.as_str();
+            while let Some(start) = rest.find('{') {
+                expanded.push_str(&rest[..start]);
+                rest = &rest[start..];
+                match vars.iter().find(|(name, _)| rest.starts_with(name)) {
+                    Some((name, value)) => {
+                        expanded.push_str(value);
+                        rest = &rest[name.len()..];
+                    }
+                    None => {
+                        expanded.push('{');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            expanded.push_str(rest);
+            expanded
+        })
+        .collect()
+}
+
+struct CustomSession {
+    cwd: PathBuf,
+    model: Option<String>,
+    history: Vec<(String, String)>,
+    session_store: Option<SessionStore>,
+    active_prompt: Option<watch::Sender<bool>>,
+}
+
+impl CliSession for CustomSession {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn active_prompt(&self) -> Option<&watch::Sender<bool>> {
+        self.active_prompt.as_ref()
+    }
+}
+
+/// Incremental translator for a custom CLI's stdout, driven by the spec's `[output]` table.
+struct CustomStreamTranslator {
+    output: CustomOutputSpec,
+    output_text: String,
+    error: Option<String>,
+}
+
+impl CustomStreamTranslator {
+    fn new(output: CustomOutputSpec) -> Self {
+        Self {
+            output,
+            output_text: String::new(),
+            error: None,
+        }
+    }
+
+    fn push_text(&mut self, text: &str) -> SessionUpdate {
+        self.output_text.push_str(text);
+        SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(TextContent::new(
+            text,
+        ))))
+    }
+}
+
+impl StreamTranslator for CustomStreamTranslator {
+    fn handle_line(&mut self, line: &str) -> Vec<SessionUpdate> {
+        // Blank lines are kept inside plain text so paragraphs survive, but never lead it.
+        if line.trim().is_empty()
+            && (self.output.format == CustomOutputFormat::JsonLines || self.output_text.is_empty())
+        {
+            return Vec::new();
+        }
+        let event = match self.output.format {
+            CustomOutputFormat::Text => None,
+            CustomOutputFormat::JsonLines => serde_json::from_str::<Value>(line.trim()).ok(),
+        };
+        let Some(event) = event else {
+            let separator = if self.output_text.is_empty() {
+                ""
+            } else {
+                "\n"
+            };
+            return vec![self.push_text(&format!("{separator}{line}"))];
+        };
+
+        let field = |pointer: &Option<String>| {
+            pointer
+                .as_deref()
+                .and_then(|pointer| event.pointer(pointer))
+                .and_then(Value::as_str)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        if let Some(error) = field(&self.output.error) {
+            self.error = Some(error);
+        }
+        let mut updates = Vec::new();
+        if let Some(thought) = field(&self.output.thought) {
+            updates.push(SessionUpdate::AgentThoughtChunk(ContentChunk::new(
+                ContentBlock::Text(TextContent::new(thought)),
+            )));
+        }
+        if let Some(text) = field(&self.output.text) {
+            updates.push(self.push_text(&text));
+        }
+        updates
+    }
+
+    fn output_text(&self) -> &str {
+        &self.output_text
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Backend driver for a CLI agent described by a [`CustomBackendSpec`].
+///
+/// - `new_session` creates an in-memory session ID (`<id>:<uuid>`), persisted via the
+///   canonical log like the other CLI drivers
+/// - `prompt` runs the spec's command with the stitched transcript and streams stdout as ACP
+///   message (and optional thought) chunks
+pub struct CustomCliDriver {
+    spec: CustomBackendSpec,
+    sessions: Rc<RefCell<HashMap<SessionId, CustomSession>>>,
+    global_session_index: Option<Arc<Mutex<GlobalSessionIndex>>>,
+}
+
+impl CustomCliDriver {
+    const AUTH_METHOD_ID: &'static str = "custom-cli";
+
+    pub fn new(spec: CustomBackendSpec) -> Self {
+        Self {
+            spec,
+            sessions: Rc::default(),
+            global_session_index: GlobalSessionIndex::load().map(|idx| Arc::new(Mutex::new(idx))),
+        }
+    }
+
+    fn help_text(&self) -> String {
+        format!(
+            "{} commands:\n- /status\n- /model <name>\n- /reset\n\n{}",
+            self.spec.display_name(),
+            BackendKind::Custom
+                .work_orchestration_profile()
+                .render_summary(),
+        )
+    }
+
+    fn status_text(&self, model: &str, history_turns: usize) -> String {
+        let profile = BackendKind::Custom.work_orchestration_profile();
+        format!(
+            "{} session status:\n- backend: custom ({})\n- bin: {}\n- model: {model}\n- history_turns: {history_turns}\n- task_orchestration: {}\n- task_monitoring: {}\n- acp_bridge: {}\n- operator_hint: {}",
+            self.spec.display_name(),
+            self.spec.id,
+            self.spec.bin,
+            profile.task_orchestration,
+            profile.task_monitoring,
+            profile.bridge_summary(),
+            profile.operator_hint,
+        )
+    }
+
+    fn validate_auth_method(method_id: &str) -> Result<(), Error> {
+        if method_id == Self::AUTH_METHOD_ID {
+            return Ok(());
+        }
+
+        Err(Error::invalid_params().data(format!(
+            "unsupported auth method for custom backend: {method_id}"
+        )))
+    }
+
+    fn check_auth(&self) -> Result<(), Error> {
+        let Some(auth) = &self.spec.auth else {
+            return Ok(());
+        };
+        let hint = auth
+            .hint
+            .as_deref()
+            .map(|hint| format!(" {hint}"))
+            .unwrap_or_default();
+        let (program, args) = auth
+            .command
+            .split_first()
+            .ok_or_else(|| Error::internal_error().data("custom backend auth command is empty"))?;
+        let output = StdCommand::new(program).args(args).output().map_err(|e| {
+            Error::invalid_params().data(format!(
+                "failed to execute {} auth check ({program}): {e}.{hint}",
+                self.spec.display_name()
+            ))
+        })?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(Error::invalid_params().data(format!(
+            "{} is not ready for ACP authenticate (exit {:?}): {stderr}.{hint}",
+            self.spec.display_name(),
+            output.status.code(),
+        )))
+    }
+
+    fn config_options(&self, current_model: Option<String>) -> Vec<SessionConfigOption> {
+        if self.spec.models.is_empty() {
+            return Vec::new();
+        }
+        let current_value = current_model.unwrap_or_else(|| "default".to_string());
+        let mut options = vec![SessionConfigSelectOption::new("default", "Default")];
+        options.extend(self.spec.models.iter().map(|model| {
+            SessionConfigSelectOption::new(
+                model.id.clone(),
+                model.name.clone().unwrap_or_else(|| model.id.clone()),
+            )
+        }));
+        vec![
+            SessionConfigOption::select("model", "Model", current_value, options)
+                .category(SessionConfigOptionCategory::Model)
+                .description(format!(
+                    "Model used by {} for this session",
+                    self.spec.display_name()
+                )),
+        ]
+    }
+
+    async fn run_cli(
+        &self,
+        session_id: &SessionId,
+        session_store: Option<SessionStore>,
+        args: Vec<String>,
+        cwd: PathBuf,
+        cancel_rx: watch::Receiver<bool>,
+    ) -> Result<CommandRunResult, Error> {
+        let name = self.spec.display_name();
+        let mut cmd = TokioCommand::new(&self.spec.bin);
+        cmd.current_dir(&cwd);
+        cmd.args(args);
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        let child = cmd.spawn().map_err(|e| {
+            Error::invalid_params().data(format!(
+                "failed to execute {name} ({}). Check `bin` in the custom backend spec. Error: {e}",
+                self.spec.bin
+            ))
+        })?;
+
+        let mut translator = CustomStreamTranslator::new(self.spec.output.clone());
+        run_streaming_cli(
+            name,
+            child,
+            session_id,
+            session_store.as_ref(),
+            &mut translator,
+            std::future::ready(()),
+            cancel_rx,
+        )
+        .await
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl BackendDriver for CustomCliDriver {
+    fn backend_kind(&self) -> BackendKind {
+        BackendKind::Custom
+    }
+
+    fn supports_load_session(&self) -> bool {
+        true
+    }
+
+    fn auth_methods(&self) -> Vec<AuthMethod> {
+        vec![AuthMethod::new(
+            Self::AUTH_METHOD_ID,
+            format!("{} (pre-authenticated)", self.spec.display_name()),
+        )
+        .description("Authenticate with the CLI before starting. ACP authenticate runs the spec's `auth.command` when one is declared.")]
+    }
+
+    async fn authenticate(
+        &self,
+        request: AuthenticateRequest,
+    ) -> Result<AuthenticateResponse, Error> {
+        Self::validate_auth_method(request.method_id.0.as_ref())?;
+        self.check_auth()?;
+        Ok(AuthenticateResponse::new())
+    }
+
+    async fn new_session(&self, request: NewSessionRequest) -> Result<NewSessionResponse, Error> {
+        let session_id = SessionId::new(format!("{}:{}", self.spec.id, Uuid::new_v4()));
+        let cwd = request.cwd;
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::Custom,
+            &self.spec.id,
+            &session_id,
+            &cwd,
+        );
+
+        self.sessions.borrow_mut().insert(
+            session_id.clone(),
+            CustomSession {
+                cwd,
+                model: None,
+                history: Vec::new(),
+                session_store,
+                active_prompt: None,
+            },
+        );
+
+        info!("Created {} session: {session_id:?}", self.spec.id);
+        Ok(NewSessionResponse::new(session_id).config_options(self.config_options(None)))
+    }
+
+    async fn load_session(
+        &self,
+        request: LoadSessionRequest,
+    ) -> Result<LoadSessionResponse, Error> {
+        let LoadSessionRequest {
+            session_id, cwd, ..
+        } = request;
+        let Some(global_id) = stored_global_id(
+            self.global_session_index.as_deref(),
+            BackendKind::Custom,
+            &session_id,
+        ) else {
+            return Err(Error::resource_not_found(Some(session_id.to_string())));
+        };
+
+        let turns = turns_from_canonical(&SessionStore::read_canonical(&global_id));
+        let (history, model) = restore_turns(&turns, None);
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::Custom,
+            &self.spec.id,
+            &session_id,
+            &cwd,
+        );
+        self.sessions.borrow_mut().insert(
+            session_id.clone(),
+            CustomSession {
+                cwd,
+                model: model.clone(),
+                history,
+                session_store,
+                active_prompt: None,
+            },
+        );
+
+        info!(
+            "Loaded {} session {session_id:?} ({} persisted turns)",
+            self.spec.id,
+            turns.len()
+        );
+        replay_turns(&session_id, &turns).await;
+        Ok(LoadSessionResponse::new().config_options(self.config_options(model)))
+    }
+
+    async fn list_sessions(
+        &self,
+        request: ListSessionsRequest,
+    ) -> Result<ListSessionsResponse, Error> {
+        Ok(list_cli_sessions(
+            &self.spec.id,
+            &self.sessions.borrow(),
+            request.cwd.as_deref(),
+            &format!("{} (in-memory)", self.spec.display_name()),
+        ))
+    }
+
+    async fn prompt(&self, request: PromptRequest) -> Result<PromptResponse, Error> {
+        let session_id = request.session_id.clone();
+        let user_text = prompt_blocks_to_text(&request.prompt);
+        debug!(
+            "{} prompt (session={session_id:?}) chars={}",
+            self.spec.id,
+            user_text.len()
+        );
+
+        {
+            let sessions = self.sessions.borrow();
+            if let Some(session) = sessions.get(&session_id)
+                && let Some(store) = &session.session_store
+            {
+                store.log(CanonicalEvent::prompt_text(user_text.as_str()));
+            }
+        }
+
+        if let Some(command) = parse_cli_command(&user_text) {
+            let message = {
+                let mut sessions = self.sessions.borrow_mut();
+                let Some(session) = sessions.get_mut(&session_id) else {
+                    return Err(Error::resource_not_found(None));
+                };
+                match command {
+                    CliCommand::Help => self.help_text(),
+                    CliCommand::Status => {
+                        let model = session.model.as_deref().unwrap_or("default");
+                        self.status_text(model, session.history.len())
+                    }
+                    CliCommand::Reset => {
+                        session.history.clear();
+                        format!(
+                            "{} session history has been reset.",
+                            self.spec.display_name()
+                        )
+                    }
+                    CliCommand::SetModel(model) => {
+                        session.model = (model != "default").then_some(model);
+                        let model_text = session.model.as_deref().unwrap_or("default");
+                        format!("{} model set to `{model_text}`.", self.spec.display_name())
+                    }
+                }
+            };
+            {
+                let sessions = self.sessions.borrow();
+                if let Some(session) = sessions.get(&session_id)
+                    && let Some(store) = &session.session_store
+                {
+                    store.log(CanonicalEvent::agent_message(message.as_str()));
+                }
+            }
+            send_agent_text(&session_id, message).await;
+            return Ok(PromptResponse::new(StopReason::EndTurn));
+        }
+
+        let (args, cwd, session_store, cancel_rx) = {
+            let mut sessions = self.sessions.borrow_mut();
+            let Some(session) = sessions.get_mut(&session_id) else {
+                return Err(Error::resource_not_found(None));
+            };
+            if session.active_prompt.is_some() {
+                return Err(Error::invalid_params().data(format!(
+                    "a {} prompt is already running for session {}",
+                    self.spec.display_name(),
+                    session_id
+                )));
+            }
+
+            let prompt = transcript_prompt(&session.history, &user_text, self.spec.history_turns);
+            let args = self
+                .spec
+                .command_args(&session.cwd, session.model.as_deref(), &prompt);
+
+            let (cancel_tx, cancel_rx) = watch::channel(false);
+            session.active_prompt = Some(cancel_tx);
+
+            (
+                args,
+                session.cwd.clone(),
+                session.session_store.clone(),
+                cancel_rx,
+            )
+        };
+
+        let output = self
+            .run_cli(&session_id, session_store, args, cwd, cancel_rx)
+            .await;
+        {
+            let mut sessions = self.sessions.borrow_mut();
+            if let Some(session) = sessions.get_mut(&session_id) {
+                session.active_prompt = None;
+            }
+        }
+        let output = output?;
+        if output.cancelled {
+            debug!(
+                "{} prompt cancelled for session {} (exit {:?})",
+                self.spec.id, session_id, output.exit_code
+            );
+            return Ok(PromptResponse::new(StopReason::Cancelled));
+        }
+
+        // Updates were already streamed (and logged) while the CLI was running.
+        let output_text = output.output_text.trim_end_matches('\n').to_string();
+
+        {
+            let mut sessions = self.sessions.borrow_mut();
+            let Some(session) = sessions.get_mut(&session_id) else {
+                return Err(Error::resource_not_found(None));
+            };
+            session.history.push((user_text, output_text));
+        }
+
+        Ok(PromptResponse::new(StopReason::EndTurn))
+    }
+
+    async fn cancel(&self, args: CancelNotification) -> Result<(), Error> {
+        cancel_cli_prompt(&self.sessions.borrow(), &args.session_id)
+    }
+
+    async fn set_session_mode(
+        &self,
+        _args: SetSessionModeRequest,
+    ) -> Result<SetSessionModeResponse, Error> {
+        Err(Error::invalid_params().data("set_session_mode is not supported for --backend=custom."))
+    }
+
+    async fn set_session_model(
+        &self,
+        args: SetSessionModelRequest,
+    ) -> Result<SetSessionModelResponse, Error> {
+        let mut sessions = self.sessions.borrow_mut();
+        let Some(session) = sessions.get_mut(&args.session_id) else {
+            return Err(Error::resource_not_found(None));
+        };
+        session.model = if args.model_id.0.as_ref() == "default" {
+            None
+        } else {
+            Some(args.model_id.0.to_string())
+        };
+        Ok(SetSessionModelResponse::new())
+    }
+
+    async fn set_session_config_option(
+        &self,
+        args: SetSessionConfigOptionRequest,
+    ) -> Result<SetSessionConfigOptionResponse, Error> {
+        if args.config_id.0.as_ref() != "model" || self.spec.models.is_empty() {
+            return Err(Error::invalid_params().data(format!(
+                "unsupported config option for custom backend: {}",
+                args.config_id
+            )));
+        }
+        let model = if args.value.0.as_ref() == "default" {
+            None
+        } else {
+            Some(args.value.0.to_string())
+        };
+        let mut sessions = self.sessions.borrow_mut();
+        let Some(session) = sessions.get_mut(&args.session_id) else {
+            return Err(Error::resource_not_found(None));
+        };
+        session.model = model;
+        Ok(SetSessionConfigOptionResponse::new(
+            self.config_options(session.model.clone()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CustomBackendSpec, CustomCliDriver, CustomOutputFormat, CustomStreamTranslator, expand_args,
+    };
+    use crate::backend::BackendDriver;
+    use crate::cli_common::StreamTranslator;
+    use agent_client_protocol::{
+        LoadSessionRequest, NewSessionRequest, PromptRequest, SessionUpdate, StopReason,
+    };
+    use std::path::Path;
+    use uuid::Uuid;
+
+    const JSON_SPEC: &str = r#"
+id = "qwen"
+name = "Qwen Code"
+bin = "qwen"
+args = ["--yolo"]
+prompt_args = ["--prompt", "{prompt}"]
+model_args = ["--model", "{model}"]
+cwd_args = ["--workspace={cwd}"]
+
+[output]
+format = "json-lines"
+text = "/message/text"
+thought = "/reasoning"
+error = "/error"
+
+[[models]]
+id = "qwen3-coder"
+"#;
+
+    #[test]
+    fn parses_spec_and_expands_argument_templates() {
+        let spec = CustomBackendSpec::parse(JSON_SPEC).unwrap();
+        assert_eq!(spec.display_name(), "Qwen Code");
+        assert_eq!(spec.output.format, CustomOutputFormat::JsonLines);
+        assert_eq!(spec.history_turns, 6);
+
+        assert_eq!(
+            spec.command_args(Path::new("/work"), Some("qwen3-coder"), "fix it"),
+            vec![
+                "--yolo",
+                "--workspace=/work",
+                "--model",
+                "qwen3-coder",
+                "--prompt",
+                "fix it",
+            ]
+        );
+        assert_eq!(
+            spec.command_args(Path::new("/work"), None, "fix it"),
+            vec!["--yolo", "--workspace=/work", "--prompt", "fix it"]
+        );
+    }
+
+    #[test]
+    fn placeholders_inside_the_prompt_are_not_expanded() {
+        let spec = CustomBackendSpec::parse(JSON_SPEC).unwrap();
+        assert_eq!(
+            spec.command_args(Path::new("/work"), None, "print {cwd} and {model}"),
+            vec![
+                "--yolo",
+                "--workspace=/work",
+                "--prompt",
+                "print {cwd} and {model}",
+            ]
+        );
+        assert_eq!(
+            expand_args(
+                &["{prompt} in {cwd} {x}".to_string()],
+                &[("{prompt}", "say {cwd}"), ("{cwd}", "/work")],
+            ),
+            vec!["say {cwd} in /work {x}"]
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_specs() {
+        for (spec, expected) in [
+            ("id = \"a b\"\nbin = \"x\"", "`id`"),
+            ("id = \"gemini\"\nbin = \"x\"", "reserved"),
+            ("id = \"local\"\nbin = \"x\"", "reserved"),
+            ("id = \"claude-code\"\nbin = \"x\"", "reserved"),
+            ("id = \"custom\"\nbin = \"x\"", "reserved"),
+            ("id = \"Ollama\"\nbin = \"x\"", "built-in `local`"),
+            (
+                "id = \"x\"\nbin = \"x\"\nprompt_args = [\"-p\"]",
+                "{prompt}",
+            ),
+            (
+                "id = \"x\"\nbin = \"x\"\n[output]\nformat = \"json-lines\"",
+                "output.text",
+            ),
+            (
+                "id = \"x\"\nbin = \"x\"\n[output]\nformat = \"json-lines\"\ntext = \"text\"",
+                "JSON pointer",
+            ),
+            ("id = \"x\"\nbin = \"x\"\ncolour = true", "unknown field"),
+        ] {
+            let err = CustomBackendSpec::parse(spec).unwrap_err();
+            assert!(format!("{err:#}").contains(expected), "{spec:?} -> {err:#}");
+        }
+    }
+
+    #[test]
+    fn translator_reads_json_lines_through_pointers() {
+        let spec = CustomBackendSpec::parse(JSON_SPEC).unwrap();
+        let mut translator = CustomStreamTranslator::new(spec.output);
+
+        let updates =
+            translator.handle_line(r#"{"reasoning":"look around","message":{"text":"Hi"}}"#);
+        assert!(matches!(
+            updates.as_slice(),
+            [
+                SessionUpdate::AgentThoughtChunk(_),
+                SessionUpdate::AgentMessageChunk(_)
+            ]
+        ));
+        assert!(translator.handle_line(r#"{"type":"usage"}"#).is_empty());
+        translator.handle_line("not json");
+        translator.handle_line(r#"{"error":"quota exceeded"}"#);
+
+        assert_eq!(translator.output_text, "Hi\nnot json");
+        assert_eq!(translator.error.as_deref(), Some("quota exceeded"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "current_thread")]
+    async fn prompt_runs_the_declared_command_and_sessions_reload() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let acp_home = std::env::temp_dir().join(format!("xsfire-custom-acp-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&acp_home).unwrap();
+        let previous = std::env::var("ACP_HOME").ok();
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            std::env::set_var("ACP_HOME", &acp_home);
+        }
+
+        let spec = CustomBackendSpec::parse(
+            r#"
+id = "echoer"
+bin = "sh"
+prompt_args = ["-c", "pwd; printf '%s\n' \"$0\"", "{prompt}"]
+history_turns = 1
+"#,
+        )
+        .unwrap();
+        let driver = CustomCliDriver::new(spec.clone());
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        let session_id = driver
+            .new_session(NewSessionRequest::new(cwd.clone()))
+            .await
+            .unwrap()
+            .session_id;
+        assert!(session_id.0.starts_with("echoer:"));
+
+        for text in ["first", "second"] {
+            let response = driver
+                .prompt(PromptRequest::new(session_id.clone(), vec![text.into()]))
+                .await
+                .unwrap();
+            assert_eq!(response.stop_reason, StopReason::EndTurn);
+        }
+
+        let history = driver.sessions.borrow()[&session_id].history.clone();
+        assert_eq!(
+            history[0],
+            ("first".to_string(), format!("{}\nfirst", cwd.display()))
+        );
+        // The second run receives the previous turn stitched into its prompt.
+        assert!(history[1].1.contains("User:\nfirst\n\nAssistant:\n"));
+        assert!(history[1].1.ends_with("\n\nUser:\nsecond"));
+
+        let reloaded = CustomCliDriver::new(spec);
+        reloaded
+            .load_session(LoadSessionRequest::new(session_id.clone(), cwd))
+            .await
+            .unwrap();
+        assert_eq!(reloaded.sessions.borrow()[&session_id].history, history);
+
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            match previous {
+                Some(value) => std::env::set_var("ACP_HOME", value),
+                None => std::env::remove_var("ACP_HOME"),
+            }
+        }
+        drop(std::fs::remove_dir_all(&acp_home));
+    }
+}
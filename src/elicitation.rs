@@ -0,0 +1,446 @@
+//! Forms for MCP elicitation requests.
+//!
+//! An MCP server can ask the user for structured input with a flat JSON schema of string,
+//! number, integer, boolean and enum properties. [`ElicitationForm`] reads that schema, renders it
+//! for clients without a form UI, parses `key=value` answers typed in the next prompt, and
+//! validates content returned by clients that implement the `elicitation/create` extension.
+
+use serde_json::{Map, Number, Value};
+
+/// ACP extension method for clients that render elicitation forms themselves.
+pub const ELICITATION_EXT_METHOD: &str = "elicitation/create";
+
+/// Capability flag (`clientCapabilities._meta.elicitation`) announcing the extension.
+pub const ELICITATION_CAPABILITY: &str = "elicitation";
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldKind {
+    String {
+        min_length: Option<u64>,
+        max_length: Option<u64>,
+        format: Option<String>,
+    },
+    Number {
+        integer: bool,
+        minimum: Option<f64>,
+        maximum: Option<f64>,
+    },
+    Boolean,
+    Enum {
+        values: Vec<String>,
+        labels: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FormField {
+    name: String,
+    title: Option<String>,
+    description: Option<String>,
+    kind: FieldKind,
+    required: bool,
+    default: Option<Value>,
+}
+
+/// The fields an elicitation request asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElicitationForm {
+    fields: Vec<FormField>,
+}
+
+/// What the user answered in a follow-up prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FollowUpReply {
+    Accept(Value),
+    Decline,
+    Cancel,
+}
+
+fn str_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_str().map(str::to_string))
+        .collect()
+}
+
+impl FormField {
+    fn from_schema(name: &str, schema: &Value, required: bool) -> Self {
+        let str_field = |key: &str| schema.get(key).and_then(Value::as_str).map(str::to_string);
+        let kind = if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            let values = values
+                .iter()
+                .map(|value| match value {
+                    Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>();
+            let mut labels = str_list(schema.get("enumNames"));
+            if labels.len() != values.len() {
+                labels = values.clone();
+            }
+            FieldKind::Enum { values, labels }
+        } else {
+            match schema.get("type").and_then(Value::as_str) {
+                Some(kind @ ("number" | "integer")) => FieldKind::Number {
+                    integer: kind == "integer",
+                    minimum: schema.get("minimum").and_then(Value::as_f64),
+                    maximum: schema.get("maximum").and_then(Value::as_f64),
+                },
+                Some("boolean") => FieldKind::Boolean,
+                _ => FieldKind::String {
+                    min_length: schema.get("minLength").and_then(Value::as_u64),
+                    max_length: schema.get("maxLength").and_then(Value::as_u64),
+                    format: str_field("format"),
+                },
+            }
+        };
+        Self {
+            name: name.to_string(),
+            title: str_field("title"),
+            description: str_field("description"),
+            kind,
+            required,
+            default: schema.get("default").cloned(),
+        }
+    }
+
+    fn type_hint(&self) -> String {
+        match &self.kind {
+            FieldKind::String {
+                min_length,
+                max_length,
+                format,
+            } => {
+                let mut hint = format.clone().unwrap_or_else(|| "text".to_string());
+                match (min_length, max_length) {
+                    (Some(min), Some(max)) => hint.push_str(&format!(", {min}-{max} chars")),
+                    (Some(min), None) => hint.push_str(&format!(", at least {min} chars")),
+                    (None, Some(max)) => hint.push_str(&format!(", at most {max} chars")),
+                    (None, None) => {}
+                }
+                hint
+            }
+            FieldKind::Number {
+                integer,
+                minimum,
+                maximum,
+            } => {
+                let mut hint = if *integer { "integer" } else { "number" }.to_string();
+                match (minimum, maximum) {
+                    (Some(min), Some(max)) => hint.push_str(&format!(", {min}..{max}")),
+                    (Some(min), None) => hint.push_str(&format!(", >= {min}")),
+                    (None, Some(max)) => hint.push_str(&format!(", <= {max}")),
+                    (None, None) => {}
+                }
+                hint
+            }
+            FieldKind::Boolean => "yes/no".to_string(),
+            FieldKind::Enum { values, labels } => {
+                let choices = values
+                    .iter()
+                    .zip(labels)
+                    .map(|(value, label)| {
+                        if value == label {
+                            format!("`{value}`")
+                        } else {
+                            format!("`{value}` ({label})")
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("one of {}", choices.join(", "))
+            }
+        }
+    }
+
+    /// Coerce and check one answer, typed as text or returned as JSON by the client.
+    fn validate(&self, value: &Value) -> Result<Value, String> {
+        let name = &self.name;
+        match &self.kind {
+            FieldKind::String {
+                min_length,
+                max_length,
+                ..
+            } => {
+                let text = match value {
+                    Value::String(text) => text.clone(),
+                    Value::Number(number) => number.to_string(),
+                    Value::Bool(flag) => flag.to_string(),
+                    _ => return Err(format!("`{name}` must be text")),
+                };
+                let len = text.chars().count() as u64;
+                if min_length.is_some_and(|min| len < min)
+                    || max_length.is_some_and(|max| len > max)
+                {
+                    return Err(format!("`{name}` must be {}", self.type_hint()));
+                }
+                Ok(Value::String(text))
+            }
+            FieldKind::Number {
+                integer,
+                minimum,
+                maximum,
+            } => {
+                let number = match value {
+                    Value::Number(number) => number.as_f64(),
+                    Value::String(text) => text.trim().parse::<f64>().ok(),
+                    _ => None,
+                }
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| format!("`{name}` must be a number"))?;
+                if *integer && number.fract() != 0.0 {
+                    return Err(format!("`{name}` must be an integer"));
+                }
+                if minimum.is_some_and(|min| number < min)
+                    || maximum.is_some_and(|max| number > max)
+                {
+                    return Err(format!("`{name}` must be {}", self.type_hint()));
+                }
+                if *integer {
+                    Ok(Value::Number(Number::from(number as i64)))
+                } else {
+                    Number::from_f64(number)
+                        .map(Value::Number)
+                        .ok_or_else(|| format!("`{name}` must be a number"))
+                }
+            }
+            FieldKind::Boolean => match value {
+                Value::Bool(flag) => Ok(Value::Bool(*flag)),
+                Value::String(text) => match text.trim().to_ascii_lowercase().as_str() {
+                    "yes" | "y" | "true" | "on" | "1" => Ok(Value::Bool(true)),
+                    "no" | "n" | "false" | "off" | "0" => Ok(Value::Bool(false)),
+                    _ => Err(format!("`{name}` must be yes or no")),
+                },
+                _ => Err(format!("`{name}` must be yes or no")),
+            },
+            FieldKind::Enum { values, labels } => {
+                let text = match value {
+                    Value::String(text) => text.trim().to_string(),
+                    other => other.to_string(),
+                };
+                values
+                    .iter()
+                    .zip(labels)
+                    .find(|(value, label)| **value == text || label.eq_ignore_ascii_case(&text))
+                    .map(|(value, _)| Value::String(value.clone()))
+                    .ok_or_else(|| format!("`{name}` must be {}", self.type_hint()))
+            }
+        }
+    }
+}
+
+impl ElicitationForm {
+    /// Read an MCP `requestedSchema`; `None` when it asks for nothing.
+    pub fn from_schema(schema: &Value) -> Option<Self> {
+        let properties = schema.get("properties")?.as_object()?;
+        let required = str_list(schema.get("required"));
+        let fields = properties
+            .iter()
+            .map(|(name, property)| FormField::from_schema(name, property, required.contains(name)))
+            .collect::<Vec<_>>();
+        (!fields.is_empty()).then_some(Self { fields })
+    }
+
+    /// Markdown shown to clients without a form UI, asking for the answers in the next prompt.
+    pub fn render_follow_up(&self, server_name: &str, message: &str) -> String {
+        let mut lines = vec![
+            format!("**{server_name}** asks for input: {message}"),
+            String::new(),
+        ];
+        for field in &self.fields {
+            let mut line = format!("- `{}`", field.name);
+            if let Some(title) = field.title.as_deref() {
+                line.push_str(&format!(" ({title})"));
+            }
+            line.push_str(&format!(": {}", field.type_hint()));
+            if field.required {
+                line.push_str(", required");
+            }
+            if let Some(default) = field.default.as_ref() {
+                line.push_str(&format!(", default {default}"));
+            }
+            if let Some(description) = field.description.as_deref() {
+                line.push_str(&format!(" — {description}"));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+        lines.push(
+            "Reply with one `key=value` per line, or `/decline` to continue without it, or `/cancel` to cancel the request."
+                .to_string(),
+        );
+        lines.join("\n")
+    }
+
+    /// Check content returned by the client and fill in defaults.
+    pub fn validate(&self, content: &Value) -> Result<Value, Vec<String>> {
+        let empty = Map::new();
+        let content = content.as_object().unwrap_or(&empty);
+        let mut errors = Vec::new();
+        let mut accepted = Map::new();
+        for (name, _) in content {
+            if !self.fields.iter().any(|field| &field.name == name) {
+                errors.push(format!("unknown field `{name}`"));
+            }
+        }
+        for field in &self.fields {
+            match content.get(&field.name).or(field.default.as_ref()) {
+                Some(Value::Null) | None if field.required => {
+                    errors.push(format!("`{}` is required", field.name));
+                }
+                Some(Value::Null) | None => {}
+                Some(value) => match field.validate(value) {
+                    Ok(value) => {
+                        accepted.insert(field.name.clone(), value);
+                    }
+                    Err(error) => errors.push(error),
+                },
+            }
+        }
+        if errors.is_empty() {
+            Ok(Value::Object(accepted))
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parse a follow-up prompt: `/decline`, `/cancel` or `key=value` (or `key: value`) lines.
+    pub fn parse_follow_up(&self, text: &str) -> Result<FollowUpReply, Vec<String>> {
+        let trimmed = text.trim();
+        match trimmed {
+            "/decline" => return Ok(FollowUpReply::Decline),
+            "/cancel" => return Ok(FollowUpReply::Cancel),
+            _ => {}
+        }
+        let single_field = match self.fields.as_slice() {
+            [field] => Some(field.name.as_str()),
+            _ => None,
+        };
+        let mut content = Map::new();
+        let mut errors = Vec::new();
+        for line in trimmed
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let line = line.trim_start_matches(['-', '*']).trim();
+            let pair = line
+                .split_once('=')
+                .or_else(|| line.split_once(':'))
+                .map(|(key, value)| (key.trim().trim_matches('`'), value.trim()))
+                .filter(|(key, _)| self.fields.iter().any(|field| field.name == *key));
+            match (pair, single_field) {
+                (Some((key, value)), _) => {
+                    content.insert(key.to_string(), Value::String(value.to_string()));
+                }
+                // A form with one field also accepts the bare value.
+                (None, Some(name)) if content.is_empty() => {
+                    content.insert(name.to_string(), Value::String(line.to_string()));
+                }
+                (None, _) => errors.push(format!("cannot read `{line}`; expected key=value")),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.validate(&Value::Object(content))
+            .map(FollowUpReply::Accept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn form() -> ElicitationForm {
+        ElicitationForm::from_schema(&json!({
+            "type": "object",
+            "properties": {
+                "username": { "type": "string", "title": "User name", "minLength": 2 },
+                "scope": {
+                    "type": "string",
+                    "enum": ["read", "write"],
+                    "enumNames": ["Read only", "Read and write"]
+                },
+                "days": { "type": "integer", "minimum": 1, "maximum": 30, "default": 7 },
+                "notify": { "type": "boolean" }
+            },
+            "required": ["username", "scope"]
+        }))
+        .expect("schema has fields")
+    }
+
+    #[test]
+    fn renders_every_field_with_constraints() {
+        let text = form().render_follow_up("github", "Pick a token scope");
+        assert!(text.starts_with("**github** asks for input: Pick a token scope"));
+        assert!(text.contains("- `username` (User name): text, at least 2 chars, required"));
+        assert!(text.contains("`read` (Read only), `write` (Read and write), required"));
+        assert!(text.contains("- `days`: integer, 1..30, default 7"));
+        assert!(text.contains("- `notify`: yes/no"));
+    }
+
+    #[test]
+    fn parses_and_validates_follow_up_answers() {
+        let form = form();
+        assert_eq!(
+            form.parse_follow_up("username = octo\n- scope: Read and write\nnotify=yes"),
+            Ok(FollowUpReply::Accept(json!({
+                "username": "octo",
+                "scope": "write",
+                "days": 7,
+                "notify": true
+            })))
+        );
+        assert_eq!(
+            form.parse_follow_up(" /decline "),
+            Ok(FollowUpReply::Decline)
+        );
+        assert_eq!(form.parse_follow_up("/cancel"), Ok(FollowUpReply::Cancel));
+
+        let mut errors = form
+            .parse_follow_up("username=o\ndays=40")
+            .expect_err("invalid answers");
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "`days` must be integer, 1..30".to_string(),
+                "`scope` is required".to_string(),
+                "`username` must be text, at least 2 chars".to_string(),
+            ]
+        );
+        assert!(form.parse_follow_up("just some text").is_err());
+    }
+
+    #[test]
+    fn validates_client_content_and_single_field_forms() {
+        let form = form();
+        assert_eq!(
+            form.validate(&json!({"username": "octo", "scope": "read", "days": 3.0})),
+            Ok(json!({"username": "octo", "scope": "read", "days": 3}))
+        );
+        assert!(
+            form.validate(&json!({"username": "octo", "scope": "admin"}))
+                .is_err()
+        );
+        assert!(
+            form.validate(&json!({"username": "octo", "scope": "read", "extra": 1}))
+                .is_err()
+        );
+
+        let single = ElicitationForm::from_schema(&json!({
+            "properties": { "token": { "type": "string" } }
+        }))
+        .unwrap();
+        assert_eq!(
+            single.parse_follow_up("abc123"),
+            Ok(FollowUpReply::Accept(json!({"token": "abc123"})))
+        );
+        assert!(
+            ElicitationForm::from_schema(&json!({"type": "object", "properties": {}})).is_none()
+        );
+    }
+}
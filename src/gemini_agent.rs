@@ -1,42 +1,360 @@
 use agent_client_protocol::{
-    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, Error,
-    ListSessionsRequest, ListSessionsResponse, LoadSessionRequest, LoadSessionResponse,
-    NewSessionRequest, NewSessionResponse, PromptRequest, PromptResponse, SessionConfigOption,
-    SessionConfigOptionCategory, SessionConfigSelectOption, SessionId, SessionInfo,
-    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
-    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse, StopReason,
+    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, ContentBlock,
+    ContentChunk, Error, ListSessionsRequest, ListSessionsResponse, LoadSessionRequest,
+    LoadSessionResponse, McpServer, NewSessionRequest, NewSessionResponse, Plan, PlanEntry,
+    PlanEntryPriority, PlanEntryStatus, PromptCapabilities, PromptRequest, PromptResponse,
+    SessionConfigOption, SessionConfigOptionCategory, SessionConfigSelectOption, SessionId,
+    SessionMode, SessionModeId, SessionModeState, SessionUpdate, SetSessionConfigOptionRequest,
+    SetSessionConfigOptionResponse, SetSessionModeRequest, SetSessionModeResponse,
+    SetSessionModelRequest, SetSessionModelResponse, StopReason, TextContent, ToolCall,
+    ToolCallLocation, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
 };
+use serde_json::Value;
 use std::sync::{Arc, Mutex};
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
     process::{Command as StdCommand, Stdio},
     rc::Rc,
 };
-use tokio::{io::AsyncReadExt, process::Command as TokioCommand, sync::watch};
-use tracing::{debug, info};
+use tokio::{process::Command as TokioCommand, sync::watch};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::{
     backend::{BackendDriver, BackendKind},
-    cli_common::{prompt_blocks_to_text, send_agent_text},
-    session_store::{GlobalSessionIndex, SessionStore},
+    canonical_event::{CanonicalEvent, ModeSetting},
+    cli_common::{
+        CliCommand, CliSession, CommandRunResult, StreamTranslator, TRANSCRIPT_TURNS,
+        cancel_cli_prompt, init_session_store, list_cli_sessions, parse_cli_command,
+        prompt_blocks_to_text, prompt_blocks_with_attachments, replay_turns, restore_turns,
+        run_streaming_cli, send_agent_text, stored_global_id, transcript_prompt,
+        turns_from_canonical,
+    },
+    mcp_config::{McpConfigFile, McpConfigFormat},
+    multi_backend::with_context_handoff,
+    prompt_attachments::PromptAttachments,
+    session_store::{CanonicalRecord, GlobalSessionIndex, SessionStore},
 };
 
+/// Gemini CLI override for its system settings file, used to pass session MCP servers.
+const SYSTEM_SETTINGS_ENV: &str = "GEMINI_CLI_SYSTEM_SETTINGS_PATH";
+
 struct GeminiSession {
     cwd: PathBuf,
     model: Option<String>,
+    approval_mode: GeminiApprovalMode,
     history: Vec<(String, String)>,
     session_store: Option<SessionStore>,
+    /// Client-provided MCP servers, loaded by the CLI as its system settings file.
+    mcp_config: Option<Value>,
     active_prompt: Option<watch::Sender<bool>>,
 }
 
-struct CommandRunResult {
-    stdout: String,
-    exit_code: Option<i32>,
-    cancelled: bool,
+impl CliSession for GeminiSession {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn active_prompt(&self) -> Option<&watch::Sender<bool>> {
+        self.active_prompt.as_ref()
+    }
+}
+
+/// Gemini CLI `--approval-mode` values, exposed as ACP session modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GeminiApprovalMode {
+    Plan,
+    Default,
+    AutoEdit,
+    Yolo,
+}
+
+impl GeminiApprovalMode {
+    const ALL: [Self; 4] = [Self::Plan, Self::Default, Self::AutoEdit, Self::Yolo];
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Plan => "plan",
+            Self::Default => "default",
+            Self::AutoEdit => "auto_edit",
+            Self::Yolo => "yolo",
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Plan => "Plan",
+            Self::Default => "Default",
+            Self::AutoEdit => "Auto Edit",
+            Self::Yolo => "YOLO",
+        }
+    }
+
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Plan => "Read-only: Gemini plans without editing files or running tools",
+            Self::Default => {
+                "Gemini asks before edits and commands (declined in non-interactive runs)"
+            }
+            Self::AutoEdit => "Automatically approve file edits; other tools still need approval",
+            Self::Yolo => "Automatically approve every tool call",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.as_str() == value)
+    }
+}
+
+struct GeminiInvocation {
+    cwd: PathBuf,
+    model: Option<String>,
+    approval_mode: GeminiApprovalMode,
+    prompt: String,
+    mcp_config: Option<PathBuf>,
+    /// Directory holding this turn's attachments, passed with `--include-directories`.
+    attachment_dir: Option<PathBuf>,
+}
+
+/// Incremental translator for `gemini --output-format stream-json` events.
+///
+/// Each stdout line is one JSON event (`init`, `message`, `tool_use`, `tool_result`,
+/// `error`, `result`); model text, tool invocations and their results are mapped onto ACP
+/// session updates as soon as they arrive. Non-JSON lines, and every line in
+/// `--output-format text` mode, are forwarded as plain agent text.
+#[derive(Default)]
+struct GeminiStreamTranslator {
+    plain_text: bool,
+    output_text: String,
+    error: Option<String>,
+    plan_tool_ids: HashSet<String>,
+}
+
+impl StreamTranslator for GeminiStreamTranslator {
+    fn handle_line(&mut self, line: &str) -> Vec<SessionUpdate> {
+        let trimmed = line.trim();
+        // Blank lines are kept inside plain text so paragraphs survive, but never lead it.
+        if trimmed.is_empty() && (!self.plain_text || self.output_text.is_empty()) {
+            return Vec::new();
+        }
+        let event = if self.plain_text {
+            None
+        } else {
+            serde_json::from_str::<Value>(trimmed).ok()
+        };
+        let Some(event) = event else {
+            let separator = if self.output_text.is_empty() {
+                ""
+            } else {
+                "\n"
+            };
+            return vec![self.push_text(&format!("{separator}{line}"))];
+        };
+
+        match event.get("type").and_then(Value::as_str) {
+            Some("message") => self.message_event(&event).into_iter().collect(),
+            Some("tool_use") => self.tool_use_event(&event).into_iter().collect(),
+            Some("tool_result") => self.tool_result_event(&event).into_iter().collect(),
+            Some("error") => self.error_event(&event).into_iter().collect(),
+            Some("result") => {
+                self.result_event(&event);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn output_text(&self) -> &str {
+        &self.output_text
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl GeminiStreamTranslator {
+    fn push_text(&mut self, text: &str) -> SessionUpdate {
+        self.output_text.push_str(text);
+        SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(TextContent::new(
+            text,
+        ))))
+    }
+
+    fn message_event(&mut self, event: &Value) -> Option<SessionUpdate> {
+        if event.get("role").and_then(Value::as_str) != Some("assistant") {
+            return None;
+        }
+        let content = event.get("content").and_then(Value::as_str)?;
+        if content.is_empty() {
+            return None;
+        }
+        // Deltas are raw token chunks; full messages are separate paragraphs.
+        let is_delta = event.get("delta").and_then(Value::as_bool).unwrap_or(false);
+        let text = if is_delta || self.output_text.is_empty() {
+            content.to_string()
+        } else {
+            format!("\n\n{content}")
+        };
+        Some(self.push_text(&text))
+    }
+
+    fn tool_use_event(&mut self, event: &Value) -> Option<SessionUpdate> {
+        let id = event.get("tool_id").and_then(Value::as_str)?;
+        let name = event
+            .get("tool_name")
+            .and_then(Value::as_str)
+            .unwrap_or("tool");
+        let parameters = event.get("parameters").cloned().unwrap_or(Value::Null);
+        if name == "write_todos"
+            && let Some(plan) = write_todos_plan(&parameters)
+        {
+            self.plan_tool_ids.insert(id.to_string());
+            return Some(SessionUpdate::Plan(plan));
+        }
+        Some(SessionUpdate::ToolCall(gemini_tool_call(
+            id, name, parameters,
+        )))
+    }
+
+    fn tool_result_event(&mut self, event: &Value) -> Option<SessionUpdate> {
+        let id = event.get("tool_id").and_then(Value::as_str)?;
+        if self.plan_tool_ids.remove(id) {
+            return None;
+        }
+        let failed = event.get("status").and_then(Value::as_str) == Some("error");
+        let text = if failed {
+            event
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("tool failed")
+        } else {
+            event.get("output").and_then(Value::as_str).unwrap_or("")
+        };
+
+        let mut fields = ToolCallUpdateFields::new()
+            .status(if failed {
+                ToolCallStatus::Failed
+            } else {
+                ToolCallStatus::Completed
+            })
+            .raw_output(event.clone());
+        if !text.is_empty() {
+            fields = fields.content(vec![text.to_string().into()]);
+        }
+        Some(SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+            id.to_string(),
+            fields,
+        )))
+    }
+
+    fn error_event(&mut self, event: &Value) -> Option<SessionUpdate> {
+        let message = event.get("message").and_then(Value::as_str)?;
+        // Non-fatal warnings are surfaced as thoughts so the turn keeps going.
+        if event.get("severity").and_then(Value::as_str) == Some("error") {
+            self.error = Some(message.to_string());
+            return None;
+        }
+        Some(SessionUpdate::AgentThoughtChunk(ContentChunk::new(
+            ContentBlock::Text(TextContent::new(message)),
+        )))
+    }
+
+    fn result_event(&mut self, event: &Value) {
+        if event.get("status").and_then(Value::as_str) != Some("error") {
+            return;
+        }
+        let message = event
+            .pointer("/error/message")
+            .and_then(Value::as_str)
+            .unwrap_or("error");
+        self.error = Some(message.to_string());
+    }
+}
+
+fn gemini_tool_call(id: &str, name: &str, parameters: Value) -> ToolCall {
+    let field = |key: &str| {
+        parameters
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let path = field("file_path")
+        .or_else(|| field("absolute_path"))
+        .or_else(|| field("path"))
+        .or_else(|| field("dir_path"));
+
+    let (kind, title) = match name {
+        "run_shell_command" => (
+            ToolKind::Execute,
+            field("command")
+                .map(|command| format!("`{command}`"))
+                .unwrap_or_else(|| "Run command".to_string()),
+        ),
+        "read_file" | "read_many_files" => (
+            ToolKind::Read,
+            format!("Read {}", path.as_deref().unwrap_or("files")),
+        ),
+        "list_directory" => (
+            ToolKind::Read,
+            format!("List {}", path.as_deref().unwrap_or("directory")),
+        ),
+        "write_file" | "replace" => (
+            ToolKind::Edit,
+            format!("Edit {}", path.as_deref().unwrap_or("file")),
+        ),
+        "glob" | "search_file_content" => (
+            ToolKind::Search,
+            format!(
+                "Search {}",
+                field("pattern").unwrap_or_else(|| "files".to_string())
+            ),
+        ),
+        "web_fetch" => (
+            ToolKind::Fetch,
+            field("prompt")
+                .map(|prompt| format!("Fetch: {prompt}"))
+                .unwrap_or_else(|| "Fetch".to_string()),
+        ),
+        "google_web_search" => (
+            ToolKind::Fetch,
+            format!("Web search: {}", field("query").unwrap_or_default()),
+        ),
+        other => (ToolKind::Other, other.to_string()),
+    };
+
+    let mut tool_call = ToolCall::new(id.to_string(), title)
+        .kind(kind)
+        .status(ToolCallStatus::InProgress)
+        .raw_input(parameters);
+    if let Some(path) = path {
+        tool_call = tool_call.locations(vec![ToolCallLocation::new(PathBuf::from(path))]);
+    }
+    tool_call
+}
+
+fn write_todos_plan(parameters: &Value) -> Option<Plan> {
+    let todos = parameters.get("todos")?.as_array()?;
+    let entries = todos
+        .iter()
+        .filter_map(|todo| {
+            let description = todo.get("description").and_then(Value::as_str)?;
+            let status = match todo.get("status").and_then(Value::as_str) {
+                Some("completed" | "cancelled") => PlanEntryStatus::Completed,
+                Some("in_progress") => PlanEntryStatus::InProgress,
+                _ => PlanEntryStatus::Pending,
+            };
+            Some(PlanEntry::new(
+                description,
+                PlanEntryPriority::Medium,
+                status,
+            ))
+        })
+        .collect::<Vec<_>>();
+    Some(Plan::new(entries))
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -77,10 +395,11 @@ struct GeminiAuthConfig {
 
 /// Gemini CLI backend driver (shells out to the `gemini` CLI).
 ///
-/// Minimal implementation:
 /// - `new_session` creates an in-memory session ID
-/// - `prompt` runs `gemini --output-format text --approval-mode plan -p "<prompt>"`
-///   to avoid interactive approvals and streams the response as a single ACP message chunk
+/// - `prompt` runs `gemini --output-format stream-json --approval-mode <mode> --prompt "<prompt>"`
+///   with the session's ACP mode (`plan` by default) and translates each event into live ACP
+///   message, tool call and plan updates (`XSFIRE_GEMINI_OUTPUT_FORMAT=text` falls back to
+///   plain text chunks)
 pub struct GeminiCliDriver {
     sessions: Rc<RefCell<HashMap<SessionId, GeminiSession>>>,
     global_session_index: Option<Arc<Mutex<GlobalSessionIndex>>>,
@@ -107,8 +426,19 @@ impl GeminiCliDriver {
             .unwrap_or_default()
     }
 
-    fn approval_mode() -> String {
-        std::env::var("XSFIRE_GEMINI_APPROVAL_MODE").unwrap_or_else(|_| "plan".to_string())
+    /// Initial mode for new sessions; `XSFIRE_GEMINI_APPROVAL_MODE` keeps working as the default.
+    fn default_approval_mode() -> GeminiApprovalMode {
+        let Ok(value) = std::env::var("XSFIRE_GEMINI_APPROVAL_MODE") else {
+            return GeminiApprovalMode::Plan;
+        };
+        GeminiApprovalMode::from_str(value.trim()).unwrap_or_else(|| {
+            warn!("Unknown XSFIRE_GEMINI_APPROVAL_MODE `{value}`; using `plan`");
+            GeminiApprovalMode::Plan
+        })
+    }
+
+    fn output_format() -> String {
+        std::env::var("XSFIRE_GEMINI_OUTPUT_FORMAT").unwrap_or_else(|_| "stream-json".to_string())
     }
 
     fn default_model() -> Option<String> {
@@ -124,10 +454,11 @@ impl GeminiCliDriver {
         )
     }
 
-    fn status_text(model: &str, history_turns: usize) -> String {
+    fn status_text(model: &str, approval_mode: GeminiApprovalMode, history_turns: usize) -> String {
         let profile = BackendKind::Gemini.work_orchestration_profile();
         format!(
-            "Gemini session status:\n- model: {model}\n- history_turns: {history_turns}\n- task_orchestration: {}\n- task_monitoring: {}\n- progress_vector_checks: {}\n- preempt_on_new_prompt: {}\n- acp_bridge: {}\n- work_orchestration_sequence: {} ({})\n- operator_hint: {}",
+            "Gemini session status:\n- model: {model}\n- approval_mode: {}\n- history_turns: {history_turns}\n- task_orchestration: {}\n- task_monitoring: {}\n- progress_vector_checks: {}\n- preempt_on_new_prompt: {}\n- acp_bridge: {}\n- work_orchestration_sequence: {} ({})\n- operator_hint: {}",
+            approval_mode.as_str(),
             profile.task_orchestration,
             profile.task_monitoring,
             profile.vector_checks_value(),
@@ -365,9 +696,38 @@ impl GeminiCliDriver {
             .unwrap_or_else(|| "~/.gemini/oauth_creds.json".to_string())
     }
 
-    fn config_options(current_model: Option<String>) -> Vec<SessionConfigOption> {
+    fn modes(current: GeminiApprovalMode) -> SessionModeState {
+        SessionModeState::new(
+            SessionModeId::new(current.as_str()),
+            GeminiApprovalMode::ALL
+                .into_iter()
+                .map(|mode| {
+                    SessionMode::new(mode.as_str(), mode.label()).description(mode.description())
+                })
+                .collect(),
+        )
+    }
+
+    fn config_options(
+        current_model: Option<String>,
+        approval_mode: GeminiApprovalMode,
+    ) -> Vec<SessionConfigOption> {
         let current_value = current_model.unwrap_or_else(|| "default".to_string());
         vec![
+            SessionConfigOption::select(
+                "mode",
+                "Approval Mode",
+                approval_mode.as_str(),
+                GeminiApprovalMode::ALL
+                    .into_iter()
+                    .map(|mode| {
+                        SessionConfigSelectOption::new(mode.as_str(), mode.label())
+                            .description(mode.description())
+                    })
+                    .collect(),
+            )
+            .category(SessionConfigOptionCategory::Mode)
+            .description("Gemini CLI --approval-mode used for the next prompt"),
             SessionConfigOption::select(
                 "model",
                 "Model",
@@ -386,134 +746,69 @@ impl GeminiCliDriver {
 
     async fn run_gemini(
         &self,
-        cwd: PathBuf,
-        model: Option<String>,
-        prompt: String,
-        mut cancel_rx: watch::Receiver<bool>,
+        session_id: &SessionId,
+        session_store: Option<SessionStore>,
+        invocation: GeminiInvocation,
+        cancel_rx: watch::Receiver<bool>,
     ) -> Result<CommandRunResult, Error> {
+        let GeminiInvocation {
+            cwd,
+            model,
+            approval_mode,
+            prompt,
+            mcp_config,
+            attachment_dir,
+        } = invocation;
         let bin = Self::bin();
         let bin_display = bin.clone();
         let extra_args = Self::extra_args();
-        let approval_mode = Self::approval_mode();
+
+        let output_format = Self::output_format();
 
         let mut cmd = TokioCommand::new(&bin);
         cmd.current_dir(&cwd);
+        if let Some(path) = mcp_config {
+            cmd.env(SYSTEM_SETTINGS_ENV, path);
+        }
         cmd.arg("--output-format");
-        cmd.arg("text");
+        cmd.arg(&output_format);
         cmd.arg("--approval-mode");
-        cmd.arg(approval_mode);
+        cmd.arg(approval_mode.as_str());
         if let Some(model) = model {
             cmd.arg("--model");
             cmd.arg(model);
         }
+        if let Some(dir) = attachment_dir {
+            cmd.arg("--include-directories");
+            cmd.arg(dir);
+        }
         cmd.args(extra_args);
         cmd.arg("--prompt");
         cmd.arg(prompt);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
-        let mut child = cmd.spawn().map_err(|e| {
+        let child = cmd.spawn().map_err(|e| {
             Error::invalid_params().data(format!(
                 "failed to execute Gemini CLI ({bin_display}). Install it or set XSFIRE_GEMINI_BIN. Error: {e}"
             ))
         })?;
 
-        let mut stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| Error::internal_error().data("Gemini CLI stdout pipe missing"))?;
-        let mut stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| Error::internal_error().data("Gemini CLI stderr pipe missing"))?;
-
-        let stdout_task = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            stdout
-                .read_to_end(&mut buf)
-                .await
-                .map(|_| buf)
-                .map_err(|e| Error::internal_error().data(e.to_string()))
-        });
-        let stderr_task = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            stderr
-                .read_to_end(&mut buf)
-                .await
-                .map(|_| buf)
-                .map_err(|e| Error::internal_error().data(e.to_string()))
-        });
-
-        let (status, cancelled) = tokio::select! {
-            result = child.wait() => (
-                result.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                false,
-            ),
-            changed = cancel_rx.changed() => {
-                if changed.is_ok() && *cancel_rx.borrow() {
-                    drop(child.start_kill());
-                    (
-                        child.wait().await.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                        true,
-                    )
-                } else {
-                    (
-                        child.wait().await.map_err(|e| Error::internal_error().data(e.to_string()))?,
-                        false,
-                    )
-                }
-            }
+        let mut translator = GeminiStreamTranslator {
+            plain_text: output_format == "text",
+            ..GeminiStreamTranslator::default()
         };
-
-        let stdout_bytes = stdout_task
-            .await
-            .map_err(|e| Error::internal_error().data(e.to_string()))??;
-        let stderr_bytes = stderr_task
-            .await
-            .map_err(|e| Error::internal_error().data(e.to_string()))??;
-        let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
-        let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
-
-        if !cancelled && !status.success() {
-            return Err(Error::internal_error().data(format!(
-                "Gemini CLI failed (exit {:?}). stderr:\n{stderr}",
-                status.code()
-            )));
-        }
-
-        Ok(CommandRunResult {
-            stdout,
-            exit_code: status.code(),
-            cancelled,
-        })
-    }
-}
-
-enum GeminiCommand {
-    Help,
-    Status,
-    Reset,
-    SetModel(String),
-}
-
-fn parse_gemini_command(input: &str) -> Option<GeminiCommand> {
-    let trimmed = input.trim();
-    if trimmed == "/help" {
-        return Some(GeminiCommand::Help);
-    }
-    if trimmed == "/status" {
-        return Some(GeminiCommand::Status);
-    }
-    if trimmed == "/reset" {
-        return Some(GeminiCommand::Reset);
-    }
-    if let Some(value) = trimmed.strip_prefix("/model ") {
-        let model = value.trim();
-        if !model.is_empty() {
-            return Some(GeminiCommand::SetModel(model.to_string()));
-        }
+        run_streaming_cli(
+            "Gemini CLI",
+            child,
+            session_id,
+            session_store.as_ref(),
+            &mut translator,
+            std::future::ready(()),
+            cancel_rx,
+        )
+        .await
     }
-    None
 }
 
 #[async_trait::async_trait(?Send)]
@@ -522,6 +817,15 @@ impl BackendDriver for GeminiCliDriver {
         BackendKind::Gemini
     }
 
+    fn supports_load_session(&self) -> bool {
+        true
+    }
+
+    /// Images and blob resources are forwarded as files (see `prompt_attachments`).
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        PromptCapabilities::new().embedded_context(true).image(true)
+    }
+
     fn auth_methods(&self) -> Vec<AuthMethod> {
         vec![AuthMethod::new(
             Self::AUTH_METHOD_ID,
@@ -543,48 +847,109 @@ impl BackendDriver for GeminiCliDriver {
     async fn new_session(&self, request: NewSessionRequest) -> Result<NewSessionResponse, Error> {
         let session_id = SessionId::new(format!("gemini:{}", Uuid::new_v4()));
         let cwd = request.cwd;
-        let session_store = self.init_session_store(&session_id, &cwd);
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::Gemini,
+            BackendKind::Gemini.as_str(),
+            &session_id,
+            &cwd,
+        );
+        let mcp_config = Self::write_mcp_config(
+            &session_id,
+            session_store.as_ref(),
+            &request.mcp_servers,
+            &cwd,
+        );
 
         self.sessions.borrow_mut().insert(
             session_id.clone(),
             GeminiSession {
                 cwd,
                 model: Self::default_model(),
+                approval_mode: Self::default_approval_mode(),
                 history: Vec::new(),
                 session_store,
+                mcp_config,
                 active_prompt: None,
             },
         );
 
         info!("Created Gemini session: {session_id:?}");
-        let model = self
+        let (model, approval_mode) = self
             .sessions
             .borrow()
             .get(&session_id)
-            .and_then(|s| s.model.clone());
-        Ok(NewSessionResponse::new(session_id).config_options(Self::config_options(model)))
+            .map(|s| (s.model.clone(), s.approval_mode))
+            .unwrap_or((None, GeminiApprovalMode::Plan));
+        Ok(NewSessionResponse::new(session_id)
+            .modes(Self::modes(approval_mode))
+            .config_options(Self::config_options(model, approval_mode)))
     }
 
     async fn load_session(
         &self,
-        _request: LoadSessionRequest,
+        request: LoadSessionRequest,
     ) -> Result<LoadSessionResponse, Error> {
-        Err(Error::invalid_params().data(
-            "load_session is not supported for --backend=gemini yet (sessions are in-memory).",
-        ))
+        let LoadSessionRequest {
+            session_id,
+            cwd,
+            mcp_servers,
+            ..
+        } = request;
+        let Some(global_id) = stored_global_id(
+            self.global_session_index.as_deref(),
+            BackendKind::Gemini,
+            &session_id,
+        ) else {
+            return Err(Error::resource_not_found(Some(session_id.to_string())));
+        };
+
+        let records = SessionStore::read_canonical(&global_id);
+        let turns = turns_from_canonical(&records);
+        let (history, model) = restore_turns(&turns, Self::default_model());
+        let approval_mode = Self::restore_approval_mode(&records);
+        let session_store = init_session_store(
+            self.global_session_index.as_deref(),
+            BackendKind::Gemini,
+            BackendKind::Gemini.as_str(),
+            &session_id,
+            &cwd,
+        );
+        let mcp_config =
+            Self::write_mcp_config(&session_id, session_store.as_ref(), &mcp_servers, &cwd);
+        self.sessions.borrow_mut().insert(
+            session_id.clone(),
+            GeminiSession {
+                cwd,
+                model: model.clone(),
+                approval_mode,
+                history,
+                session_store,
+                mcp_config,
+                active_prompt: None,
+            },
+        );
+
+        info!(
+            "Loaded Gemini session {session_id:?} ({} persisted turns)",
+            turns.len()
+        );
+        replay_turns(&session_id, &turns).await;
+        Ok(LoadSessionResponse::new()
+            .modes(Self::modes(approval_mode))
+            .config_options(Self::config_options(model, approval_mode)))
     }
 
     async fn list_sessions(
         &self,
-        _request: ListSessionsRequest,
+        request: ListSessionsRequest,
     ) -> Result<ListSessionsResponse, Error> {
-        let sessions = self
-            .sessions
-            .borrow()
-            .iter()
-            .map(|(id, s)| SessionInfo::new(id.clone(), s.cwd.clone()).title("Gemini (in-memory)"))
-            .collect::<Vec<_>>();
-        Ok(ListSessionsResponse::new(sessions))
+        Ok(list_cli_sessions(
+            BackendKind::Gemini.as_str(),
+            &self.sessions.borrow(),
+            request.cwd.as_deref(),
+            "Gemini (in-memory)",
+        ))
     }
 
     async fn prompt(&self, request: PromptRequest) -> Result<PromptResponse, Error> {
@@ -600,27 +965,27 @@ impl BackendDriver for GeminiCliDriver {
             if let Some(session) = sessions.get(&session_id)
                 && let Some(store) = &session.session_store
             {
-                store.log("acp.prompt", serde_json::json!({ "text": user_text }));
+                store.log(CanonicalEvent::prompt_text(user_text.as_str()));
             }
         }
 
-        if let Some(command) = parse_gemini_command(&user_text) {
+        if let Some(command) = parse_cli_command(&user_text) {
             let message = {
                 let mut sessions = self.sessions.borrow_mut();
                 let Some(session) = sessions.get_mut(&session_id) else {
                     return Err(Error::resource_not_found(None));
                 };
                 match command {
-                    GeminiCommand::Help => Self::help_text(),
-                    GeminiCommand::Status => {
+                    CliCommand::Help => Self::help_text(),
+                    CliCommand::Status => {
                         let model = session.model.as_deref().unwrap_or("default");
-                        Self::status_text(model, session.history.len())
+                        Self::status_text(model, session.approval_mode, session.history.len())
                     }
-                    GeminiCommand::Reset => {
+                    CliCommand::Reset => {
                         session.history.clear();
                         "Gemini session history has been reset.".to_string()
                     }
-                    GeminiCommand::SetModel(model) => {
+                    CliCommand::SetModel(model) => {
                         let normalized = if model == "default" {
                             None
                         } else {
@@ -637,17 +1002,14 @@ impl BackendDriver for GeminiCliDriver {
                 if let Some(session) = sessions.get(&session_id)
                     && let Some(store) = &session.session_store
                 {
-                    store.log(
-                        "acp.agent_message_chunk",
-                        serde_json::json!({ "text": message }),
-                    );
+                    store.log(CanonicalEvent::agent_message(message.as_str()));
                 }
             }
             send_agent_text(&session_id, message).await;
             return Ok(PromptResponse::new(StopReason::EndTurn));
         }
 
-        let (cwd, model, full_prompt, cancel_rx) = {
+        let (invocation, session_store, cancel_rx, turn_files) = {
             let mut sessions = self.sessions.borrow_mut();
             let Some(session) = sessions.get_mut(&session_id) else {
                 return Err(Error::resource_not_found(None));
@@ -659,29 +1021,51 @@ impl BackendDriver for GeminiCliDriver {
                 )));
             }
 
-            let mut full_prompt = String::new();
-            for (user, assistant) in session.history.iter().rev().take(6).rev() {
-                full_prompt.push_str("User:\n");
-                full_prompt.push_str(user);
-                full_prompt.push_str("\n\nAssistant:\n");
-                full_prompt.push_str(assistant);
-                full_prompt.push_str("\n\n");
-            }
-            full_prompt.push_str("User:\n");
-            full_prompt.push_str(&user_text);
-
             let (cancel_tx, cancel_rx) = watch::channel(false);
             session.active_prompt = Some(cancel_tx);
 
+            // Gemini inlines `@path` mentions, images included, before sending the prompt.
+            let attachments = PromptAttachments::write(&session_id, &request.prompt);
+            let cli_text = match &attachments {
+                Some(attachments) => {
+                    prompt_blocks_with_attachments(&request.prompt, attachments, |attachment| {
+                        format!(
+                            "@{}",
+                            attachment.path.display().to_string().replace(' ', "\\ ")
+                        )
+                    })
+                }
+                None => user_text.clone(),
+            };
+            let mcp_config = McpConfigFile::write(session.mcp_config.as_ref());
+            let invocation = GeminiInvocation {
+                cwd: session.cwd.clone(),
+                model: session.model.clone(),
+                approval_mode: session.approval_mode,
+                prompt: with_context_handoff(
+                    &request,
+                    transcript_prompt(&session.history, &cli_text, TRANSCRIPT_TURNS),
+                ),
+                mcp_config: mcp_config
+                    .as_ref()
+                    .map(|config| config.path().to_path_buf()),
+                attachment_dir: attachments
+                    .as_ref()
+                    .map(|attachments| attachments.dir().to_path_buf()),
+            };
             (
-                session.cwd.clone(),
-                session.model.clone(),
-                full_prompt,
+                invocation,
+                session.session_store.clone(),
                 cancel_rx,
+                (attachments, mcp_config),
             )
         };
 
-        let output = self.run_gemini(cwd, model, full_prompt, cancel_rx).await;
+        let output = self
+            .run_gemini(&session_id, session_store, invocation, cancel_rx)
+            .await;
+        // The turn is over; remove its attachments and MCP config.
+        drop(turn_files);
         {
             let mut sessions = self.sessions.borrow_mut();
             if let Some(session) = sessions.get_mut(&session_id) {
@@ -697,19 +1081,8 @@ impl BackendDriver for GeminiCliDriver {
             return Ok(PromptResponse::new(StopReason::Cancelled));
         }
 
-        let output_text = output.stdout.trim_end_matches('\n').to_string();
-        {
-            let sessions = self.sessions.borrow();
-            if let Some(session) = sessions.get(&session_id)
-                && let Some(store) = &session.session_store
-            {
-                store.log(
-                    "acp.agent_message_chunk",
-                    serde_json::json!({ "text": output_text }),
-                );
-            }
-        }
-        send_agent_text(&session_id, &output_text).await;
+        // Updates were already streamed (and logged) while the CLI was running.
+        let output_text = output.output_text.trim_end_matches('\n').to_string();
 
         {
             let mut sessions = self.sessions.borrow_mut();
@@ -723,22 +1096,15 @@ impl BackendDriver for GeminiCliDriver {
     }
 
     async fn cancel(&self, args: CancelNotification) -> Result<(), Error> {
-        let sessions = self.sessions.borrow();
-        let Some(session) = sessions.get(&args.session_id) else {
-            return Err(Error::resource_not_found(None));
-        };
-        if let Some(cancel_tx) = &session.active_prompt {
-            let _ = cancel_tx.send(true);
-        }
-        Ok(())
+        cancel_cli_prompt(&self.sessions.borrow(), &args.session_id)
     }
 
     async fn set_session_mode(
         &self,
-        _args: SetSessionModeRequest,
+        args: SetSessionModeRequest,
     ) -> Result<SetSessionModeResponse, Error> {
-        Err(Error::invalid_params()
-            .data("set_session_mode is not supported for --backend=gemini yet."))
+        self.set_approval_mode(&args.session_id, args.mode_id.0.as_ref())?;
+        Ok(SetSessionModeResponse::new())
     }
 
     async fn set_session_model(
@@ -761,52 +1127,117 @@ impl BackendDriver for GeminiCliDriver {
         &self,
         args: SetSessionConfigOptionRequest,
     ) -> Result<SetSessionConfigOptionResponse, Error> {
-        if args.config_id.0.as_ref() != "model" {
-            return Err(Error::invalid_params().data(format!(
-                "unsupported config option for gemini backend: {}",
-                args.config_id
-            )));
+        match args.config_id.0.as_ref() {
+            "mode" => self.set_approval_mode(&args.session_id, args.value.0.as_ref())?,
+            "model" => {
+                let model = if args.value.0.as_ref() == "default" {
+                    None
+                } else {
+                    Some(args.value.0.to_string())
+                };
+                let mut sessions = self.sessions.borrow_mut();
+                let Some(session) = sessions.get_mut(&args.session_id) else {
+                    return Err(Error::resource_not_found(None));
+                };
+                session.model = model;
+            }
+            _ => {
+                return Err(Error::invalid_params().data(format!(
+                    "unsupported config option for gemini backend: {}",
+                    args.config_id
+                )));
+            }
         }
-        let model = if args.value.0.as_ref() == "default" {
-            None
-        } else {
-            Some(args.value.0.to_string())
-        };
-        let mut sessions = self.sessions.borrow_mut();
-        let Some(session) = sessions.get_mut(&args.session_id) else {
+        let sessions = self.sessions.borrow();
+        let Some(session) = sessions.get(&args.session_id) else {
             return Err(Error::resource_not_found(None));
         };
-        session.model = model;
         Ok(SetSessionConfigOptionResponse::new(Self::config_options(
             session.model.clone(),
+            session.approval_mode,
         )))
     }
 }
 
 impl GeminiCliDriver {
-    fn init_session_store(&self, session_id: &SessionId, cwd: &Path) -> Option<SessionStore> {
-        let idx = self.global_session_index.as_ref()?;
-        let global_id = idx
-            .lock()
-            .ok()
-            .and_then(|mut i| i.get_or_create(&format!("gemini:{}", session_id.0)))?;
-        SessionStore::init(
-            global_id,
-            "gemini",
-            session_id.0.to_string(),
-            session_id.0.to_string(),
-            Some(cwd),
+    /// Switch the `--approval-mode` used by the session's next CLI run.
+    fn set_approval_mode(&self, session_id: &SessionId, mode_id: &str) -> Result<(), Error> {
+        let Some(mode) = GeminiApprovalMode::from_str(mode_id) else {
+            return Err(Error::invalid_params().data(format!(
+                "unsupported Gemini approval mode: {mode_id} (expected plan, default, auto_edit or yolo)"
+            )));
+        };
+        let mut sessions = self.sessions.borrow_mut();
+        let Some(session) = sessions.get_mut(session_id) else {
+            return Err(Error::resource_not_found(None));
+        };
+        session.approval_mode = mode;
+        if let Some(store) = &session.session_store {
+            store.log(CanonicalEvent::SessionMode(ModeSetting {
+                mode: mode.as_str().to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Last approval mode recorded in the canonical log, or the configured default.
+    fn restore_approval_mode(records: &[CanonicalRecord]) -> GeminiApprovalMode {
+        records
+            .iter()
+            .rev()
+            .find_map(|record| match record.event()? {
+                CanonicalEvent::SessionMode(setting) => GeminiApprovalMode::from_str(&setting.mode),
+                _ => None,
+            })
+            .unwrap_or_else(Self::default_approval_mode)
+    }
+
+    /// Settings file the CLI would load as its system settings, so the session's MCP servers
+    /// are layered over it instead of hiding it.
+    fn system_settings() -> Option<Value> {
+        let path = std::env::var_os(SYSTEM_SETTINGS_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                PathBuf::from(if cfg!(target_os = "macos") {
+                    "/Library/Application Support/GeminiCli/settings.json"
+                } else if cfg!(windows) {
+                    "C:\\ProgramData\\gemini-cli\\settings.json"
+                } else {
+                    "/etc/gemini-cli/settings.json"
+                })
+            });
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    fn write_mcp_config(
+        session_id: &SessionId,
+        session_store: Option<&SessionStore>,
+        mcp_servers: &[McpServer],
+        cwd: &Path,
+    ) -> Option<Value> {
+        if mcp_servers.is_empty() {
+            return None;
+        }
+        McpConfigFormat::Gemini.session_config(
+            session_id,
+            session_store,
+            mcp_servers,
+            cwd,
+            Self::system_settings(),
         )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::GeminiCliDriver;
+    use super::{GeminiApprovalMode, GeminiCliDriver, GeminiStreamTranslator};
     use crate::backend::BackendDriver;
+    use crate::cli_common::StreamTranslator;
+    use crate::session_store::CanonicalRecord;
     use agent_client_protocol::{
-        AuthenticateRequest, CancelNotification, Error, NewSessionRequest, PromptRequest,
-        SessionConfigKind, SetSessionConfigOptionRequest, SetSessionModelRequest, StopReason,
+        AuthenticateRequest, CancelNotification, Error, NewSessionRequest, PlanEntryStatus,
+        PromptRequest, SessionConfigKind, SessionUpdate, SetSessionConfigOptionRequest,
+        SetSessionModeRequest, SetSessionModelRequest, StopReason, ToolCallStatus, ToolKind,
     };
     use std::{
         fs,
@@ -1303,19 +1734,83 @@ printf '%s\n' 'fake gemini output'
         assert!(message.contains("unsupported config option for gemini backend"));
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn session_modes_switch_the_gemini_approval_mode() {
+        let driver = GeminiCliDriver::new();
+        let cwd = std::env::current_dir().unwrap();
+        let session = driver
+            .new_session(NewSessionRequest::new(cwd))
+            .await
+            .unwrap();
+        let session_id = session.session_id.clone();
+        let modes = session.modes.expect("gemini sessions expose modes");
+        assert_eq!(modes.available_modes.len(), 4);
+
+        driver
+            .set_session_mode(SetSessionModeRequest::new(session_id.clone(), "auto_edit"))
+            .await
+            .unwrap();
+        assert_eq!(
+            driver.sessions.borrow()[&session_id].approval_mode,
+            GeminiApprovalMode::AutoEdit
+        );
+
+        let response = driver
+            .set_session_config_option(SetSessionConfigOptionRequest::new(
+                session_id.clone(),
+                "mode",
+                "yolo",
+            ))
+            .await
+            .unwrap();
+        let selected_mode = response
+            .config_options
+            .iter()
+            .find(|option| option.id.0.as_ref() == "mode")
+            .map(|option| match &option.kind {
+                SessionConfigKind::Select(select) => select.current_value.0.to_string(),
+                _ => panic!("mode config option should be a select"),
+            });
+        assert_eq!(selected_mode.as_deref(), Some("yolo"));
+
+        let error = driver
+            .set_session_mode(SetSessionModeRequest::new(session_id.clone(), "reckless"))
+            .await
+            .unwrap_err();
+        assert!(error_message(&error).contains("unsupported Gemini approval mode"));
+        assert_eq!(
+            driver.sessions.borrow()[&session_id].approval_mode,
+            GeminiApprovalMode::Yolo
+        );
+
+        let records = ["plan", "auto_edit"]
+            .into_iter()
+            .map(|mode| CanonicalRecord {
+                ts_ms: 0,
+                kind: "acp.session_mode".to_string(),
+                data: serde_json::json!({ "mode": mode }),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            GeminiCliDriver::restore_approval_mode(&records),
+            GeminiApprovalMode::AutoEdit
+        );
+    }
+
     #[test]
     fn gemini_help_text_exposes_work_orchestration_profile() {
         let help = GeminiCliDriver::help_text();
         assert!(help.contains("Gemini commands:"));
         assert!(help.contains("Work orchestration profile: Gemini CLI"));
         assert!(help.contains("Goal/Rubric/Next Action"));
-        assert!(help.contains("single ACP message chunk only"));
+        assert!(help.contains("live ACP plan/tool updates available"));
     }
 
     #[test]
     fn gemini_status_text_exposes_sequential_profile_defaults() {
-        let status = GeminiCliDriver::status_text("gemini-2.5-pro", 3);
+        let status = GeminiCliDriver::status_text("gemini-2.5-pro", GeminiApprovalMode::Plan, 3);
         assert!(status.contains("- model: gemini-2.5-pro"));
+        assert!(status.contains("- approval_mode: plan"));
         assert!(status.contains("- history_turns: 3"));
         assert!(status.contains("- task_orchestration: sequential"));
         assert!(status.contains("- task_monitoring: status-only"));
@@ -1323,4 +1818,96 @@ printf '%s\n' 'fake gemini output'
         assert!(status.contains("- preempt_on_new_prompt: off"));
         assert!(status.contains("- work_orchestration_sequence: R->P->M->W->A"));
     }
+
+    #[test]
+    fn stream_translator_maps_gemini_events_to_acp_updates() {
+        let mut translator = GeminiStreamTranslator::default();
+
+        let init = translator
+            .handle_line(r#"{"type":"init","session_id":"abc","model":"gemini-2.5-pro"}"#);
+        assert!(init.is_empty());
+        let echo = translator.handle_line(r#"{"type":"message","role":"user","content":"hi"}"#);
+        assert!(echo.is_empty());
+
+        let tool = translator.handle_line(
+            r#"{"type":"tool_use","tool_name":"read_file","tool_id":"read-1","parameters":{"absolute_path":"/tmp/a.rs"}}"#,
+        );
+        match tool.as_slice() {
+            [SessionUpdate::ToolCall(call)] => {
+                assert_eq!(call.tool_call_id.0.as_ref(), "read-1");
+                assert_eq!(call.kind, ToolKind::Read);
+                assert_eq!(call.status, ToolCallStatus::InProgress);
+                assert_eq!(call.locations.len(), 1);
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+
+        let result = translator.handle_line(
+            r#"{"type":"tool_result","tool_id":"read-1","status":"error","error":{"type":"not_found","message":"missing"}}"#,
+        );
+        match result.as_slice() {
+            [SessionUpdate::ToolCallUpdate(update)] => {
+                assert_eq!(update.tool_call_id.0.as_ref(), "read-1");
+                assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+
+        translator
+            .handle_line(r#"{"type":"message","role":"assistant","content":"Hel","delta":true}"#);
+        translator
+            .handle_line(r#"{"type":"message","role":"assistant","content":"lo","delta":true}"#);
+        let done = translator.handle_line(r#"{"type":"result","status":"success","stats":{}}"#);
+        assert!(done.is_empty());
+        assert_eq!(translator.output_text, "Hello");
+        assert!(translator.error.is_none());
+    }
+
+    #[test]
+    fn stream_translator_turns_write_todos_into_plan_and_records_errors() {
+        let mut translator = GeminiStreamTranslator::default();
+
+        let plan = translator.handle_line(
+            r#"{"type":"tool_use","tool_name":"write_todos","tool_id":"todo-1","parameters":{"todos":[{"description":"Survey papers","status":"completed"},{"description":"Summarize","status":"in_progress"}]}}"#,
+        );
+        match plan.as_slice() {
+            [SessionUpdate::Plan(plan)] => {
+                assert_eq!(plan.entries.len(), 2);
+                assert_eq!(plan.entries[0].status, PlanEntryStatus::Completed);
+                assert_eq!(plan.entries[1].status, PlanEntryStatus::InProgress);
+            }
+            other => panic!("unexpected updates: {other:?}"),
+        }
+        let todo_result = translator
+            .handle_line(r#"{"type":"tool_result","tool_id":"todo-1","status":"success"}"#);
+        assert!(todo_result.is_empty());
+
+        let warning = translator
+            .handle_line(r#"{"type":"error","severity":"warning","message":"loop detected"}"#);
+        assert!(matches!(
+            warning.as_slice(),
+            [SessionUpdate::AgentThoughtChunk(_)]
+        ));
+        translator.handle_line(
+            r#"{"type":"result","status":"error","error":{"type":"api","message":"quota exceeded"}}"#,
+        );
+        assert_eq!(translator.error.as_deref(), Some("quota exceeded"));
+    }
+
+    #[test]
+    fn stream_translator_keeps_plain_text_lines_in_text_mode() {
+        let mut translator = GeminiStreamTranslator {
+            plain_text: true,
+            ..GeminiStreamTranslator::default()
+        };
+
+        translator.handle_line("");
+        translator.handle_line("first line");
+        translator.handle_line("");
+        translator.handle_line(r#"{"type":"message"}"#);
+        assert_eq!(
+            translator.output_text,
+            "first line\n\n{\"type\":\"message\"}"
+        );
+    }
 }
@@ -2,9 +2,9 @@
 #![deny(clippy::print_stdout, clippy::print_stderr)]
 
 use agent_client_protocol::{
-    AgentSideConnection, Client, CreateTerminalRequest, CreateTerminalResponse,
-    KillTerminalCommandRequest, KillTerminalCommandResponse, ReleaseTerminalRequest,
-    ReleaseTerminalResponse, TerminalOutputRequest, TerminalOutputResponse,
+    AgentSideConnection, Client, CreateTerminalRequest, CreateTerminalResponse, ExtRequest,
+    ExtResponse, KillTerminalCommandRequest, KillTerminalCommandResponse, ReleaseTerminalRequest,
+    ReleaseTerminalResponse, SessionId, TerminalId, TerminalOutputRequest, TerminalOutputResponse,
     WaitForTerminalExitRequest, WaitForTerminalExitResponse,
 };
 use codex_common::CliConfigOverrides;
@@ -19,17 +19,35 @@ use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 use tracing_subscriber::EnvFilter;
 
 mod acp_agent;
+mod approval_policy;
 pub mod backend;
+mod canonical_event;
+mod canonical_log;
 mod claude_code_agent;
+pub mod claude_permission;
 mod cli_common;
 mod codex_agent;
+mod custom_backend;
+mod elicitation;
 mod gemini_agent;
 mod link_paths;
+mod local_backend;
 mod local_spawner;
+pub mod log_cli;
+mod mcp_config;
+pub mod mcp_sse_bridge;
 mod multi_backend;
+mod plan_timeline;
+mod project_settings;
 mod prompt_args;
+mod prompt_attachments;
+pub mod pty_terminal;
+mod redaction;
+mod retrospective;
+mod search_index;
 mod session_store;
 mod thread;
+mod token_counter;
 
 pub static ACP_CLIENT: OnceLock<Arc<AgentSideConnection>> = OnceLock::new();
 static ACP_CLIENT_INFO: OnceLock<Arc<Mutex<Option<String>>>> = OnceLock::new();
@@ -60,6 +78,11 @@ enum AcpTerminalRpc {
         request: KillTerminalCommandRequest,
         respond_to: oneshot::Sender<AcpTerminalRpcResult<KillTerminalCommandResponse>>,
     },
+    /// `terminal/write` extension, only sent to clients that advertise `terminal_write`.
+    WriteTerminal {
+        request: ExtRequest,
+        respond_to: oneshot::Sender<AcpTerminalRpcResult<ExtResponse>>,
+    },
 }
 
 fn acp_client_info() -> &'static Arc<Mutex<Option<String>>> {
@@ -170,6 +193,19 @@ async fn run_acp_terminal_rpc_loop(
                     ),
                 );
             }
+            AcpTerminalRpc::WriteTerminal {
+                request,
+                respond_to,
+            } => {
+                drop(
+                    respond_to.send(
+                        client
+                            .ext_method(request)
+                            .await
+                            .map_err(|err| err.to_string()),
+                    ),
+                );
+            }
         }
     }
 }
@@ -243,6 +279,26 @@ pub(crate) async fn acp_kill_terminal_command(
     .await
 }
 
+/// Write `data` to the stdin of a command running in an ACP client terminal.
+pub(crate) async fn acp_write_terminal(
+    session_id: SessionId,
+    terminal_id: TerminalId,
+    data: String,
+) -> AcpTerminalRpcResult<()> {
+    let params = serde_json::value::to_raw_value(&serde_json::json!({
+        "sessionId": session_id,
+        "terminalId": terminal_id,
+        "data": data,
+    }))
+    .map_err(|err| err.to_string())?;
+    dispatch_acp_terminal_rpc(|respond_to| AcpTerminalRpc::WriteTerminal {
+        request: ExtRequest::new("terminal/write", params.into()),
+        respond_to,
+    })
+    .await
+    .map(drop)
+}
+
 /// Run the Codex ACP agent.
 ///
 /// This sets up an ACP agent that communicates over stdio, bridging
@@ -286,6 +342,12 @@ pub async fn run_main(
                 )
             })?;
 
+    // Prune old canonical sessions and index unseen ones without delaying the ACP handshake.
+    tokio::task::spawn_blocking(|| {
+        session_store::SessionStore::apply_retention();
+        session_store::SessionStore::backfill_search_index();
+    });
+
     let client_capabilities: Arc<Mutex<agent_client_protocol::ClientCapabilities>> = Arc::default();
 
     let driver: Rc<dyn backend::BackendDriver> = match backend_kind {
@@ -293,8 +355,24 @@ pub async fn run_main(
             config,
             client_capabilities.clone(),
         )),
+        backend::BackendKind::Local => {
+            let provider = local_backend::LocalProvider::from_env().map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{e:#}"))
+            })?;
+            let mut config = config;
+            provider.configure(&mut config).await;
+            let codex = codex_agent::CodexDriver::new(config.clone(), client_capabilities.clone())
+                .with_models_manager(Arc::new(local_backend::LocalModelsManager));
+            Rc::new(local_backend::LocalModelDriver::new(codex, config))
+        }
         backend::BackendKind::ClaudeCode => Rc::new(claude_code_agent::ClaudeCodeDriver::new()),
         backend::BackendKind::Gemini => Rc::new(gemini_agent::GeminiCliDriver::new()),
+        backend::BackendKind::Custom => {
+            let spec = custom_backend::CustomBackendSpec::from_env().map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{e:#}"))
+            })?;
+            Rc::new(custom_backend::CustomCliDriver::new(spec))
+        }
         backend::BackendKind::Multi => Rc::new(multi_backend::MultiBackendDriver::new(
             Rc::new(codex_agent::CodexDriver::new(
                 config.clone(),
@@ -0,0 +1,499 @@
+//! Fully local backend (`--backend=local`).
+//!
+//! Runs the Codex runtime against an OpenAI-compatible endpoint on the machine (Ollama by
+//! default), so air-gapped repositories keep sessions, approvals, tool calls and plan updates.
+//! Models are discovered through the vendored Ollama client and surfaced in the `model`
+//! config option; `authenticate` only checks that the endpoint is reachable.
+
+use agent_client_protocol::{
+    AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, Error,
+    ForkSessionRequest, ForkSessionResponse, ListSessionsRequest, ListSessionsResponse,
+    LoadSessionRequest, LoadSessionResponse, NewSessionRequest, NewSessionResponse,
+    PromptCapabilities, PromptRequest, PromptResponse, ResumeSessionRequest, ResumeSessionResponse,
+    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
+    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
+};
+use anyhow::bail;
+use codex_core::{
+    OLLAMA_OSS_PROVIDER_ID, WireApi, config::Config, create_oss_provider_with_base_url,
+};
+use codex_ollama::{DEFAULT_OSS_MODEL, OllamaClient, detect_wire_api};
+use codex_protocol::openai_models::{ModelPreset, ReasoningEffort};
+use tracing::warn;
+
+use crate::{
+    backend::{BackendDriver, BackendKind},
+    codex_agent::CodexDriver,
+    thread::ModelsManagerImpl,
+};
+
+/// Where the local backend sends model traffic, read from `XSFIRE_LOCAL_*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalProvider {
+    pub base_url: String,
+    /// Forced wire API; detected from the Ollama version when unset.
+    pub wire_api: Option<WireApi>,
+    pub model: Option<String>,
+}
+
+impl LocalProvider {
+    const BASE_URL_ENV: &'static str = "XSFIRE_LOCAL_BASE_URL";
+    const MODEL_ENV: &'static str = "XSFIRE_LOCAL_MODEL";
+    const WIRE_API_ENV: &'static str = "XSFIRE_LOCAL_WIRE_API";
+    const DEFAULT_BASE_URL: &'static str = "http://localhost:11434/v1";
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let wire_api = match var(Self::WIRE_API_ENV).as_deref() {
+            None => None,
+            Some("chat") => Some(WireApi::Chat),
+            Some("responses") => Some(WireApi::Responses),
+            Some(other) => bail!(
+                "{} must be `chat` or `responses` (got `{other}`)",
+                Self::WIRE_API_ENV
+            ),
+        };
+        Ok(Self {
+            base_url: var(Self::BASE_URL_ENV).unwrap_or_else(|| Self::DEFAULT_BASE_URL.to_string()),
+            wire_api,
+            model: var(Self::MODEL_ENV),
+        })
+    }
+
+    /// Point `config` at the local endpoint. The provider is registered under the built-in
+    /// Ollama id so the vendored Ollama client picks up the same base URL.
+    pub async fn configure(&self, config: &mut Config) {
+        let wire_api = match self.wire_api {
+            Some(wire_api) => wire_api,
+            None => {
+                let provider =
+                    create_oss_provider_with_base_url(&self.base_url, WireApi::Responses);
+                match detect_wire_api(&provider).await {
+                    Ok(Some(detection)) => detection.wire_api,
+                    Ok(None) | Err(_) => WireApi::Responses,
+                }
+            }
+        };
+        let provider = create_oss_provider_with_base_url(&self.base_url, wire_api);
+        config
+            .model_providers
+            .insert(OLLAMA_OSS_PROVIDER_ID.to_string(), provider.clone());
+        config.model_provider_id = OLLAMA_OSS_PROVIDER_ID.to_string();
+        config.model_provider = provider;
+        // Hosted model names from config.toml mean nothing to a local server.
+        config.model = match &self.model {
+            Some(model) => Some(model.clone()),
+            None => Some(
+                served_models(config)
+                    .await
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| DEFAULT_OSS_MODEL.to_string()),
+            ),
+        };
+    }
+}
+
+/// Models reported by the local server; empty when it is unreachable or not Ollama.
+async fn served_models(config: &Config) -> Vec<String> {
+    let client = match OllamaClient::try_from_oss_provider(config).await {
+        Ok(client) => client,
+        Err(err) => {
+            warn!("Local model server is not reachable: {err}");
+            return Vec::new();
+        }
+    };
+    client.fetch_models().await.unwrap_or_else(|err| {
+        warn!("Failed to list local models: {err}");
+        Vec::new()
+    })
+}
+
+/// Model catalog built from the models the local server has pulled.
+pub struct LocalModelsManager;
+
+#[async_trait::async_trait]
+impl ModelsManagerImpl for LocalModelsManager {
+    async fn get_model(&self, model_id: &Option<String>, config: &Config) -> String {
+        model_id
+            .clone()
+            .or_else(|| config.model.clone())
+            .unwrap_or_else(|| DEFAULT_OSS_MODEL.to_string())
+    }
+
+    async fn list_models(&self, config: &Config) -> Vec<ModelPreset> {
+        let host = config.model_provider.base_url.clone().unwrap_or_default();
+        served_models(config)
+            .await
+            .into_iter()
+            .map(|model| ModelPreset {
+                id: model.clone(),
+                display_name: model.clone(),
+                description: format!("Served locally by {host}"),
+                default_reasoning_effort: ReasoningEffort::Medium,
+                supported_reasoning_efforts: Vec::new(),
+                supports_personality: false,
+                is_default: config.model.as_deref() == Some(model.as_str()),
+                upgrade: None,
+                show_in_picker: true,
+                supported_in_api: true,
+                model,
+            })
+            .collect()
+    }
+}
+
+/// [`CodexDriver`] with local-endpoint authentication and model discovery.
+pub struct LocalModelDriver {
+    codex: CodexDriver,
+    config: Config,
+}
+
+impl LocalModelDriver {
+    const AUTH_METHOD_ID: &'static str = "local-endpoint";
+
+    /// `config` must already be [configured](LocalProvider::configure) for the local endpoint.
+    pub fn new(codex: CodexDriver, config: Config) -> Self {
+        Self { codex, config }
+    }
+
+    fn base_url(&self) -> &str {
+        self.config
+            .model_provider
+            .base_url
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    async fn check_endpoint(&self) -> Result<(), Error> {
+        let client = OllamaClient::try_from_oss_provider(&self.config)
+            .await
+            .map_err(|e| {
+                Error::invalid_params().data(format!(
+                    "local model server at {} is not reachable: {e}",
+                    self.base_url()
+                ))
+            })?;
+        // Plain OpenAI-compatible servers have no Ollama model list; only Ollama can tell us
+        // that the configured model is missing.
+        let models = client.fetch_models().await.unwrap_or_default();
+        if let Some(model) = &self.config.model
+            && !models.is_empty()
+            && !models.contains(model)
+        {
+            return Err(Error::invalid_params().data(format!(
+                "model `{model}` is not available at {} (found: {}). Pull it with `ollama pull {model}` or set XSFIRE_LOCAL_MODEL.",
+                self.base_url(),
+                models.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl BackendDriver for LocalModelDriver {
+    fn backend_kind(&self) -> BackendKind {
+        BackendKind::Local
+    }
+
+    fn supports_load_session(&self) -> bool {
+        self.codex.supports_load_session()
+    }
+
+    fn supports_fork_session(&self) -> bool {
+        self.codex.supports_fork_session()
+    }
+
+    fn supports_resume_session(&self) -> bool {
+        self.codex.supports_resume_session()
+    }
+
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        self.codex.prompt_capabilities()
+    }
+
+    fn auth_methods(&self) -> Vec<AuthMethod> {
+        vec![
+            AuthMethod::new(Self::AUTH_METHOD_ID, "Local model server").description(format!(
+                "No credentials needed. ACP authenticate checks that {} is reachable and serves the selected model.",
+                self.base_url()
+            )),
+        ]
+    }
+
+    async fn authenticate(
+        &self,
+        request: AuthenticateRequest,
+    ) -> Result<AuthenticateResponse, Error> {
+        if request.method_id.0.as_ref() != Self::AUTH_METHOD_ID {
+            return Err(Error::invalid_params().data(format!(
+                "unsupported auth method for local backend: {}",
+                request.method_id.0
+            )));
+        }
+        self.check_endpoint().await?;
+        Ok(AuthenticateResponse::new())
+    }
+
+    async fn new_session(&self, request: NewSessionRequest) -> Result<NewSessionResponse, Error> {
+        self.codex.new_session(request).await
+    }
+
+    async fn load_session(
+        &self,
+        request: LoadSessionRequest,
+    ) -> Result<LoadSessionResponse, Error> {
+        self.codex.load_session(request).await
+    }
+
+    async fn fork_session(
+        &self,
+        request: ForkSessionRequest,
+    ) -> Result<ForkSessionResponse, Error> {
+        self.codex.fork_session(request).await
+    }
+
+    async fn resume_session(
+        &self,
+        request: ResumeSessionRequest,
+    ) -> Result<ResumeSessionResponse, Error> {
+        self.codex.resume_session(request).await
+    }
+
+    async fn list_sessions(
+        &self,
+        request: ListSessionsRequest,
+    ) -> Result<ListSessionsResponse, Error> {
+        self.codex.list_sessions(request).await
+    }
+
+    async fn prompt(&self, request: PromptRequest) -> Result<PromptResponse, Error> {
+        self.codex.prompt(request).await
+    }
+
+    async fn cancel(&self, args: CancelNotification) -> Result<(), Error> {
+        self.codex.cancel(args).await
+    }
+
+    async fn set_session_mode(
+        &self,
+        args: SetSessionModeRequest,
+    ) -> Result<SetSessionModeResponse, Error> {
+        self.codex.set_session_mode(args).await
+    }
+
+    async fn set_session_model(
+        &self,
+        args: SetSessionModelRequest,
+    ) -> Result<SetSessionModelResponse, Error> {
+        self.codex.set_session_model(args).await
+    }
+
+    async fn set_session_config_option(
+        &self,
+        args: SetSessionConfigOptionRequest,
+    ) -> Result<SetSessionConfigOptionResponse, Error> {
+        self.codex.set_session_config_option(args).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalModelDriver, LocalModelsManager, LocalProvider};
+    use crate::{backend::BackendDriver, codex_agent::CodexDriver, thread::ModelsManagerImpl};
+    use agent_client_protocol::AuthenticateRequest;
+    use codex_core::{
+        OLLAMA_OSS_PROVIDER_ID, WireApi,
+        config::{Config, ConfigOverrides},
+    };
+    use std::path::PathBuf;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use uuid::Uuid;
+
+    /// Points `CODEX_HOME` at an empty directory so the developer's own Codex config does
+    /// not leak into the loaded [`Config`]. Hold `ENV_LOCK` while it is alive.
+    struct TempCodexHome {
+        previous: Option<String>,
+        dir: PathBuf,
+    }
+
+    impl TempCodexHome {
+        fn install() -> Self {
+            let dir = std::env::temp_dir().join(format!("xsfire-local-codex-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let previous = std::env::var("CODEX_HOME").ok();
+            // Safe in tests when serialized by ENV_LOCK.
+            unsafe {
+                std::env::set_var("CODEX_HOME", &dir);
+            }
+            Self { previous, dir }
+        }
+    }
+
+    impl Drop for TempCodexHome {
+        fn drop(&mut self) {
+            // Safe in tests when serialized by ENV_LOCK.
+            unsafe {
+                match &self.previous {
+                    Some(previous) => std::env::set_var("CODEX_HOME", previous),
+                    None => std::env::remove_var("CODEX_HOME"),
+                }
+            }
+            drop(std::fs::remove_dir_all(&self.dir));
+        }
+    }
+
+    /// Minimal Ollama stand-in: `/v1/models` for the reachability probe and `/api/tags` for
+    /// model discovery.
+    async fn spawn_mock_ollama(models: &'static [&'static str]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    break;
+                };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match path {
+                    "/v1/models" => (
+                        "200 OK",
+                        serde_json::json!({
+                            "object": "list",
+                            "data": models.iter().map(|id| serde_json::json!({ "id": id })).collect::<Vec<_>>(),
+                        }),
+                    ),
+                    "/api/tags" => (
+                        "200 OK",
+                        serde_json::json!({
+                            "models": models.iter().map(|name| serde_json::json!({ "name": name })).collect::<Vec<_>>(),
+                        }),
+                    ),
+                    _ => ("404 Not Found", serde_json::json!({})),
+                };
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                drop(stream.write_all(response.as_bytes()).await);
+            }
+        });
+        format!("http://{addr}/v1")
+    }
+
+    async fn local_config(base_url: String, model: Option<&str>) -> Config {
+        let mut config = Config::load_with_cli_overrides_and_harness_overrides(
+            vec![],
+            ConfigOverrides::default(),
+        )
+        .await
+        .unwrap();
+        LocalProvider {
+            base_url,
+            wire_api: None,
+            model: model.map(str::to_string),
+        }
+        .configure(&mut config)
+        .await;
+        config
+    }
+
+    fn local_driver(config: Config) -> LocalModelDriver {
+        LocalModelDriver::new(CodexDriver::new(config.clone(), Default::default()), config)
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    #[allow(clippy::await_holding_lock)]
+    async fn configure_targets_the_endpoint_and_lists_served_models() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _codex_home = TempCodexHome::install();
+        let base_url = spawn_mock_ollama(&["qwen3-coder:30b", "gpt-oss:20b"]).await;
+        let config = local_config(base_url.clone(), None).await;
+
+        assert_eq!(config.model_provider_id, OLLAMA_OSS_PROVIDER_ID);
+        // No `/api/version` on the mock: keep the Responses API default.
+        assert_eq!(config.model_provider.wire_api, WireApi::Responses);
+        assert_eq!(
+            config.model_provider.base_url.as_deref(),
+            Some(base_url.as_str())
+        );
+        // Without XSFIRE_LOCAL_MODEL the first served model is picked.
+        assert_eq!(config.model.as_deref(), Some("qwen3-coder:30b"));
+
+        let presets = LocalModelsManager.list_models(&config).await;
+        let models = presets
+            .iter()
+            .map(|preset| preset.model.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(models, vec!["qwen3-coder:30b", "gpt-oss:20b"]);
+        assert!(presets[0].is_default);
+        assert_eq!(
+            LocalModelsManager
+                .get_model(&Some("gpt-oss:20b".to_string()), &config)
+                .await,
+            "gpt-oss:20b"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    #[allow(clippy::await_holding_lock)]
+    async fn authenticate_checks_reachability_and_the_selected_model() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let _codex_home = TempCodexHome::install();
+        let base_url = spawn_mock_ollama(&["gpt-oss:20b"]).await;
+
+        let driver = local_driver(local_config(base_url.clone(), Some("gpt-oss:20b")).await);
+        driver
+            .authenticate(AuthenticateRequest::new("local-endpoint"))
+            .await
+            .unwrap();
+        assert!(
+            driver
+                .authenticate(AuthenticateRequest::new("chatgpt"))
+                .await
+                .is_err()
+        );
+
+        let driver = local_driver(local_config(base_url, Some("llama3.3")).await);
+        let err = driver
+            .authenticate(AuthenticateRequest::new("local-endpoint"))
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("ollama pull llama3.3"));
+
+        // Nothing listens on a freshly released port.
+        let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead_url = format!("http://{}/v1", unused.local_addr().unwrap());
+        drop(unused);
+        let driver = local_driver(local_config(dead_url, Some("gpt-oss:20b")).await);
+        let err = driver
+            .authenticate(AuthenticateRequest::new("local-endpoint"))
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("not reachable"));
+    }
+}
@@ -0,0 +1,525 @@
+//! `xsfire-camp log ...`: inspect, search and export the canonical session store.
+//!
+//! Runs before ACP stdio starts and only reads `ACP_HOME`. Sessions can be named by their
+//! global id (or a unique prefix of it), by an index key such as `codex:<session id>`, or by
+//! the ACP session id the client saw. `log schema` prints the JSON Schema of a log line.
+
+use std::{
+    cmp::Reverse,
+    io::{self, Write},
+};
+
+use anyhow::{Context as _, bail};
+use chrono::{DateTime, Local};
+use regex_lite::Regex;
+use serde_json::{Value, json};
+
+use crate::{
+    canonical_event::{self, CanonicalEvent, PlanStepStatus},
+    session_store::{CanonicalRecord, GlobalSessionIndex, SessionStore, StoredSession},
+};
+
+/// Subcommand that exposes the canonical log tooling.
+pub const LOG_SUBCOMMAND: &str = "log";
+
+const USAGE: &str = "usage:
+  xsfire-camp log list [--verbose]
+  xsfire-camp log show <session>
+  xsfire-camp log grep <pattern>
+  xsfire-camp log export <session> [--format md|json|html]
+  xsfire-camp log schema";
+
+/// Longest event text shown on one line by `show` and `grep`.
+const LINE_TEXT_MAX_CHARS: usize = 240;
+
+/// Entry point of the `log` subcommand; `args` are the arguments after `log`.
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let mut out = io::stdout().lock();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["list"] => list(&mut out, false),
+        ["list", "--verbose" | "-v"] => list(&mut out, true),
+        ["show", session] => show(&mut out, &resolve_session(session)?),
+        ["grep", pattern] => grep(&mut out, pattern),
+        ["export", session, rest @ ..] => {
+            let format = match rest {
+                [] => ExportFormat::Markdown,
+                ["--format", format] => ExportFormat::parse(format)?,
+                [flag] => match flag.strip_prefix("--format=") {
+                    Some(format) => ExportFormat::parse(format)?,
+                    None => bail!("{USAGE}"),
+                },
+                _ => bail!("{USAGE}"),
+            };
+            export(&mut out, &resolve_session(session)?, format)
+        }
+        ["schema"] => {
+            let schema = canonical_event::json_schema();
+            writeln!(out, "{}", serde_json::to_string_pretty(&schema)?)?;
+            Ok(())
+        }
+        ["help" | "--help" | "-h"] => {
+            writeln!(out, "{USAGE}")?;
+            Ok(())
+        }
+        _ => bail!("{USAGE}"),
+    }
+}
+
+/// Find the stored session named by `query`.
+fn resolve_session(query: &str) -> anyhow::Result<StoredSession> {
+    let sessions = SessionStore::list_all();
+    let indexed = GlobalSessionIndex::load().and_then(|index| index.get(query));
+    let exact = sessions.iter().find(|session| {
+        session.global_session_id == query
+            || indexed.as_deref() == Some(session.global_session_id.as_str())
+            || session.acp_session_id == query
+    });
+    if let Some(session) = exact {
+        return Ok(session.clone());
+    }
+    let mut matches = sessions
+        .into_iter()
+        .filter(|session| session.global_session_id.starts_with(query));
+    match (matches.next(), matches.next()) {
+        (Some(session), None) => Ok(session),
+        (Some(_), Some(_)) => bail!("session id prefix {query:?} is ambiguous"),
+        (None, _) => bail!("no stored session matches {query:?}"),
+    }
+}
+
+fn format_ts(ts_ms: u64) -> String {
+    DateTime::from_timestamp_millis(ts_ms as i64).map_or_else(
+        || ts_ms.to_string(),
+        |ts| {
+            ts.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+fn one_line(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= LINE_TEXT_MAX_CHARS {
+        return text;
+    }
+    let cut = text
+        .chars()
+        .take(LINE_TEXT_MAX_CHARS - 3)
+        .collect::<String>();
+    format!("{cut}...")
+}
+
+/// A readable step of a session: prompts, replies, tool calls, approvals and plans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimelineEntry {
+    ts_ms: u64,
+    label: &'static str,
+    text: String,
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+/// Fold canonical records into timeline entries. Consecutive message chunks are joined and
+/// bookkeeping events (diagnostics, settings, token usage) are left out.
+fn timeline(records: &[CanonicalRecord]) -> Vec<TimelineEntry> {
+    let mut entries: Vec<TimelineEntry> = Vec::new();
+    for record in records {
+        let Some(event) = record.event() else {
+            continue;
+        };
+        let (label, text) = match event {
+            CanonicalEvent::Prompt(prompt) => ("prompt", prompt.text),
+            CanonicalEvent::UserMessageChunk(chunk) => ("user", chunk.text),
+            CanonicalEvent::AgentMessageChunk(chunk) => ("agent", chunk.text),
+            CanonicalEvent::AgentThoughtChunk(chunk) => ("thought", chunk.text),
+            CanonicalEvent::ToolCall(tool_call) => {
+                let title = str_field(&tool_call, "title").unwrap_or("tool call");
+                let status = str_field(&tool_call, "status").unwrap_or("pending");
+                ("tool", format!("{title} [{status}]"))
+            }
+            CanonicalEvent::ToolCallUpdate(update) => {
+                let Some(status) = str_field(&update, "status") else {
+                    continue;
+                };
+                let name = str_field(&update, "title")
+                    .or_else(|| str_field(&update, "toolCallId"))
+                    .unwrap_or("tool call");
+                ("tool", format!("{name} -> {status}"))
+            }
+            CanonicalEvent::RequestPermission(request) => {
+                let title = str_field(&request.tool_call, "title").unwrap_or("tool call");
+                ("approval", format!("requested: {title}"))
+            }
+            CanonicalEvent::RequestPermissionResponse(response) => {
+                let outcome = &response.outcome;
+                let text = match str_field(outcome, "optionId") {
+                    Some(option) => format!("answered: {option}"),
+                    None => format!(
+                        "answered: {}",
+                        str_field(outcome, "outcome").unwrap_or("unknown")
+                    ),
+                };
+                ("approval", text)
+            }
+            CanonicalEvent::Plan(plan) => {
+                let items = plan
+                    .items
+                    .iter()
+                    .map(|item| {
+                        let mark = match item.status {
+                            PlanStepStatus::Completed => "[x]",
+                            PlanStepStatus::InProgress => "[~]",
+                            PlanStepStatus::Pending => "[ ]",
+                        };
+                        format!("{mark} {}", item.step)
+                    })
+                    .collect::<Vec<_>>();
+                ("plan", items.join("\n"))
+            }
+            _ => continue,
+        };
+        if matches!(label, "user" | "agent" | "thought")
+            && let Some(last) = entries.last_mut()
+            && last.label == label
+        {
+            last.text.push_str(&text);
+            continue;
+        }
+        entries.push(TimelineEntry {
+            ts_ms: record.ts_ms,
+            label,
+            text,
+        });
+    }
+    entries
+}
+
+/// List stored sessions from their manifests. `verbose` adds the event count, which reads
+/// (and decompresses) every log.
+fn list(out: &mut impl Write, verbose: bool) -> anyhow::Result<()> {
+    let mut sessions = SessionStore::list_all();
+    sessions.sort_by_key(|session| Reverse(session.created_at_ms));
+    let events_header = if verbose { "  EVENTS" } else { "" };
+    writeln!(
+        out,
+        "{:<36}  {:<12}  {:<19}{events_header}  CWD",
+        "SESSION", "BACKEND", "CREATED"
+    )?;
+    for session in sessions {
+        let events = if verbose {
+            let count = SessionStore::read_canonical(&session.global_session_id).len();
+            format!("  {count:>6}")
+        } else {
+            String::new()
+        };
+        writeln!(
+            out,
+            "{:<36}  {:<12}  {:<19}{events}  {}",
+            session.global_session_id,
+            session.backend,
+            format_ts(session.created_at_ms),
+            session
+                .cwd
+                .as_deref()
+                .map_or_else(|| "-".to_string(), |cwd| cwd.display().to_string()),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_header(out: &mut impl Write, session: &StoredSession) -> io::Result<()> {
+    writeln!(out, "session  {}", session.global_session_id)?;
+    writeln!(out, "backend  {}", session.backend)?;
+    writeln!(out, "acp id   {}", session.acp_session_id)?;
+    if let Some(cwd) = &session.cwd {
+        writeln!(out, "cwd      {}", cwd.display())?;
+    }
+    writeln!(out, "created  {}", format_ts(session.created_at_ms))
+}
+
+fn show(out: &mut impl Write, session: &StoredSession) -> anyhow::Result<()> {
+    write_header(out, session)?;
+    writeln!(out)?;
+    for entry in timeline(&SessionStore::read_canonical(&session.global_session_id)) {
+        writeln!(
+            out,
+            "{}  {:<8}  {}",
+            format_ts(entry.ts_ms),
+            entry.label,
+            one_line(&entry.text)
+        )?;
+    }
+    Ok(())
+}
+
+fn grep(out: &mut impl Write, pattern: &str) -> anyhow::Result<()> {
+    let regex = Regex::new(pattern).with_context(|| format!("invalid pattern {pattern:?}"))?;
+    let mut sessions = SessionStore::list_all();
+    sessions.sort_by_key(|session| session.created_at_ms);
+    let mut found = false;
+    for session in sessions {
+        for entry in timeline(&SessionStore::read_canonical(&session.global_session_id)) {
+            if regex.is_match(&entry.text) {
+                found = true;
+                writeln!(
+                    out,
+                    "{}  {}  {:<8}  {}",
+                    session.global_session_id,
+                    format_ts(entry.ts_ms),
+                    entry.label,
+                    one_line(&entry.text)
+                )?;
+            }
+        }
+    }
+    if !found {
+        bail!("no events match {pattern:?}");
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "html" => Ok(Self::Html),
+            _ => bail!("unknown export format {value:?} (expected md, json or html)"),
+        }
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn export(
+    out: &mut impl Write,
+    session: &StoredSession,
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    let records = SessionStore::read_canonical(&session.global_session_id);
+    match format {
+        ExportFormat::Json => {
+            // The full event stream, including bookkeeping events `show` leaves out.
+            let document = json!({ "session": session, "events": records });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        ExportFormat::Markdown => {
+            writeln!(out, "# Session {}\n", session.global_session_id)?;
+            writeln!(out, "- Backend: {}", session.backend)?;
+            if let Some(cwd) = &session.cwd {
+                writeln!(out, "- Working directory: `{}`", cwd.display())?;
+            }
+            writeln!(out, "- Created: {}", format_ts(session.created_at_ms))?;
+            for entry in timeline(&records) {
+                writeln!(out, "\n## {} ({})\n", entry.label, format_ts(entry.ts_ms))?;
+                writeln!(out, "{}", entry.text.trim_end())?;
+            }
+        }
+        ExportFormat::Html => {
+            let title = html_escape(&session.global_session_id);
+            writeln!(
+                out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Session {title}</title>\n<style>body{{font-family:sans-serif;max-width:60rem;margin:auto}}pre{{white-space:pre-wrap;background:#f6f6f6;padding:.5rem}}</style>\n</head>\n<body>\n<h1>Session {title}</h1>"
+            )?;
+            writeln!(out, "<ul>")?;
+            writeln!(out, "<li>Backend: {}</li>", html_escape(&session.backend))?;
+            if let Some(cwd) = &session.cwd {
+                writeln!(
+                    out,
+                    "<li>Working directory: <code>{}</code></li>",
+                    html_escape(&cwd.display().to_string())
+                )?;
+            }
+            writeln!(
+                out,
+                "<li>Created: {}</li>\n</ul>",
+                format_ts(session.created_at_ms)
+            )?;
+            for entry in timeline(&records) {
+                writeln!(
+                    out,
+                    "<h2>{} <small>{}</small></h2>\n<pre>{}</pre>",
+                    entry.label,
+                    format_ts(entry.ts_ms),
+                    html_escape(entry.text.trim_end())
+                )?;
+            }
+            writeln!(out, "</body>\n</html>")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_store::ENV_LOCK;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    fn record(kind: &str, data: Value) -> CanonicalRecord {
+        CanonicalRecord {
+            ts_ms: 1_700_000_000_000,
+            kind: kind.to_string(),
+            data,
+        }
+    }
+
+    #[test]
+    fn timeline_joins_chunks_and_summarizes_events() {
+        let records = vec![
+            record("acp.prompt", json!({ "text": "fix the build" })),
+            record("acp.context_opt.token_usage", json!({ "used": 10 })),
+            record("acp.agent_message_chunk", json!({ "text": "Look" })),
+            record("acp.agent_message_chunk", json!({ "text": "ing." })),
+            record(
+                "acp.tool_call",
+                json!({ "toolCallId": "t1", "title": "cargo build", "status": "in_progress" }),
+            ),
+            record(
+                "acp.request_permission",
+                json!({
+                    "tool_call": { "toolCallId": "t1", "title": "cargo build" },
+                    "options": [],
+                }),
+            ),
+            record(
+                "acp.request_permission_response",
+                json!({ "outcome": { "outcome": "selected", "optionId": "approved" } }),
+            ),
+            record("acp.tool_call_update", json!({ "toolCallId": "t1" })),
+            record(
+                "acp.tool_call_update",
+                json!({ "toolCallId": "t1", "status": "completed" }),
+            ),
+            record(
+                "acp.plan",
+                json!({ "items": [
+                    { "step": "build", "status": "completed" },
+                    { "step": "test", "status": "pending" },
+                ] }),
+            ),
+        ];
+        let entries = timeline(&records)
+            .into_iter()
+            .map(|entry| (entry.label, entry.text))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("prompt", "fix the build".to_string()),
+                ("agent", "Looking.".to_string()),
+                ("tool", "cargo build [in_progress]".to_string()),
+                ("approval", "requested: cargo build".to_string()),
+                ("approval", "answered: approved".to_string()),
+                ("tool", "t1 -> completed".to_string()),
+                ("plan", "[x] build\n[ ] test".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_shows_greps_and_exports_stored_sessions() {
+        let _guard = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-log-cli-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::set_var("ACP_HOME", &root);
+        }
+
+        let mut index = GlobalSessionIndex::load().unwrap();
+        let global_id = index.get_or_create("gemini:gemini:abc").unwrap();
+        let store = SessionStore::init(
+            global_id.clone(),
+            "gemini",
+            "gemini:abc",
+            "gemini:abc",
+            Some(std::path::Path::new("/tmp/project")),
+        )
+        .unwrap();
+        store.log(CanonicalEvent::prompt_text("rename <Widget>"));
+        store.log(CanonicalEvent::agent_message("Renamed it."));
+
+        let session = resolve_session("gemini:gemini:abc").unwrap();
+        assert_eq!(session.global_session_id, global_id);
+        assert_eq!(
+            resolve_session("gemini:abc").unwrap().global_session_id,
+            global_id
+        );
+        assert_eq!(
+            resolve_session(&global_id[..8]).unwrap().global_session_id,
+            global_id
+        );
+        assert!(resolve_session("missing").is_err());
+
+        let mut out = Vec::new();
+        list(&mut out, false).unwrap();
+        let listed = String::from_utf8(out).unwrap();
+        assert!(listed.contains(&global_id), "{listed}");
+        assert!(listed.contains("/tmp/project"), "{listed}");
+        assert!(!listed.contains("EVENTS"), "{listed}");
+
+        let mut out = Vec::new();
+        list(&mut out, true).unwrap();
+        let listed = String::from_utf8(out).unwrap();
+        assert!(listed.contains("EVENTS"), "{listed}");
+        assert!(listed.contains("       2  /tmp/project"), "{listed}");
+
+        let mut out = Vec::new();
+        show(&mut out, &session).unwrap();
+        let shown = String::from_utf8(out).unwrap();
+        assert!(shown.contains("prompt    rename <Widget>"), "{shown}");
+        assert!(shown.contains("agent     Renamed it."), "{shown}");
+
+        let mut out = Vec::new();
+        grep(&mut out, "(?i)renamed").unwrap();
+        let found = String::from_utf8(out).unwrap();
+        assert_eq!(found.lines().count(), 1, "{found}");
+        assert!(grep(&mut Vec::new(), "nothing-like-this").is_err());
+
+        let mut out = Vec::new();
+        export(&mut out, &session, ExportFormat::Json).unwrap();
+        let exported: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(exported["session"]["backend"], "gemini");
+        assert_eq!(exported["events"][1]["data"]["text"], "Renamed it.");
+
+        let mut out = Vec::new();
+        export(&mut out, &session, ExportFormat::Html).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("rename &lt;Widget&gt;"), "{html}");
+
+        let mut out = Vec::new();
+        export(&mut out, &session, ExportFormat::Markdown).unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.contains("## agent"), "{markdown}");
+        assert!(ExportFormat::parse("pdf").is_err());
+
+        drop(std::fs::remove_dir_all(&root));
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+    }
+}
@@ -14,7 +14,9 @@ fn extract_backend_arg(args: &mut Vec<std::ffi::OsString>) -> anyhow::Result<Bac
         let arg = args[i].to_string_lossy();
         if arg == "--backend" {
             let Some(value) = args.get(i + 1) else {
-                anyhow::bail!("--backend requires a value (codex|claude-code|gemini|multi)");
+                anyhow::bail!(
+                    "--backend requires a value (codex|local|claude-code|gemini|custom|multi)"
+                );
             };
             let value = value.to_string_lossy();
             backend = BackendKind::parse(&value)
@@ -46,6 +48,34 @@ fn main() -> Result<()> {
             let arg1 = args[1].to_string_lossy();
             if arg1 == "acp" || arg1 == "--acp" {
                 args.remove(1);
+            } else if arg1 == xsfire_camp::claude_permission::PERMISSION_MCP_SUBCOMMAND {
+                // Spawned by the Claude CLI as its permission prompt MCP server.
+                xsfire_camp::claude_permission::run_mcp_server().await?;
+                return Ok(());
+            } else if arg1 == xsfire_camp::mcp_sse_bridge::MCP_SSE_BRIDGE_SUBCOMMAND {
+                // Spawned by codex-rs as the stdio MCP server for a client SSE server.
+                let Some(url) = args.get(2) else {
+                    anyhow::bail!("{arg1} requires a server url");
+                };
+                xsfire_camp::mcp_sse_bridge::run_bridge(&url.to_string_lossy()).await?;
+                return Ok(());
+            } else if arg1 == xsfire_camp::log_cli::LOG_SUBCOMMAND {
+                // Canonical log tooling; never starts the ACP server.
+                let log_args = args[2..]
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>();
+                xsfire_camp::log_cli::run(&log_args)?;
+                return Ok(());
+            } else if arg1 == xsfire_camp::pty_terminal::TERMINAL_MIRROR_SUBCOMMAND {
+                // Runs inside an ACP terminal to show a locally spawned PTY.
+                let Some(log_path) = args.get(2) else {
+                    anyhow::bail!("{arg1} requires a transcript path");
+                };
+                let code =
+                    xsfire_camp::pty_terminal::run_terminal_mirror(std::path::Path::new(log_path))
+                        .await?;
+                std::process::exit(code);
             }
         }
 
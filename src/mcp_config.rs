@@ -0,0 +1,349 @@
+//! Client-provided MCP servers for backends that cannot receive them over ACP.
+//!
+//! Claude Code and Gemini read MCP servers from their own config files, so each session
+//! renders the servers from the ACP request in the format the CLI expects, and every run
+//! writes them to a temporary file that is removed once the run is over.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use agent_client_protocol::{McpServer, McpServerHttp, McpServerSse, McpServerStdio, SessionId};
+use serde_json::{Map, Value, json};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    canonical_event::{CanonicalEvent, McpServerSkipped},
+    cli_common::send_agent_text,
+    session_store::SessionStore,
+};
+
+/// A client-provided MCP server that could not be attached to a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SkippedMcpServer {
+    pub name: String,
+    pub transport: &'static str,
+    pub reason: String,
+}
+
+impl SkippedMcpServer {
+    /// Entry for a transport this build does not know about.
+    pub fn unsupported(server: &McpServer, backend: &str) -> Self {
+        Self {
+            name: serde_json::to_value(server)
+                .ok()
+                .and_then(|value| value.get("name")?.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            transport: "unknown",
+            reason: format!("transport is not supported by {backend}"),
+        }
+    }
+}
+
+/// Log skipped MCP servers as `mcp.server_skipped` and tell the user once the session is up.
+pub(crate) fn report_skipped_mcp_servers(
+    session_id: &SessionId,
+    session_store: Option<&SessionStore>,
+    skipped: Vec<SkippedMcpServer>,
+) {
+    if skipped.is_empty() {
+        return;
+    }
+    let mut message = String::new();
+    for server in &skipped {
+        warn!(
+            "Skipping MCP server {:?} ({}): {}",
+            server.name, server.transport, server.reason
+        );
+        if let Some(store) = session_store {
+            store.log(CanonicalEvent::McpServerSkipped(McpServerSkipped {
+                name: server.name.clone(),
+                transport: server.transport.to_string(),
+                reason: server.reason.clone(),
+            }));
+        }
+        message.push_str(&format!(
+            "Warning: MCP server `{}` ({}) was not attached: {}\n",
+            server.name, server.transport, server.reason
+        ));
+    }
+    // Send after the session response so the client already knows the session.
+    let session_id = session_id.clone();
+    tokio::task::spawn_local(async move {
+        send_agent_text(&session_id, message).await;
+    });
+}
+
+/// MCP config file layout of a CLI backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum McpConfigFormat {
+    /// `claude --mcp-config <file>`: `{"mcpServers": {name: {"type": ..}}}`.
+    Claude,
+    /// Gemini `settings.json` (`mcpServers` with `command`, `url` or `httpUrl` entries).
+    Gemini,
+}
+
+impl McpConfigFormat {
+    fn backend(self) -> &'static str {
+        match self {
+            Self::Claude => "claude-code",
+            Self::Gemini => "gemini",
+        }
+    }
+
+    fn server_entry(self, server: &McpServer, cwd: &Path) -> Option<(String, Value)> {
+        let pairs = |pairs: Vec<(String, String)>| {
+            Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::String(value)))
+                    .collect(),
+            )
+        };
+        let (name, mut entry) = match server {
+            McpServer::Stdio(McpServerStdio {
+                name,
+                command,
+                args,
+                env,
+                ..
+            }) => {
+                let mut entry = json!({
+                    "command": command.display().to_string(),
+                    "args": args,
+                    "env": pairs(env.iter().map(|e| (e.name.clone(), e.value.clone())).collect()),
+                });
+                match self {
+                    Self::Claude => entry["type"] = json!("stdio"),
+                    Self::Gemini => entry["cwd"] = json!(cwd.display().to_string()),
+                }
+                (name, entry)
+            }
+            McpServer::Http(McpServerHttp {
+                name, url, headers, ..
+            }) => {
+                let url_key = match self {
+                    Self::Claude => "url",
+                    Self::Gemini => "httpUrl",
+                };
+                let mut entry = json!({ url_key: url });
+                if self == Self::Claude {
+                    entry["type"] = json!("http");
+                }
+                entry["headers"] = pairs(
+                    headers
+                        .iter()
+                        .map(|h| (h.name.clone(), h.value.clone()))
+                        .collect(),
+                );
+                (name, entry)
+            }
+            McpServer::Sse(McpServerSse {
+                name, url, headers, ..
+            }) => {
+                let mut entry = json!({ "url": url });
+                if self == Self::Claude {
+                    entry["type"] = json!("sse");
+                }
+                entry["headers"] = pairs(
+                    headers
+                        .iter()
+                        .map(|h| (h.name.clone(), h.value.clone()))
+                        .collect(),
+                );
+                (name, entry)
+            }
+            _ => return None,
+        };
+        if let Some(object) = entry.as_object_mut() {
+            object.retain(|_, value| !value.as_object().is_some_and(Map::is_empty));
+        }
+        Some((name.clone(), entry))
+    }
+
+    /// Config document for `servers`, layered over `base` (e.g. existing Gemini settings).
+    pub fn config(
+        self,
+        servers: &[McpServer],
+        cwd: &Path,
+        base: Option<Value>,
+    ) -> (Value, Vec<SkippedMcpServer>) {
+        let mut config = base.filter(Value::is_object).unwrap_or_else(|| json!({}));
+        let mut skipped = Vec::new();
+        let mut entries = config
+            .get("mcpServers")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        for server in servers {
+            match self.server_entry(server, cwd) {
+                Some((name, entry)) => {
+                    entries.insert(name, entry);
+                }
+                None => skipped.push(SkippedMcpServer::unsupported(server, self.backend())),
+            }
+        }
+        config["mcpServers"] = Value::Object(entries);
+        (config, skipped)
+    }
+
+    /// Config for a session's MCP servers, or `None` when the client passed none. Servers
+    /// that cannot be attached are reported here, once per session.
+    pub fn session_config(
+        self,
+        session_id: &SessionId,
+        session_store: Option<&SessionStore>,
+        servers: &[McpServer],
+        cwd: &Path,
+        base: Option<Value>,
+    ) -> Option<Value> {
+        if servers.is_empty() {
+            return None;
+        }
+        let (config, skipped) = self.config(servers, cwd, base);
+        report_skipped_mcp_servers(session_id, session_store, skipped);
+        Some(config)
+    }
+}
+
+/// Temporary MCP config file that lives for one CLI run.
+#[derive(Debug)]
+pub(crate) struct McpConfigFile {
+    path: PathBuf,
+}
+
+impl McpConfigFile {
+    /// Write a session config for one run; a failure is logged and the run goes without it.
+    pub fn write(config: Option<&Value>) -> Option<Self> {
+        match Self::create(config?) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                warn!("Failed to write MCP config: {err}");
+                None
+            }
+        }
+    }
+
+    /// Write `config` to a new private temporary file.
+    pub fn create(config: &Value) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("xsfire-mcp-{}.json", Uuid::new_v4()));
+        // Headers, env values and bridge tokens may carry credentials, so keep the file private.
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        let file_guard = Self { path };
+        file.write_all(config.to_string().as_bytes())?;
+        Ok(file_guard)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for McpConfigFile {
+    fn drop(&mut self) {
+        drop(fs::remove_file(&self.path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{McpConfigFile, McpConfigFormat};
+    use agent_client_protocol::{
+        EnvVariable, HttpHeader, McpServer, McpServerHttp, McpServerStdio, SessionId,
+    };
+    use serde_json::json;
+    use std::path::Path;
+
+    fn servers() -> Vec<McpServer> {
+        vec![
+            McpServer::Stdio(
+                McpServerStdio::new("files", "/usr/bin/files-mcp")
+                    .args(vec!["--root".to_string(), ".".to_string()])
+                    .env(vec![EnvVariable::new("TOKEN", "t")]),
+            ),
+            McpServer::Http(
+                McpServerHttp::new("docs", "https://docs.example/mcp")
+                    .headers(vec![HttpHeader::new("Authorization", "Bearer x")]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn claude_config_uses_typed_entries() {
+        let (config, skipped) = McpConfigFormat::Claude.config(&servers(), Path::new("/w"), None);
+        assert!(skipped.is_empty());
+        assert_eq!(
+            config,
+            json!({
+                "mcpServers": {
+                    "files": {
+                        "type": "stdio",
+                        "command": "/usr/bin/files-mcp",
+                        "args": ["--root", "."],
+                        "env": { "TOKEN": "t" },
+                    },
+                    "docs": {
+                        "type": "http",
+                        "url": "https://docs.example/mcp",
+                        "headers": { "Authorization": "Bearer x" },
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn gemini_config_keeps_existing_settings() {
+        let base = json!({
+            "general": { "vimMode": true },
+            "mcpServers": { "system": { "command": "sys-mcp" } },
+        });
+        let (config, _) = McpConfigFormat::Gemini.config(&servers(), Path::new("/w"), Some(base));
+        assert_eq!(config["general"], json!({ "vimMode": true }));
+        assert_eq!(
+            config["mcpServers"]["system"],
+            json!({ "command": "sys-mcp" })
+        );
+        assert_eq!(config["mcpServers"]["files"]["cwd"], json!("/w"));
+        assert_eq!(
+            config["mcpServers"]["docs"],
+            json!({
+                "httpUrl": "https://docs.example/mcp",
+                "headers": { "Authorization": "Bearer x" },
+            })
+        );
+    }
+
+    #[test]
+    fn config_file_is_removed_after_the_run() {
+        let session_id = SessionId::new("claude:test");
+        assert!(
+            McpConfigFormat::Claude
+                .session_config(&session_id, None, &[], Path::new("/w"), None)
+                .is_none()
+        );
+
+        let config = McpConfigFormat::Claude.session_config(
+            &session_id,
+            None,
+            &servers(),
+            Path::new("/w"),
+            None,
+        );
+        assert!(McpConfigFile::write(None).is_none());
+        let file = McpConfigFile::write(config.as_ref()).unwrap();
+        let path = file.path().to_path_buf();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["mcpServers"]["docs"]["type"], "http");
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,337 @@
+//! Stdio bridge for MCP servers that only speak the legacy HTTP+SSE transport.
+//!
+//! The vendored Codex MCP client supports stdio and streamable HTTP, but not SSE. SSE
+//! servers passed by the ACP client are therefore registered as stdio servers that run this
+//! binary as `xsfire-camp mcp-sse-bridge <url>`: it opens the SSE stream, waits for the
+//! `endpoint` event, POSTs every JSON-RPC line read from stdin to that endpoint and writes
+//! every `message` event back to stdout.
+
+use std::{collections::HashMap, io};
+
+use anyhow::Context as _;
+use eventsource_stream::Eventsource;
+use futures::StreamExt;
+use reqwest::{
+    Url,
+    header::{ACCEPT, HeaderMap, HeaderName, HeaderValue},
+};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+use tracing::warn;
+
+/// Subcommand that runs the stdio-to-SSE MCP bridge.
+pub const MCP_SSE_BRIDGE_SUBCOMMAND: &str = "mcp-sse-bridge";
+
+/// JSON object of extra HTTP headers, kept out of the command line.
+const HEADERS_ENV: &str = "XSFIRE_MCP_SSE_HEADERS";
+
+/// JSON-RPC error code reported when a request could not be delivered to the server.
+const DELIVERY_ERROR_CODE: i64 = -32000;
+
+/// Stdio command (program, args, env) that bridges the SSE server at `url`.
+pub(crate) fn bridge_command(
+    url: &str,
+    headers: &[(String, String)],
+) -> io::Result<(String, Vec<String>, HashMap<String, String>)> {
+    let exe = std::env::current_exe()?;
+    let mut env = HashMap::new();
+    if !headers.is_empty() {
+        let headers = headers.iter().cloned().collect::<HashMap<_, _>>();
+        env.insert(HEADERS_ENV.to_string(), json!(headers).to_string());
+    }
+    Ok((
+        exe.display().to_string(),
+        vec![MCP_SSE_BRIDGE_SUBCOMMAND.to_string(), url.to_string()],
+        env,
+    ))
+}
+
+/// Entry point of the `mcp-sse-bridge` subcommand.
+pub async fn run_bridge(url: &str) -> anyhow::Result<()> {
+    let headers = match std::env::var(HEADERS_ENV) {
+        Ok(raw) => header_map(
+            serde_json::from_str(&raw).with_context(|| format!("{HEADERS_ENV} is not valid"))?,
+        )?,
+        Err(_) => HeaderMap::new(),
+    };
+    bridge(url, headers, tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+fn header_map(headers: HashMap<String, String>) -> anyhow::Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        map.insert(
+            HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid header name {name:?}"))?,
+            HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("invalid value for header {name:?}"))?,
+        );
+    }
+    Ok(map)
+}
+
+async fn bridge(
+    url: &str,
+    headers: HeaderMap,
+    input: impl AsyncRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::new();
+    let base = Url::parse(url).with_context(|| format!("invalid SSE url {url:?}"))?;
+    let response = client
+        .get(base.clone())
+        .headers(headers.clone())
+        .header(ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("failed to open SSE stream {base}"))?;
+    let mut events = response.bytes_stream().eventsource();
+
+    let endpoint = loop {
+        match events.next().await {
+            Some(Ok(event)) if event.event == "endpoint" => {
+                let endpoint = base
+                    .join(event.data.trim())
+                    .context("invalid endpoint event")?;
+                // Requests carry the configured headers (often credentials), so they must
+                // stay on the server the client asked for.
+                anyhow::ensure!(
+                    endpoint.origin() == base.origin(),
+                    "SSE endpoint {endpoint} is not on the origin of {base}"
+                );
+                break endpoint;
+            }
+            Some(Ok(_)) => {}
+            Some(Err(err)) => anyhow::bail!("SSE stream failed: {err}"),
+            None => anyhow::bail!("SSE stream closed before the endpoint event"),
+        }
+    };
+
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+    let inbound = {
+        let out_tx = out_tx.clone();
+        async move {
+            while let Some(event) = events.next().await {
+                let event = event.map_err(|err| anyhow::anyhow!("SSE stream failed: {err}"))?;
+                if event.event != "message" {
+                    continue;
+                }
+                // Re-serialize so every message stays on a single stdout line.
+                match serde_json::from_str::<Value>(&event.data) {
+                    Ok(message) => drop(out_tx.send(message.to_string())),
+                    Err(err) => warn!("Ignoring malformed SSE message: {err}"),
+                }
+            }
+            anyhow::bail!("SSE stream closed")
+        }
+    };
+    let outbound = async move {
+        let mut lines = BufReader::new(input).lines();
+        while let Some(line) = lines.next_line().await? {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let message = match serde_json::from_str::<Value>(line) {
+                Ok(message) => message,
+                Err(err) => {
+                    warn!("Ignoring malformed JSON-RPC line: {err}");
+                    continue;
+                }
+            };
+            let delivered = client
+                .post(endpoint.clone())
+                .headers(headers.clone())
+                .json(&message)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+            if let Err(err) = delivered {
+                warn!("Failed to deliver MCP message to {endpoint}: {err}");
+                // Answer requests locally so the MCP client does not wait forever.
+                if let (Some(id), Some(_)) = (message.get("id"), message.get("method")) {
+                    let reply = json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": DELIVERY_ERROR_CODE, "message": err.to_string() },
+                    });
+                    drop(out_tx.send(reply.to_string()));
+                }
+            }
+        }
+        anyhow::Ok(())
+    };
+    let writer = async {
+        while let Some(line) = out_rx.recv().await {
+            output.write_all(line.as_bytes()).await?;
+            output.write_all(b"\n").await?;
+            output.flush().await?;
+        }
+        anyhow::Ok(())
+    };
+
+    tokio::select! {
+        result = inbound => result,
+        result = outbound => result,
+        result = writer => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bridge;
+    use reqwest::header::HeaderMap;
+    use serde_json::{Value, json};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        sync::mpsc,
+    };
+
+    async fn read_request(stream: &mut TcpStream) -> (String, String) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        let head_end = loop {
+            if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed mid-request");
+            request.extend_from_slice(&buf[..n]);
+        };
+        let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        while request.len() < head_end + content_length {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let body = String::from_utf8_lossy(&request[head_end..]).to_string();
+        (head, body)
+    }
+
+    /// Minimal legacy MCP SSE server that echoes every posted request back as its result.
+    async fn spawn_mock_sse_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (events_tx, events_rx) = mpsc::unbounded_channel::<Value>();
+            let mut events_rx = Some(events_rx);
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    break;
+                };
+                let (head, body) = read_request(&mut stream).await;
+                if head.starts_with("GET /sse")
+                    && let Some(mut events_rx) = events_rx.take()
+                {
+                    assert!(head.contains("x-api-key: secret"), "{head}");
+                    stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\nevent: endpoint\ndata: /messages?session=1\n\n",
+                        )
+                        .await
+                        .unwrap();
+                    tokio::spawn(async move {
+                        while let Some(request) = events_rx.recv().await {
+                            let reply = json!({ "jsonrpc": "2.0", "id": request["id"], "result": request["params"] });
+                            let frame = format!("event: message\ndata: {reply}\n\n");
+                            if stream.write_all(frame.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                } else if head.starts_with("POST /messages?session=1") {
+                    events_tx
+                        .send(serde_json::from_str(&body).unwrap())
+                        .unwrap();
+                    drop(
+                        stream
+                            .write_all(b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                            .await,
+                    );
+                } else {
+                    drop(
+                        stream
+                            .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                            .await,
+                    );
+                }
+            }
+        });
+        format!("http://{addr}/sse")
+    }
+
+    /// SSE server whose `endpoint` event points at another host.
+    async fn spawn_cross_origin_sse_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\nevent: endpoint\ndata: http://attacker.invalid/messages\n\n",
+                )
+                .await
+                .unwrap();
+            let mut rest = Vec::new();
+            drop(stream.read_to_end(&mut rest).await);
+        });
+        format!("http://{addr}/sse")
+    }
+
+    #[tokio::test]
+    async fn bridge_relays_stdio_messages_over_sse() {
+        let url = spawn_mock_sse_server().await;
+        let (mut client_in, bridge_in) = tokio::io::duplex(4096);
+        let (bridge_out, client_out) = tokio::io::duplex(4096);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        tokio::spawn(async move { bridge(&url, headers, bridge_in, bridge_out).await });
+
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": "ping", "params": { "n": 1 } });
+        client_in
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+
+        let mut lines = BufReader::new(client_out).lines();
+        let line = tokio::time::timeout(std::time::Duration::from_secs(10), lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let reply: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            reply,
+            json!({ "jsonrpc": "2.0", "id": 7, "result": { "n": 1 } })
+        );
+    }
+
+    #[tokio::test]
+    async fn bridge_refuses_cross_origin_endpoint() {
+        let url = spawn_cross_origin_sse_server().await;
+        let (_client_in, bridge_in) = tokio::io::duplex(4096);
+        let (bridge_out, _client_out) = tokio::io::duplex(4096);
+
+        let err = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            bridge(&url, HeaderMap::new(), bridge_in, bridge_out),
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert!(err.to_string().contains("is not on the origin of"), "{err}");
+    }
+}
@@ -1,12 +1,14 @@
 use agent_client_protocol::{
     AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, Error,
     ForkSessionRequest, ForkSessionResponse, ListSessionsRequest, ListSessionsResponse,
-    LoadSessionRequest, LoadSessionResponse, NewSessionRequest, NewSessionResponse, PromptRequest,
-    PromptResponse, ResumeSessionRequest, ResumeSessionResponse, SessionConfigOption,
-    SessionConfigOptionCategory, SessionConfigSelectOption, SessionId, SessionInfo,
-    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
-    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse, StopReason,
+    LoadSessionRequest, LoadSessionResponse, Meta, NewSessionRequest, NewSessionResponse,
+    PromptCapabilities, PromptRequest, PromptResponse, ResumeSessionRequest, ResumeSessionResponse,
+    SessionConfigOption, SessionConfigOptionCategory, SessionConfigSelectOption, SessionId,
+    SessionInfo, SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+    SetSessionModeRequest, SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse,
+    StopReason,
 };
+use serde_json::Value;
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
@@ -16,8 +18,9 @@ use uuid::Uuid;
 
 use crate::{
     backend::{BackendDriver, BackendKind},
-    cli_common::{prompt_blocks_to_text, send_agent_text},
+    cli_common::{prompt_blocks_to_text, send_agent_text, turns_from_canonical},
     register_session_alias,
+    session_store::{CanonicalRecord, GlobalSessionIndex, SessionStore},
 };
 
 struct RoutedSession {
@@ -27,6 +30,17 @@ struct RoutedSession {
     cwd: std::path::PathBuf,
     mcp_servers: Vec<agent_client_protocol::McpServer>,
     meta: Option<agent_client_protocol::Meta>,
+    /// Handed-off context waiting for the next non-command prompt on that backend.
+    pending_handoff: HashMap<BackendKind, String>,
+}
+
+/// Transcript of earlier backends' turns, seeded into the backend a thread switches to.
+#[derive(Debug)]
+struct ContextHandoff {
+    text: String,
+    included_turns: usize,
+    total_turns: usize,
+    sources: Vec<BackendKind>,
 }
 
 pub struct MultiBackendDriver {
@@ -38,6 +52,29 @@ pub struct MultiBackendDriver {
 
 const MULTI_CODEX_CURSOR_PREFIX: &str = "multi:codex:";
 const MULTI_ROUTED_CURSOR: &str = "multi:routed";
+const HANDOFF_MAX_CHARS_DEFAULT: usize = 8_000;
+const HANDOFF_END_MARKER: &str = "[end of handed-off context]";
+
+/// `_meta` key of a prompt carrying context handed off from earlier backends. Drivers show it
+/// to the model ahead of the prompt but keep it out of the logged prompt and their history.
+pub(crate) const CONTEXT_HANDOFF_META_KEY: &str = "xsfireContextHandoff";
+
+/// Context handed off with `request`, if any.
+pub(crate) fn context_handoff(request: &PromptRequest) -> Option<&str> {
+    request
+        .meta
+        .as_ref()?
+        .get(CONTEXT_HANDOFF_META_KEY)?
+        .as_str()
+}
+
+/// `text` preceded by the context handed off with `request`.
+pub(crate) fn with_context_handoff(request: &PromptRequest, text: String) -> String {
+    match context_handoff(request) {
+        Some(handoff) => format!("{handoff}\n\n{text}"),
+        None => text,
+    }
+}
 
 impl MultiBackendDriver {
     pub fn new(
@@ -57,7 +94,12 @@ impl MultiBackendDriver {
         std::env::var("XSFIRE_DEFAULT_BACKEND")
             .ok()
             .and_then(|v| BackendKind::parse(v.trim()))
-            .filter(|k| *k != BackendKind::Multi)
+            .filter(|k| {
+                !matches!(
+                    k,
+                    BackendKind::Multi | BackendKind::Local | BackendKind::Custom
+                )
+            })
             .unwrap_or(BackendKind::Codex)
     }
 
@@ -66,7 +108,7 @@ impl MultiBackendDriver {
             BackendKind::Codex => self.codex.clone(),
             BackendKind::ClaudeCode => self.claude.clone(),
             BackendKind::Gemini => self.gemini.clone(),
-            BackendKind::Multi => self.codex.clone(),
+            BackendKind::Local | BackendKind::Custom | BackendKind::Multi => self.codex.clone(),
         }
     }
 
@@ -77,7 +119,10 @@ impl MultiBackendDriver {
         if cmd != "/backend" {
             return None;
         }
-        parts.next().and_then(BackendKind::parse)
+        parts
+            .next()
+            .and_then(BackendKind::parse)
+            .filter(|k| !matches!(k, BackendKind::Local | BackendKind::Custom))
     }
 
     fn is_switch_backend_command(raw: &str) -> bool {
@@ -141,6 +186,104 @@ impl MultiBackendDriver {
         options
     }
 
+    fn handoff_max_chars() -> usize {
+        std::env::var("XSFIRE_BACKEND_HANDOFF_MAX_CHARS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(HANDOFF_MAX_CHARS_DEFAULT)
+    }
+
+    /// Make `target_backend` the active backend of a routed session and queue a context
+    /// handoff for it. Returns the message to show the user.
+    async fn switch_backend(
+        &self,
+        session_id: &SessionId,
+        target_backend: BackendKind,
+    ) -> Result<String, Error> {
+        self.ensure_backend_session(session_id, target_backend)
+            .await?;
+        let previous_backend = {
+            let mut sessions = self.sessions.borrow_mut();
+            let Some(session) = sessions.get_mut(session_id) else {
+                return Err(Error::resource_not_found(None));
+            };
+            std::mem::replace(&mut session.active_backend, target_backend)
+        };
+
+        let mut message = Self::backend_switch_message(target_backend);
+        if previous_backend == target_backend {
+            return Ok(message);
+        }
+        if let Some(handoff) = self.build_context_handoff(session_id, target_backend) {
+            message.push_str(&Self::handoff_message(target_backend, &handoff));
+            if let Some(session) = self.sessions.borrow_mut().get_mut(session_id) {
+                session.pending_handoff.insert(target_backend, handoff.text);
+            }
+        }
+        Ok(message)
+    }
+
+    fn handoff_message(target_backend: BackendKind, handoff: &ContextHandoff) -> String {
+        let sources = handoff
+            .sources
+            .iter()
+            .map(|backend| backend.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "\n\nContext handed off to `{}`: {} of {} earlier turns from {} ({} chars). It will be prepended to your next prompt.",
+            target_backend.as_str(),
+            handoff.included_turns,
+            handoff.total_turns,
+            sources,
+            handoff.text.chars().count(),
+        )
+    }
+
+    /// Collect the turns other backends handled in this thread since `target_backend` last
+    /// saw it, using each child session's canonical log.
+    fn build_context_handoff(
+        &self,
+        session_id: &SessionId,
+        target_backend: BackendKind,
+    ) -> Option<ContextHandoff> {
+        let max_chars = Self::handoff_max_chars();
+        if max_chars == 0 {
+            return None;
+        }
+        let backend_sessions = self
+            .sessions
+            .borrow()
+            .get(session_id)?
+            .backend_sessions
+            .clone();
+        let index = GlobalSessionIndex::load()?;
+        let records_for = |backend: BackendKind, child: &SessionId| {
+            index
+                .get(&backend.session_index_key(child))
+                .map(|global_id| SessionStore::read_canonical(&global_id))
+                .unwrap_or_default()
+        };
+
+        let seen_until = backend_sessions
+            .get(&target_backend)
+            .and_then(|child| records_for(target_backend, child).last().map(|r| r.ts_ms))
+            .unwrap_or(0);
+        let mut records = backend_sessions
+            .iter()
+            .filter(|(backend, _)| **backend != target_backend)
+            .flat_map(|(backend, child)| {
+                records_for(*backend, child)
+                    .into_iter()
+                    .filter(|record| record.ts_ms > seen_until)
+                    .map(|record| (*backend, record))
+            })
+            .collect::<Vec<_>>();
+        records.sort_by_key(|(_, record)| record.ts_ms);
+
+        render_context_handoff(&handoff_turns(records), max_chars)
+    }
+
     async fn ensure_backend_session(
         &self,
         session_id: &SessionId,
@@ -259,6 +402,7 @@ impl MultiBackendDriver {
                 cwd,
                 mcp_servers,
                 meta,
+                pending_handoff: HashMap::new(),
             },
         );
         register_session_alias(&child_session_id, &session_id);
@@ -307,6 +451,16 @@ impl BackendDriver for MultiBackendDriver {
         self.codex.supports_resume_session()
     }
 
+    /// Only what every backend a prompt can be routed to accepts.
+    fn prompt_capabilities(&self) -> PromptCapabilities {
+        let all =
+            [&self.codex, &self.claude, &self.gemini].map(|driver| driver.prompt_capabilities());
+        PromptCapabilities::new()
+            .image(all.iter().all(|capabilities| capabilities.image))
+            .audio(all.iter().all(|capabilities| capabilities.audio))
+            .embedded_context(all.iter().all(|capabilities| capabilities.embedded_context))
+    }
+
     fn auth_methods(&self) -> Vec<AuthMethod> {
         let mut out = Vec::new();
         out.extend(self.codex.auth_methods());
@@ -518,17 +672,8 @@ impl BackendDriver for MultiBackendDriver {
         let prompt_text = prompt_blocks_to_text(&request.prompt);
 
         if let Some(target_backend) = Self::parse_backend_selector(&prompt_text) {
-            self.ensure_backend_session(&session_id, target_backend)
-                .await?;
-            {
-                let mut sessions = self.sessions.borrow_mut();
-                let Some(session) = sessions.get_mut(&session_id) else {
-                    return Err(Error::resource_not_found(None));
-                };
-                session.active_backend = target_backend;
-            }
-
-            send_agent_text(&session_id, Self::backend_switch_message(target_backend)).await;
+            let message = self.switch_backend(&session_id, target_backend).await?;
+            send_agent_text(&session_id, message).await;
             return Ok(PromptResponse::new(StopReason::EndTurn));
         }
 
@@ -554,9 +699,25 @@ impl BackendDriver for MultiBackendDriver {
             }
         };
 
+        // Slash commands are answered by the driver itself; the context waits for the next
+        // prompt that reaches the model.
+        let handoff = if prompt_text.trim_start().starts_with('/') {
+            None
+        } else {
+            self.sessions
+                .borrow_mut()
+                .get_mut(&session_id)
+                .and_then(|session| session.pending_handoff.remove(&backend))
+        };
+
         let mut routed = request;
         routed.session_id = child_session_id;
-        let _ = backend; // Reserved for backend-specific behavior.
+        if let Some(handoff) = handoff {
+            routed
+                .meta
+                .get_or_insert_with(Meta::new)
+                .insert(CONTEXT_HANDOFF_META_KEY.to_string(), Value::String(handoff));
+        }
         driver.prompt(routed).await
     }
 
@@ -595,12 +756,16 @@ impl BackendDriver for MultiBackendDriver {
             let target_backend = BackendKind::parse(args.value.0.as_ref()).ok_or_else(|| {
                 Error::invalid_params().data("backend must be one of: codex|claude-code|gemini")
             })?;
-            if target_backend == BackendKind::Multi {
+            if matches!(
+                target_backend,
+                BackendKind::Multi | BackendKind::Local | BackendKind::Custom
+            ) {
                 return Err(Error::invalid_params()
                     .data("backend must be one of: codex|claude-code|gemini"));
             }
 
-            self.ensure_backend_session(&args.session_id, target_backend)
+            let message = self
+                .switch_backend(&args.session_id, target_backend)
                 .await?;
             let merged_options = {
                 let sessions = self.sessions.borrow();
@@ -616,18 +781,7 @@ impl BackendDriver for MultiBackendDriver {
                         .unwrap_or_default(),
                 )
             };
-            {
-                let mut sessions = self.sessions.borrow_mut();
-                let Some(session) = sessions.get_mut(&args.session_id) else {
-                    return Err(Error::resource_not_found(None));
-                };
-                session.active_backend = target_backend;
-            }
-            send_agent_text(
-                &args.session_id,
-                Self::backend_switch_message(target_backend),
-            )
-            .await;
+            send_agent_text(&args.session_id, message).await;
             return Ok(SetSessionConfigOptionResponse::new(merged_options));
         }
 
@@ -649,10 +803,100 @@ impl BackendDriver for MultiBackendDriver {
     }
 }
 
+/// Split time-ordered records into `(backend, user, assistant)` turns, dropping slash
+/// commands and any context that was itself handed off earlier.
+fn handoff_turns(
+    records: Vec<(BackendKind, CanonicalRecord)>,
+) -> Vec<(BackendKind, String, String)> {
+    let mut turns = Vec::new();
+    let mut run: Vec<CanonicalRecord> = Vec::new();
+    let mut run_backend = None;
+    let mut flush = |backend: Option<BackendKind>, run: &mut Vec<CanonicalRecord>| {
+        let Some(backend) = backend else {
+            return;
+        };
+        for (user, assistant) in turns_from_canonical(run) {
+            let user = match user.rsplit_once(HANDOFF_END_MARKER) {
+                Some((_, rest)) => rest.trim_start().to_string(),
+                None => user,
+            };
+            if !user.trim_start().starts_with('/') {
+                turns.push((backend, user, assistant));
+            }
+        }
+        run.clear();
+    };
+    for (backend, record) in records {
+        if run_backend != Some(backend) {
+            flush(run_backend, &mut run);
+            run_backend = Some(backend);
+        }
+        run.push(record);
+    }
+    flush(run_backend, &mut run);
+    turns
+}
+
+/// Render the most recent turns that fit in `max_chars`, oldest first.
+fn render_context_handoff(
+    turns: &[(BackendKind, String, String)],
+    max_chars: usize,
+) -> Option<ContextHandoff> {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut used = 0usize;
+    for (backend, user, assistant) in turns.iter().rev() {
+        let block = format!(
+            "[{backend}] User:\n{user}\n\n[{backend}] Assistant:\n{assistant}\n\n",
+            backend = backend.as_str(),
+        );
+        let len = block.chars().count();
+        if used + len > max_chars {
+            if blocks.is_empty() {
+                // Keep the tail of an oversized latest turn rather than nothing.
+                let tail = block
+                    .chars()
+                    .skip(len.saturating_sub(max_chars))
+                    .collect::<String>();
+                blocks.push(format!("...[truncated]\n{tail}"));
+            }
+            break;
+        }
+        used += len;
+        blocks.push(block);
+    }
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let mut sources: Vec<BackendKind> = Vec::new();
+    for (backend, _, _) in &turns[turns.len() - blocks.len()..] {
+        if !sources.contains(backend) {
+            sources.push(*backend);
+        }
+    }
+    blocks.reverse();
+    let text = format!(
+        "Context handed off from earlier backends in this thread ({} of {} turns, oldest first):\n\n{}{HANDOFF_END_MARKER}",
+        blocks.len(),
+        turns.len(),
+        blocks.concat(),
+    );
+    Some(ContextHandoff {
+        text,
+        included_turns: blocks.len(),
+        total_turns: turns.len(),
+        sources,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::MultiBackendDriver;
-    use crate::backend::{BackendDriver, BackendKind};
+    use super::{MultiBackendDriver, context_handoff, handoff_turns, render_context_handoff};
+    use crate::{
+        backend::{BackendDriver, BackendKind},
+        canonical_event::CanonicalEvent,
+        session_store::{CanonicalRecord, GlobalSessionIndex, SessionStore},
+    };
     use agent_client_protocol::{
         AuthMethod, AuthenticateRequest, AuthenticateResponse, CancelNotification, Error,
         ForkSessionRequest, ForkSessionResponse, ListSessionsRequest, ListSessionsResponse,
@@ -675,6 +919,7 @@ mod tests {
         resume_response: RefCell<Option<ResumeSessionResponse>>,
         fork_requests: RefCell<Vec<ForkSessionRequest>>,
         resume_requests: RefCell<Vec<ResumeSessionRequest>>,
+        prompt_requests: RefCell<Vec<PromptRequest>>,
     }
 
     impl StubDriver {
@@ -694,6 +939,7 @@ mod tests {
                 resume_response: RefCell::new(None),
                 fork_requests: RefCell::new(Vec::new()),
                 resume_requests: RefCell::new(Vec::new()),
+                prompt_requests: RefCell::new(Vec::new()),
             }
         }
 
@@ -714,6 +960,7 @@ mod tests {
                 resume_response: RefCell::new(None),
                 fork_requests: RefCell::new(Vec::new()),
                 resume_requests: RefCell::new(Vec::new()),
+                prompt_requests: RefCell::new(Vec::new()),
             }
         }
 
@@ -809,7 +1056,8 @@ mod tests {
             Ok(ListSessionsResponse::new(self.sessions.borrow().clone()))
         }
 
-        async fn prompt(&self, _request: PromptRequest) -> Result<PromptResponse, Error> {
+        async fn prompt(&self, request: PromptRequest) -> Result<PromptResponse, Error> {
+            self.prompt_requests.borrow_mut().push(request);
             Ok(PromptResponse::new(StopReason::EndTurn))
         }
 
@@ -1134,14 +1382,188 @@ mod tests {
         assert!(message.contains("Switched backend to `claude-code`"));
         assert!(message.contains("Work orchestration profile: Claude Code"));
         assert!(message.contains("Goal/Rubric/Next Action"));
-        assert!(message.contains("single ACP message chunk only"));
+        assert!(message.contains("live ACP plan/tool updates available"));
     }
 
     #[test]
     fn backend_usage_message_lists_backend_bridge_modes() {
         let message = MultiBackendDriver::backend_usage_message();
         assert!(message.contains("codex: live ACP plan/tool updates available"));
-        assert!(message.contains("claude-code: single ACP message chunk only"));
-        assert!(message.contains("gemini: single ACP message chunk only"));
+        assert!(message.contains("claude-code: live ACP plan/tool updates available"));
+        assert!(message.contains("gemini: live ACP plan/tool updates available"));
+    }
+
+    fn record(ts_ms: u64, kind: &str, text: &str) -> CanonicalRecord {
+        CanonicalRecord {
+            ts_ms,
+            kind: kind.to_string(),
+            data: serde_json::json!({ "text": text }),
+        }
+    }
+
+    #[test]
+    fn handoff_turns_label_backends_and_skip_commands_and_nested_handoffs() {
+        let records = vec![
+            (BackendKind::Codex, record(1, "acp.prompt", "plan the fix")),
+            (
+                BackendKind::Codex,
+                record(2, "acp.agent_message_chunk", "Edit lib.rs"),
+            ),
+            (BackendKind::Codex, record(3, "acp.prompt", "/status")),
+            (
+                BackendKind::Gemini,
+                record(
+                    4,
+                    "acp.prompt",
+                    "old context\n[end of handed-off context]\nreview it",
+                ),
+            ),
+            (
+                BackendKind::Gemini,
+                record(5, "acp.agent_message_chunk", "Looks fine"),
+            ),
+        ];
+
+        let turns = handoff_turns(records);
+        assert_eq!(
+            turns,
+            vec![
+                (
+                    BackendKind::Codex,
+                    "plan the fix".to_string(),
+                    "Edit lib.rs".to_string()
+                ),
+                (
+                    BackendKind::Gemini,
+                    "review it".to_string(),
+                    "Looks fine".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_context_handoff_keeps_most_recent_turns_within_budget() {
+        let turns = vec![
+            (BackendKind::Codex, "a".repeat(500), "b".repeat(500)),
+            (
+                BackendKind::Gemini,
+                "latest question".to_string(),
+                "latest answer".to_string(),
+            ),
+        ];
+
+        let handoff = render_context_handoff(&turns, 200).unwrap();
+        assert_eq!(handoff.included_turns, 1);
+        assert_eq!(handoff.total_turns, 2);
+        assert_eq!(handoff.sources, vec![BackendKind::Gemini]);
+        assert!(handoff.text.contains("[gemini] User:\nlatest question"));
+        assert!(!handoff.text.contains("aaaa"));
+        assert!(handoff.text.ends_with("[end of handed-off context]"));
+
+        let truncated = render_context_handoff(&turns[..1], 100).unwrap();
+        assert!(truncated.text.contains("...[truncated]"));
+        assert!(render_context_handoff(&[], 100).is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn backend_switch_hands_off_context_to_next_prompt() {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let acp_home =
+            std::env::temp_dir().join(format!("xsfire-multi-handoff-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&acp_home).unwrap();
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            std::env::set_var("ACP_HOME", &acp_home);
+        }
+
+        let claude = Rc::new(StubDriver::new(BackendKind::ClaudeCode, Vec::new(), false));
+        let driver = MultiBackendDriver::new(
+            Rc::new(StubDriver::new(BackendKind::Codex, Vec::new(), false)),
+            claude.clone(),
+            Rc::new(StubDriver::new(BackendKind::Gemini, Vec::new(), false)),
+        );
+        let cwd = PathBuf::from("/tmp/handoff");
+        let session_id = driver
+            .new_session(NewSessionRequest::new(cwd.clone()))
+            .await
+            .unwrap()
+            .session_id;
+
+        let codex_child = SessionId::new("codex:stub");
+        let global_id = GlobalSessionIndex::load()
+            .unwrap()
+            .get_or_create(&BackendKind::Codex.session_index_key(&codex_child))
+            .unwrap();
+        let store =
+            SessionStore::init(global_id, "codex", "codex:stub", "thread", Some(&cwd)).unwrap();
+        store.log(CanonicalEvent::prompt_text("find the flaky test"));
+        store.log(CanonicalEvent::agent_message(
+            "It is cancel_stops_running_prompt.",
+        ));
+
+        driver
+            .prompt(PromptRequest::new(
+                session_id.clone(),
+                vec!["/backend claude".into()],
+            ))
+            .await
+            .unwrap();
+        assert!(
+            driver.sessions.borrow()[&session_id]
+                .pending_handoff
+                .contains_key(&BackendKind::ClaudeCode)
+        );
+
+        // A command answered by the backend leaves the context for the next real prompt.
+        driver
+            .prompt(PromptRequest::new(
+                session_id.clone(),
+                vec!["/status".into()],
+            ))
+            .await
+            .unwrap();
+        assert!(
+            driver.sessions.borrow()[&session_id]
+                .pending_handoff
+                .contains_key(&BackendKind::ClaudeCode)
+        );
+
+        driver
+            .prompt(PromptRequest::new(
+                session_id.clone(),
+                vec!["fix it".into()],
+            ))
+            .await
+            .unwrap();
+        let prompts = claude.prompt_requests.borrow();
+        assert_eq!(prompts.len(), 2);
+        assert_eq!(
+            crate::cli_common::prompt_blocks_to_text(&prompts[0].prompt),
+            "/status"
+        );
+        assert_eq!(context_handoff(&prompts[0]), None);
+        // The context travels beside the user's text rather than inside it.
+        assert_eq!(
+            crate::cli_common::prompt_blocks_to_text(&prompts[1].prompt),
+            "fix it"
+        );
+        let handed_off = context_handoff(&prompts[1]).unwrap();
+        assert!(handed_off.contains("[codex] User:\nfind the flaky test"));
+        assert!(handed_off.contains("It is cancel_stops_running_prompt."));
+        assert!(
+            driver.sessions.borrow()[&session_id]
+                .pending_handoff
+                .is_empty()
+        );
+
+        drop(std::fs::remove_dir_all(&acp_home));
+        // Safe in tests when serialized by ENV_LOCK.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
     }
 }
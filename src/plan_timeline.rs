@@ -0,0 +1,466 @@
+//! Plan-step transition history of a session, behind `/monitor timeline`.
+//!
+//! Every plan update is diffed against the previous one; each step that was added, removed or
+//! changed status becomes an `acp.plan.step_transition` canonical event, so the history survives
+//! a session reload. From the transitions the timeline knows when each step was added, started
+//! and completed, which gives per-step wall times, a Gantt-style rendering and a stall threshold
+//! relative to how long steps usually take in this session.
+
+use std::time::Duration;
+
+use crate::{
+    canonical_event::{CanonicalEvent, PlanItem, PlanStepStatus, PlanStepTransition},
+    session_store::CanonicalRecord,
+    thread::format_monitor_duration,
+};
+
+/// A step counts as stalled once it runs this many times longer than the typical step.
+const STALL_FACTOR: u32 = 3;
+
+/// Longest step label shown in the Gantt chart.
+const LABEL_MAX_CHARS: usize = 28;
+
+/// Transitions listed under the chart.
+const RECENT_TRANSITIONS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimedTransition {
+    ts_ms: u64,
+    transition: PlanStepTransition,
+}
+
+/// One step of the current plan and when it went through each status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepSpan {
+    pub step: String,
+    pub status: PlanStepStatus,
+    pub added_ms: u64,
+    pub started_ms: Option<u64>,
+    pub completed_ms: Option<u64>,
+}
+
+impl StepSpan {
+    fn new(step: String, status: PlanStepStatus, ts_ms: u64) -> Self {
+        let mut span = Self {
+            step,
+            status,
+            added_ms: ts_ms,
+            started_ms: None,
+            completed_ms: None,
+        };
+        span.set_status(status, ts_ms);
+        span
+    }
+
+    fn set_status(&mut self, status: PlanStepStatus, ts_ms: u64) {
+        self.status = status;
+        match status {
+            PlanStepStatus::Pending => {
+                self.started_ms = None;
+                self.completed_ms = None;
+            }
+            PlanStepStatus::InProgress => {
+                self.started_ms.get_or_insert(ts_ms);
+                self.completed_ms = None;
+            }
+            PlanStepStatus::Completed => self.completed_ms = Some(ts_ms),
+        }
+    }
+
+    /// Time from the step's start (or addition, if it never showed as in progress) to its
+    /// completion, or until `now_ms` while it is in progress.
+    pub fn wall_time(&self, now_ms: u64) -> Option<Duration> {
+        let end = match self.status {
+            PlanStepStatus::Pending => return None,
+            PlanStepStatus::InProgress => now_ms,
+            PlanStepStatus::Completed => self.completed_ms?,
+        };
+        let start = self.started_ms.unwrap_or(self.added_ms);
+        Some(Duration::from_millis(end.saturating_sub(start)))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanTimeline {
+    transitions: Vec<TimedTransition>,
+    /// Steps of the current plan, in plan order.
+    steps: Vec<StepSpan>,
+}
+
+impl PlanTimeline {
+    /// Rebuild the timeline from the `acp.plan.step_transition` events of a canonical log.
+    pub fn from_records(records: &[CanonicalRecord]) -> Self {
+        let mut timeline = Self::default();
+        for record in records {
+            if let Some(CanonicalEvent::PlanStepTransition(transition)) = record.event() {
+                timeline.apply(record.ts_ms, transition);
+            }
+        }
+        timeline
+    }
+
+    /// Diff a plan update against the current steps; records and returns the transitions.
+    pub fn observe(&mut self, ts_ms: u64, items: &[PlanItem]) -> Vec<PlanStepTransition> {
+        let mut transitions = self
+            .steps
+            .iter()
+            .filter(|span| !items.iter().any(|item| item.step == span.step))
+            .map(|span| PlanStepTransition {
+                step: span.step.clone(),
+                from: Some(span.status),
+                to: None,
+            })
+            .collect::<Vec<_>>();
+        for item in items {
+            let from = self
+                .steps
+                .iter()
+                .find(|span| span.step == item.step)
+                .map(|span| span.status);
+            if from != Some(item.status) {
+                transitions.push(PlanStepTransition {
+                    step: item.step.clone(),
+                    from,
+                    to: Some(item.status),
+                });
+            }
+        }
+
+        for transition in &transitions {
+            self.apply(ts_ms, transition.clone());
+        }
+        self.steps.sort_by_key(|span| {
+            items
+                .iter()
+                .position(|item| item.step == span.step)
+                .unwrap_or(usize::MAX)
+        });
+        transitions
+    }
+
+    fn apply(&mut self, ts_ms: u64, transition: PlanStepTransition) {
+        let index = self
+            .steps
+            .iter()
+            .position(|span| span.step == transition.step);
+        match (index, transition.to) {
+            (Some(index), Some(status)) => self.steps[index].set_status(status, ts_ms),
+            (Some(index), None) => {
+                self.steps.remove(index);
+            }
+            (None, Some(status)) => {
+                self.steps
+                    .push(StepSpan::new(transition.step.clone(), status, ts_ms))
+            }
+            (None, None) => {}
+        }
+        self.transitions.push(TimedTransition { ts_ms, transition });
+    }
+
+    pub fn steps(&self) -> &[StepSpan] {
+        &self.steps
+    }
+
+    /// Median wall time of the completed steps.
+    pub fn typical_step_time(&self) -> Option<Duration> {
+        let mut times = self
+            .steps
+            .iter()
+            .filter(|span| span.status == PlanStepStatus::Completed)
+            .filter_map(|span| span.wall_time(0))
+            .collect::<Vec<_>>();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort();
+        Some(times[times.len() / 2])
+    }
+
+    /// In-progress time after which a step counts as stalled: a multiple of the typical step
+    /// time, but never less than `floor`.
+    pub fn stall_threshold(&self, floor: Duration) -> Duration {
+        self.typical_step_time()
+            .map(|typical| typical * STALL_FACTOR)
+            .unwrap_or(floor)
+            .max(floor)
+    }
+
+    /// In-progress steps running past the stall threshold, with their wall time.
+    pub fn stalled_steps(&self, now_ms: u64, floor: Duration) -> Vec<(&StepSpan, Duration)> {
+        let threshold = self.stall_threshold(floor);
+        self.steps
+            .iter()
+            .filter(|span| span.status == PlanStepStatus::InProgress)
+            .filter_map(|span| Some((span, span.wall_time(now_ms)?)))
+            .filter(|(_, wall_time)| *wall_time >= threshold)
+            .collect()
+    }
+
+    /// Gantt-style chart of the current plan: `.` pending, `#` in progress, with per-step wall
+    /// times and the most recent transitions.
+    pub fn render(&self, now_ms: u64, bar_width: usize, stall_floor: Duration) -> String {
+        let mut lines = vec!["Plan timeline".to_string()];
+        if self.steps.is_empty() {
+            lines.push("No plan updates received yet.".to_string());
+            return lines.join("\n");
+        }
+
+        let start_ms = self
+            .transitions
+            .first()
+            .map(|timed| timed.ts_ms)
+            .unwrap_or(now_ms);
+        let end_ms = now_ms.max(start_ms + 1);
+        let completed = self
+            .steps
+            .iter()
+            .filter(|span| span.status == PlanStepStatus::Completed)
+            .count();
+        lines.push(format!(
+            "{completed}/{} steps completed, {} transitions over {}",
+            self.steps.len(),
+            self.transitions.len(),
+            format_monitor_duration(Duration::from_millis(end_ms - start_ms))
+        ));
+
+        let width = bar_width.max(8);
+        let column = |ts_ms: u64| {
+            let offset = u128::from(ts_ms.saturating_sub(start_ms));
+            ((offset * width as u128 / u128::from(end_ms - start_ms)) as usize).min(width)
+        };
+        let labels = self
+            .steps
+            .iter()
+            .map(|span| truncate_label(&span.step))
+            .collect::<Vec<_>>();
+        let label_width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let stalled = self.stalled_steps(now_ms, stall_floor);
+        for (span, label) in self.steps.iter().zip(labels) {
+            let added = column(span.added_ms).min(width - 1);
+            let end = match span.completed_ms {
+                Some(completed_ms) if span.status == PlanStepStatus::Completed => {
+                    column(completed_ms)
+                }
+                _ => width,
+            };
+            let started = match (span.started_ms, span.status) {
+                (Some(started_ms), _) => Some(column(started_ms).max(added)),
+                // Completed without ever showing as in progress: mark the completion column.
+                (None, PlanStepStatus::Completed) => Some(end.saturating_sub(1).max(added)),
+                (None, _) => None,
+            };
+            let end = started
+                .map_or(end, |started| end.max(started + 1))
+                .min(width);
+            let bar = (0..width)
+                .map(|index| match started {
+                    _ if index < added || index >= end => ' ',
+                    Some(started) if index >= started => '#',
+                    _ => '.',
+                })
+                .collect::<String>();
+            let status = match span.status {
+                PlanStepStatus::Pending => "pending",
+                PlanStepStatus::InProgress => "in_progress",
+                PlanStepStatus::Completed => "completed",
+            };
+            let mut line = format!("{label:<label_width$} |{bar}| {status:<11}");
+            if let Some(wall_time) = span.wall_time(now_ms) {
+                line.push_str(&format!(" {}", format_monitor_duration(wall_time)));
+            }
+            if stalled.iter().any(|(stalled, _)| stalled.step == span.step) {
+                line.push_str(" (stalled)");
+            }
+            lines.push(line.trim_end().to_string());
+        }
+
+        lines.push(String::new());
+        lines.push(match self.typical_step_time() {
+            Some(typical) => format!(
+                "Typical step: {} (stall threshold {})",
+                format_monitor_duration(typical),
+                format_monitor_duration(self.stall_threshold(stall_floor))
+            ),
+            None => format!(
+                "Typical step: no completed steps yet (stall threshold {})",
+                format_monitor_duration(self.stall_threshold(stall_floor))
+            ),
+        });
+        lines.push("Recent transitions:".to_string());
+        let skip = self.transitions.len().saturating_sub(RECENT_TRANSITIONS);
+        for timed in self.transitions.iter().skip(skip) {
+            lines.push(format!(
+                "- +{} {}: {} -> {}",
+                format_monitor_duration(Duration::from_millis(
+                    timed.ts_ms.saturating_sub(start_ms)
+                )),
+                timed.transition.step,
+                status_name(timed.transition.from, "added"),
+                status_name(timed.transition.to, "removed")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn status_name(status: Option<PlanStepStatus>, missing: &'static str) -> &'static str {
+    match status {
+        Some(PlanStepStatus::Pending) => "pending",
+        Some(PlanStepStatus::InProgress) => "in_progress",
+        Some(PlanStepStatus::Completed) => "completed",
+        None => missing,
+    }
+}
+
+fn truncate_label(step: &str) -> String {
+    let step = step.trim();
+    if step.chars().count() <= LABEL_MAX_CHARS {
+        return step.to_string();
+    }
+    let mut label = step
+        .chars()
+        .take(LABEL_MAX_CHARS.saturating_sub(3))
+        .collect::<String>();
+    label.push_str("...");
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(steps: &[(&str, PlanStepStatus)]) -> Vec<PlanItem> {
+        steps
+            .iter()
+            .map(|(step, status)| PlanItem {
+                step: step.to_string(),
+                status: *status,
+            })
+            .collect()
+    }
+
+    fn sample_timeline() -> PlanTimeline {
+        use PlanStepStatus::*;
+        let mut timeline = PlanTimeline::default();
+        timeline.observe(0, &plan(&[("parse", InProgress), ("test", Pending)]));
+        timeline.observe(60_000, &plan(&[("parse", Completed), ("test", InProgress)]));
+        timeline.observe(
+            90_000,
+            &plan(&[
+                ("parse", Completed),
+                ("test", InProgress),
+                ("docs", Pending),
+            ]),
+        );
+        timeline
+    }
+
+    #[test]
+    fn test_observe_records_transitions_and_step_times() {
+        use PlanStepStatus::*;
+        let mut timeline = sample_timeline();
+        let transitions = timeline.observe(120_000, &plan(&[("test", Completed)]));
+        assert_eq!(
+            transitions,
+            vec![
+                PlanStepTransition {
+                    step: "parse".to_string(),
+                    from: Some(Completed),
+                    to: None,
+                },
+                PlanStepTransition {
+                    step: "docs".to_string(),
+                    from: Some(Pending),
+                    to: None,
+                },
+                PlanStepTransition {
+                    step: "test".to_string(),
+                    from: Some(InProgress),
+                    to: Some(Completed),
+                },
+            ]
+        );
+
+        let mut timeline = sample_timeline();
+        let parse = &timeline.steps()[0];
+        assert_eq!(parse.wall_time(999_999), Some(Duration::from_secs(60)));
+        let test = &timeline.steps()[1];
+        assert_eq!(test.added_ms, 0);
+        assert_eq!(test.started_ms, Some(60_000));
+        assert_eq!(test.wall_time(100_000), Some(Duration::from_secs(40)));
+        assert_eq!(timeline.steps()[2].wall_time(100_000), None);
+        assert!(
+            timeline
+                .observe(
+                    100_000,
+                    &plan(&[
+                        ("parse", Completed),
+                        ("test", InProgress),
+                        ("docs", Pending)
+                    ])
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_from_records_rebuilds_the_timeline() {
+        let timeline = sample_timeline();
+        let records = timeline
+            .transitions
+            .iter()
+            .map(|timed| {
+                let value = serde_json::to_value(CanonicalEvent::PlanStepTransition(
+                    timed.transition.clone(),
+                ))
+                .unwrap();
+                CanonicalRecord {
+                    ts_ms: timed.ts_ms,
+                    kind: value["kind"].as_str().unwrap().to_string(),
+                    data: value["data"].clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(PlanTimeline::from_records(&records), timeline);
+    }
+
+    #[test]
+    fn test_stall_threshold_follows_typical_step_time() {
+        let timeline = sample_timeline();
+        let floor = Duration::from_secs(45);
+        assert_eq!(timeline.typical_step_time(), Some(Duration::from_secs(60)));
+        assert_eq!(timeline.stall_threshold(floor), Duration::from_secs(180));
+        assert!(timeline.stalled_steps(200_000, floor).is_empty());
+        let stalled = timeline.stalled_steps(300_000, floor);
+        assert_eq!(stalled.len(), 1);
+        assert_eq!(stalled[0].0.step, "test");
+        assert_eq!(stalled[0].1, Duration::from_secs(240));
+
+        assert_eq!(
+            PlanTimeline::default().stall_threshold(floor),
+            Duration::from_secs(45)
+        );
+    }
+
+    #[test]
+    fn test_render_draws_gantt_rows() {
+        let rendered = sample_timeline().render(300_000, 10, Duration::from_secs(45));
+        let rows = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(rows[1], "1/3 steps completed, 5 transitions over 5m");
+        assert_eq!(rows[2], "parse |##        | completed   1m");
+        assert_eq!(rows[3], "test  |..########| in_progress 4m (stalled)");
+        assert_eq!(rows[4], "docs  |   .......| pending");
+        assert!(
+            rendered.contains("Typical step: 1m (stall threshold 3m)"),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("- +1m 30s docs: added -> pending"),
+            "{rendered}"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Per-workspace defaults for the Codex thread's monitoring and orchestration settings.
+//!
+//! New threads start from the environment defaults (`ACP_CONTEXT_OPT_MODE`, ...) and then apply,
+//! in order:
+//!
+//! 1. the repository file `.xsfire/settings.toml` at the repository root (the nearest ancestor
+//!    of the session cwd with a `.git` entry), meant to be committed and shared;
+//! 2. the personal file `ACP_HOME/projects/<name>-<hash>.toml`, keyed by the session cwd.
+//!
+//! Both files use the same keys and every key is optional:
+//!
+//! ```toml
+//! context_optimization_mode = "auto"   # off | monitor | auto
+//! context_trigger_percent = 85         # 75 | 80 | 85 | 90 | 95
+//! task_orchestration_mode = "parallel" # parallel | sequential
+//! task_monitoring_mode = "auto"        # on | auto | off
+//! vector_checks = true
+//! preempt_on_new_prompt = true
+//! ```
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::session_store::AcpHome;
+
+/// Settings file inside a repository.
+pub const REPO_SETTINGS_FILE: &str = ".xsfire/settings.toml";
+
+/// Values a workspace overrides; `None` keeps the inherited default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_optimization_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_trigger_percent: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_orchestration_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_monitoring_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_checks: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preempt_on_new_prompt: Option<bool>,
+}
+
+/// Where "save as project default" writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsScope {
+    /// `.xsfire/settings.toml` at the repository root.
+    Repo,
+    /// `ACP_HOME/projects/...`, private to this user.
+    Workspace,
+}
+
+impl SettingsScope {
+    /// The file of this scope for `cwd`; `None` when `ACP_HOME` cannot be resolved.
+    pub fn path(self, cwd: &Path) -> Option<PathBuf> {
+        match self {
+            Self::Repo => Some(repo_root(cwd).join(REPO_SETTINGS_FILE)),
+            Self::Workspace => {
+                let key = cwd.display().to_string();
+                let name = cwd
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "root".to_string());
+                Some(
+                    AcpHome::resolve()?
+                        .join("projects")
+                        .join(format!("{name}-{:016x}.toml", stable_hash(&key))),
+                )
+            }
+        }
+    }
+}
+
+/// FNV-1a; stable across builds and platforms, unlike `DefaultHasher`, so it can name files
+/// that outlive the process (project settings, search index buckets).
+pub(crate) fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Nearest ancestor of `cwd` holding a `.git` entry, or `cwd` itself.
+pub(crate) fn repo_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+impl ProjectSettings {
+    fn read(path: &Path) -> Option<Self> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read project settings {}: {e}", path.display());
+                return None;
+            }
+        };
+        match toml::from_str(&data) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                warn!("Ignoring invalid project settings {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// `other`'s values where set, ours otherwise.
+    fn overlay(self, other: Self) -> Self {
+        Self {
+            context_optimization_mode: other
+                .context_optimization_mode
+                .or(self.context_optimization_mode),
+            context_trigger_percent: other
+                .context_trigger_percent
+                .or(self.context_trigger_percent),
+            task_orchestration_mode: other
+                .task_orchestration_mode
+                .or(self.task_orchestration_mode),
+            task_monitoring_mode: other.task_monitoring_mode.or(self.task_monitoring_mode),
+            vector_checks: other.vector_checks.or(self.vector_checks),
+            preempt_on_new_prompt: other.preempt_on_new_prompt.or(self.preempt_on_new_prompt),
+        }
+    }
+
+    /// Merged settings for `cwd` and the files they were read from.
+    pub fn load(cwd: &Path) -> (Self, Vec<PathBuf>) {
+        let mut merged = Self::default();
+        let mut sources = Vec::new();
+        for scope in [SettingsScope::Repo, SettingsScope::Workspace] {
+            let Some(path) = scope.path(cwd) else {
+                continue;
+            };
+            if let Some(settings) = Self::read(&path) {
+                merged = merged.overlay(settings);
+                sources.push(path);
+            }
+        }
+        (merged, sources)
+    }
+
+    /// Write these settings as the `scope` defaults of `cwd`, replacing the file.
+    pub fn save(&self, scope: SettingsScope, cwd: &Path) -> io::Result<PathBuf> {
+        let path = scope
+            .path(cwd)
+            .ok_or_else(|| io::Error::other("ACP_HOME is not resolvable"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let header = match scope {
+            SettingsScope::Repo => "# xsfire-camp defaults for this repository.\n".to_string(),
+            SettingsScope::Workspace => format!("# xsfire-camp defaults for {}\n", cwd.display()),
+        };
+        let body = toml::to_string(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, format!("{header}{body}"))?;
+        std::fs::rename(tmp, &path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_store::ENV_LOCK;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[test]
+    fn workspace_file_overrides_repo_file() {
+        let _guard = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-project-settings-{}", Uuid::new_v4()));
+        let repo = root.join("repo");
+        let cwd = repo.join("crates").join("app");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(&cwd).unwrap();
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::set_var("ACP_HOME", root.join("acp"));
+        }
+
+        assert_eq!(
+            ProjectSettings::load(&cwd),
+            (ProjectSettings::default(), vec![])
+        );
+
+        let shared = ProjectSettings {
+            context_optimization_mode: Some("auto".to_string()),
+            vector_checks: Some(false),
+            ..ProjectSettings::default()
+        };
+        let repo_path = shared.save(SettingsScope::Repo, &cwd).unwrap();
+        assert_eq!(repo_path, repo.join(REPO_SETTINGS_FILE));
+
+        let personal = ProjectSettings {
+            context_optimization_mode: Some("off".to_string()),
+            context_trigger_percent: Some(80),
+            ..ProjectSettings::default()
+        };
+        let workspace_path = personal.save(SettingsScope::Workspace, &cwd).unwrap();
+        assert!(workspace_path.starts_with(root.join("acp").join("projects")));
+        assert!(
+            std::fs::read_to_string(&workspace_path)
+                .unwrap()
+                .starts_with("# xsfire-camp defaults for ")
+        );
+
+        let (merged, sources) = ProjectSettings::load(&cwd);
+        assert_eq!(sources, vec![repo_path.clone(), workspace_path]);
+        assert_eq!(merged.context_optimization_mode.as_deref(), Some("off"));
+        assert_eq!(merged.context_trigger_percent, Some(80));
+        assert_eq!(merged.vector_checks, Some(false));
+        assert_eq!(merged.preempt_on_new_prompt, None);
+
+        // A broken file is ignored instead of failing the session.
+        std::fs::write(&repo_path, "vector_checks = \"maybe\"\n").unwrap();
+        let (merged, sources) = ProjectSettings::load(&cwd);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(merged.vector_checks, None);
+
+        drop(std::fs::remove_dir_all(&root));
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+    }
+}
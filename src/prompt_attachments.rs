@@ -0,0 +1,231 @@
+//! Images and binary resources attached to a prompt, for CLI backends that take text only.
+//!
+//! Claude Code and Gemini receive the prompt as a command-line argument, so attached images and
+//! blob resources are decoded into a fresh private (0700) temporary directory for each turn and
+//! the prompt refers to them by path. The directory is removed when the turn ends.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use agent_client_protocol::{
+    BlobResourceContents, ContentBlock, EmbeddedResource, EmbeddedResourceResource, ImageContent,
+    SessionId,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use tempfile::TempDir;
+use tracing::warn;
+
+/// One attachment written to disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attachment {
+    pub path: PathBuf,
+    pub mime_type: String,
+    pub is_image: bool,
+}
+
+/// Attachments of one prompt, removed from disk when dropped.
+#[derive(Debug)]
+pub(crate) struct PromptAttachments {
+    dir: TempDir,
+    /// Prompt block index and the file written for it.
+    files: Vec<(usize, Attachment)>,
+}
+
+impl PromptAttachments {
+    /// Write the images and blob resources of `blocks` to a new attachment directory, or return
+    /// `None` when the prompt has none (or nothing could be written).
+    pub fn write(session_id: &SessionId, blocks: &[ContentBlock]) -> Option<Self> {
+        let pending = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, block)| attachment_source(block).map(|source| (index, source)))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return None;
+        }
+
+        // A random name, created with mode 0700, so no other user can predict or read it.
+        let dir = match tempfile::Builder::new()
+            .prefix(&format!(
+                "xsfire-attachments-{}-",
+                sanitize_file_name(&session_id.0)
+            ))
+            .tempdir()
+        {
+            Ok(dir) => dir,
+            Err(err) => {
+                warn!("Failed to create attachment dir: {err}");
+                return None;
+            }
+        };
+
+        let mut attachments = Self {
+            dir,
+            files: Vec::new(),
+        };
+        for (index, source) in pending {
+            let name = format!("{}-{}", index + 1, source.file_name);
+            let path = attachments.dir.path().join(name);
+            let written = STANDARD
+                .decode(source.data.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| fs::write(&path, bytes).map_err(|err| err.to_string()));
+            match written {
+                Ok(()) => attachments.files.push((
+                    index,
+                    Attachment {
+                        path,
+                        mime_type: source.mime_type.to_string(),
+                        is_image: source.is_image,
+                    },
+                )),
+                Err(err) => warn!("Failed to write attachment {}: {err}", path.display()),
+            }
+        }
+        if attachments.files.is_empty() {
+            return None;
+        }
+        Some(attachments)
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Attachment written for prompt block `index`.
+    pub fn get(&self, index: usize) -> Option<&Attachment> {
+        self.files
+            .iter()
+            .find(|(block, _)| *block == index)
+            .map(|(_, attachment)| attachment)
+    }
+}
+
+struct AttachmentSource<'a> {
+    data: &'a str,
+    mime_type: &'a str,
+    file_name: String,
+    is_image: bool,
+}
+
+fn attachment_source(block: &ContentBlock) -> Option<AttachmentSource<'_>> {
+    match block {
+        ContentBlock::Image(ImageContent {
+            data, mime_type, ..
+        }) => Some(AttachmentSource {
+            data,
+            mime_type,
+            file_name: format!("image.{}", extension_for(mime_type)),
+            is_image: true,
+        }),
+        ContentBlock::Resource(EmbeddedResource {
+            resource:
+                EmbeddedResourceResource::BlobResourceContents(BlobResourceContents {
+                    blob,
+                    uri,
+                    mime_type,
+                    ..
+                }),
+            ..
+        }) => {
+            let mime_type = mime_type.as_deref().unwrap_or("application/octet-stream");
+            let name = uri
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .map(sanitize_file_name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("resource.{}", extension_for(mime_type)));
+            Some(AttachmentSource {
+                data: blob,
+                mime_type,
+                file_name: name,
+                is_image: mime_type.starts_with("image/"),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn extension_for(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "application/json" => "json",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
+
+/// Keep ASCII letters, digits, `.`, `-` and `_`; everything else becomes `_`.
+fn sanitize_file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    name.trim_start_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::TextContent;
+
+    #[test]
+    fn writes_images_and_blobs_and_removes_them_on_drop() {
+        let session_id = SessionId::new("attachments-test");
+        let blocks = vec![
+            ContentBlock::Text(TextContent::new("look at these")),
+            ContentBlock::Image(ImageContent::new(
+                STANDARD.encode(b"png bytes"),
+                "image/png",
+            )),
+            ContentBlock::Resource(EmbeddedResource::new(
+                EmbeddedResourceResource::BlobResourceContents(
+                    BlobResourceContents::new(STANDARD.encode(b"%PDF"), "file:///docs/spec v2.pdf")
+                        .mime_type(Some("application/pdf".to_string())),
+                ),
+            )),
+            ContentBlock::Image(ImageContent::new("not base64!", "image/png")),
+        ];
+
+        let attachments = PromptAttachments::write(&session_id, &blocks).unwrap();
+        let dir = attachments.dir().to_path_buf();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        // Every turn gets a directory of its own.
+        let other = PromptAttachments::write(&session_id, &blocks).unwrap();
+        assert_ne!(other.dir(), dir);
+        drop(other);
+        assert!(attachments.get(0).is_none());
+        let image = attachments.get(1).unwrap();
+        assert_eq!(image.path, dir.join("2-image.png"));
+        assert!(image.is_image);
+        assert_eq!(fs::read(&image.path).unwrap(), b"png bytes");
+        let pdf = attachments.get(2).unwrap();
+        assert_eq!(pdf.path, dir.join("3-spec_v2.pdf"));
+        assert_eq!(pdf.mime_type, "application/pdf");
+        assert!(!pdf.is_image);
+        assert!(attachments.get(3).is_none());
+
+        drop(attachments);
+        assert!(!dir.exists());
+        assert!(PromptAttachments::write(&session_id, &blocks[..1]).is_none());
+    }
+}
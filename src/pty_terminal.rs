@@ -0,0 +1,298 @@
+//! Local PTY fallback for interactive unified exec commands.
+//!
+//! ACP terminals have no stdin, so when a client does not advertise the `terminal_write`
+//! extension, `exec_command` with `tty=true` runs the command in a PTY spawned here. Its
+//! output is appended to a transcript file that an ACP terminal follows by running this
+//! binary as `xsfire-camp terminal-mirror <log>`, so the user still sees the process live in
+//! the client while the model drives it through `write_stdin`.
+
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use codex_utils_pty::{ProcessHandle, SpawnedProcess, spawn_pty_process};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::{broadcast, oneshot, watch},
+};
+use uuid::Uuid;
+
+/// Subcommand run inside the ACP terminal to mirror a PTY transcript.
+pub const TERMINAL_MIRROR_SUBCOMMAND: &str = "terminal-mirror";
+
+const MIRROR_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Grace period for trailing PTY output after the child has exited.
+const EXIT_DRAIN_DELAY: Duration = Duration::from_millis(50);
+/// Output kept for the model between reads: the largest ACP terminal `output_byte_limit`, so
+/// no read can return more anyway.
+const UNREAD_BYTE_LIMIT: usize = 512 * 1024;
+
+/// Interactive process running in a local PTY.
+pub(crate) struct PtyTerminal {
+    process: ProcessHandle,
+    /// Output not yet returned to the model, capped at [`UNREAD_BYTE_LIMIT`].
+    unread: Arc<Mutex<Vec<u8>>>,
+    exit_rx: watch::Receiver<Option<i32>>,
+    transcript: Arc<Transcript>,
+}
+
+/// The transcript file and its exit marker, removed once neither the terminal nor its output
+/// pump holds them.
+struct Transcript {
+    log_path: PathBuf,
+}
+
+impl Transcript {
+    /// Create an empty transcript readable by the current user only: it holds raw,
+    /// unredacted terminal output.
+    fn create() -> io::Result<Self> {
+        let log_path = std::env::temp_dir().join(format!("xsfire-pty-{}.log", Uuid::new_v4()));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&log_path)?;
+        Ok(Self { log_path })
+    }
+}
+
+impl Drop for Transcript {
+    fn drop(&mut self) {
+        drop(std::fs::remove_file(&self.log_path));
+        drop(std::fs::remove_file(exit_marker_path(&self.log_path)));
+    }
+}
+
+impl PtyTerminal {
+    pub async fn spawn(command: &[String], cwd: &Path) -> anyhow::Result<Self> {
+        let Some((program, args)) = command.split_first() else {
+            anyhow::bail!("missing command line for PTY spawn");
+        };
+        let env = std::env::vars().collect::<HashMap<_, _>>();
+        let SpawnedProcess {
+            session: process,
+            output_rx,
+            exit_rx,
+        } = spawn_pty_process(program, args, cwd, &env, &None).await?;
+
+        let transcript = Arc::new(Transcript::create()?);
+        let unread = Arc::new(Mutex::new(Vec::new()));
+        let (exit_tx, watch_rx) = watch::channel(None);
+        tokio::spawn(pump_output(
+            output_rx,
+            exit_rx,
+            unread.clone(),
+            transcript.clone(),
+            exit_tx,
+        ));
+
+        Ok(Self {
+            process,
+            unread,
+            exit_rx: watch_rx,
+            transcript,
+        })
+    }
+
+    /// Command for an ACP terminal that mirrors this PTY's output and exit code.
+    pub fn mirror_command(&self) -> io::Result<(String, Vec<String>)> {
+        let exe = std::env::current_exe()?;
+        Ok((
+            exe.display().to_string(),
+            vec![
+                TERMINAL_MIRROR_SUBCOMMAND.to_string(),
+                self.transcript.log_path.display().to_string(),
+            ],
+        ))
+    }
+
+    pub async fn write(&self, input: &[u8]) -> io::Result<()> {
+        self.process
+            .writer_sender()
+            .send(input.to_vec())
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    /// Wait up to `yield_time` (or until exit) and return the output produced since the last
+    /// read, keeping at most `limit` trailing bytes, plus the exit code once known.
+    pub async fn read(&self, yield_time: Duration, limit: usize) -> (String, Option<i32>) {
+        let mut exit_rx = self.exit_rx.clone();
+        drop(tokio::time::timeout(yield_time, exit_rx.wait_for(Option::is_some)).await);
+        let exit_code = *exit_rx.borrow();
+        let bytes = std::mem::take(&mut *self.unread.lock().unwrap());
+        let start = bytes.len().saturating_sub(limit);
+        (
+            String::from_utf8_lossy(&bytes[start..]).into_owned(),
+            exit_code,
+        )
+    }
+
+    /// Kill the process and tell the mirror to stop.
+    pub fn terminate(&self) {
+        self.process.terminate();
+        if self.exit_rx.borrow().is_none() {
+            drop(write_exit_marker(&self.transcript.log_path, -1));
+        }
+    }
+}
+
+async fn pump_output(
+    mut output_rx: broadcast::Receiver<Vec<u8>>,
+    mut exit_rx: oneshot::Receiver<i32>,
+    unread: Arc<Mutex<Vec<u8>>>,
+    transcript: Arc<Transcript>,
+    exit_tx: watch::Sender<Option<i32>>,
+) {
+    let log_path = &transcript.log_path;
+    let mut log = OpenOptions::new().append(true).open(log_path).ok();
+    let mut record = |chunk: Vec<u8>| {
+        if let Some(file) = log.as_mut() {
+            drop(file.write_all(&chunk));
+        }
+        append_capped(&mut unread.lock().unwrap(), &chunk, UNREAD_BYTE_LIMIT);
+    };
+    let exit_code = loop {
+        tokio::select! {
+            chunk = output_rx.recv() => match chunk {
+                Ok(chunk) => record(chunk),
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => {
+                    break (&mut exit_rx).await.unwrap_or(-1);
+                }
+            },
+            code = &mut exit_rx => break code.unwrap_or(-1),
+        }
+    };
+    tokio::time::sleep(EXIT_DRAIN_DELAY).await;
+    while let Ok(chunk) = output_rx.try_recv() {
+        record(chunk);
+    }
+    drop(write_exit_marker(log_path, exit_code));
+    drop(exit_tx.send(Some(exit_code)));
+}
+
+/// Append `chunk`, dropping the oldest bytes beyond `limit`.
+fn append_capped(buf: &mut Vec<u8>, chunk: &[u8], limit: usize) {
+    buf.extend_from_slice(chunk);
+    let excess = buf.len().saturating_sub(limit);
+    if excess > 0 {
+        buf.drain(..excess);
+    }
+}
+
+fn exit_marker_path(log_path: &Path) -> PathBuf {
+    let mut path = log_path.as_os_str().to_owned();
+    path.push(".exit");
+    PathBuf::from(path)
+}
+
+fn write_exit_marker(log_path: &Path, exit_code: i32) -> io::Result<()> {
+    std::fs::write(exit_marker_path(log_path), exit_code.to_string())
+}
+
+/// Copy `log_path` to `out` as it grows until the exit marker appears, then remove both
+/// files and return the mirrored exit code.
+pub async fn follow_log(log_path: &Path, out: &mut (impl AsyncWrite + Unpin)) -> io::Result<i32> {
+    let exit_path = exit_marker_path(log_path);
+    let mut offset = 0;
+    loop {
+        // Check for the marker first so the final read drains everything before it.
+        let exited = exit_path.exists();
+        let mut file = std::fs::File::open(log_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk)?;
+        if !chunk.is_empty() {
+            out.write_all(&chunk).await?;
+            out.flush().await?;
+            offset += chunk.len() as u64;
+        }
+        if exited {
+            break;
+        }
+        tokio::time::sleep(MIRROR_POLL_INTERVAL).await;
+    }
+    let exit_code = std::fs::read_to_string(&exit_path)?
+        .trim()
+        .parse()
+        .unwrap_or(1);
+    drop(std::fs::remove_file(log_path));
+    drop(std::fs::remove_file(exit_path));
+    Ok(exit_code)
+}
+
+/// Entry point of the `terminal-mirror` subcommand.
+pub async fn run_terminal_mirror(log_path: &Path) -> io::Result<i32> {
+    follow_log(log_path, &mut tokio::io::stdout()).await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{PtyTerminal, append_capped, exit_marker_path, follow_log};
+    use std::{os::unix::fs::PermissionsExt, time::Duration};
+
+    #[test]
+    fn unread_output_keeps_only_the_newest_bytes() {
+        let mut buf = Vec::new();
+        append_capped(&mut buf, b"abc", 4);
+        append_capped(&mut buf, b"def", 4);
+        assert_eq!(buf, b"cdef");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn pty_accepts_input_and_is_mirrored_with_its_exit_code() {
+        let command = ["sh", "-c", "read line; echo \"got:$line\"; exit 3"]
+            .map(str::to_string)
+            .to_vec();
+        let pty = PtyTerminal::spawn(&command, &std::env::temp_dir())
+            .await
+            .unwrap();
+
+        let (_, exit_code) = pty.read(Duration::from_millis(100), 1024).await;
+        assert_eq!(exit_code, None);
+
+        pty.write(b"hello\n").await.unwrap();
+        let (output, exit_code) = pty.read(Duration::from_secs(10), 1024).await;
+        assert!(output.contains("got:hello"), "{output:?}");
+        assert_eq!(exit_code, Some(3));
+
+        let log_path = pty.transcript.log_path.clone();
+        let mode = std::fs::metadata(&log_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mut mirrored = Vec::new();
+        assert_eq!(follow_log(&log_path, &mut mirrored).await.unwrap(), 3);
+        assert!(String::from_utf8_lossy(&mirrored).contains("got:hello"));
+        assert!(!log_path.exists());
+        assert!(!exit_marker_path(&log_path).exists());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn released_pty_removes_its_transcript() {
+        let command = ["sh", "-c", "echo secret; sleep 5"]
+            .map(str::to_string)
+            .to_vec();
+        let pty = PtyTerminal::spawn(&command, &std::env::temp_dir())
+            .await
+            .unwrap();
+        let log_path = pty.transcript.log_path.clone();
+        assert!(log_path.exists());
+
+        pty.terminate();
+        drop(pty);
+        // The output pump holds the transcript until it has seen the exit.
+        for _ in 0..100 {
+            if !log_path.exists() && !exit_marker_path(&log_path).exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!log_path.exists());
+        assert!(!exit_marker_path(&log_path).exists());
+    }
+}
@@ -0,0 +1,756 @@
+//! `/monitor retro`: a retrospective of the current session built from the plan history kept
+//! by the thread monitor and from the session's canonical log (tool calls, approvals,
+//! auto-compactions and token usage).
+//!
+//! The report is rendered in English or Korean. The language comes from the command
+//! (`/monitor retro ko`), then `XSFIRE_MONITOR_LANGUAGE`, then defaults to English.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use serde_json::Value;
+
+use crate::{
+    canonical_event::CanonicalEvent, session_store::CanonicalRecord,
+    thread::format_monitor_duration,
+};
+
+/// Default language of `/monitor retro` when the command does not name one.
+pub const MONITOR_LANGUAGE_ENV: &str = "XSFIRE_MONITOR_LANGUAGE";
+
+/// Steps listed per section before the rest is summarized.
+const MAX_LISTED_STEPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetroLanguage {
+    #[default]
+    English,
+    Korean,
+}
+
+impl RetroLanguage {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().trim_start_matches("lang=").to_lowercase();
+        match value.as_str() {
+            "en" | "eng" | "english" => Some(Self::English),
+            "ko" | "kr" | "kor" | "korean" | "한국어" => Some(Self::Korean),
+            _ => None,
+        }
+    }
+
+    /// Language named in the `/monitor` arguments, else the environment default.
+    pub fn from_rest(rest: &str) -> Self {
+        rest.split_whitespace()
+            .find_map(Self::parse)
+            .or_else(|| {
+                std::env::var(MONITOR_LANGUAGE_ENV)
+                    .ok()
+                    .as_deref()
+                    .and_then(Self::parse)
+            })
+            .unwrap_or_default()
+    }
+
+    fn pick(self, english: impl Into<String>, korean: impl Into<String>) -> String {
+        match self {
+            Self::English => english.into(),
+            Self::Korean => korean.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+    Completed,
+    InProgress,
+    Pending,
+}
+
+/// How one plan step went, derived from the plan timeline of the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub step: String,
+    pub state: StepState,
+    /// Time from the step's start (or first appearance) to its completion, or until now.
+    pub elapsed: Option<Duration>,
+    /// In progress for longer than the session's stall threshold.
+    pub stalled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ToolKindStats {
+    calls: usize,
+    failures: usize,
+    total: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ToolFailure {
+    kind: String,
+    exit_code: Option<i32>,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TokenSample {
+    total_tokens: i64,
+}
+
+/// Aggregates of a session's canonical log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogSummary {
+    tools: BTreeMap<String, ToolKindStats>,
+    slowest_tool: Option<(String, Duration)>,
+    failures: Vec<ToolFailure>,
+    permission_requests: usize,
+    permissions_approved: usize,
+    permissions_declined: usize,
+    rules_allowed: usize,
+    rules_denied: usize,
+    compactions: usize,
+    compaction_errors: usize,
+    first_tokens: Option<TokenSample>,
+    last_tokens: Option<TokenSample>,
+    peak_percent: Option<i64>,
+    trigger_percent: Option<i64>,
+}
+
+impl LogSummary {
+    pub fn from_records(records: &[CanonicalRecord]) -> Self {
+        let mut summary = Self::default();
+        let mut started = BTreeMap::new();
+        for record in records {
+            let Some(event) = record.event() else {
+                continue;
+            };
+            match event {
+                CanonicalEvent::BridgeToolExecStarted(call) => {
+                    started.insert(call.tool_call_id, record.ts_ms);
+                }
+                CanonicalEvent::BridgeToolExecFinished(finished) => {
+                    let duration = started
+                        .remove(&finished.tool_call_id)
+                        .map(|start| Duration::from_millis(record.ts_ms.saturating_sub(start)))
+                        .unwrap_or_default();
+                    let stats = summary.tools.entry(finished.tool_kind.clone()).or_default();
+                    stats.calls += 1;
+                    stats.total += duration;
+                    if summary
+                        .slowest_tool
+                        .as_ref()
+                        .is_none_or(|(_, slowest)| duration > *slowest)
+                    {
+                        summary.slowest_tool = Some((finished.tool_kind.clone(), duration));
+                    }
+                    if finished.status == "failed" {
+                        stats.failures += 1;
+                        summary.failures.push(ToolFailure {
+                            kind: finished.tool_kind,
+                            exit_code: finished.exit_code,
+                            reason: finished.reason,
+                        });
+                    }
+                }
+                CanonicalEvent::RequestPermission(_) => summary.permission_requests += 1,
+                CanonicalEvent::RequestPermissionResponse(response) => {
+                    if permission_approved(&response.outcome) {
+                        summary.permissions_approved += 1;
+                    } else {
+                        summary.permissions_declined += 1;
+                    }
+                }
+                CanonicalEvent::ApprovalPolicyDecision(decision) => {
+                    match decision.decision.as_str() {
+                        "allow" => summary.rules_allowed += 1,
+                        "deny" => summary.rules_denied += 1,
+                        _ => {}
+                    }
+                }
+                CanonicalEvent::ContextOptAutoCompactTriggered(_) => summary.compactions += 1,
+                CanonicalEvent::ContextOptAutoCompactError(_) => summary.compaction_errors += 1,
+                CanonicalEvent::ContextOptTokenUsage(usage) => {
+                    let sample = TokenSample {
+                        total_tokens: usage.total_tokens,
+                    };
+                    summary.first_tokens.get_or_insert(sample);
+                    summary.last_tokens = Some(sample);
+                    summary.trigger_percent = Some(usage.trigger_percent);
+                    if let Some(percent) = usage.used_percent {
+                        summary.peak_percent = Some(
+                            summary
+                                .peak_percent
+                                .map_or(percent, |peak| peak.max(percent)),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    fn tool_calls(&self) -> usize {
+        self.tools.values().map(|stats| stats.calls).sum()
+    }
+
+    fn context_pressure(&self) -> Option<i64> {
+        let peak = self.peak_percent?;
+        (peak >= self.trigger_percent?).then_some(peak)
+    }
+}
+
+fn permission_approved(outcome: &Value) -> bool {
+    outcome.get("outcome").and_then(Value::as_str) == Some("selected")
+        && outcome
+            .get("optionId")
+            .and_then(Value::as_str)
+            .is_some_and(|id| id.starts_with("approved") || id.starts_with("allow"))
+}
+
+/// Everything `/monitor retro` reports on.
+#[derive(Debug, Clone, Default)]
+pub struct Retrospective {
+    pub steps: Vec<StepOutcome>,
+    pub plan_updates: usize,
+    /// Time spent per flow phase (`A`, `E`, `V`, `C`).
+    pub phase_time: Vec<(char, Duration)>,
+    /// Latest action and how many times in a row it was repeated.
+    pub repeated_action: Option<(String, usize)>,
+    /// Log aggregates; `None` when the session has no canonical log.
+    pub log: Option<LogSummary>,
+}
+
+impl Retrospective {
+    pub fn render(&self, language: RetroLanguage) -> String {
+        let lang = language;
+        let mut lines = vec![lang.pick("Session retrospective", "세션 회고")];
+
+        let count = |state| self.steps.iter().filter(|s| s.state == state).count();
+        let (completed, in_progress, pending) = (
+            count(StepState::Completed),
+            count(StepState::InProgress),
+            count(StepState::Pending),
+        );
+        if self.steps.is_empty() {
+            lines.push(lang.pick(
+                "Plan: no plan updates received yet.",
+                "계획: 아직 받은 계획 업데이트가 없습니다.",
+            ));
+        } else {
+            lines.push(lang.pick(
+                format!(
+                    "Plan: {completed}/{} steps completed, {in_progress} in progress, {pending} pending ({} plan updates)",
+                    self.steps.len(),
+                    self.plan_updates
+                ),
+                format!(
+                    "계획: {}단계 중 {completed}단계 완료, {in_progress}단계 진행 중, {pending}단계 대기 (계획 업데이트 {}회)",
+                    self.steps.len(),
+                    self.plan_updates
+                ),
+            ));
+        }
+
+        let completed_steps = self
+            .steps
+            .iter()
+            .filter(|s| s.state == StepState::Completed)
+            .map(|s| match s.elapsed {
+                Some(elapsed) => format!("{} ({})", s.step, format_monitor_duration(elapsed)),
+                None => s.step.clone(),
+            })
+            .collect::<Vec<_>>();
+        push_section(
+            &mut lines,
+            lang.pick("Completed steps", "완료된 단계"),
+            completed_steps,
+        );
+
+        let stalled_steps = self
+            .steps
+            .iter()
+            .filter(|s| s.stalled)
+            .map(|s| {
+                let elapsed = s.elapsed.map(format_monitor_duration).unwrap_or_default();
+                lang.pick(
+                    format!("{} (in progress for {elapsed})", s.step),
+                    format!("{} ({elapsed} 동안 진행 중)", s.step),
+                )
+            })
+            .collect::<Vec<_>>();
+        push_section(
+            &mut lines,
+            lang.pick("Stalled steps", "정체된 단계"),
+            stalled_steps,
+        );
+
+        let phase_total = self
+            .phase_time
+            .iter()
+            .map(|(_, duration)| *duration)
+            .sum::<Duration>();
+        let phases = self
+            .phase_time
+            .iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(phase, duration)| {
+                let percent = duration.as_millis() * 100 / phase_total.as_millis().max(1);
+                format!(
+                    "{}: {} ({percent}%)",
+                    phase_label(*phase, lang),
+                    format_monitor_duration(*duration)
+                )
+            })
+            .collect::<Vec<_>>();
+        push_section(
+            &mut lines,
+            lang.pick("Time per phase", "단계별 소요 시간"),
+            phases,
+        );
+
+        match &self.log {
+            Some(log) => self.render_log(log, lang, &mut lines),
+            None => {
+                lines.push(String::new());
+                lines.push(lang.pick(
+                    "Session log: not available (session store disabled).",
+                    "세션 로그: 사용할 수 없습니다 (세션 저장소 비활성화).",
+                ));
+            }
+        }
+
+        push_section(&mut lines, lang.pick("Risks", "리스크"), self.risks(lang));
+        push_section(
+            &mut lines,
+            lang.pick("Next actions", "다음 작업"),
+            self.next_actions(lang),
+        );
+        lines.join("\n")
+    }
+
+    fn render_log(&self, log: &LogSummary, lang: RetroLanguage, lines: &mut Vec<String>) {
+        let mut tools = Vec::new();
+        if log.tool_calls() == 0 {
+            tools.push(lang.pick(
+                "No tool calls finished yet.",
+                "완료된 도구 호출이 없습니다.",
+            ));
+        } else {
+            let total = log
+                .tools
+                .values()
+                .map(|stats| stats.total)
+                .sum::<Duration>();
+            let by_kind = log
+                .tools
+                .iter()
+                .map(|(kind, stats)| {
+                    format!(
+                        "{kind} {} / {}",
+                        stats.calls,
+                        format_monitor_duration(stats.total)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            tools.push(lang.pick(
+                format!(
+                    "{} calls, {} failed, {} total ({by_kind})",
+                    log.tool_calls(),
+                    log.failures.len(),
+                    format_monitor_duration(total)
+                ),
+                format!(
+                    "호출 {}회, 실패 {}회, 총 {} ({by_kind})",
+                    log.tool_calls(),
+                    log.failures.len(),
+                    format_monitor_duration(total)
+                ),
+            ));
+            if let Some((kind, duration)) = &log.slowest_tool {
+                tools.push(lang.pick(
+                    format!("Slowest: {kind} ({})", format_monitor_duration(*duration)),
+                    format!(
+                        "가장 느린 호출: {kind} ({})",
+                        format_monitor_duration(*duration)
+                    ),
+                ));
+            }
+            for failure in log.failures.iter().rev().take(3) {
+                let mut detail = failure.kind.clone();
+                if let Some(code) = failure.exit_code {
+                    detail.push_str(&format!(" exit {code}"));
+                }
+                if let Some(reason) = &failure.reason {
+                    detail.push_str(&format!(": {reason}"));
+                }
+                tools.push(lang.pick(format!("Failed: {detail}"), format!("실패: {detail}")));
+            }
+        }
+        push_section(lines, lang.pick("Tool calls", "도구 호출"), tools);
+
+        let mut approvals = vec![lang.pick(
+            format!(
+                "{} permission requests: {} approved, {} declined",
+                log.permission_requests, log.permissions_approved, log.permissions_declined
+            ),
+            format!(
+                "권한 요청 {}회: 승인 {}회, 거절 {}회",
+                log.permission_requests, log.permissions_approved, log.permissions_declined
+            ),
+        )];
+        if log.rules_allowed + log.rules_denied > 0 {
+            approvals.push(lang.pick(
+                format!(
+                    "Approval rules: {} auto-approved, {} denied",
+                    log.rules_allowed, log.rules_denied
+                ),
+                format!(
+                    "승인 규칙: 자동 승인 {}회, 거부 {}회",
+                    log.rules_allowed, log.rules_denied
+                ),
+            ));
+        }
+        push_section(lines, lang.pick("Approvals", "승인"), approvals);
+
+        let mut context = Vec::new();
+        if let (Some(first), Some(last)) = (log.first_tokens, log.last_tokens) {
+            let peak = log
+                .peak_percent
+                .map(|peak| lang.pick(format!(", peak {peak}%"), format!(", 최고 {peak}%")))
+                .unwrap_or_default();
+            context.push(lang.pick(
+                format!(
+                    "Tokens in context: {} -> {}{peak}",
+                    first.total_tokens, last.total_tokens
+                ),
+                format!(
+                    "컨텍스트 토큰: {} -> {}{peak}",
+                    first.total_tokens, last.total_tokens
+                ),
+            ));
+        } else {
+            context.push(lang.pick(
+                "Tokens in context: no usage reported yet",
+                "컨텍스트 토큰: 아직 보고된 사용량이 없습니다",
+            ));
+        }
+        context.push(lang.pick(
+            format!(
+                "Auto-compactions: {} ({} failed)",
+                log.compactions, log.compaction_errors
+            ),
+            format!(
+                "자동 압축: {}회 (실패 {}회)",
+                log.compactions, log.compaction_errors
+            ),
+        ));
+        push_section(lines, lang.pick("Context", "컨텍스트"), context);
+    }
+
+    fn risks(&self, lang: RetroLanguage) -> Vec<String> {
+        let mut risks = Vec::new();
+        if let Some((action, streak)) = &self.repeated_action {
+            risks.push(lang.pick(
+                format!("Repeat loop: `{action}` repeated {streak}x in a row"),
+                format!("반복 루프: `{action}` {streak}회 연속 반복"),
+            ));
+        }
+        for step in self.steps.iter().filter(|s| s.stalled) {
+            risks.push(lang.pick(
+                format!("Stall: `{}` is not completing", step.step),
+                format!("정체: `{}` 단계가 완료되지 않고 있습니다", step.step),
+            ));
+        }
+        if let Some(log) = &self.log {
+            if !log.failures.is_empty() {
+                risks.push(lang.pick(
+                    format!("{} tool call(s) failed", log.failures.len()),
+                    format!("도구 호출 {}회 실패", log.failures.len()),
+                ));
+            }
+            if log.permissions_declined + log.rules_denied > 0 {
+                risks.push(lang.pick(
+                    format!(
+                        "{} approval(s) declined or denied by rule",
+                        log.permissions_declined + log.rules_denied
+                    ),
+                    format!(
+                        "승인 거절 또는 규칙 거부 {}회",
+                        log.permissions_declined + log.rules_denied
+                    ),
+                ));
+            }
+            if let Some(peak) = log.context_pressure() {
+                risks.push(lang.pick(
+                    format!("Context pressure: usage peaked at {peak}%"),
+                    format!("컨텍스트 압박: 사용량 최고 {peak}%"),
+                ));
+            }
+            if log.compaction_errors > 0 {
+                risks.push(lang.pick(
+                    "Auto-compaction failed at least once",
+                    "자동 압축이 한 번 이상 실패했습니다",
+                ));
+            }
+        }
+        if risks.is_empty() {
+            risks.push(lang.pick("No risk signals.", "감지된 리스크가 없습니다."));
+        }
+        risks
+    }
+
+    fn next_actions(&self, lang: RetroLanguage) -> Vec<String> {
+        let mut actions = Vec::new();
+        if let Some((action, _)) = &self.repeated_action {
+            actions.push(lang.pick(
+                format!("Change approach instead of retrying `{action}` again"),
+                format!("`{action}` 재시도 대신 접근 방식을 바꾸세요"),
+            ));
+        }
+        for step in self.steps.iter().filter(|s| s.stalled) {
+            actions.push(lang.pick(
+                format!("Split `{}` or note what blocks it", step.step),
+                format!("`{}` 단계를 나누거나 막힌 이유를 기록하세요", step.step),
+            ));
+        }
+        if let Some(log) = &self.log {
+            if !log.failures.is_empty() {
+                actions.push(lang.pick(
+                    "Review the failed tool calls before retrying them",
+                    "실패한 도구 호출을 재시도하기 전에 원인을 확인하세요",
+                ));
+            }
+            if log.context_pressure().is_some() {
+                actions.push(lang.pick(
+                    "Run /compact or continue in a fresh session",
+                    "/compact를 실행하거나 새 세션에서 이어가세요",
+                ));
+            }
+        }
+        let next_step = self
+            .steps
+            .iter()
+            .find(|s| s.state == StepState::InProgress && !s.stalled)
+            .or_else(|| self.steps.iter().find(|s| s.state == StepState::Pending));
+        match next_step {
+            Some(step) => actions.push(lang.pick(
+                format!("Continue with `{}`", step.step),
+                format!("`{}` 단계를 이어서 진행하세요", step.step),
+            )),
+            None if !self.steps.is_empty()
+                && self.steps.iter().all(|s| s.state == StepState::Completed) =>
+            {
+                actions.push(lang.pick(
+                    "All plan steps are completed: verify the result and wrap up",
+                    "모든 계획 단계가 완료되었습니다: 결과를 검증하고 마무리하세요",
+                ));
+            }
+            _ => {}
+        }
+        if actions.is_empty() {
+            actions.push(lang.pick(
+                "Ask for a plan so progress can be tracked",
+                "진행 상황을 추적할 수 있도록 계획을 요청하세요",
+            ));
+        }
+        actions
+    }
+}
+
+fn push_section(lines: &mut Vec<String>, title: String, items: Vec<String>) {
+    if items.is_empty() {
+        return;
+    }
+    lines.push(String::new());
+    lines.push(title);
+    let hidden = items.len().saturating_sub(MAX_LISTED_STEPS);
+    lines.extend(
+        items
+            .into_iter()
+            .take(MAX_LISTED_STEPS)
+            .map(|item| format!("- {item}")),
+    );
+    if hidden > 0 {
+        lines.push(format!("- (+{hidden})"));
+    }
+}
+
+fn phase_label(phase: char, lang: RetroLanguage) -> &'static str {
+    match (phase, lang) {
+        ('A', RetroLanguage::English) => "analysis",
+        ('E', RetroLanguage::English) => "execution",
+        ('V', RetroLanguage::English) => "validation",
+        ('C', RetroLanguage::English) => "coordination",
+        ('A', RetroLanguage::Korean) => "분석",
+        ('E', RetroLanguage::Korean) => "실행",
+        ('V', RetroLanguage::Korean) => "검증",
+        ('C', RetroLanguage::Korean) => "조율",
+        (_, RetroLanguage::English) => "unknown",
+        (_, RetroLanguage::Korean) => "알 수 없음",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(ts_ms: u64, kind: &str, data: Value) -> CanonicalRecord {
+        CanonicalRecord {
+            ts_ms,
+            kind: kind.to_string(),
+            data,
+        }
+    }
+
+    fn sample_log() -> Vec<CanonicalRecord> {
+        let call =
+            |id: &str| json!({"submission_id": "s1", "tool_call_id": id, "tool_kind": "exec"});
+        vec![
+            record(1_000, "acp.bridge.tool_exec_started", call("a")),
+            record(
+                4_000,
+                "acp.bridge.tool_exec_finished",
+                json!({"submission_id": "s1", "tool_call_id": "a", "tool_kind": "exec", "status": "completed", "exit_code": 0, "reason": null}),
+            ),
+            record(5_000, "acp.bridge.tool_exec_started", call("b")),
+            record(
+                12_000,
+                "acp.bridge.tool_exec_finished",
+                json!({"submission_id": "s1", "tool_call_id": "b", "tool_kind": "exec", "status": "failed", "exit_code": 101, "reason": null}),
+            ),
+            record(
+                13_000,
+                "acp.request_permission",
+                json!({"tool_call": {}, "options": []}),
+            ),
+            record(
+                14_000,
+                "acp.request_permission_response",
+                json!({"outcome": {"outcome": "selected", "optionId": "approved"}}),
+            ),
+            record(
+                15_000,
+                "acp.approval_policy.decision",
+                json!({"call_id": "c", "request": "exec", "decision": "deny", "rule": "no push", "source": "approvals.toml"}),
+            ),
+            record(
+                16_000,
+                "acp.context_opt.token_usage",
+                json!({"submission_id": "s1", "total_tokens": 40_000, "context_window": 100_000, "used_percent": 40, "mode": "auto", "trigger_percent": 90}),
+            ),
+            record(
+                17_000,
+                "acp.context_opt.token_usage",
+                json!({"submission_id": "s1", "total_tokens": 93_000, "context_window": 100_000, "used_percent": 93, "mode": "auto", "trigger_percent": 90}),
+            ),
+            record(
+                18_000,
+                "acp.context_opt.auto_compact_triggered",
+                json!({"source_submission_id": "s1", "compact_submission_id": "s2", "total_tokens": 93_000, "context_window": 100_000, "used_percent": 93}),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_log_summary_aggregates_tools_approvals_and_tokens() {
+        let summary = LogSummary::from_records(&sample_log());
+
+        assert_eq!(summary.tool_calls(), 2);
+        let exec = &summary.tools["exec"];
+        assert_eq!(exec.total, Duration::from_secs(10));
+        assert_eq!(exec.failures, 1);
+        assert_eq!(
+            summary.slowest_tool,
+            Some(("exec".to_string(), Duration::from_secs(7)))
+        );
+        assert_eq!(summary.failures[0].exit_code, Some(101));
+        assert_eq!(summary.permission_requests, 1);
+        assert_eq!(summary.permissions_approved, 1);
+        assert_eq!(summary.rules_denied, 1);
+        assert_eq!(summary.compactions, 1);
+        assert_eq!(summary.first_tokens.map(|t| t.total_tokens), Some(40_000));
+        assert_eq!(summary.context_pressure(), Some(93));
+    }
+
+    #[test]
+    fn test_render_reports_steps_risks_and_next_actions_in_both_languages() {
+        let retro = Retrospective {
+            steps: vec![
+                StepOutcome {
+                    step: "Write parser".to_string(),
+                    state: StepState::Completed,
+                    elapsed: Some(Duration::from_secs(125)),
+                    stalled: false,
+                },
+                StepOutcome {
+                    step: "Add tests".to_string(),
+                    state: StepState::InProgress,
+                    elapsed: Some(Duration::from_secs(600)),
+                    stalled: true,
+                },
+                StepOutcome {
+                    step: "Update docs".to_string(),
+                    state: StepState::Pending,
+                    elapsed: None,
+                    stalled: false,
+                },
+            ],
+            plan_updates: 5,
+            phase_time: vec![
+                ('A', Duration::from_secs(60)),
+                ('E', Duration::from_secs(180)),
+                ('V', Duration::ZERO),
+                ('C', Duration::from_secs(60)),
+            ],
+            repeated_action: Some(("exec begin: cargo test".to_string(), 4)),
+            log: Some(LogSummary::from_records(&sample_log())),
+        };
+
+        let english = retro.render(RetroLanguage::English);
+        for expected in [
+            "Plan: 1/3 steps completed, 1 in progress, 1 pending (5 plan updates)",
+            "- Write parser (2m 5s)",
+            "- Add tests (in progress for 10m)",
+            "- execution: 3m (60%)",
+            "- 2 calls, 1 failed, 10s total (exec 2 / 10s)",
+            "- Failed: exec exit 101",
+            "- Approval rules: 0 auto-approved, 1 denied",
+            "- Tokens in context: 40000 -> 93000, peak 93%",
+            "- Repeat loop: `exec begin: cargo test` repeated 4x in a row",
+            "- Stall: `Add tests` is not completing",
+            "- Context pressure: usage peaked at 93%",
+            "- Split `Add tests` or note what blocks it",
+            "- Continue with `Update docs`",
+        ] {
+            assert!(
+                english.contains(expected),
+                "missing {expected:?} in\n{english}"
+            );
+        }
+        assert!(!english.contains("validation"), "{english}");
+
+        let korean = retro.render(RetroLanguage::Korean);
+        for expected in ["세션 회고", "정체된 단계", "- 실행: 3m (60%)", "다음 작업"]
+        {
+            assert!(
+                korean.contains(expected),
+                "missing {expected:?} in\n{korean}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_language_from_rest() {
+        assert_eq!(RetroLanguage::from_rest("retro ko"), RetroLanguage::Korean);
+        assert_eq!(
+            RetroLanguage::from_rest("retro lang=en"),
+            RetroLanguage::English
+        );
+        assert_eq!(
+            RetroLanguage::from_rest("retro 한국어"),
+            RetroLanguage::Korean
+        );
+    }
+}
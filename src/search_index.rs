@@ -0,0 +1,682 @@
+//! Full-text index over the canonical logs of every backend.
+//!
+//! The index lives in `ACP_HOME/search` and is appended to as `SessionStore::log` completes
+//! documents, so it never has to be rebuilt while the agent runs. Those appends are queued to a
+//! background writer thread that batches them, keeping file I/O off the logging caller:
+//!
+//! - `docs/<global_session_id>.jsonl`: one [`SearchDoc`] per prompt, agent message or tool call
+//!   title. A document is addressed by its byte offset in this file.
+//! - `postings/<xx>.tsv`: `term \t session \t offset \t count` lines, bucketed by a hash of the
+//!   term so a query only reads the buckets of its own terms.
+//!
+//! Words are lowercased alphanumeric runs; Hangul, kana and CJK ideographs are indexed as
+//! character bigrams so Korean, Japanese and Chinese text is searchable without a dictionary.
+//! Removing sessions (retention) rewrites the postings without them; deleting `ACP_HOME/search`
+//! rebuilds the index from the logs on the next start.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, mpsc},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{canonical_event::CanonicalEvent, project_settings::stable_hash};
+
+/// Directory of the index under `ACP_HOME`.
+pub const SEARCH_DIR: &str = "search";
+
+const BUCKETS: u64 = 256;
+
+/// Longest text kept per document; snippets are cut from it.
+const DOC_TEXT_MAX_CHARS: usize = 4000;
+
+/// Characters shown around the first match of a snippet.
+const SNIPPET_CONTEXT_CHARS: usize = 80;
+
+/// Serializes writers of this process; each session only appends to its own docs file.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Queue of the background writer, started on first use.
+static WRITER: OnceLock<mpsc::Sender<WriteJob>> = OnceLock::new();
+
+enum WriteJob {
+    Add {
+        index: SearchIndex,
+        global_session_id: String,
+        docs: Vec<SearchDoc>,
+    },
+    /// Answered once every job queued before it has been written.
+    Flush(mpsc::Sender<()>),
+}
+
+fn writer() -> &'static mpsc::Sender<WriteJob> {
+    WRITER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("search-index-writer".to_string())
+            .spawn(move || run_writer(&rx));
+        if let Err(e) = spawned {
+            // The receiver is gone with the closure, so every queue falls back to writing inline.
+            warn!("Failed to start the search index writer: {e}");
+        }
+        tx
+    })
+}
+
+/// Write queued documents, merging everything that is already waiting into one batch per
+/// index so each posting bucket is appended to once.
+fn run_writer(rx: &mpsc::Receiver<WriteJob>) {
+    while let Ok(job) = rx.recv() {
+        let mut batches = Vec::<(SearchIndex, Vec<(String, Vec<SearchDoc>)>)>::new();
+        let mut flushes = Vec::new();
+        for job in std::iter::once(job).chain(rx.try_iter()) {
+            match job {
+                WriteJob::Add {
+                    index,
+                    global_session_id,
+                    docs,
+                } => match batches
+                    .iter_mut()
+                    .find(|(queued, _)| queued.root == index.root)
+                {
+                    Some((_, sessions)) => sessions.push((global_session_id, docs)),
+                    None => batches.push((index, vec![(global_session_id, docs)])),
+                },
+                WriteJob::Flush(done) => flushes.push(done),
+            }
+        }
+        for (index, sessions) in batches {
+            let sessions = sessions
+                .iter()
+                .map(|(id, docs)| (id.as_str(), docs.as_slice()))
+                .collect::<Vec<_>>();
+            if let Err(e) = index.add_batch(&sessions) {
+                warn!("Failed to update search index: {e}");
+            }
+        }
+        for done in flushes {
+            drop(done.send(()));
+        }
+    }
+}
+
+/// Block until every document queued so far has been written.
+pub fn flush_writes() {
+    let Some(writer) = WRITER.get() else {
+        return;
+    };
+    let (done_tx, done_rx) = mpsc::channel();
+    if writer.send(WriteJob::Flush(done_tx)).is_ok() {
+        drop(done_rx.recv());
+    }
+}
+
+/// One searchable piece of a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchDoc {
+    pub ts_ms: u64,
+    /// `prompt`, `agent` or `tool`.
+    pub label: String,
+    pub text: String,
+}
+
+/// A ranked match returned by [`SearchIndex::search`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub global_session_id: String,
+    pub ts_ms: u64,
+    pub label: String,
+    pub snippet: String,
+    /// Distinct query terms found in the document.
+    pub matched_terms: usize,
+    pub score: f64,
+}
+
+/// Label and text of the events that are indexed; everything else is bookkeeping.
+pub fn searchable_text(event: &CanonicalEvent) -> Option<(&'static str, String)> {
+    match event {
+        CanonicalEvent::Prompt(prompt) => Some(("prompt", prompt.text.clone())),
+        CanonicalEvent::AgentMessageChunk(chunk) => Some(("agent", chunk.text.clone())),
+        CanonicalEvent::ToolCall(tool_call) | CanonicalEvent::ToolCallUpdate(tool_call) => {
+            Some(("tool", tool_call.get("title")?.as_str()?.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Joins streamed agent message chunks into one document per message.
+#[derive(Debug, Default)]
+pub struct DocBuilder {
+    pending: Option<SearchDoc>,
+}
+
+impl DocBuilder {
+    /// Feed one canonical event; returns the documents that are complete.
+    pub fn push(&mut self, ts_ms: u64, event: &CanonicalEvent) -> Vec<SearchDoc> {
+        let Some((label, text)) = searchable_text(event) else {
+            return Vec::new();
+        };
+        if label == "agent" {
+            if let Some(pending) = self.pending.as_mut() {
+                pending.text.push_str(&text);
+                return Vec::new();
+            }
+            self.pending = Some(SearchDoc {
+                ts_ms,
+                label: label.to_string(),
+                text,
+            });
+            return Vec::new();
+        }
+        let mut done = self.finish().into_iter().collect::<Vec<_>>();
+        done.push(SearchDoc {
+            ts_ms,
+            label: label.to_string(),
+            text,
+        });
+        done
+    }
+
+    /// The message still being assembled, if any.
+    pub fn finish(&mut self) -> Option<SearchDoc> {
+        self.pending.take()
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF
+            | 0x3040..=0x30FF
+            | 0x3130..=0x318F
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+    )
+}
+
+/// Index terms of `text`, in order and with repetitions.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut cjk = Vec::<char>::new();
+    let flush_cjk = |cjk: &mut Vec<char>, terms: &mut Vec<String>| {
+        match cjk.len() {
+            0 => {}
+            1 => terms.push(cjk[0].to_string()),
+            _ => terms.extend(cjk.windows(2).map(|pair| pair.iter().collect::<String>())),
+        }
+        cjk.clear();
+    };
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            cjk.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk, &mut terms);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_cjk(&mut cjk, &mut terms);
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    flush_cjk(&mut cjk, &mut terms);
+    if !word.is_empty() {
+        terms.push(word);
+    }
+    terms
+}
+
+fn bucket_of(term: &str) -> String {
+    format!("{:02x}", stable_hash(term) % BUCKETS)
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((cut, _)) => text[..cut].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Text around the first occurrence of one of `terms`, on one line.
+fn snippet(text: &str, terms: &[String]) -> String {
+    let chars = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect::<Vec<_>>();
+    let lower = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<_>>();
+    let first = terms
+        .iter()
+        .filter_map(|term| {
+            let needle = term.chars().collect::<Vec<_>>();
+            lower.windows(needle.len()).position(|w| w == needle)
+        })
+        .min()
+        .unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (first + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let mut out = chars[start..end].iter().collect::<String>();
+    if start > 0 {
+        out.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        out.push_str("...");
+    }
+    out
+}
+
+/// Handle on the index directory.
+#[derive(Debug, Clone)]
+pub struct SearchIndex {
+    root: PathBuf,
+}
+
+impl SearchIndex {
+    pub fn at(acp_home: &Path) -> Self {
+        Self {
+            root: acp_home.join(SEARCH_DIR),
+        }
+    }
+
+    fn docs_path(&self, global_session_id: &str) -> PathBuf {
+        self.root
+            .join("docs")
+            .join(format!("{global_session_id}.jsonl"))
+    }
+
+    fn postings_path(&self, bucket: &str) -> PathBuf {
+        self.root.join("postings").join(format!("{bucket}.tsv"))
+    }
+
+    /// Whether `global_session_id` has been indexed (possibly with no documents).
+    pub fn contains(&self, global_session_id: &str) -> bool {
+        self.docs_path(global_session_id).exists()
+    }
+
+    /// Queue documents of one session for the background writer; see [`flush_writes`].
+    pub fn queue(&self, global_session_id: &str, docs: Vec<SearchDoc>) {
+        if docs.is_empty() {
+            return;
+        }
+        let job = WriteJob::Add {
+            index: self.clone(),
+            global_session_id: global_session_id.to_string(),
+            docs,
+        };
+        if let Err(mpsc::SendError(WriteJob::Add {
+            global_session_id,
+            docs,
+            ..
+        })) = writer().send(job)
+            && let Err(e) = self.add(&global_session_id, &docs)
+        {
+            warn!("Failed to update search index: {e}");
+        }
+    }
+
+    /// Append documents of one session.
+    pub fn add(&self, global_session_id: &str, docs: &[SearchDoc]) -> io::Result<()> {
+        self.add_batch(&[(global_session_id, docs)])
+    }
+
+    /// Append documents of several sessions, touching each posting bucket once.
+    fn add_batch(&self, sessions: &[(&str, &[SearchDoc])]) -> io::Result<()> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // A queued write must not recreate an `ACP_HOME` that was removed meanwhile.
+        if !self.root.parent().is_some_and(Path::exists) {
+            return Ok(());
+        }
+        fs::create_dir_all(self.root.join("docs"))?;
+        fs::create_dir_all(self.root.join("postings"))?;
+        let mut postings = BTreeMap::<String, String>::new();
+        for (global_session_id, docs) in sessions {
+            self.append_docs(global_session_id, docs, &mut postings)?;
+        }
+        for (bucket, lines) in postings {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.postings_path(&bucket))?
+                .write_all(lines.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Append `docs` to the session's docs file and collect their posting lines per bucket.
+    fn append_docs(
+        &self,
+        global_session_id: &str,
+        docs: &[SearchDoc],
+        postings: &mut BTreeMap<String, String>,
+    ) -> io::Result<()> {
+        let mut docs_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.docs_path(global_session_id))?;
+        for doc in docs {
+            let doc = SearchDoc {
+                text: truncate_chars(&doc.text, DOC_TEXT_MAX_CHARS),
+                ..doc.clone()
+            };
+            let mut counts = HashMap::<String, usize>::new();
+            for term in tokenize(&doc.text) {
+                *counts.entry(term).or_default() += 1;
+            }
+            if counts.is_empty() {
+                continue;
+            }
+            let offset = docs_file.seek(SeekFrom::End(0))?;
+            let line = serde_json::to_string(&doc).map_err(io::Error::other)?;
+            docs_file.write_all(format!("{line}\n").as_bytes())?;
+            for (term, count) in counts {
+                postings
+                    .entry(bucket_of(&term))
+                    .or_default()
+                    .push_str(&format!("{term}\t{global_session_id}\t{offset}\t{count}\n"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget removed sessions: delete their documents and rewrite the postings without them,
+    /// so they no longer count towards term frequencies.
+    pub fn remove_sessions(&self, global_session_ids: &[String]) {
+        if global_session_ids.is_empty() {
+            return;
+        }
+        flush_writes();
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for id in global_session_ids {
+            let path = self.docs_path(id);
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to remove search documents {}: {e}", path.display()),
+            }
+        }
+        let removed = global_session_ids
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+        if let Err(e) = self.drop_postings(&removed) {
+            warn!("Failed to prune search postings: {e}");
+        }
+    }
+
+    fn drop_postings(&self, removed: &HashSet<&str>) -> io::Result<()> {
+        let entries = match fs::read_dir(self.root.join("postings")) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "tsv") {
+                continue;
+            }
+            let data = fs::read_to_string(&path)?;
+            let kept = data
+                .lines()
+                .filter(|line| {
+                    line.split('\t')
+                        .nth(1)
+                        .is_none_or(|session| !removed.contains(session))
+                })
+                .map(|line| format!("{line}\n"))
+                .collect::<String>();
+            if kept.len() == data.len() {
+                continue;
+            }
+            // Replace the bucket atomically so a concurrent reader sees the old or new file.
+            let tmp = path.with_extension("tsv.tmp");
+            fs::write(&tmp, kept)?;
+            fs::rename(&tmp, &path)?;
+        }
+        Ok(())
+    }
+
+    fn read_doc(&self, global_session_id: &str, offset: u64) -> Option<SearchDoc> {
+        let mut file = File::open(self.docs_path(global_session_id)).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line).ok()?;
+        serde_json::from_str(&line).ok()
+    }
+
+    /// Documents matching `query`, best first: more distinct query terms, then rarer and more
+    /// frequent terms, then newer documents.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms = tokenize(query);
+        let mut seen = HashSet::new();
+        terms.retain(|term| seen.insert(term.clone()));
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        // (session, offset) -> count per query term
+        let mut matches = HashMap::<(String, u64), Vec<usize>>::new();
+        let mut doc_freq = vec![0usize; terms.len()];
+        for (i, term) in terms.iter().enumerate() {
+            let Ok(file) = File::open(self.postings_path(&bucket_of(term))) else {
+                continue;
+            };
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                let mut fields = line.split('\t');
+                let (Some(t), Some(session), Some(offset), Some(count)) =
+                    (fields.next(), fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                if t != term {
+                    continue;
+                }
+                let (Ok(offset), Ok(count)) = (offset.parse::<u64>(), count.parse::<usize>())
+                else {
+                    continue;
+                };
+                doc_freq[i] += 1;
+                matches
+                    .entry((session.to_string(), offset))
+                    .or_insert_with(|| vec![0; terms.len()])[i] = count;
+            }
+        }
+
+        let mut ranked = matches
+            .into_iter()
+            .map(|(key, counts)| {
+                let matched = counts.iter().filter(|count| **count > 0).count();
+                let score = counts
+                    .iter()
+                    .zip(&doc_freq)
+                    .filter(|(count, _)| **count > 0)
+                    .map(|(count, df)| {
+                        (1.0 + (*count as f64).ln()) / (1.0 + ((*df).max(1) as f64).ln())
+                    })
+                    .sum::<f64>();
+                (key, matched, score)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2)));
+
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for ((session, offset), matched, score) in ranked {
+            if hits.len() >= limit {
+                break;
+            }
+            // Stale postings of removed sessions point at files that no longer exist.
+            let Some(doc) = self.read_doc(&session, offset) else {
+                continue;
+            };
+            if hits.iter().any(|hit| {
+                hit.global_session_id == session && hit.ts_ms == doc.ts_ms && hit.label == doc.label
+            }) {
+                continue;
+            }
+            hits.push(SearchHit {
+                global_session_id: session,
+                ts_ms: doc.ts_ms,
+                snippet: snippet(&doc.text, &terms),
+                label: doc.label,
+                matched_terms: matched,
+                score,
+            });
+        }
+        hits.sort_by(|a, b| {
+            b.matched_terms
+                .cmp(&a.matched_terms)
+                .then(b.score.total_cmp(&a.score))
+                .then(b.ts_ms.cmp(&a.ts_ms))
+        });
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("acp-search-index-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn tokenizes_words_and_cjk_bigrams() {
+        assert_eq!(
+            tokenize("Fix the PTY_resize bug!"),
+            vec!["fix", "the", "pty", "resize", "bug"]
+        );
+        assert_eq!(
+            tokenize("세션 검색기능"),
+            vec!["세션", "검색", "색기", "기능"]
+        );
+        assert_eq!(tokenize("a 한"), vec!["a", "한"]);
+    }
+
+    #[test]
+    fn builder_joins_message_chunks() {
+        let mut builder = DocBuilder::default();
+        assert!(
+            builder
+                .push(1, &CanonicalEvent::agent_message("Hello "))
+                .is_empty()
+        );
+        assert!(
+            builder
+                .push(2, &CanonicalEvent::agent_message("world"))
+                .is_empty()
+        );
+        let mode = CanonicalEvent::SessionMode(crate::canonical_event::ModeSetting {
+            mode: "yolo".to_string(),
+        });
+        assert!(builder.push(3, &mode).is_empty());
+        let docs = builder.push(
+            4,
+            &CanonicalEvent::ToolCall(json!({"title": "Read lib.rs"})),
+        );
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].text, "Hello world");
+        assert_eq!(docs[0].ts_ms, 1);
+        assert_eq!(docs[1].label, "tool");
+        assert!(builder.finish().is_none());
+    }
+
+    #[test]
+    fn ranks_hits_and_skips_removed_sessions() {
+        let home = temp_dir();
+        let index = SearchIndex::at(&home);
+        let doc = |ts_ms, label: &str, text: &str| SearchDoc {
+            ts_ms,
+            label: label.to_string(),
+            text: text.to_string(),
+        };
+        index
+            .add(
+                "s1",
+                &[
+                    doc(1, "prompt", "Why does the websocket reconnect loop?"),
+                    doc(2, "agent", "The reconnect timer is never reset."),
+                ],
+            )
+            .unwrap();
+        index
+            .add("s2", &[doc(3, "tool", "Edit websocket.rs reconnect")])
+            .unwrap();
+        index
+            .add("s3", &[doc(4, "prompt", "세션 검색 추가")])
+            .unwrap();
+
+        let hits = index.search("websocket reconnect", 10);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].matched_terms, 2);
+        assert_eq!(hits[2].matched_terms, 1);
+        assert_eq!(hits[2].global_session_id, "s1");
+        assert_eq!(hits[2].ts_ms, 2);
+        assert!(hits[2].snippet.contains("reconnect timer"));
+
+        let hits = index.search("검색", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].global_session_id, "s3");
+
+        index.remove_sessions(&["s2".to_string()]);
+        assert!(!index.contains("s2"));
+        assert_eq!(index.search("websocket", 10).len(), 1);
+        assert!(index.search("   ", 10).is_empty());
+        // Removed sessions leave no postings behind to skew document frequencies.
+        for entry in fs::read_dir(home.join(SEARCH_DIR).join("postings")).unwrap() {
+            let postings = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!postings.contains("\ts2\t"), "{postings}");
+        }
+
+        drop(fs::remove_dir_all(&home));
+    }
+
+    #[test]
+    fn queued_documents_are_searchable_after_a_flush() {
+        let home = temp_dir();
+        let index = SearchIndex::at(&home);
+        for (session, text) in [("q1", "queued retry budget"), ("q2", "another retry")] {
+            index.queue(
+                session,
+                vec![SearchDoc {
+                    ts_ms: 1,
+                    label: "agent".to_string(),
+                    text: text.to_string(),
+                }],
+            );
+        }
+        flush_writes();
+
+        assert!(index.contains("q1"));
+        assert_eq!(index.search("retry", 10).len(), 2);
+
+        drop(fs::remove_dir_all(&home));
+    }
+
+    #[test]
+    fn snippet_centers_on_the_first_match() {
+        let text = format!("{} needle {}", "x ".repeat(100), "y ".repeat(100));
+        let out = snippet(&text, &["needle".to_string()]);
+        assert!(out.starts_with("..."));
+        assert!(out.ends_with("..."));
+        assert!(out.contains("needle"));
+    }
+}
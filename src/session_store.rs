@@ -1,53 +1,29 @@
-use regex_lite::Regex;
-use serde::Serialize;
+use crate::{
+    canonical_event::{self, CANONICAL_SCHEMA_VERSION, CanonicalEvent, CanonicalLine},
+    canonical_log::{self, RetentionPolicy, RotationPolicy, SegmentWriter},
+    redaction::Redactor,
+    search_index::{DocBuilder, SearchHit, SearchIndex, flush_writes},
+};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
-    fs::{File, OpenOptions},
-    io::Write,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex, OnceLock},
+    sync::{Arc, Mutex},
     time::{SystemTime, UNIX_EPOCH},
 };
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-static SECRET_REDACTION_RE: OnceLock<Regex> = OnceLock::new();
 #[cfg(test)]
-pub(crate) static ENV_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-
-fn secret_redaction_re() -> &'static Regex {
-    SECRET_REDACTION_RE.get_or_init(|| {
-        // Very rough: redact common "sk-..." style tokens.
-        Regex::new(r"\b(sk-[A-Za-z0-9]{20,})\b").unwrap_or_else(|_| std::process::abort())
-    })
-}
+pub(crate) static ENV_LOCK: std::sync::OnceLock<Mutex<()>> = std::sync::OnceLock::new();
 
-fn now_unix_ms() -> u64 {
+pub(crate) fn now_unix_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64
 }
 
-fn redact_string(s: &str) -> String {
-    secret_redaction_re()
-        .replace_all(s, "sk-REDACTED")
-        .into_owned()
-}
-
-fn redact_json(v: serde_json::Value) -> serde_json::Value {
-    match v {
-        serde_json::Value::String(s) => serde_json::Value::String(redact_string(&s)),
-        serde_json::Value::Array(items) => {
-            serde_json::Value::Array(items.into_iter().map(redact_json).collect())
-        }
-        serde_json::Value::Object(map) => {
-            serde_json::Value::Object(map.into_iter().map(|(k, v)| (k, redact_json(v))).collect())
-        }
-        other => other,
-    }
-}
-
 pub struct AcpHome;
 
 impl AcpHome {
@@ -86,6 +62,41 @@ impl GlobalSessionIndex {
         Some(Self { path, map })
     }
 
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.map.get(key).cloned()
+    }
+
+    /// Record an explicit value (e.g. a backend-native session id) under `key`.
+    pub fn insert(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        if self.map.get(key) == Some(&value) {
+            return;
+        }
+        self.map.insert(key.to_string(), value);
+        if let Err(e) = self.save() {
+            warn!("Failed to save ACP session index: {e}");
+        }
+    }
+
+    /// Drop every key that maps to one of `global_session_ids`.
+    pub fn remove_global_ids(&mut self, global_session_ids: &[String]) {
+        let before = self.map.len();
+        self.map.retain(|_, id| !global_session_ids.contains(id));
+        if self.map.len() != before
+            && let Err(e) = self.save()
+        {
+            warn!("Failed to save ACP session index: {e}");
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if self.map.remove(key).is_some()
+            && let Err(e) = self.save()
+        {
+            warn!("Failed to save ACP session index: {e}");
+        }
+    }
+
     pub fn get_or_create(&mut self, key: &str) -> Option<String> {
         if let Some(existing) = self.map.get(key) {
             return Some(existing.clone());
@@ -122,7 +133,24 @@ struct SessionStoreInner {
     backend_session_id: String,
     #[allow(dead_code)]
     root: PathBuf,
-    canonical_file: Mutex<File>,
+    canonical_log: Mutex<SegmentWriter>,
+    redactor: Redactor,
+    search_index: SearchIndex,
+    search_docs: Mutex<DocBuilder>,
+}
+
+impl Drop for SessionStoreInner {
+    fn drop(&mut self) {
+        // Index the agent message that was still being streamed.
+        let pending = self
+            .search_docs
+            .get_mut()
+            .ok()
+            .and_then(|builder| builder.finish());
+        if let Some(doc) = pending {
+            self.search_index.queue(&self.global_session_id, vec![doc]);
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -136,16 +164,32 @@ struct SessionState {
     cwd: Option<String>,
 }
 
-#[derive(Serialize)]
-struct CanonicalEvent {
-    schema_version: u32,
-    ts_ms: u64,
-    global_session_id: String,
-    backend: String,
-    acp_session_id: String,
-    backend_session_id: String,
-    kind: String,
-    data: serde_json::Value,
+/// Session metadata read back from a persisted `state.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub global_session_id: String,
+    pub backend: String,
+    pub acp_session_id: String,
+    pub cwd: Option<PathBuf>,
+    pub created_at_ms: u64,
+}
+
+/// One line of `canonical.jsonl`, keeping only the fields needed to rebuild a session.
+///
+/// Lines are upgraded to the current schema version when read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanonicalRecord {
+    pub ts_ms: u64,
+    pub kind: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+impl CanonicalRecord {
+    /// The typed event, or `None` for kinds this build does not know.
+    pub fn event(&self) -> Option<CanonicalEvent> {
+        CanonicalEvent::from_parts(&self.kind, self.data.clone()).ok()
+    }
 }
 
 impl SessionStore {
@@ -193,17 +237,13 @@ impl SessionStore {
             }
         }
 
-        let canonical_path = root.join("canonical.jsonl");
-        let canonical_file = match OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&canonical_path)
-        {
-            Ok(f) => f,
+        let redactor = Redactor::load(&acp_home);
+        let canonical_log = match SegmentWriter::open(&root, RotationPolicy::from_env()) {
+            Ok(writer) => writer,
             Err(e) => {
                 warn!(
-                    "Failed to open canonical session log {}: {}",
-                    canonical_path.display(),
+                    "Failed to open canonical session log in {}: {}",
+                    root.display(),
                     e
                 );
                 return None;
@@ -217,11 +257,121 @@ impl SessionStore {
                 acp_session_id,
                 backend_session_id,
                 root,
-                canonical_file: Mutex::new(canonical_file),
+                canonical_log: Mutex::new(canonical_log),
+                redactor,
+                search_index: SearchIndex::at(&acp_home),
+                search_docs: Mutex::new(DocBuilder::default()),
             }),
         })
     }
 
+    /// Persisted sessions of every backend, read from `ACP_HOME/sessions/*/state.json`.
+    pub fn list_all() -> Vec<StoredSession> {
+        let Some(acp_home) = AcpHome::resolve() else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(acp_home.join("sessions")) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| std::fs::read_to_string(entry.path().join("state.json")).ok())
+            .filter_map(|data| serde_json::from_str::<StoredSession>(&data).ok())
+            .collect()
+    }
+
+    /// Persisted sessions written by `backend`.
+    pub fn list_stored(backend: &str) -> Vec<StoredSession> {
+        Self::list_all()
+            .into_iter()
+            .filter(|state| state.backend == backend)
+            .collect()
+    }
+
+    /// Read back the canonical log of a persisted session, skipping unparseable lines.
+    pub fn read_canonical(global_session_id: &str) -> Vec<CanonicalRecord> {
+        let Some(acp_home) = AcpHome::resolve() else {
+            return Vec::new();
+        };
+        let dir = acp_home.join("sessions").join(global_session_id);
+        let lines = match canonical_log::read_lines(&dir) {
+            Ok(lines) => lines,
+            Err(e) => {
+                warn!(
+                    "Failed to read canonical session log in {}: {}",
+                    dir.display(),
+                    e
+                );
+                return Vec::new();
+            }
+        };
+        lines
+            .iter()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter_map(|line| {
+                serde_json::from_value::<CanonicalRecord>(canonical_event::upgrade_line(line)).ok()
+            })
+            .collect()
+    }
+
+    /// Prune `ACP_HOME/sessions` according to the retention settings and forget the removed
+    /// sessions in the index. Meant to run once at startup.
+    pub fn apply_retention() {
+        let Some(acp_home) = AcpHome::resolve() else {
+            return;
+        };
+        let removed = canonical_log::apply_retention(
+            &acp_home.join("sessions"),
+            RetentionPolicy::from_env(),
+            SystemTime::now(),
+        );
+        if removed.is_empty() {
+            return;
+        }
+        info!("Removed {} canonical sessions by retention", removed.len());
+        if let Some(mut index) = GlobalSessionIndex::load() {
+            index.remove_global_ids(&removed);
+        }
+        SearchIndex::at(&acp_home).remove_sessions(&removed);
+    }
+
+    /// Index the canonical logs of sessions the search index has not seen yet, for example
+    /// sessions written before the index existed. Meant to run once at startup.
+    pub fn backfill_search_index() {
+        let Some(acp_home) = AcpHome::resolve() else {
+            return;
+        };
+        let search_index = SearchIndex::at(&acp_home);
+        // Sessions already being logged must not look unindexed because of queued writes.
+        flush_writes();
+        for session in Self::list_all() {
+            if search_index.contains(&session.global_session_id) {
+                continue;
+            }
+            let mut builder = DocBuilder::default();
+            let mut docs = Vec::new();
+            for record in Self::read_canonical(&session.global_session_id) {
+                if let Some(event) = record.event() {
+                    docs.extend(builder.push(record.ts_ms, &event));
+                }
+            }
+            docs.extend(builder.finish());
+            if let Err(e) = search_index.add(&session.global_session_id, &docs) {
+                warn!("Failed to update search index: {e}");
+                return;
+            }
+        }
+    }
+
+    /// Ranked prompts, agent messages and tool call titles of every stored session.
+    pub fn search(query: &str, limit: usize) -> Vec<SearchHit> {
+        let Some(acp_home) = AcpHome::resolve() else {
+            return Vec::new();
+        };
+        flush_writes();
+        SearchIndex::at(&acp_home).search(query, limit)
+    }
+
     #[allow(dead_code)]
     pub fn global_session_id(&self) -> &str {
         &self.inner.global_session_id
@@ -232,33 +382,48 @@ impl SessionStore {
         &self.inner.root
     }
 
-    pub fn log(&self, kind: &str, data: serde_json::Value) {
-        let event = CanonicalEvent {
-            schema_version: 1,
-            ts_ms: now_unix_ms(),
+    pub fn log(&self, event: CanonicalEvent) {
+        let ts_ms = now_unix_ms();
+        let mut docs = match self.inner.search_docs.lock() {
+            Ok(mut builder) => builder.push(ts_ms, &event),
+            Err(_) => Vec::new(),
+        };
+        for doc in &mut docs {
+            doc.text = self.inner.redactor.redact_str(&doc.text);
+        }
+        let line = CanonicalLine {
+            schema_version: CANONICAL_SCHEMA_VERSION,
+            ts_ms,
             global_session_id: self.inner.global_session_id.clone(),
             backend: self.inner.backend.clone(),
             acp_session_id: self.inner.acp_session_id.clone(),
             backend_session_id: self.inner.backend_session_id.clone(),
-            kind: kind.to_string(),
-            data: redact_json(data),
+            event,
         };
-
-        let Ok(line) = serde_json::to_string(&event) else {
+        let Ok(mut line) = serde_json::to_value(&line) else {
             return;
         };
+        if let Some(data) = line.get_mut("data") {
+            *data = self.inner.redactor.redact_json(data.take());
+        }
+        let line = line.to_string();
 
-        let mut file = match self.inner.canonical_file.lock() {
-            Ok(f) => f,
+        let mut canonical_log = match self.inner.canonical_log.lock() {
+            Ok(log) => log,
             Err(_) => {
                 error!("SessionStore lock poisoned");
                 return;
             }
         };
 
-        if let Err(e) = writeln!(file, "{line}") {
+        if let Err(e) = canonical_log.append_line(&line) {
             error!("Failed to append canonical event: {e}");
         }
+        drop(canonical_log);
+
+        self.inner
+            .search_index
+            .queue(&self.inner.global_session_id, docs);
     }
 }
 
@@ -278,6 +443,11 @@ mod tests {
         unsafe {
             std::env::set_var("ACP_HOME", &root);
         }
+        std::fs::write(
+            root.join(crate::redaction::REDACTION_CONFIG_FILE),
+            "[[rules]]\nname = \"ticket\"\npattern = \"TCK-[0-9]{6}\"\n",
+        )
+        .unwrap();
 
         let mut idx = GlobalSessionIndex::load().expect("ACP_HOME should be resolvable");
         let global_id = idx.get_or_create("codex:test-session").unwrap();
@@ -291,25 +461,188 @@ mod tests {
         )
         .expect("SessionStore should init");
 
-        store.log(
-            "test.event",
-            json!({
-                "token": "sk-1234567890123456789012345",
-                "nested": { "t": "sk-aaaaaaaaaaaaaaaaaaaaaa" }
-            }),
-        );
+        store.log(CanonicalEvent::agent_message(
+            "token sk-1234567890123456789012345, see TCK-123456",
+        ));
+        store.log(CanonicalEvent::ToolCall(json!({
+            "rawInput": { "env": { "OPENAI_API_KEY": "sk-aaaaaaaaaaaaaaaaaaaaaa" } }
+        })));
 
         let canonical_path = root
             .join("sessions")
             .join(&global_id)
             .join("canonical.jsonl");
         let s = std::fs::read_to_string(&canonical_path).unwrap();
-        assert!(s.contains("sk-REDACTED"), "expected redaction in: {s}");
+        assert!(
+            s.contains("[REDACTED:openai_api_key]"),
+            "expected redaction in: {s}"
+        );
+        assert!(!s.contains("sk-1234567890"), "secret leaked in: {s}");
+        assert!(!s.contains("sk-aaaaaaaa"), "secret leaked in: {s}");
+        assert!(
+            s.contains("see [REDACTED:ticket]"),
+            "expected user rule in: {s}"
+        );
+
+        drop(std::fs::remove_dir_all(&root));
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+    }
+
+    #[test]
+    fn lists_stored_sessions_and_reads_canonical_log_back() {
+        let _guard = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-session-store-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::set_var("ACP_HOME", &root);
+        }
+
+        let mut idx = GlobalSessionIndex::load().expect("ACP_HOME should be resolvable");
+        let global_id = idx.get_or_create("claude:claude:abc").unwrap();
+        assert_eq!(
+            idx.get("claude:claude:abc").as_deref(),
+            Some(global_id.as_str())
+        );
+        assert!(idx.get("claude:missing").is_none());
+
+        let cwd = PathBuf::from("/tmp/project");
+        let store = SessionStore::init(
+            global_id.clone(),
+            "claude-code",
+            "claude:abc",
+            "claude:abc",
+            Some(&cwd),
+        )
+        .expect("SessionStore should init");
+        store.log(CanonicalEvent::prompt_text("hello"));
+        store.log(CanonicalEvent::agent_message("hi"));
+
+        let stored = SessionStore::list_stored("claude-code");
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].acp_session_id, "claude:abc");
+        assert_eq!(stored[0].cwd.as_deref(), Some(cwd.as_path()));
+        assert!(SessionStore::list_stored("gemini").is_empty());
+
+        let records = SessionStore::read_canonical(&global_id);
+        let kinds = records.iter().map(|r| r.kind.as_str()).collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["acp.prompt", "acp.agent_message_chunk"]);
+        assert_eq!(records[1].data["text"], "hi");
+        assert_eq!(
+            records[1].event(),
+            Some(CanonicalEvent::agent_message("hi"))
+        );
+
+        drop(std::fs::remove_dir_all(&root));
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+    }
+
+    #[test]
+    fn indexes_logged_events_for_search_and_backfills_old_sessions() {
+        let _guard = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-session-store-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::set_var("ACP_HOME", &root);
+        }
+
+        let store = SessionStore::init("live".to_string(), "gemini", "g", "g", None)
+            .expect("SessionStore should init");
+        store.log(CanonicalEvent::prompt_text(
+            "why is the flaky retry test failing",
+        ));
+        store.log(CanonicalEvent::agent_message("The retry "));
+        store.log(CanonicalEvent::agent_message("budget is shared."));
+        store.log(CanonicalEvent::ToolCall(
+            json!({ "title": "Read retry.rs" }),
+        ));
+        drop(store);
+
+        // A session logged before the index existed.
+        let old_dir = root.join("sessions").join("old");
+        std::fs::create_dir_all(&old_dir).unwrap();
+        std::fs::write(
+            old_dir.join("state.json"),
+            r#"{"global_session_id":"old","backend":"codex","acp_session_id":"o","cwd":null,"created_at_ms":1}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            old_dir.join(canonical_log::ACTIVE_SEGMENT),
+            r#"{"schema_version":2,"ts_ms":5,"global_session_id":"old","backend":"codex","acp_session_id":"o","backend_session_id":"o","kind":"acp.prompt","data":{"text":"retry budget"}}"#,
+        )
+        .unwrap();
+        assert_eq!(SessionStore::search("budget", 10).len(), 1);
+        SessionStore::backfill_search_index();
+
+        let hits = SessionStore::search("retry budget", 10);
+        let found = hits
+            .iter()
+            .map(|hit| (hit.global_session_id.as_str(), hit.label.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            &found[..2],
+            &[("live", "agent"), ("old", "prompt")][..],
+            "unexpected ranking: {hits:?}"
+        );
+        assert_eq!(hits[0].snippet, "The retry budget is shared.");
+        assert_eq!(hits.len(), 4);
+
+        drop(std::fs::remove_dir_all(&root));
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+    }
+
+    #[test]
+    fn reads_rotated_segments_and_prunes_index_by_retention() {
+        let _guard = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-session-store-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        // Safe within this test due to ENV_LOCK serialization.
+        unsafe {
+            std::env::set_var("ACP_HOME", &root);
+            std::env::set_var("ACP_LOG_SEGMENT_BYTES", "300");
+        }
+
+        let mut idx = GlobalSessionIndex::load().expect("ACP_HOME should be resolvable");
+        let global_id = idx.get_or_create("gemini:rotated").unwrap();
+        let store = SessionStore::init(global_id.clone(), "gemini", "g", "g", None)
+            .expect("SessionStore should init");
+        for i in 0..10 {
+            store.log(CanonicalEvent::prompt_text(format!("prompt {i}")));
+        }
+        let session_dir = root.join("sessions").join(&global_id);
+        assert!(!canonical_log::closed_segments(&session_dir).is_empty());
+        let texts = SessionStore::read_canonical(&global_id)
+            .into_iter()
+            .map(|r| r.data["text"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        let expected = (0..10).map(|i| format!("prompt {i}")).collect::<Vec<_>>();
+        assert_eq!(texts, expected);
+
+        idx.remove_global_ids(std::slice::from_ref(&global_id));
+        let idx = GlobalSessionIndex::load().unwrap();
+        assert!(idx.get("gemini:rotated").is_none());
 
         drop(std::fs::remove_dir_all(&root));
         // Safe within this test due to ENV_LOCK serialization.
         unsafe {
             std::env::remove_var("ACP_HOME");
+            std::env::remove_var("ACP_LOG_SEGMENT_BYTES");
         }
     }
 }
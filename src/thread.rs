@@ -17,15 +17,15 @@ use codex_apply_patch::parse_patch;
 use agent_client_protocol::{
     Annotations, AudioContent, AvailableCommand, AvailableCommandInput, AvailableCommandsUpdate,
     BlobResourceContents, Client, ClientCapabilities, ConfigOptionUpdate, Content, ContentBlock,
-    ContentChunk, Diff, EmbeddedResource, EmbeddedResourceResource, Error, ImageContent,
-    LoadSessionResponse, Meta, ModelId, ModelInfo, PermissionOption, PermissionOptionKind, Plan,
-    PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptRequest, RequestPermissionOutcome,
-    RequestPermissionRequest, RequestPermissionResponse, ResourceLink, SelectedPermissionOutcome,
-    SessionConfigId, SessionConfigOption, SessionConfigOptionCategory, SessionConfigSelectOption,
-    SessionConfigValueId, SessionId, SessionMode, SessionModeId, SessionModeState,
-    SessionModelState, SessionNotification, SessionUpdate, StopReason, Terminal, TextContent,
-    TextResourceContents, ToolCall, ToolCallContent, ToolCallId, ToolCallLocation, ToolCallStatus,
-    ToolCallUpdate, ToolCallUpdateFields, ToolKind, UnstructuredCommandInput,
+    ContentChunk, Diff, EmbeddedResource, EmbeddedResourceResource, Error, ExtRequest,
+    ImageContent, LoadSessionResponse, Meta, ModelId, ModelInfo, PermissionOption,
+    PermissionOptionKind, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptRequest,
+    RequestPermissionOutcome, RequestPermissionRequest, RequestPermissionResponse, ResourceLink,
+    SelectedPermissionOutcome, SessionConfigId, SessionConfigOption, SessionConfigOptionCategory,
+    SessionConfigSelectOption, SessionConfigValueId, SessionId, SessionMode, SessionModeId,
+    SessionModeState, SessionModelState, SessionNotification, SessionUpdate, StopReason, Terminal,
+    TextContent, TextResourceContents, ToolCall, ToolCallContent, ToolCallId, ToolCallLocation,
+    ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind, UnstructuredCommandInput,
 };
 use codex_common::approval_presets::{ApprovalPreset, builtin_approval_presets};
 use codex_core::{
@@ -69,7 +69,7 @@ use codex_protocol::{
     protocol::{RolloutItem, SessionMetaLine},
     user_input::UserInput,
 };
-use heck::ToTitleCase;
+use heck::{ToSnakeCase, ToTitleCase};
 use itertools::Itertools;
 use mcp_types::{CallToolResult, RequestId};
 use serde_json::json;
@@ -80,16 +80,35 @@ use uuid::Uuid;
 
 use crate::{
     ACP_CLIENT,
+    approval_policy::{ApprovalPolicy, CommandKind, PolicyVerdict, RuleDecision},
     backend::{BackendKind, WorkOrchestrationProfile},
+    canonical_event::{
+        ApprovalPolicyDecision, AutoCompactError, AutoCompactTriggered, BetaFeature,
+        BridgeFinalEmitted, BridgePreempt, BridgeToolCall, BridgeToolExecFinished,
+        BridgeToolResultSent, CanonicalEvent, CodexSubmit, ContextBudgetDecision,
+        EmbeddedTextSummary, ModeSetting, PermissionRequest, PermissionResponse, PlanItem,
+        PlanStepStatus, PlanStepTransition, PlanUpdate, PromptEstimate, PromptSummary,
+        ResourceLinkSummary, SubmissionRef, Toggle, TokenUsage, TriggerPercent,
+    },
     current_client_info,
+    elicitation::{ELICITATION_CAPABILITY, ELICITATION_EXT_METHOD, ElicitationForm, FollowUpReply},
     link_paths::normalize_outgoing_local_markdown_links,
+    multi_backend::context_handoff,
+    plan_timeline::PlanTimeline,
+    project_settings::{ProjectSettings, REPO_SETTINGS_FILE, SettingsScope},
     prompt_args::{expand_custom_prompt, parse_slash_name},
-    session_store::SessionStore,
+    retrospective::{LogSummary, RetroLanguage, Retrospective, StepOutcome, StepState},
+    search_index::SearchHit,
+    session_store::{SessionStore, StoredSession, now_unix_ms},
+    token_counter::{
+        self, HeuristicCounter, IMAGE_TOKENS_FALLBACK, TokenCounter, image_dimensions, image_tokens,
+    },
 };
 
 static APPROVAL_PRESETS: LazyLock<Vec<ApprovalPreset>> = LazyLock::new(builtin_approval_presets);
 const INIT_COMMAND_PROMPT: &str = include_str!("./prompt_for_init_command.md");
 const SESSION_LIST_PAGE_SIZE: usize = 25;
+const SESSION_SEARCH_RESULT_LIMIT: usize = 15;
 const SESSION_TITLE_MAX_GRAPHEMES: usize = 120;
 const CONTEXT_OPT_TRIGGER_PERCENT_DEFAULT: i64 = 90;
 const CONTEXT_OPT_TRIGGER_PERCENT_OPTIONS: [i64; 5] = [75, 80, 85, 90, 95];
@@ -293,8 +312,8 @@ impl RuntimeDiagnosticsState {
     }
 }
 
-#[derive(Debug, serde::Serialize)]
-struct RuntimeDiagnosticsSnapshot {
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub(crate) struct RuntimeDiagnosticsSnapshot {
     reason: String,
     client_info: Option<String>,
     ui_visibility_mode: String,
@@ -373,6 +392,7 @@ enum MonitorMode {
     Standard,
     Detail,
     Retrospective,
+    Timeline,
 }
 
 impl MonitorMode {
@@ -380,6 +400,8 @@ impl MonitorMode {
         let rest_lower = rest.to_lowercase();
         if rest_lower.contains("retro") || rest_lower.contains("retrospective") {
             Self::Retrospective
+        } else if rest_lower.contains("timeline") {
+            Self::Timeline
         } else if rest_lower.contains("detail") || rest_lower.contains("details") {
             Self::Detail
         } else {
@@ -708,22 +730,41 @@ struct PromptTokenEstimate {
     image_tokens: i64,
     audio_tokens: i64,
     total_tokens: i64,
+    /// Name of the counter used for text, e.g. `o200k_base` or `heuristic`.
+    tokenizer: String,
+    /// Images whose cost fell back to the fixed assumption (unreadable header).
+    images_assumed: usize,
 }
 
 impl PromptTokenEstimate {
-    fn to_json(&self) -> serde_json::Value {
-        json!({
-            "text_tokens": self.text_tokens,
-            "embedded_context_tokens": self.embedded_context_tokens,
-            "resource_link_tokens": self.resource_link_tokens,
-            "image_tokens_assumed": self.image_tokens,
-            "audio_tokens_assumed": self.audio_tokens,
-            "total_tokens": self.total_tokens,
-            "notes": [
-                "This is a rough estimate to monitor context pressure",
-                "Image/audio costs are assumption-based and may differ per model",
-            ],
-        })
+    fn notes(&self) -> Vec<String> {
+        let mut notes = vec!["This is an estimate to monitor context pressure".to_string()];
+        if self.tokenizer == HeuristicCounter.name() {
+            notes.push("Text counted with the ~4 chars/token heuristic".to_string());
+        }
+        if self.images_assumed > 0 {
+            notes.push(format!(
+                "{} image(s) without readable dimensions assumed at {IMAGE_TOKENS_FALLBACK} tokens",
+                self.images_assumed
+            ));
+        }
+        if self.audio_tokens > 0 {
+            notes.push("Audio cost is assumption-based and may differ per model".to_string());
+        }
+        notes
+    }
+
+    fn to_log_event(&self) -> PromptEstimate {
+        PromptEstimate {
+            text_tokens: self.text_tokens,
+            embedded_context_tokens: self.embedded_context_tokens,
+            resource_link_tokens: self.resource_link_tokens,
+            image_tokens_assumed: self.image_tokens,
+            audio_tokens_assumed: self.audio_tokens,
+            total_tokens: self.total_tokens,
+            tokenizer: Some(self.tokenizer.clone()),
+            notes: self.notes(),
+        }
     }
 }
 
@@ -735,6 +776,45 @@ struct PendingAutoCompact {
     used_percent: Option<i64>,
 }
 
+/// Projected context usage of a prompt: the usage reported after the last turn plus the prompt
+/// estimate.
+#[derive(Clone, Debug)]
+struct ContextBudget {
+    current_tokens: i64,
+    prompt_tokens: i64,
+    context_window: i64,
+}
+
+impl ContextBudget {
+    fn projected_tokens(&self) -> i64 {
+        self.current_tokens + self.prompt_tokens
+    }
+
+    fn projected_percent(&self) -> i64 {
+        ((self.projected_tokens() as f64 / self.context_window as f64) * 100.0).round() as i64
+    }
+
+    /// Tokens the prompt would add above the auto-compact threshold.
+    fn excess_tokens(&self, trigger_percent: i64) -> i64 {
+        self.projected_tokens() - self.context_window * trigger_percent / 100
+    }
+}
+
+/// How a prompt projected past the auto-compact threshold goes ahead.
+enum BudgetOutcome {
+    Send,
+    CompactFirst(ContextBudget),
+    Cancel,
+}
+
+/// A prompt held back until the compaction submitted ahead of it finishes.
+struct DeferredPrompt {
+    compact_submission_id: String,
+    op: Op,
+    response_tx: oneshot::Sender<Result<StopReason, Error>>,
+    budget: ContextBudget,
+}
+
 #[derive(Clone, Debug)]
 struct PlanSnapshotEntry {
     step: String,
@@ -761,6 +841,11 @@ struct FlowVectorState {
     stalled_plan_updates: usize,
     last_completed_steps: usize,
     last_plan_total_steps: usize,
+    plan_updates: usize,
+    timeline: PlanTimeline,
+    /// Time spent in each phase, in `A`, `E`, `V`, `C` order.
+    phase_time: [Duration; 4],
+    current_phase: Option<(char, Instant)>,
 }
 
 impl FlowVectorState {
@@ -803,6 +888,13 @@ impl FlowVectorState {
     }
 
     fn record_phase(&mut self, phase: char, detail: impl Into<String>) {
+        let now = Instant::now();
+        if let Some((previous, since)) = self.current_phase.replace((phase, now))
+            && let Some(slot) = flow_phase_slot(previous)
+        {
+            self.phase_time[slot] += now.duration_since(since);
+        }
+
         match phase {
             'A' => self.analysis += 1,
             'E' => self.execution += 1,
@@ -823,7 +915,12 @@ impl FlowVectorState {
         }
     }
 
-    fn record_plan_update(&mut self, explanation: Option<String>, plan: &[PlanItemArg]) {
+    /// Records the update and returns the step transitions it caused.
+    fn record_plan_update(
+        &mut self,
+        explanation: Option<String>,
+        plan: &[PlanItemArg],
+    ) -> Vec<PlanStepTransition> {
         let now = Instant::now();
         let completed = plan
             .iter()
@@ -854,7 +951,51 @@ impl FlowVectorState {
                 })
                 .collect(),
         };
+        self.plan_updates = self.plan_updates.saturating_add(1);
+        let items = plan
+            .iter()
+            .map(|item| PlanItem {
+                step: item.step.clone(),
+                status: plan_step_status(&item.status),
+            })
+            .collect::<Vec<_>>();
+        let transitions = self.timeline.observe(now_unix_ms(), &items);
         self.record_phase('C', "plan updated");
+        transitions
+    }
+
+    /// Time spent per phase, counting the phase that is still running.
+    fn phase_durations(&self) -> Vec<(char, Duration)> {
+        let mut phase_time = self.phase_time;
+        if let Some((phase, since)) = self.current_phase
+            && let Some(slot) = flow_phase_slot(phase)
+        {
+            phase_time[slot] += since.elapsed();
+        }
+        ['A', 'E', 'V', 'C'].into_iter().zip(phase_time).collect()
+    }
+
+    /// How each step of the current plan went, from the plan timeline.
+    fn step_outcomes(&self) -> Vec<StepOutcome> {
+        let now_ms = now_unix_ms();
+        let stalled = self.timeline.stalled_steps(
+            now_ms,
+            Duration::from_secs(MONITOR_STALL_NO_PROGRESS_WARN_SECS),
+        );
+        self.timeline
+            .steps()
+            .iter()
+            .map(|span| StepOutcome {
+                step: span.step.clone(),
+                state: match span.status {
+                    PlanStepStatus::Pending => StepState::Pending,
+                    PlanStepStatus::InProgress => StepState::InProgress,
+                    PlanStepStatus::Completed => StepState::Completed,
+                },
+                elapsed: span.wall_time(now_ms).filter(|elapsed| !elapsed.is_zero()),
+                stalled: stalled.iter().any(|(stalled, _)| stalled.step == span.step),
+            })
+            .collect()
     }
 
     fn trailing_action_streak(&self) -> Option<(String, usize)> {
@@ -943,6 +1084,27 @@ impl FlowVectorState {
             .unwrap_or_default();
         let plan_update_age = last_plan_update_at.elapsed();
 
+        let stall_floor = Duration::from_secs(MONITOR_STALL_NO_PROGRESS_WARN_SECS);
+        if active_task_count > 0
+            && let Some((span, wall_time)) = self
+                .timeline
+                .stalled_steps(now_unix_ms(), stall_floor)
+                .into_iter()
+                .max_by_key(|(_, wall_time)| *wall_time)
+        {
+            let typical = self
+                .timeline
+                .typical_step_time()
+                .map(format_monitor_duration)
+                .unwrap_or_else(|| "n/a".to_string());
+            return format!(
+                "Progress stall: `{}` in progress for {} (typical step {typical}, threshold {})",
+                span.step,
+                format_monitor_duration(wall_time),
+                format_monitor_duration(self.timeline.stall_threshold(stall_floor))
+            );
+        }
+
         if active_task_count > 0 && self.stalled_plan_updates >= MONITOR_STALL_PLAN_UPDATE_WARN {
             return format!(
                 "Progress stall: {} consecutive plan updates without completed-step gain (last completion {} ago)",
@@ -1109,6 +1271,24 @@ fn flow_semantic_from_direction(direction: &str) -> &'static str {
     }
 }
 
+fn plan_step_status(status: &StepStatus) -> PlanStepStatus {
+    match status {
+        StepStatus::Pending => PlanStepStatus::Pending,
+        StepStatus::InProgress => PlanStepStatus::InProgress,
+        StepStatus::Completed => PlanStepStatus::Completed,
+    }
+}
+
+fn flow_phase_slot(phase: char) -> Option<usize> {
+    match phase {
+        'A' => Some(0),
+        'E' => Some(1),
+        'V' => Some(2),
+        'C' => Some(3),
+        _ => None,
+    }
+}
+
 fn flow_phase_name(phase: char) -> &'static str {
     match phase {
         'A' => "analysis",
@@ -1119,7 +1299,7 @@ fn flow_phase_name(phase: char) -> &'static str {
     }
 }
 
-fn format_monitor_duration(duration: Duration) -> String {
+pub(crate) fn format_monitor_duration(duration: Duration) -> String {
     let secs = duration.as_secs();
     if secs < 1 {
         return "<1s".to_string();
@@ -1183,6 +1363,55 @@ impl Default for ContextOptimizationState {
     }
 }
 
+impl ContextOptimizationState {
+    /// Project a prompt of `prompt_tokens` onto the last reported usage. `None` when the context
+    /// window is unknown.
+    fn project_budget(
+        &self,
+        prompt_tokens: i64,
+        model_context_window: Option<i64>,
+    ) -> Option<ContextBudget> {
+        let info = self.last_token_info.as_ref();
+        let context_window = info
+            .and_then(|info| info.model_context_window)
+            .or(model_context_window)
+            .filter(|window| *window > 0)?;
+        Some(ContextBudget {
+            current_tokens: info
+                .map_or(0, |info| info.total_token_usage.tokens_in_context_window()),
+            prompt_tokens,
+            context_window,
+        })
+    }
+
+    fn budget_decision(&self, budget: &ContextBudget, decision: &str) -> ContextBudgetDecision {
+        ContextBudgetDecision {
+            current_tokens: budget.current_tokens,
+            prompt_tokens: budget.prompt_tokens,
+            projected_tokens: budget.projected_tokens(),
+            context_window: budget.context_window,
+            projected_percent: budget.projected_percent(),
+            trigger_percent: self.trigger_percent,
+            mode: self.mode.as_config_value().to_string(),
+            decision: decision.to_string(),
+            resources: Vec::new(),
+            compact_submission_id: None,
+        }
+    }
+
+    fn apply_project_settings(&mut self, settings: &ProjectSettings) {
+        if let Some(raw) = settings.context_optimization_mode.as_deref() {
+            match ContextOptimizationMode::from_config_value(raw) {
+                Ok(mode) => self.mode = mode,
+                Err(_) => warn!("Ignoring project setting context_optimization_mode = {raw:?}"),
+            }
+        }
+        if let Some(percent) = settings.context_trigger_percent {
+            self.trigger_percent = percent.clamp(50, 99);
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct TaskMonitoringState {
     orchestration_mode: TaskOrchestrationMode,
@@ -1212,6 +1441,29 @@ impl Default for TaskMonitoringState {
     }
 }
 
+impl TaskMonitoringState {
+    fn apply_project_settings(&mut self, settings: &ProjectSettings) {
+        if let Some(raw) = settings.task_orchestration_mode.as_deref() {
+            match TaskOrchestrationMode::from_config_value(raw) {
+                Ok(mode) => self.orchestration_mode = mode,
+                Err(_) => warn!("Ignoring project setting task_orchestration_mode = {raw:?}"),
+            }
+        }
+        if let Some(raw) = settings.task_monitoring_mode.as_deref() {
+            match TaskMonitoringMode::from_config_value(raw) {
+                Ok(mode) => self.monitor_mode = mode,
+                Err(_) => warn!("Ignoring project setting task_monitoring_mode = {raw:?}"),
+            }
+        }
+        if let Some(enabled) = settings.vector_checks {
+            self.vector_check_enabled = enabled;
+        }
+        if let Some(enabled) = settings.preempt_on_new_prompt {
+            self.preempt_on_new_prompt = enabled;
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 struct SetupWizardProgressState {
     status_checked: bool,
@@ -1565,6 +1817,10 @@ impl SubmissionState {
         }
     }
 
+    fn has_pending_elicitation(&self) -> bool {
+        matches!(self, Self::Prompt(state) if state.has_pending_elicitation())
+    }
+
     fn monitor_label(&self) -> Option<&'static str> {
         match self {
             Self::CustomPrompts(_) => None,
@@ -1748,6 +2004,20 @@ struct PromptState {
     run_started_logged: bool,
     awaiting_model_resume: bool,
     tool_watchdog_timeout: Duration,
+    /// Elicitations waiting for `key=value` answers in the next prompt, oldest first.
+    pending_elicitations: Vec<PendingElicitation>,
+    /// Approval-rule notes shown on the tool call once an auto-approved call starts.
+    auto_approval_notes: HashMap<String, String>,
+    /// Running MCP tool calls by call id, as (server, tool).
+    open_mcp_tools: HashMap<String, (String, String)>,
+}
+
+/// An MCP elicitation shown as a follow-up message because the client cannot render the form.
+struct PendingElicitation {
+    server_name: String,
+    request_id: RequestId,
+    tool_call_id: ToolCallId,
+    form: ElicitationForm,
 }
 
 impl PromptState {
@@ -1837,6 +2107,9 @@ impl PromptState {
             run_started_logged: false,
             awaiting_model_resume: false,
             tool_watchdog_timeout: Self::tool_watchdog_timeout_from_env(),
+            pending_elicitations: Vec::new(),
+            auto_approval_notes: HashMap::new(),
+            open_mcp_tools: HashMap::new(),
         }
     }
 
@@ -1856,6 +2129,9 @@ impl PromptState {
             run_started_logged: false,
             awaiting_model_resume: false,
             tool_watchdog_timeout: Self::tool_watchdog_timeout_from_env(),
+            pending_elicitations: Vec::new(),
+            auto_approval_notes: HashMap::new(),
+            open_mcp_tools: HashMap::new(),
         }
     }
 
@@ -1880,6 +2156,7 @@ impl PromptState {
         if let Some(response_tx) = self.response_tx.take() {
             drop(response_tx.send(result));
         }
+        self.pending_elicitations.clear();
         self.completed = true;
     }
 
@@ -1918,12 +2195,9 @@ impl PromptState {
             return;
         }
         self.run_started_logged = true;
-        client.log_canonical(
-            "acp.bridge.run_started",
-            json!({
-                "submission_id": self.submission_id,
-            }),
-        );
+        client.log_canonical(CanonicalEvent::BridgeRunStarted(SubmissionRef {
+            submission_id: self.submission_id.clone(),
+        }));
     }
 
     fn mark_tool_call_started(
@@ -1939,22 +2213,13 @@ impl PromptState {
                 started_at: Instant::now(),
             },
         );
-        client.log_canonical(
-            "acp.bridge.tool_call_received",
-            json!({
-                "submission_id": self.submission_id,
-                "tool_call_id": call_id,
-                "tool_kind": kind,
-            }),
-        );
-        client.log_canonical(
-            "acp.bridge.tool_exec_started",
-            json!({
-                "submission_id": self.submission_id,
-                "tool_call_id": call_id,
-                "tool_kind": kind,
-            }),
-        );
+        let tool_call = BridgeToolCall {
+            submission_id: self.submission_id.clone(),
+            tool_call_id: call_id.to_string(),
+            tool_kind: kind.to_string(),
+        };
+        client.log_canonical(CanonicalEvent::BridgeToolCallReceived(tool_call.clone()));
+        client.log_canonical(CanonicalEvent::BridgeToolExecStarted(tool_call));
     }
 
     fn mark_tool_exec_finished(
@@ -1966,17 +2231,16 @@ impl PromptState {
         exit_code: Option<i32>,
         reason: Option<&str>,
     ) {
-        client.log_canonical(
-            "acp.bridge.tool_exec_finished",
-            json!({
-                "submission_id": self.submission_id,
-                "tool_call_id": call_id,
-                "tool_kind": kind,
-                "status": format!("{status:?}"),
-                "exit_code": exit_code,
-                "reason": reason,
-            }),
-        );
+        client.log_canonical(CanonicalEvent::BridgeToolExecFinished(
+            BridgeToolExecFinished {
+                submission_id: self.submission_id.clone(),
+                tool_call_id: call_id.to_string(),
+                tool_kind: kind.to_string(),
+                status: tool_status_name(status),
+                exit_code,
+                reason: reason.map(str::to_string),
+            },
+        ));
     }
 
     fn mark_tool_result_sent(
@@ -1987,15 +2251,12 @@ impl PromptState {
         reason: Option<&str>,
     ) {
         self.awaiting_model_resume = true;
-        client.log_canonical(
-            "acp.bridge.tool_result_sent",
-            json!({
-                "submission_id": self.submission_id,
-                "tool_call_id": call_id,
-                "status": format!("{status:?}"),
-                "reason": reason,
-            }),
-        );
+        client.log_canonical(CanonicalEvent::BridgeToolResultSent(BridgeToolResultSent {
+            submission_id: self.submission_id.clone(),
+            tool_call_id: call_id.to_string(),
+            status: tool_status_name(status),
+            reason: reason.map(str::to_string),
+        }));
     }
 
     fn maybe_log_model_resumed(&mut self, client: &SessionClient) {
@@ -2003,22 +2264,16 @@ impl PromptState {
             return;
         }
         self.awaiting_model_resume = false;
-        client.log_canonical(
-            "acp.bridge.model_resumed",
-            json!({
-                "submission_id": self.submission_id,
-            }),
-        );
+        client.log_canonical(CanonicalEvent::BridgeModelResumed(SubmissionRef {
+            submission_id: self.submission_id.clone(),
+        }));
     }
 
     fn mark_final_emitted(&self, client: &SessionClient, stop_reason: &str) {
-        client.log_canonical(
-            "acp.bridge.final_emitted",
-            json!({
-                "submission_id": self.submission_id,
-                "stop_reason": stop_reason,
-            }),
-        );
+        client.log_canonical(CanonicalEvent::BridgeFinalEmitted(BridgeFinalEmitted {
+            submission_id: self.submission_id.clone(),
+            stop_reason: stop_reason.to_string(),
+        }));
     }
 
     async fn fail_tool_call_with_reason(
@@ -2449,7 +2704,7 @@ impl PromptState {
     }
 
     async fn mcp_elicitation(
-        &self,
+        &mut self,
         client: &SessionClient,
         event: ElicitationRequestEvent,
     ) -> Result<(), Error> {
@@ -2458,11 +2713,121 @@ impl PromptState {
             server_name,
             id,
             message,
+            requested_schema,
         } = event;
         let tool_call_id = ToolCallId::new(match &id {
             RequestId::String(s) => s.clone(),
             RequestId::Integer(i) => i.to_string(),
         });
+
+        let form = requested_schema
+            .as_ref()
+            .and_then(ElicitationForm::from_schema);
+
+        let tool = self.running_mcp_tool(&server_name);
+        if let Some(verdict) = client
+            .approval_policy
+            .check_mcp(&server_name, tool.as_deref())
+        {
+            client.log_approval_policy_decision(&tool_call_id.0, "mcp", &verdict);
+            let decision = match verdict.decision {
+                RuleDecision::Deny => Some(ElicitationAction::Decline),
+                // A form still needs answers, so `allow` only skips yes/no confirmations.
+                RuleDecision::Allow if form.is_none() => Some(ElicitationAction::Accept),
+                RuleDecision::Allow | RuleDecision::Ask => None,
+            };
+            if let Some(decision) = decision {
+                client
+                    .send_tool_call(
+                        ToolCall::new(tool_call_id.clone(), server_name.clone())
+                            .status(ToolCallStatus::Pending)
+                            .content(vec![verdict.describe().into()])
+                            .raw_input(raw_input),
+                    )
+                    .await;
+                return self
+                    .resolve_elicitation(client, server_name, id, tool_call_id, decision, None)
+                    .await;
+            }
+        }
+
+        let (Some(schema), Some(form)) = (requested_schema, form) else {
+            return self
+                .confirm_elicitation(client, server_name, id, tool_call_id, message, raw_input)
+                .await;
+        };
+
+        if client.supports_elicitation() {
+            match client
+                .request_elicitation(&server_name, &message, &schema)
+                .await
+            {
+                Ok((ElicitationAction::Accept, content)) => {
+                    match form.validate(&content.unwrap_or_else(|| json!({}))) {
+                        Ok(content) => {
+                            return self
+                                .resolve_elicitation(
+                                    client,
+                                    server_name,
+                                    id,
+                                    tool_call_id,
+                                    ElicitationAction::Accept,
+                                    Some(content),
+                                )
+                                .await;
+                        }
+                        Err(errors) => warn!(
+                            "Client returned invalid elicitation content for {server_name}: {}",
+                            errors.join("; ")
+                        ),
+                    }
+                }
+                Ok((decision, _)) => {
+                    return self
+                        .resolve_elicitation(client, server_name, id, tool_call_id, decision, None)
+                        .await;
+                }
+                Err(err) => warn!("{ELICITATION_EXT_METHOD} failed, asking in chat instead: {err}"),
+            }
+        }
+
+        // Ask in the conversation and end this prompt; the next prompt carries the answers and
+        // resumes the turn (see `answer_elicitation`).
+        client
+            .send_tool_call(
+                ToolCall::new(tool_call_id.clone(), server_name.clone())
+                    .status(ToolCallStatus::Pending)
+                    .content(vec![message.clone().into()])
+                    .raw_input(raw_input),
+            )
+            .await;
+        self.flush_final_agent_text_if_needed(client).await;
+        client
+            .send_agent_text(form.render_follow_up(&server_name, &message))
+            .await;
+        self.pending_elicitations.push(PendingElicitation {
+            server_name,
+            request_id: id,
+            tool_call_id,
+            form,
+        });
+        if let Some(response_tx) = self.response_tx.take() {
+            self.mark_final_emitted(client, "awaiting_elicitation");
+            drop(response_tx.send(Ok(StopReason::EndTurn)));
+        }
+        Ok(())
+    }
+
+    /// Yes/no confirmation for elicitations without a form.
+    async fn confirm_elicitation(
+        &self,
+        client: &SessionClient,
+        server_name: String,
+        id: RequestId,
+        tool_call_id: ToolCallId,
+        message: String,
+        raw_input: serde_json::Value,
+    ) -> Result<(), Error> {
         let response = client
             .request_permission(
                 ToolCallUpdate::new(
@@ -2504,11 +2869,25 @@ impl PromptState {
             RequestPermissionOutcome::Cancelled | _ => ElicitationAction::Cancel,
         };
 
+        self.resolve_elicitation(client, server_name, id, tool_call_id, decision, None)
+            .await
+    }
+
+    async fn resolve_elicitation(
+        &self,
+        client: &SessionClient,
+        server_name: String,
+        request_id: RequestId,
+        tool_call_id: ToolCallId,
+        decision: ElicitationAction,
+        content: Option<serde_json::Value>,
+    ) -> Result<(), Error> {
         self.thread
             .submit(Op::ResolveElicitation {
                 server_name,
-                request_id: id,
+                request_id,
                 decision,
+                content,
             })
             .await
             .map_err(|e| Error::from(anyhow::anyhow!(e)))?;
@@ -2527,6 +2906,79 @@ impl PromptState {
         Ok(())
     }
 
+    /// Tool of the running MCP call to `server`, when exactly one tool of it is running.
+    fn running_mcp_tool(&self, server: &str) -> Option<String> {
+        let tools = self
+            .open_mcp_tools
+            .values()
+            .filter(|(running_server, _)| running_server == server)
+            .map(|(_, tool)| tool)
+            .unique()
+            .collect::<Vec<_>>();
+        match tools.as_slice() {
+            [tool] => Some((*tool).clone()),
+            _ => None,
+        }
+    }
+
+    fn has_pending_elicitation(&self) -> bool {
+        !self.completed && !self.pending_elicitations.is_empty()
+    }
+
+    /// Answer the oldest pending elicitation with the text of a new prompt.
+    ///
+    /// On success the turn resumes and reports to `response_tx`; invalid answers are listed and
+    /// the prompt ends so the user can try again.
+    async fn answer_elicitation(
+        &mut self,
+        client: &SessionClient,
+        text: &str,
+        response_tx: oneshot::Sender<Result<StopReason, Error>>,
+    ) -> Result<(), Error> {
+        let Some(pending) = self.pending_elicitations.first() else {
+            drop(response_tx.send(Ok(StopReason::EndTurn)));
+            return Ok(());
+        };
+        let (decision, content) = match pending.form.parse_follow_up(text) {
+            Ok(FollowUpReply::Accept(content)) => (ElicitationAction::Accept, Some(content)),
+            Ok(FollowUpReply::Decline) => (ElicitationAction::Decline, None),
+            Ok(FollowUpReply::Cancel) => (ElicitationAction::Cancel, None),
+            Err(errors) => {
+                let errors = errors
+                    .iter()
+                    .map(|error| format!("- {error}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                client
+                    .send_agent_text(format!(
+                        "The answers for **{}** need fixing:\n{errors}\n\nReply again with `key=value` lines, or `/decline` or `/cancel`.",
+                        pending.server_name
+                    ))
+                    .await;
+                drop(response_tx.send(Ok(StopReason::EndTurn)));
+                return Ok(());
+            }
+        };
+
+        let pending = self.pending_elicitations.remove(0);
+        self.resolve_elicitation(
+            client,
+            pending.server_name,
+            pending.request_id,
+            pending.tool_call_id,
+            decision,
+            content,
+        )
+        .await?;
+        if !self.pending_elicitations.is_empty() {
+            // Another form is still open; the turn resumes once it is answered.
+            drop(response_tx.send(Ok(StopReason::EndTurn)));
+        } else if let Some(previous) = self.response_tx.replace(response_tx) {
+            drop(previous.send(Ok(StopReason::EndTurn)));
+        }
+        Ok(())
+    }
+
     async fn review_mode_exit(
         &self,
         client: &SessionClient,
@@ -2560,7 +3012,7 @@ impl PromptState {
     }
 
     async fn patch_approval(
-        &self,
+        &mut self,
         client: &SessionClient,
         event: ApplyPatchApprovalRequestEvent,
     ) -> Result<(), Error> {
@@ -2573,7 +3025,39 @@ impl PromptState {
             grant_root: _,
             turn_id: _,
         } = event;
+        let paths = changes
+            .iter()
+            .flat_map(|(path, change)| {
+                let moved = match change {
+                    FileChange::Update {
+                        move_path: Some(move_path),
+                        ..
+                    } => Some(move_path.clone()),
+                    _ => None,
+                };
+                std::iter::once(path.clone()).chain(moved)
+            })
+            .collect::<Vec<_>>();
+        let verdict = client.approval_policy.check_patch(&paths);
         let (title, locations, content) = extract_tool_call_content_from_changes(changes);
+
+        if let Some(verdict) = verdict.as_ref() {
+            client.log_approval_policy_decision(&call_id, "patch", verdict);
+            if let Some(decision) = self
+                .apply_policy_verdict(
+                    client,
+                    verdict,
+                    ToolCall::new(call_id.clone(), title.clone())
+                        .kind(ToolKind::Edit)
+                        .locations(locations.clone())
+                        .raw_input(raw_input.clone()),
+                )
+                .await
+            {
+                return self.submit_patch_approval(decision).await;
+            }
+        }
+
         let response = client
             .request_permission(
                 ToolCallUpdate::new(
@@ -2583,7 +3067,14 @@ impl PromptState {
                         .status(ToolCallStatus::Pending)
                         .title(title)
                         .locations(locations)
-                        .content(content.chain(reason.map(|r| r.into())).collect::<Vec<_>>())
+                        .content(
+                            verdict
+                                .map(|verdict| ToolCallContent::from(verdict.describe()))
+                                .into_iter()
+                                .chain(content)
+                                .chain(reason.map(|r| r.into()))
+                                .collect::<Vec<_>>(),
+                        )
                         .raw_input(raw_input),
                 ),
                 vec![
@@ -2607,6 +3098,10 @@ impl PromptState {
             RequestPermissionOutcome::Cancelled | _ => ReviewDecision::Abort,
         };
 
+        self.submit_patch_approval(decision).await
+    }
+
+    async fn submit_patch_approval(&self, decision: ReviewDecision) -> Result<(), Error> {
         self.thread
             .submit(Op::PatchApproval {
                 id: self.submission_id.clone(),
@@ -2627,6 +3122,10 @@ impl PromptState {
         } = event;
 
         let (title, locations, content) = extract_tool_call_content_from_changes(changes);
+        let note = self
+            .auto_approval_notes
+            .remove(&call_id)
+            .map(ToolCallContent::from);
 
         client
             .send_tool_call(
@@ -2634,7 +3133,7 @@ impl PromptState {
                     .kind(ToolKind::Edit)
                     .status(ToolCallStatus::InProgress)
                     .locations(locations)
-                    .content(content.collect())
+                    .content(note.into_iter().chain(content).collect())
                     .raw_input(raw_input),
             )
             .await;
@@ -2692,6 +3191,10 @@ impl PromptState {
         invocation: McpInvocation,
     ) {
         let title = format!("Tool: {}/{}", invocation.server, invocation.tool);
+        self.open_mcp_tools.insert(
+            call_id.clone(),
+            (invocation.server.clone(), invocation.tool.clone()),
+        );
         client
             .send_tool_call(
                 ToolCall::new(call_id.clone(), title)
@@ -2727,6 +3230,7 @@ impl PromptState {
             .get(&call_id)
             .map(|open| open.kind)
             .unwrap_or("mcp_tool_call");
+        self.open_mcp_tools.remove(&call_id);
         self.mark_tool_exec_finished(client, &call_id, kind, status, None, None);
 
         client
@@ -2758,7 +3262,7 @@ impl PromptState {
         let raw_input = serde_json::json!(&event);
         let ExecApprovalRequestEvent {
             call_id,
-            command: _,
+            command,
             turn_id: _,
             cwd,
             reason,
@@ -2766,6 +3270,9 @@ impl PromptState {
             proposed_execpolicy_amendment,
         } = event;
 
+        let kinds = parsed_cmd.iter().map(CommandKind::from).collect::<Vec<_>>();
+        let verdict = client.approval_policy.check_exec(&command, &kinds);
+
         // Create a new tool call for the command execution
         let tool_call_id = ToolCallId::new(call_id.clone());
         let ParseCommandToolCall {
@@ -2775,8 +3282,28 @@ impl PromptState {
             ..
         } = parse_command_tool_call(parsed_cmd, &cwd);
 
+        if let Some(verdict) = verdict.as_ref() {
+            client.log_approval_policy_decision(&call_id, "exec", verdict);
+            if let Some(decision) = self
+                .apply_policy_verdict(
+                    client,
+                    verdict,
+                    ToolCall::new(tool_call_id.clone(), title.clone())
+                        .kind(kind)
+                        .locations(locations.clone())
+                        .raw_input(raw_input.clone()),
+                )
+                .await
+            {
+                return self.submit_exec_approval(decision).await;
+            }
+        }
+
         let mut content = vec![];
 
+        if let Some(verdict) = verdict.as_ref() {
+            content.push(verdict.describe());
+        }
         if let Some(reason) = reason {
             content.push(reason);
         }
@@ -2793,6 +3320,26 @@ impl PromptState {
             Some(vec![content.join("\n").into()])
         };
 
+        // An `ask` rule wants every run confirmed, so "Always" is not offered.
+        let mut options = vec![
+            PermissionOption::new("approved", "Yes", PermissionOptionKind::AllowOnce),
+            PermissionOption::new(
+                "abort",
+                "No, provide feedback",
+                PermissionOptionKind::RejectOnce,
+            ),
+        ];
+        if verdict.is_none() {
+            options.insert(
+                0,
+                PermissionOption::new(
+                    "approved-for-session",
+                    "Always",
+                    PermissionOptionKind::AllowAlways,
+                ),
+            );
+        }
+
         let response = client
             .request_permission(
                 ToolCallUpdate::new(
@@ -2809,19 +3356,7 @@ impl PromptState {
                             Some(locations)
                         }),
                 ),
-                vec![
-                    PermissionOption::new(
-                        "approved-for-session",
-                        "Always",
-                        PermissionOptionKind::AllowAlways,
-                    ),
-                    PermissionOption::new("approved", "Yes", PermissionOptionKind::AllowOnce),
-                    PermissionOption::new(
-                        "abort",
-                        "No, provide feedback",
-                        PermissionOptionKind::RejectOnce,
-                    ),
-                ],
+                options,
             )
             .await?;
 
@@ -2836,6 +3371,10 @@ impl PromptState {
             RequestPermissionOutcome::Cancelled | _ => ReviewDecision::Abort,
         };
 
+        self.submit_exec_approval(decision).await
+    }
+
+    async fn submit_exec_approval(&self, decision: ReviewDecision) -> Result<(), Error> {
         self.thread
             .submit(Op::ExecApproval {
                 id: self.submission_id.clone(),
@@ -2847,13 +3386,41 @@ impl PromptState {
         Ok(())
     }
 
-    async fn exec_command_begin(&mut self, client: &SessionClient, event: ExecCommandBeginEvent) {
-        let raw_input = serde_json::json!(&event);
-        let ExecCommandBeginEvent {
-            turn_id: _,
-            source: _,
-            interaction_input: _,
-            call_id,
+    /// Answer an approval from a local rule: `allow` approves and notes the rule on the tool call
+    /// once it starts, `deny` fails the tool call. `ask` returns `None` to ask the client.
+    async fn apply_policy_verdict(
+        &mut self,
+        client: &SessionClient,
+        verdict: &PolicyVerdict,
+        tool_call: ToolCall,
+    ) -> Option<ReviewDecision> {
+        match verdict.decision {
+            RuleDecision::Allow => {
+                self.auto_approval_notes
+                    .insert(tool_call.tool_call_id.0.to_string(), verdict.describe());
+                Some(ReviewDecision::Approved)
+            }
+            RuleDecision::Deny => {
+                client
+                    .send_tool_call(
+                        tool_call
+                            .status(ToolCallStatus::Failed)
+                            .content(vec![verdict.describe().into()]),
+                    )
+                    .await;
+                Some(ReviewDecision::Denied)
+            }
+            RuleDecision::Ask => None,
+        }
+    }
+
+    async fn exec_command_begin(&mut self, client: &SessionClient, event: ExecCommandBeginEvent) {
+        let raw_input = serde_json::json!(&event);
+        let ExecCommandBeginEvent {
+            turn_id: _,
+            source: _,
+            interaction_input: _,
+            call_id,
             command: _,
             cwd,
             parsed_cmd,
@@ -2924,6 +3491,13 @@ impl PromptState {
         };
 
         self.active_command = Some(active_command);
+        let content = self
+            .auto_approval_notes
+            .remove(&call_id)
+            .map(ToolCallContent::from)
+            .into_iter()
+            .chain(content)
+            .collect::<Vec<_>>();
 
         client
             .send_tool_call(
@@ -3294,6 +3868,8 @@ struct SessionClient {
     session_store: Option<SessionStore>,
     ui_visibility_mode: UiVisibilityMode,
     diagnostics: Arc<RuntimeDiagnosticsState>,
+    /// Local auto-approval rules, loaded by the thread actor for the session cwd.
+    approval_policy: Arc<ApprovalPolicy>,
 }
 
 impl SessionClient {
@@ -3309,6 +3885,7 @@ impl SessionClient {
             session_store,
             ui_visibility_mode: UiVisibilityMode::from_env(),
             diagnostics: Arc::new(RuntimeDiagnosticsState::new()),
+            approval_policy: Arc::default(),
         }
     }
 
@@ -3343,15 +3920,28 @@ impl SessionClient {
             session_store,
             ui_visibility_mode,
             diagnostics: Arc::new(RuntimeDiagnosticsState::new()),
+            approval_policy: Arc::default(),
         }
     }
 
-    fn log_canonical(&self, kind: &str, data: serde_json::Value) {
+    fn log_canonical(&self, event: CanonicalEvent) {
         if let Some(store) = self.session_store.as_ref() {
-            store.log(kind, data);
+            store.log(event);
         }
     }
 
+    fn log_approval_policy_decision(&self, call_id: &str, request: &str, verdict: &PolicyVerdict) {
+        self.log_canonical(CanonicalEvent::ApprovalPolicyDecision(
+            ApprovalPolicyDecision {
+                call_id: call_id.to_string(),
+                request: request.to_string(),
+                decision: verdict.decision.as_str().to_string(),
+                rule: verdict.rule.clone(),
+                source: verdict.source.display().to_string(),
+            },
+        ));
+    }
+
     fn ui_visibility_mode(&self) -> UiVisibilityMode {
         self.ui_visibility_mode
     }
@@ -3488,12 +4078,7 @@ impl SessionClient {
 
     fn log_runtime_diagnostics(&self, reason: impl Into<String>, active_tasks: usize) {
         let snapshot = self.runtime_diagnostics_snapshot(reason, active_tasks);
-        let value = serde_json::to_value(snapshot).unwrap_or_else(|_| {
-            json!({
-                "reason": "serialize_failed"
-            })
-        });
-        self.log_canonical("acp.runtime_diagnostics", value);
+        self.log_canonical(CanonicalEvent::RuntimeDiagnostics(snapshot));
     }
 
     fn maybe_log_runtime_diagnostics(&self, trigger: &str) {
@@ -3545,6 +4130,51 @@ impl SessionClient {
             })
     }
 
+    fn supports_elicitation(&self) -> bool {
+        self.client_capabilities
+            .lock()
+            .unwrap()
+            .meta
+            .as_ref()
+            .is_some_and(|v| {
+                v.get(ELICITATION_CAPABILITY)
+                    .is_some_and(|v| v.as_bool().unwrap_or_default())
+            })
+    }
+
+    /// Ask the client to render an elicitation form through the `elicitation/create` extension.
+    async fn request_elicitation(
+        &self,
+        server_name: &str,
+        message: &str,
+        requested_schema: &serde_json::Value,
+    ) -> Result<(ElicitationAction, Option<serde_json::Value>), Error> {
+        let params = serde_json::value::to_raw_value(&json!({
+            "sessionId": self.session_id,
+            "serverName": server_name,
+            "message": message,
+            "requestedSchema": requested_schema,
+        }))
+        .map_err(|e| Error::internal_error().data(e.to_string()))?;
+        let response = self
+            .client
+            .ext_method(ExtRequest::new(ELICITATION_EXT_METHOD, params.into()))
+            .await?;
+        let response = serde_json::to_value(&response)
+            .map_err(|e| Error::internal_error().data(e.to_string()))?;
+        let action = match response.get("action").and_then(|v| v.as_str()) {
+            Some("accept") => ElicitationAction::Accept,
+            Some("decline") => ElicitationAction::Decline,
+            Some("cancel") => ElicitationAction::Cancel,
+            other => {
+                return Err(
+                    Error::invalid_params().data(format!("unknown elicitation action: {other:?}"))
+                );
+            }
+        };
+        Ok((action, response.get("content").cloned()))
+    }
+
     fn supports_embedded_terminal_output(&self, active_command: &ActiveCommand) -> bool {
         active_command.terminal_output && self.supports_legacy_terminal_output_extension()
     }
@@ -3574,7 +4204,7 @@ impl SessionClient {
         self.diagnostics
             .user_message_chars
             .fetch_add(text.chars().count() as u64, Ordering::Relaxed);
-        self.log_canonical("acp.user_message_chunk", json!({ "text": text }));
+        self.log_canonical(CanonicalEvent::user_message(text.as_str()));
         self.send_notification(SessionUpdate::UserMessageChunk(ContentChunk::new(
             text.into(),
         )))
@@ -3591,7 +4221,7 @@ impl SessionClient {
             self.diagnostics
                 .agent_message_chars
                 .fetch_add(chunk.chars().count() as u64, Ordering::Relaxed);
-            self.log_canonical("acp.agent_message_chunk", json!({ "text": &chunk }));
+            self.log_canonical(CanonicalEvent::agent_message(chunk.as_str()));
             self.send_notification(SessionUpdate::AgentMessageChunk(ContentChunk::new(
                 chunk.into(),
             )))
@@ -3607,7 +4237,7 @@ impl SessionClient {
         self.diagnostics
             .agent_thought_chars
             .fetch_add(text.chars().count() as u64, Ordering::Relaxed);
-        self.log_canonical("acp.agent_thought_chunk", json!({ "text": text }));
+        self.log_canonical(CanonicalEvent::agent_thought(text.as_str()));
         if self.hides_internal_updates() {
             return;
         }
@@ -3628,7 +4258,7 @@ impl SessionClient {
         self.diagnostics
             .tool_call_payload_chars
             .fetch_add(json_payload_chars(&value), Ordering::Relaxed);
-        self.log_canonical("acp.tool_call", value);
+        self.log_canonical(CanonicalEvent::ToolCall(value));
         if self.hides_internal_updates() {
             return;
         }
@@ -3649,7 +4279,7 @@ impl SessionClient {
         self.diagnostics
             .tool_call_update_payload_chars
             .fetch_add(json_payload_chars(&value), Ordering::Relaxed);
-        self.log_canonical("acp.tool_call_update", value);
+        self.log_canonical(CanonicalEvent::ToolCallUpdate(value));
         if self.hides_internal_updates() {
             return;
         }
@@ -3690,25 +4320,20 @@ impl SessionClient {
 
     async fn update_plan(&self, plan: Vec<PlanItemArg>, explanation: Option<String>) {
         let progress_text = self.non_zed_plan_progress_text(&plan, explanation.as_deref());
-        let mut data = json!({
-            "items": plan.iter().map(|p| {
-                json!({
-                    "step": p.step,
-                    "status": match p.status {
-                        StepStatus::Pending => "pending",
-                        StepStatus::InProgress => "in_progress",
-                        StepStatus::Completed => "completed",
-                    }
+        let data = PlanUpdate {
+            items: plan
+                .iter()
+                .map(|p| PlanItem {
+                    step: p.step.clone(),
+                    status: plan_step_status(&p.status),
                 })
-            }).collect::<Vec<_>>(),
-        });
-        if let Some(explanation) = explanation {
-            data["explanation"] = serde_json::Value::String(explanation);
-        }
+                .collect(),
+            explanation,
+        };
         self.diagnostics
             .plan_updates
             .fetch_add(1, Ordering::Relaxed);
-        self.log_canonical("acp.plan", data);
+        self.log_canonical(CanonicalEvent::Plan(data));
         if self.hides_internal_updates() {
             return;
         }
@@ -3745,13 +4370,12 @@ impl SessionClient {
         self.diagnostics
             .permission_requests
             .fetch_add(1, Ordering::Relaxed);
-        self.log_canonical(
-            "acp.request_permission",
-            json!({
-                "tool_call": serde_json::to_value(&tool_call).unwrap_or_else(|_| json!({"debug": format!("{tool_call:?}")})),
-                "options": serde_json::to_value(&options).unwrap_or_else(|_| json!({"debug": format!("{options:?}")})),
-            }),
-        );
+        self.log_canonical(CanonicalEvent::RequestPermission(PermissionRequest {
+            tool_call: serde_json::to_value(&tool_call)
+                .unwrap_or_else(|_| json!({"debug": format!("{tool_call:?}")})),
+            options: serde_json::to_value(&options)
+                .unwrap_or_else(|_| json!({"debug": format!("{options:?}")})),
+        }));
 
         let resp = self
             .client
@@ -3762,12 +4386,12 @@ impl SessionClient {
             ))
             .await?;
 
-        self.log_canonical(
-            "acp.request_permission_response",
-            json!({
-                "outcome": serde_json::to_value(&resp.outcome).unwrap_or_else(|_| json!({"debug": format!("{:?}", resp.outcome)})),
-            }),
-        );
+        self.log_canonical(CanonicalEvent::RequestPermissionResponse(
+            PermissionResponse {
+                outcome: serde_json::to_value(&resp.outcome)
+                    .unwrap_or_else(|_| json!({"debug": format!("{:?}", resp.outcome)})),
+            },
+        ));
 
         Ok(resp)
     }
@@ -3806,6 +4430,8 @@ struct ThreadActor<A> {
     setup_wizard_progress: SetupWizardProgressState,
     /// Thread monitoring state for plan/progress and flow-direction UX.
     flow_vector: FlowVectorState,
+    /// Prompt waiting for the compaction submitted ahead of it.
+    deferred_prompt: Option<DeferredPrompt>,
 }
 
 impl<A: Auth> ThreadActor<A> {
@@ -3815,12 +4441,34 @@ impl<A: Auth> ThreadActor<A> {
 
     fn new(
         auth: A,
-        client: SessionClient,
+        mut client: SessionClient,
         thread: Arc<dyn CodexThreadImpl>,
         models_manager: Arc<dyn ModelsManagerImpl>,
         config: Config,
         message_rx: mpsc::UnboundedReceiver<ThreadMessage>,
     ) -> Self {
+        let approval_policy = ApprovalPolicy::load(&config.cwd);
+        if !approval_policy.is_empty() {
+            info!("Loaded local approval rules for {}", config.cwd.display());
+        }
+        client.approval_policy = Arc::new(approval_policy);
+        let (project_settings, sources) = ProjectSettings::load(&config.cwd);
+        let mut context_optimization = ContextOptimizationState::default();
+        context_optimization.apply_project_settings(&project_settings);
+        let mut task_monitoring = TaskMonitoringState::default();
+        task_monitoring.apply_project_settings(&project_settings);
+        for source in sources {
+            info!("Applied project settings from {}", source.display());
+        }
+        // A reloaded session keeps the plan history it logged before.
+        let timeline = client
+            .session_store
+            .as_ref()
+            .map(|store| {
+                PlanTimeline::from_records(&SessionStore::read_canonical(store.global_session_id()))
+            })
+            .unwrap_or_default();
+
         Self {
             auth,
             client,
@@ -3832,12 +4480,16 @@ impl<A: Auth> ThreadActor<A> {
             message_rx,
             last_sent_config_options: None,
             last_session_list: Vec::new(),
-            context_optimization: ContextOptimizationState::default(),
-            task_monitoring: TaskMonitoringState::default(),
+            context_optimization,
+            task_monitoring,
             advanced_options_panel: AdvancedOptionsPanel::Context,
             setup_wizard_active: false,
             setup_wizard_progress: SetupWizardProgressState::default(),
-            flow_vector: FlowVectorState::default(),
+            flow_vector: FlowVectorState {
+                timeline,
+                ..FlowVectorState::default()
+            },
+            deferred_prompt: None,
         }
     }
 
@@ -3896,12 +4548,10 @@ impl<A: Auth> ThreadActor<A> {
             return Ok(());
         }
 
-        self.client.log_canonical(
-            "acp.bridge.preempt_before_prompt",
-            json!({
-                "active_submission_ids": active_submission_ids.clone(),
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::BridgePreemptBeforePrompt(BridgePreempt {
+                active_submission_ids: active_submission_ids.clone(),
+            }));
 
         self.handle_cancel().await?;
         self.fail_open_tool_calls("preempted_by_new_prompt").await;
@@ -4080,7 +4730,7 @@ impl<A: Auth> ThreadActor<A> {
                 "monitor plan progress, execution trace, and context optimization state",
             )
             .input(AvailableCommandInput::Unstructured(
-                UnstructuredCommandInput::new("optional: detail | retro"),
+                UnstructuredCommandInput::new("optional: detail | retro [en|ko] | timeline"),
             )),
             AvailableCommand::new(
                 "vector",
@@ -4121,6 +4771,13 @@ impl<A: Auth> ThreadActor<A> {
             ),
             AvailableCommand::new("undo", "undo Codex’s most recent turn"),
             AvailableCommand::new("sessions", "list recent sessions for the current workspace"),
+            AvailableCommand::new(
+                "search",
+                "search prompts, replies and tool calls of past sessions (all backends)",
+            )
+            .input(AvailableCommandInput::Unstructured(
+                UnstructuredCommandInput::new("search terms"),
+            )),
             AvailableCommand::new("load", "show instructions to open a previous session").input(
                 AvailableCommandInput::Unstructured(UnstructuredCommandInput::new(
                     "session id or list number",
@@ -4478,6 +5135,32 @@ impl<A: Auth> ThreadActor<A> {
                 );
             }
 
+            if show_context || show_tasks {
+                options.push(
+                    SessionConfigOption::select(
+                        "project_defaults",
+                        "Project Defaults",
+                        "session",
+                        vec![
+                            SessionConfigSelectOption::new("session", "This Session Only")
+                                .description("Keep the current settings for this thread only"),
+                            SessionConfigSelectOption::new("save_repo", "Save to Repository")
+                                .description(format!(
+                                    "Write context and task settings to `{REPO_SETTINGS_FILE}` for everyone using this repository"
+                                )),
+                            SessionConfigSelectOption::new("save_workspace", "Save for Me")
+                                .description(
+                                    "Write context and task settings to ACP_HOME for this workspace; overrides the repository file",
+                                ),
+                        ],
+                    )
+                    .category(SessionConfigOptionCategory::Other)
+                    .description(
+                        "Save the Context and Task settings above as defaults for new threads in this workspace",
+                    ),
+                );
+            }
+
             if show_beta {
                 for spec in experimental_feature_specs() {
                     let current = if self.config.features.enabled(spec.id) {
@@ -4588,8 +5271,7 @@ impl<A: Auth> ThreadActor<A> {
         }
         let plan = self.setup_wizard_plan_items();
         let explanation = explanation.map(ToOwned::to_owned);
-        self.flow_vector
-            .record_plan_update(explanation.clone(), plan.as_slice());
+        self.record_plan_update(explanation.clone(), plan.as_slice());
         self.client.update_plan(plan, explanation).await;
     }
 
@@ -4613,6 +5295,7 @@ impl<A: Auth> ThreadActor<A> {
             "task_monitoring_enabled" => self.handle_set_task_monitoring_mode(value).await,
             "task_vector_check_enabled" => self.handle_set_task_vector_check_enabled(value).await,
             "preempt_on_new_prompt" => self.handle_set_preempt_on_new_prompt(value).await,
+            "project_defaults" => self.handle_save_project_defaults(value).await,
             _ if parse_experimental_feature_config_id(&raw_config_id).is_some() => {
                 let spec = parse_experimental_feature_config_id(&raw_config_id)
                     .ok_or_else(|| Error::invalid_params().data("Unsupported beta feature"))?;
@@ -4766,12 +5449,10 @@ impl<A: Auth> ThreadActor<A> {
         let mode = ContextOptimizationMode::from_config_value(value.0.as_ref())?;
         self.context_optimization.mode = mode;
 
-        self.client.log_canonical(
-            "acp.context_opt.mode",
-            json!({
-                "mode": mode.as_config_value(),
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptMode(ModeSetting {
+                mode: mode.as_config_value().to_string(),
+            }));
 
         Ok(())
     }
@@ -4794,12 +5475,10 @@ impl<A: Auth> ThreadActor<A> {
         }
 
         self.context_optimization.trigger_percent = parsed;
-        self.client.log_canonical(
-            "acp.context_opt.trigger_percent",
-            json!({
-                "trigger_percent": parsed,
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptTriggerPercent(TriggerPercent {
+                trigger_percent: parsed,
+            }));
         Ok(())
     }
 
@@ -4809,12 +5488,10 @@ impl<A: Auth> ThreadActor<A> {
     ) -> Result<(), Error> {
         let mode = TaskOrchestrationMode::from_config_value(value.0.as_ref())?;
         self.task_monitoring.orchestration_mode = mode;
-        self.client.log_canonical(
-            "acp.task_monitoring.orchestration_mode",
-            json!({
-                "mode": mode.as_config_value(),
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::TaskOrchestrationMode(ModeSetting {
+                mode: mode.as_config_value().to_string(),
+            }));
         Ok(())
     }
 
@@ -4824,12 +5501,10 @@ impl<A: Auth> ThreadActor<A> {
     ) -> Result<(), Error> {
         let monitor_mode = TaskMonitoringMode::from_config_value(value.0.as_ref())?;
         self.task_monitoring.monitor_mode = monitor_mode;
-        self.client.log_canonical(
-            "acp.task_monitoring.mode",
-            json!({
-                "mode": monitor_mode.as_config_value(),
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::TaskMonitoringMode(ModeSetting {
+                mode: monitor_mode.as_config_value().to_string(),
+            }));
         Ok(())
     }
 
@@ -4839,12 +5514,8 @@ impl<A: Auth> ThreadActor<A> {
     ) -> Result<(), Error> {
         let enabled = parse_on_off_toggle(value.0.as_ref(), "Progress Vector Checks")?;
         self.task_monitoring.vector_check_enabled = enabled;
-        self.client.log_canonical(
-            "acp.task_monitoring.vector_checks",
-            json!({
-                "enabled": enabled,
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::TaskVectorChecks(Toggle { enabled }));
         Ok(())
     }
 
@@ -4854,12 +5525,60 @@ impl<A: Auth> ThreadActor<A> {
     ) -> Result<(), Error> {
         let enabled = parse_on_off_toggle(value.0.as_ref(), "New Prompt Preemption")?;
         self.task_monitoring.preempt_on_new_prompt = enabled;
-        self.client.log_canonical(
-            "acp.task_monitoring.preempt_on_new_prompt",
-            json!({
-                "enabled": enabled,
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::TaskPreemptOnNewPrompt(Toggle { enabled }));
+        Ok(())
+    }
+
+    fn project_settings_snapshot(&self) -> ProjectSettings {
+        ProjectSettings {
+            context_optimization_mode: Some(
+                self.context_optimization.mode.as_config_value().to_string(),
+            ),
+            context_trigger_percent: Some(self.context_optimization.trigger_percent),
+            task_orchestration_mode: Some(
+                self.task_monitoring
+                    .orchestration_mode
+                    .as_config_value()
+                    .to_string(),
+            ),
+            task_monitoring_mode: Some(
+                self.task_monitoring
+                    .monitor_mode
+                    .as_config_value()
+                    .to_string(),
+            ),
+            vector_checks: Some(self.task_monitoring.vector_check_enabled),
+            preempt_on_new_prompt: Some(self.task_monitoring.preempt_on_new_prompt),
+        }
+    }
+
+    async fn handle_save_project_defaults(
+        &mut self,
+        value: SessionConfigValueId,
+    ) -> Result<(), Error> {
+        let scope = match value.0.as_ref() {
+            "session" => return Ok(()),
+            "save_repo" => SettingsScope::Repo,
+            "save_workspace" => SettingsScope::Workspace,
+            _ => {
+                return Err(Error::invalid_params().data(
+                    "Project Defaults values must be one of: session, save_repo, save_workspace",
+                ));
+            }
+        };
+        let path = self
+            .project_settings_snapshot()
+            .save(scope, &self.config.cwd)
+            .map_err(|e| {
+                Error::internal_error().data(format!("failed to save project defaults: {e}"))
+            })?;
+        self.client
+            .send_agent_text(format!(
+                "Saved context and task settings as project defaults in `{}`. New threads in this workspace will start with them.",
+                path.display()
+            ))
+            .await;
         Ok(())
     }
 
@@ -4902,13 +5621,11 @@ impl<A: Auth> ThreadActor<A> {
             )));
         }
 
-        self.client.log_canonical(
-            "acp.beta_feature",
-            json!({
-                "feature": spec.key,
-                "enabled": enabled,
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::BetaFeature(BetaFeature {
+                feature: spec.key.to_string(),
+                enabled,
+            }));
 
         Ok(())
     }
@@ -5016,7 +5733,13 @@ impl<A: Auth> ThreadActor<A> {
             }
         ));
         lines.push("- `/monitor detail`: progress + trace + context telemetry".to_string());
-        lines.push("- `/monitor retro`: 회고형 상태 보고서(레인/진행률/리스크/다음)".to_string());
+        lines.push(
+            "- `/monitor retro [en|ko]`: session retrospective (steps, phases, risks, next actions)"
+                .to_string(),
+        );
+        lines.push(
+            "- `/monitor timeline`: plan-step Gantt chart with per-step wall time".to_string(),
+        );
         lines.push("- `/vector`: workflow minimap + semantic compass".to_string());
         lines.push("- `/new-window`: how to open a fresh thread in your client".to_string());
         lines.push(String::new());
@@ -5267,72 +5990,35 @@ impl<A: Auth> ThreadActor<A> {
         lines.join("\n")
     }
 
-    fn render_monitor_retrospective(&self) -> String {
-        let progress_bar = |latest_percent: u8| -> String {
-            let width: usize = 10;
-            let safe_percent = latest_percent.min(100) as usize;
-            let filled = std::cmp::min(width, safe_percent * width / 100);
-            let empty = width.saturating_sub(filled);
-            format!("[{}{}]", "#".repeat(filled), "-".repeat(empty))
-        };
-
-        let render_lane = |label: char, checkpoints: &[u8]| -> String {
-            let mut entries = checkpoints
-                .iter()
-                .map(|value| format!("{value}%"))
-                .collect::<Vec<_>>();
-            let latest = checkpoints.last().copied().unwrap_or(0);
-            if entries.is_empty() {
-                entries.push("0%".to_string());
-            }
-            format!(
-                "Lane {label} 진행률: {} {}",
-                entries.join(" → "),
-                progress_bar(latest)
-            )
-        };
+    fn render_monitor_retrospective(&self, language: RetroLanguage) -> String {
+        let log = self.client.session_store.as_ref().map(|store| {
+            LogSummary::from_records(&SessionStore::read_canonical(store.global_session_id()))
+        });
+        Retrospective {
+            steps: self.flow_vector.step_outcomes(),
+            plan_updates: self.flow_vector.plan_updates,
+            phase_time: self.flow_vector.phase_durations(),
+            repeated_action: self
+                .flow_vector
+                .trailing_action_streak()
+                .filter(|(_, streak)| *streak >= MONITOR_REPEAT_STREAK_WARN),
+            log,
+        }
+        .render(language)
+    }
 
-        let lines = vec![
-            "회고형 상태 보고서 (2026-02-14)".to_string(),
-            "병렬 오케스트레이션으로 각 순위를 분해해 동시 진행 중이며, 순위 순서대로 정리합니다."
-                .to_string(),
-            String::new(),
-            "1. brain job payload templates by type: 스펙 확정 + 예제 입력/출력 정의".to_string(),
-            "병렬 레인: A 스펙 확정 | B 예제 입력/출력 정의".to_string(),
-            render_lane('A', &[41, 56, 69]),
-            render_lane('B', &[28, 44, 61]),
-            "회고: 타입 경계 정의에서 합의 시간이 길어졌고, 예제는 경계값 중심으로 정리되면서 속도가 붙었습니다.".to_string(),
-            "배운 점: 스펙 합의가 먼저 고정되면 예제 정의가 안정적으로 따라옵니다.".to_string(),
-            "다음: 스펙 승인 1회, 예제 3세트 확정, 템플릿 버전 태깅.".to_string(),
-            "리스크/블로커: 타입별 예외 케이스 정의가 미완이면 예제 변경이 재발할 수 있음.".to_string(),
-            String::new(),
-            "2. runner multi-worker locking/duplicate prevention: 락 정책 정의 + 최소 통합 테스트 추가"
-                .to_string(),
-            "병렬 레인: A 락 정책 정의 | B 최소 통합 테스트 추가".to_string(),
-            render_lane('A', &[33, 47, 62]),
-            render_lane('B', &[22, 38, 55]),
-            "회고: 락 범위/만료 전략 합의가 길어졌으나, 중복 방지 기준을 명확히 하며 테스트 범위가 좁혀졌습니다."
-                .to_string(),
-            "배운 점: 락 정책을 문서화할 때 재시도/타임아웃을 함께 정의해야 테스트 기준이 흔들리지 않습니다."
-                .to_string(),
-            "다음: 정책 문서 1차 확정, 통합 테스트 1건 최소 패스 기준 설정.".to_string(),
-            "리스크/블로커: 재시도 정책이 확정되지 않으면 테스트 플랜이 무한 대기 시나리오를 놓칠 수 있음."
-                .to_string(),
-            String::new(),
-            "3. E2E validation에 동시성 시나리오 1건 추가".to_string(),
-            "병렬 레인: A 시나리오 설계 | B E2E 추가".to_string(),
-            render_lane('A', &[18, 34, 52]),
-            render_lane('B', &[12, 29, 46]),
-            "회고: 시나리오 조건이 확정되며 추가 구현의 불확실성이 줄어들고 있습니다.".to_string(),
-            "배운 점: 동시성 시나리오는 “성공 기준”과 “실패 허용 기준”을 동시에 적어야 검증이 단단해집니다."
-                .to_string(),
-            "다음: 시나리오 승인, E2E 1건 추가 후 안정성 확인.".to_string(),
-            "리스크/블로커: 러너 락 정책이 확정되기 전에는 E2E 기준이 임시로 남아 있음.".to_string(),
-            String::new(),
-            "원하면 각 레인의 다음 업데이트 시 진행률 숫자를 더 촘촘히 틱업 형태로 표기하겠습니다."
-                .to_string(),
-        ];
-        lines.join("\n")
+    fn render_monitor_timeline(&self) -> String {
+        let panel_width = monitor_panel_width();
+        let chart = self.flow_vector.timeline.render(
+            now_unix_ms(),
+            monitor_progress_bar_width(panel_width),
+            Duration::from_secs(MONITOR_STALL_NO_PROGRESS_WARN_SECS),
+        );
+        // Monospace keeps the Gantt rows aligned in markdown clients.
+        format!(
+            "```text\n{}\n```",
+            monitor_fit_block(&chart, panel_width).join("\n")
+        )
     }
 
     fn render_monitor_message(&self, detail: bool) -> String {
@@ -5437,8 +6123,9 @@ impl<A: Auth> ThreadActor<A> {
         if let Some(estimate) = &self.context_optimization.last_prompt_estimate {
             lines.push(monitor_fit_line(
                 &format!(
-                    "Latest prompt estimate: {} tokens (text={}, embedded={}, links={}, image_assumed={}, audio_assumed={})",
+                    "Latest prompt estimate: {} tokens via {} (text={}, embedded={}, links={}, image={}, audio_assumed={})",
                 estimate.total_tokens,
+                estimate.tokenizer,
                 estimate.text_tokens,
                 estimate.embedded_context_tokens,
                 estimate.resource_link_tokens,
@@ -5540,6 +6227,28 @@ impl<A: Auth> ThreadActor<A> {
         Ok(())
     }
 
+    async fn handle_search_command(&mut self, rest: &str) -> Result<(), Error> {
+        let query = rest.trim().to_string();
+        if query.is_empty() {
+            self.client.send_agent_text("Usage: /search <query>").await;
+            return Ok(());
+        }
+        let (hits, sessions) = tokio::task::spawn_blocking({
+            let query = query.clone();
+            move || {
+                (
+                    SessionStore::search(&query, SESSION_SEARCH_RESULT_LIMIT),
+                    SessionStore::list_all(),
+                )
+            }
+        })
+        .await
+        .map_err(|e| Error::internal_error().data(e.to_string()))?;
+        let message = format_search_results_message(&query, &hits, &sessions);
+        self.client.send_agent_text(message).await;
+        Ok(())
+    }
+
     async fn handle_load_command(&mut self, rest: &str) -> Result<(), Error> {
         let selection = rest.trim();
         if selection.is_empty() {
@@ -5584,13 +6293,59 @@ impl<A: Auth> ThreadActor<A> {
         let (response_tx, response_rx) = oneshot::channel();
 
         self.client
-            .log_canonical("acp.prompt", summarize_prompt_for_log(&request.prompt));
-        let prompt_estimate = estimate_prompt_tokens(&request.prompt);
+            .log_canonical(CanonicalEvent::Prompt(summarize_prompt_for_log(
+                &request.prompt,
+            )));
+        let counter = token_counter::for_model(&self.get_current_model().await);
+        let prompt_estimate = estimate_prompt_tokens(&request.prompt, counter.as_ref());
         self.context_optimization.last_prompt_estimate = Some(prompt_estimate.clone());
         self.client
-            .log_canonical("acp.context_opt.prompt_estimate", prompt_estimate.to_json());
+            .log_canonical(CanonicalEvent::ContextOptPromptEstimate(
+                prompt_estimate.to_log_event(),
+            ));
+
+        if let Some(SubmissionState::Prompt(state)) = self
+            .submissions
+            .values_mut()
+            .find(|submission| submission.has_pending_elicitation())
+        {
+            let answer = request
+                .prompt
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            state
+                .answer_elicitation(&self.client, &answer, response_tx)
+                .await?;
+            return Ok(response_rx);
+        }
+
+        let handoff = context_handoff(&request).map(str::to_string);
+        let mut prompt = request.prompt;
+        let budget_outcome = if starts_with_slash_command(&prompt) {
+            BudgetOutcome::Send
+        } else {
+            self.check_context_budget(&mut prompt, &prompt_estimate, counter.as_ref())
+                .await?
+        };
+        let compact_first = match budget_outcome {
+            BudgetOutcome::Send => None,
+            BudgetOutcome::CompactFirst(budget) => Some(budget),
+            BudgetOutcome::Cancel => {
+                drop(response_tx.send(Ok(StopReason::Cancelled)));
+                return Ok(response_rx);
+            }
+        };
+        // Handed-off context reaches the model but was left out of the logged prompt above.
+        if let Some(handoff) = handoff {
+            prompt.insert(0, ContentBlock::Text(TextContent::new(handoff)));
+        }
 
-        let items = build_prompt_items(request.prompt);
+        let items = build_prompt_items(prompt);
         let op;
         let mut skills_options = SkillsCommandOptions::default();
         if let Some((name, rest)) = extract_slash_command(&items) {
@@ -5659,7 +6414,16 @@ impl<A: Auth> ThreadActor<A> {
                     match mode {
                         MonitorMode::Retrospective => {
                             self.client
-                                .send_agent_text(self.render_monitor_retrospective())
+                                .send_agent_text(
+                                    self.render_monitor_retrospective(RetroLanguage::from_rest(
+                                        rest,
+                                    )),
+                                )
+                                .await;
+                        }
+                        MonitorMode::Timeline => {
+                            self.client
+                                .send_agent_text(self.render_monitor_timeline())
                                 .await;
                         }
                         _ => {
@@ -5791,6 +6555,11 @@ impl<A: Auth> ThreadActor<A> {
                     drop(response_tx.send(Ok(StopReason::EndTurn)));
                     return Ok(response_rx);
                 }
+                "search" => {
+                    self.handle_search_command(rest).await?;
+                    drop(response_tx.send(Ok(StopReason::EndTurn)));
+                    return Ok(response_rx);
+                }
                 "load" => {
                     self.handle_load_command(rest).await?;
                     drop(response_tx.send(Ok(StopReason::EndTurn)));
@@ -5901,6 +6670,18 @@ impl<A: Auth> ThreadActor<A> {
             }
         }
 
+        if let Some(budget) = compact_first {
+            self.compact_before_prompt(budget, op, response_tx).await;
+            return Ok(response_rx);
+        }
+
+        let submission_id = self.submit_prompt_op(&op).await?;
+        self.track_prompt_submission(submission_id, op, response_tx);
+
+        Ok(response_rx)
+    }
+
+    async fn submit_prompt_op(&mut self, op: &Op) -> Result<String, Error> {
         let submission_id = self
             .thread
             .submit(op.clone())
@@ -5910,14 +6691,20 @@ impl<A: Auth> ThreadActor<A> {
         info!("Submitted prompt with submission_id: {submission_id}");
         info!("Starting to wait for conversation events for submission_id: {submission_id}");
 
-        self.client.log_canonical(
-            "backend.codex.submit",
-            json!({
-                "submission_id": submission_id,
-                "op_kind": op_kind_for_log(&op),
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::CodexSubmit(CodexSubmit {
+                submission_id: submission_id.clone(),
+                op_kind: op_kind_for_log(op).to_string(),
+            }));
+        Ok(submission_id)
+    }
 
+    fn track_prompt_submission(
+        &mut self,
+        submission_id: String,
+        op: Op,
+        response_tx: oneshot::Sender<Result<StopReason, Error>>,
+    ) {
         let state = match op {
             Op::Compact | Op::Undo => SubmissionState::Task(TaskState::new(
                 self.thread.clone(),
@@ -5940,33 +6727,282 @@ impl<A: Auth> ThreadActor<A> {
         };
 
         self.submissions.insert(submission_id, state);
-
-        Ok(response_rx)
     }
 
-    async fn handle_set_mode(&mut self, mode: SessionModeId) -> Result<(), Error> {
-        let preset = APPROVAL_PRESETS
-            .iter()
-            .find(|preset| mode.0.as_ref() == preset.id)
-            .ok_or_else(Error::invalid_params)?;
-
-        self.thread
-            .submit(Op::OverrideTurnContext {
-                cwd: None,
-                approval_policy: Some(preset.approval),
-                sandbox_policy: Some(preset.sandbox.clone()),
-                model: None,
-                effort: None,
-                summary: None,
-                collaboration_mode: None,
-                personality: None,
-            })
-            .await
-            .map_err(|e| Error::from(anyhow::anyhow!(e)))?;
+    /// Predictive context budgeting: compare the usage reported after the last turn plus this
+    /// prompt with the auto-compact threshold before anything is submitted.
+    async fn check_context_budget(
+        &mut self,
+        prompt: &mut [ContentBlock],
+        estimate: &PromptTokenEstimate,
+        counter: &dyn TokenCounter,
+    ) -> Result<BudgetOutcome, Error> {
+        if matches!(self.context_optimization.mode, ContextOptimizationMode::Off) {
+            return Ok(BudgetOutcome::Send);
+        }
+        let Some(budget) = self
+            .context_optimization
+            .project_budget(estimate.total_tokens, self.config.model_context_window)
+        else {
+            return Ok(BudgetOutcome::Send);
+        };
+        let trigger_percent = self.context_optimization.trigger_percent;
+        let excess = budget.excess_tokens(trigger_percent);
+        if excess <= 0 {
+            return Ok(BudgetOutcome::Send);
+        }
 
-        self.config
-            .approval_policy
-            .set(preset.approval)
+        // Compaction only helps when the prompt fits under the threshold on its own.
+        let threshold = budget.context_window * trigger_percent / 100;
+        let compaction_helps = budget.current_tokens > 0 && budget.prompt_tokens < threshold;
+        if matches!(
+            self.context_optimization.mode,
+            ContextOptimizationMode::Auto
+        ) && compaction_helps
+        {
+            return Ok(BudgetOutcome::CompactFirst(budget));
+        }
+
+        // Asking only helps when this prompt is what crosses the trigger and it has embedded
+        // files to shed; otherwise the answer could only be "send" or "cancel".
+        let crosses_trigger = budget.current_tokens < threshold;
+        let can_shed = !embedded_resources_by_size(prompt, counter).is_empty();
+        if !(crosses_trigger && can_shed) {
+            warn!(
+                "Prompt projected to {}% of the {}-token context window, above the {trigger_percent}% auto-compact trigger; sending it as is",
+                budget.projected_percent(),
+                budget.context_window,
+            );
+            self.client.log_canonical(CanonicalEvent::ContextOptBudget(
+                self.context_optimization.budget_decision(&budget, "send"),
+            ));
+            return Ok(BudgetOutcome::Send);
+        }
+        let choice = self.confirm_context_budget(&budget).await?;
+        let mut decision = self.context_optimization.budget_decision(&budget, choice);
+        let outcome = match choice {
+            "trim" | "drop" => {
+                decision.resources =
+                    shed_embedded_context(prompt, excess, choice == "drop", counter);
+                let files = decision
+                    .resources
+                    .iter()
+                    .map(|uri| format!("`{uri}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.client
+                    .send_agent_text(if choice == "drop" {
+                        format!("Sending {files} as links to fit the context budget.")
+                    } else {
+                        format!("Trimmed {files} to fit the context budget.")
+                    })
+                    .await;
+                BudgetOutcome::Send
+            }
+            "send" => BudgetOutcome::Send,
+            _ => BudgetOutcome::Cancel,
+        };
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptBudget(decision));
+        Ok(outcome)
+    }
+
+    /// Ask whether a prompt projected past the auto-compact threshold is sent as is, with its
+    /// largest embedded resources shrunk, or not at all.
+    async fn confirm_context_budget(&self, budget: &ContextBudget) -> Result<&'static str, Error> {
+        let message = format!(
+            "This prompt adds ~{} tokens to the {} already in context: {}% of the {}-token window, above the {}% auto-compact trigger.",
+            budget.prompt_tokens,
+            budget.current_tokens,
+            budget.projected_percent(),
+            budget.context_window,
+            self.context_optimization.trigger_percent,
+        );
+        let options = vec![
+            PermissionOption::new("send", "Send as is", PermissionOptionKind::AllowOnce),
+            PermissionOption::new(
+                "trim",
+                "Trim the largest embedded files",
+                PermissionOptionKind::AllowOnce,
+            ),
+            PermissionOption::new(
+                "drop",
+                "Send the largest embedded files as links",
+                PermissionOptionKind::AllowOnce,
+            ),
+            PermissionOption::new("cancel", "Don't send", PermissionOptionKind::RejectOnce),
+        ];
+
+        let response = self
+            .client
+            .request_permission(
+                ToolCallUpdate::new(
+                    ToolCallId::new(format!("context-budget-{}", now_unix_ms())),
+                    ToolCallUpdateFields::new()
+                        .title("Prompt may overflow the context window")
+                        .status(ToolCallStatus::Pending)
+                        .content(vec![message.into()]),
+                ),
+                options,
+            )
+            .await?;
+
+        Ok(match response.outcome {
+            RequestPermissionOutcome::Selected(SelectedPermissionOutcome { option_id, .. }) => {
+                match option_id.0.as_ref() {
+                    "send" => "send",
+                    "trim" => "trim",
+                    "drop" => "drop",
+                    _ => "cancel",
+                }
+            }
+            RequestPermissionOutcome::Cancelled | _ => "cancel",
+        })
+    }
+
+    /// Submit a compaction, or reuse the automatic one already running, and hold `op` until it
+    /// finishes.
+    async fn compact_before_prompt(
+        &mut self,
+        budget: ContextBudget,
+        op: Op,
+        response_tx: oneshot::Sender<Result<StopReason, Error>>,
+    ) {
+        if let Some(previous) = self.deferred_prompt.take() {
+            drop(previous.response_tx.send(Ok(StopReason::Cancelled)));
+        }
+
+        let compact_submission_id =
+            match self.context_optimization.auto_compact_submission_id.clone() {
+                Some(running) => running,
+                None => match self.thread.submit(Op::Compact).await {
+                    Ok(compact_submission_id) => {
+                        self.context_optimization.auto_compact_submission_id =
+                            Some(compact_submission_id.clone());
+                        self.context_optimization.auto_compact_count += 1;
+                        self.context_optimization.pending_auto_compact = None;
+                        self.submissions.insert(
+                            compact_submission_id.clone(),
+                            SubmissionState::Task(TaskState::new_background(
+                                self.thread.clone(),
+                                compact_submission_id.clone(),
+                            )),
+                        );
+                        compact_submission_id
+                    }
+                    Err(err) => {
+                        warn!("failed to compact before prompt: {err}");
+                        self.client.log_canonical(CanonicalEvent::ContextOptBudget(
+                            self.context_optimization.budget_decision(&budget, "send"),
+                        ));
+                        match self.submit_prompt_op(&op).await {
+                            Ok(submission_id) => {
+                                self.track_prompt_submission(submission_id, op, response_tx);
+                            }
+                            Err(err) => drop(response_tx.send(Err(err))),
+                        }
+                        return;
+                    }
+                },
+            };
+
+        self.flow_vector.record_phase(
+            'C',
+            format!(
+                "compact before prompt at {}% projected usage",
+                budget.projected_percent()
+            ),
+        );
+        let mut decision = self
+            .context_optimization
+            .budget_decision(&budget, "compact_first");
+        decision.compact_submission_id = Some(compact_submission_id.clone());
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptBudget(decision));
+        self.client
+            .send_agent_text(format!(
+                "This prompt would fill ~{}% of the context window; compacting the conversation before sending it.",
+                budget.projected_percent()
+            ))
+            .await;
+        self.deferred_prompt = Some(DeferredPrompt {
+            compact_submission_id,
+            op,
+            response_tx,
+            budget,
+        });
+    }
+
+    /// Send the prompt held for compaction `submission_id` once it ends, or end the prompt too
+    /// when the compaction was aborted.
+    async fn release_deferred_prompt(&mut self, submission_id: &str, aborted: bool) {
+        if self
+            .deferred_prompt
+            .as_ref()
+            .is_none_or(|deferred| deferred.compact_submission_id != submission_id)
+        {
+            return;
+        }
+        let Some(DeferredPrompt {
+            op,
+            response_tx,
+            budget,
+            ..
+        }) = self.deferred_prompt.take()
+        else {
+            return;
+        };
+
+        // Re-project against the usage reported by the compaction, when there is one.
+        let budget = self
+            .context_optimization
+            .project_budget(budget.prompt_tokens, Some(budget.context_window))
+            .unwrap_or(budget);
+        let mut decision = self.context_optimization.budget_decision(
+            &budget,
+            if aborted {
+                "cancel"
+            } else {
+                "submit_after_compact"
+            },
+        );
+        decision.compact_submission_id = Some(submission_id.to_string());
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptBudget(decision));
+
+        if aborted {
+            drop(response_tx.send(Ok(StopReason::Cancelled)));
+            return;
+        }
+        match self.submit_prompt_op(&op).await {
+            Ok(submission_id) => self.track_prompt_submission(submission_id, op, response_tx),
+            Err(err) => drop(response_tx.send(Err(err))),
+        }
+    }
+
+    async fn handle_set_mode(&mut self, mode: SessionModeId) -> Result<(), Error> {
+        let preset = APPROVAL_PRESETS
+            .iter()
+            .find(|preset| mode.0.as_ref() == preset.id)
+            .ok_or_else(Error::invalid_params)?;
+
+        self.thread
+            .submit(Op::OverrideTurnContext {
+                cwd: None,
+                approval_policy: Some(preset.approval),
+                sandbox_policy: Some(preset.sandbox.clone()),
+                model: None,
+                effort: None,
+                summary: None,
+                collaboration_mode: None,
+                personality: None,
+            })
+            .await
+            .map_err(|e| Error::from(anyhow::anyhow!(e)))?;
+
+        self.config
+            .approval_policy
+            .set(preset.approval)
             .map_err(|e| Error::from(anyhow::anyhow!(e)))?;
         self.config
             .sandbox_policy
@@ -6398,17 +7434,15 @@ impl<A: Auth> ThreadActor<A> {
             }
         });
 
-        self.client.log_canonical(
-            "acp.context_opt.token_usage",
-            json!({
-                "submission_id": submission_id,
-                "total_tokens": total_tokens,
-                "context_window": context_window,
-                "used_percent": used_percent,
-                "mode": self.context_optimization.mode.as_config_value(),
-                "trigger_percent": self.context_optimization.trigger_percent,
-            }),
-        );
+        self.client
+            .log_canonical(CanonicalEvent::ContextOptTokenUsage(TokenUsage {
+                submission_id: submission_id.to_string(),
+                total_tokens,
+                context_window,
+                used_percent,
+                mode: self.context_optimization.mode.as_config_value().to_string(),
+                trigger_percent: self.context_optimization.trigger_percent,
+            }));
 
         let should_stage_auto_compact = matches!(
             self.context_optimization.mode,
@@ -6438,10 +7472,12 @@ impl<A: Auth> ThreadActor<A> {
             == Some(submission_id)
         {
             self.context_optimization.auto_compact_submission_id = None;
-            self.client.log_canonical(
-                "acp.context_opt.auto_compact_completed",
-                json!({ "submission_id": submission_id }),
-            );
+            self.client
+                .log_canonical(CanonicalEvent::ContextOptAutoCompactCompleted(
+                    SubmissionRef {
+                        submission_id: submission_id.to_string(),
+                    },
+                ));
             self.flow_vector
                 .record_phase('C', "auto compaction completed");
             return;
@@ -6481,16 +7517,16 @@ impl<A: Auth> ThreadActor<A> {
                         pending.used_percent.unwrap_or_default()
                     ),
                 );
-                self.client.log_canonical(
-                    "acp.context_opt.auto_compact_triggered",
-                    json!({
-                        "source_submission_id": submission_id,
-                        "compact_submission_id": auto_submission_id,
-                        "total_tokens": pending.total_tokens,
-                        "context_window": pending.context_window,
-                        "used_percent": pending.used_percent,
-                    }),
-                );
+                self.client
+                    .log_canonical(CanonicalEvent::ContextOptAutoCompactTriggered(
+                        AutoCompactTriggered {
+                            source_submission_id: submission_id.to_string(),
+                            compact_submission_id: auto_submission_id,
+                            total_tokens: pending.total_tokens,
+                            context_window: pending.context_window,
+                            used_percent: pending.used_percent,
+                        },
+                    ));
 
                 let auto_submission_id = self
                     .context_optimization
@@ -6507,22 +7543,29 @@ impl<A: Auth> ThreadActor<A> {
             }
             Err(err) => {
                 warn!("failed to trigger automatic compact: {err}");
-                self.client.log_canonical(
-                    "acp.context_opt.auto_compact_error",
-                    json!({
-                        "source_submission_id": submission_id,
-                        "error": err.to_string(),
-                    }),
-                );
+                self.client
+                    .log_canonical(CanonicalEvent::ContextOptAutoCompactError(
+                        AutoCompactError {
+                            source_submission_id: submission_id.to_string(),
+                            error: err.to_string(),
+                        },
+                    ));
             }
         }
     }
 
+    /// Feed a plan update to the flow monitor and log the step transitions it caused.
+    fn record_plan_update(&mut self, explanation: Option<String>, plan: &[PlanItemArg]) {
+        for transition in self.flow_vector.record_plan_update(explanation, plan) {
+            self.client
+                .log_canonical(CanonicalEvent::PlanStepTransition(transition));
+        }
+    }
+
     fn observe_flow_event(&mut self, msg: &EventMsg) {
         match msg {
             EventMsg::PlanUpdate(UpdatePlanArgs { explanation, plan }) => {
-                self.flow_vector
-                    .record_plan_update(explanation.clone(), plan.as_slice());
+                self.record_plan_update(explanation.clone(), plan.as_slice());
             }
             EventMsg::ExecCommandBegin(event) => self
                 .flow_vector
@@ -6563,6 +7606,7 @@ impl<A: Auth> ThreadActor<A> {
         match &msg {
             EventMsg::TurnComplete(_) => {
                 self.maybe_trigger_auto_compact_after_turn(&id).await;
+                self.release_deferred_prompt(&id, false).await;
             }
             EventMsg::TurnAborted(_) | EventMsg::Error(_) | EventMsg::StreamError(_) => {
                 if self
@@ -6573,6 +7617,12 @@ impl<A: Auth> ThreadActor<A> {
                 {
                     self.context_optimization.auto_compact_submission_id = None;
                 }
+                // A failed compaction still lets the prompt through; a cancelled one ends it.
+                match &msg {
+                    EventMsg::TurnAborted(_) => self.release_deferred_prompt(&id, true).await,
+                    EventMsg::Error(_) => self.release_deferred_prompt(&id, false).await,
+                    _ => {}
+                }
             }
             _ => {}
         }
@@ -6615,22 +7665,23 @@ fn build_prompt_items(prompt: Vec<ContentBlock>) -> Vec<UserInput> {
         .collect()
 }
 
-fn estimate_prompt_tokens(prompt: &[ContentBlock]) -> PromptTokenEstimate {
-    fn estimate_text_tokens(text: &str) -> i64 {
-        // Coarse heuristic for monitoring only: ~4 chars/token.
-        ((text.chars().count() as f64) / 4.0).ceil() as i64
-    }
-
-    let mut estimate = PromptTokenEstimate::default();
+fn estimate_prompt_tokens(
+    prompt: &[ContentBlock],
+    counter: &dyn TokenCounter,
+) -> PromptTokenEstimate {
+    let mut estimate = PromptTokenEstimate {
+        tokenizer: counter.name().to_string(),
+        ..PromptTokenEstimate::default()
+    };
     for block in prompt {
         match block {
             ContentBlock::Text(text_block) => {
-                estimate.text_tokens += estimate_text_tokens(&text_block.text);
+                estimate.text_tokens += counter.count(&text_block.text);
             }
             ContentBlock::ResourceLink(ResourceLink { name, uri, .. }) => {
                 let mut token_guess = 12_i64;
-                token_guess += estimate_text_tokens(uri);
-                token_guess += estimate_text_tokens(name);
+                token_guess += counter.count(uri);
+                token_guess += counter.count(name);
                 estimate.resource_link_tokens += token_guess;
             }
             ContentBlock::Resource(EmbeddedResource {
@@ -6642,11 +7693,17 @@ fn estimate_prompt_tokens(prompt: &[ContentBlock]) -> PromptTokenEstimate {
                     }),
                 ..
             }) => {
-                estimate.embedded_context_tokens += estimate_text_tokens(text);
-                estimate.resource_link_tokens += estimate_text_tokens(uri);
-            }
-            // Model-dependent assumptions for visibility only.
-            ContentBlock::Image(_) => estimate.image_tokens += 1024,
+                estimate.embedded_context_tokens += counter.count(text);
+                estimate.resource_link_tokens += counter.count(uri);
+            }
+            ContentBlock::Image(image) => match image_dimensions(&image.data) {
+                Some((width, height)) => estimate.image_tokens += image_tokens(width, height),
+                None => {
+                    estimate.images_assumed += 1;
+                    estimate.image_tokens += IMAGE_TOKENS_FALLBACK;
+                }
+            },
+            // Model-dependent assumption for visibility only.
             ContentBlock::Audio(_) => estimate.audio_tokens += 2048,
             _ => {}
         }
@@ -6660,7 +7717,89 @@ fn estimate_prompt_tokens(prompt: &[ContentBlock]) -> PromptTokenEstimate {
     estimate
 }
 
-fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> serde_json::Value {
+fn starts_with_slash_command(prompt: &[ContentBlock]) -> bool {
+    matches!(prompt.first(), Some(ContentBlock::Text(text)) if parse_slash_name(&text.text).is_some())
+}
+
+/// Embedded text resources of a prompt, largest first, as `(block index, tokens)`.
+fn embedded_resources_by_size(
+    prompt: &[ContentBlock],
+    counter: &dyn TokenCounter,
+) -> Vec<(usize, i64)> {
+    let mut resources = prompt
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| match block {
+            ContentBlock::Resource(EmbeddedResource {
+                resource: EmbeddedResourceResource::TextResourceContents(contents),
+                ..
+            }) => Some((index, counter.count(&contents.text))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    resources.sort_by_key(|(_, tokens)| std::cmp::Reverse(*tokens));
+    resources
+}
+
+/// Shrink the largest embedded text resources until `excess` tokens are saved. With `as_links`
+/// they are replaced by resource links; otherwise each keeps its leading lines, and only
+/// resources that would lose everything become links. Returns the URIs that changed.
+fn shed_embedded_context(
+    prompt: &mut [ContentBlock],
+    mut excess: i64,
+    as_links: bool,
+    counter: &dyn TokenCounter,
+) -> Vec<String> {
+    let mut changed = Vec::new();
+    for (index, tokens) in embedded_resources_by_size(prompt, counter) {
+        if excess <= 0 {
+            break;
+        }
+        let ContentBlock::Resource(EmbeddedResource {
+            resource: EmbeddedResourceResource::TextResourceContents(contents),
+            ..
+        }) = &mut prompt[index]
+        else {
+            continue;
+        };
+        let uri = contents.uri.clone();
+        changed.push(uri.clone());
+
+        // The marker names at most every line of the resource, plus a newline ahead of it.
+        let marker_tokens =
+            counter.count(&format!("\n{}", trim_marker(contents.text.lines().count())));
+        let keep_tokens = tokens - excess - marker_tokens;
+        if as_links || keep_tokens <= 0 {
+            let name = uri.rsplit('/').next().unwrap_or(&uri).to_string();
+            prompt[index] = ContentBlock::ResourceLink(ResourceLink::new(name, uri));
+            excess -= tokens;
+            continue;
+        }
+
+        let text = &contents.text;
+        let keep_chars = (text.chars().count() as i64 * keep_tokens / tokens) as usize;
+        let end = text
+            .char_indices()
+            .nth(keep_chars)
+            .map_or(text.len(), |(offset, _)| offset);
+        let end = text[..end].rfind('\n').map_or(end, |newline| newline + 1);
+        let trimmed_lines = text[end..].lines().count();
+        let mut kept = text[..end].to_string();
+        if !kept.ends_with('\n') {
+            kept.push('\n');
+        }
+        kept.push_str(&trim_marker(trimmed_lines));
+        excess -= tokens - counter.count(&kept);
+        contents.text = kept;
+    }
+    changed
+}
+
+fn trim_marker(trimmed_lines: usize) -> String {
+    format!("[... {trimmed_lines} more lines trimmed to fit the context budget]")
+}
+
+fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> PromptSummary {
     let log_embedded_context = std::env::var("ACP_LOG_EMBEDDED_CONTEXT")
         .ok()
         .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
@@ -6670,8 +7809,8 @@ fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> serde_json::Value {
         .unwrap_or(16_384);
 
     let mut text_blocks: Vec<String> = Vec::new();
-    let mut resource_links: Vec<serde_json::Value> = Vec::new();
-    let mut embedded_text_resources: Vec<serde_json::Value> = Vec::new();
+    let mut resource_links: Vec<ResourceLinkSummary> = Vec::new();
+    let mut embedded_text_resources: Vec<EmbeddedTextSummary> = Vec::new();
 
     let mut image_count = 0usize;
     let mut audio_count = 0usize;
@@ -6681,7 +7820,10 @@ fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> serde_json::Value {
         match block {
             ContentBlock::Text(t) => text_blocks.push(t.text.clone()),
             ContentBlock::ResourceLink(ResourceLink { name, uri, .. }) => {
-                resource_links.push(json!({ "name": name, "uri": uri }));
+                resource_links.push(ResourceLinkSummary {
+                    name: name.clone(),
+                    uri: uri.clone(),
+                });
             }
             ContentBlock::Resource(EmbeddedResource {
                 resource:
@@ -6693,18 +7835,19 @@ fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> serde_json::Value {
                 ..
             }) => {
                 // Avoid duplicating large / sensitive context by default.
-                let mut item = json!({
-                    "uri": uri,
-                    "text_len": text.len(),
-                    "included": log_embedded_context,
-                });
+                let mut item = EmbeddedTextSummary {
+                    uri: uri.clone(),
+                    text_len: text.len(),
+                    included: log_embedded_context,
+                    text: None,
+                };
 
                 if log_embedded_context {
                     let mut s = text.clone();
                     if s.chars().count() > max_text_chars {
                         s = s.chars().take(max_text_chars).collect::<String>() + "\n...[truncated]";
                     }
-                    item["text"] = serde_json::Value::String(s);
+                    item.text = Some(s);
                 }
                 embedded_text_resources.push(item);
             }
@@ -6719,15 +7862,23 @@ fn summarize_prompt_for_log(prompt: &[ContentBlock]) -> serde_json::Value {
         text = text.chars().take(max_text_chars).collect::<String>() + "\n...[truncated]";
     }
 
-    json!({
-        "block_count": prompt.len(),
-        "text": text,
-        "resource_links": resource_links,
-        "embedded_text_resources": embedded_text_resources,
-        "image_count": image_count,
-        "audio_count": audio_count,
-        "other_count": other_count,
-    })
+    PromptSummary {
+        text,
+        block_count: prompt.len(),
+        resource_links,
+        embedded_text_resources,
+        image_count,
+        audio_count,
+        other_count,
+    }
+}
+
+/// ACP wire name of a tool call status (`in_progress`, `completed`, ...).
+fn tool_status_name(status: ToolCallStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{status:?}").to_snake_case())
 }
 
 fn op_kind_for_log(op: &Op) -> &'static str {
@@ -7007,6 +8158,52 @@ fn format_session_list_message(cwd: &Path, sessions: &[SessionListEntry]) -> Str
     lines.join("\n")
 }
 
+fn format_search_results_message(
+    query: &str,
+    hits: &[SearchHit],
+    sessions: &[StoredSession],
+) -> String {
+    if hits.is_empty() {
+        return format!("No past session matches `{query}`.");
+    }
+
+    let mut lines = Vec::with_capacity(hits.len() * 2 + 2);
+    lines.push(format!("Sessions matching `{query}`:"));
+    for (index, hit) in hits.iter().enumerate() {
+        let when = chrono::DateTime::from_timestamp_millis(hit.ts_ms as i64).map_or_else(
+            || hit.ts_ms.to_string(),
+            |ts| {
+                ts.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            },
+        );
+        let session = sessions
+            .iter()
+            .find(|session| session.global_session_id == hit.global_session_id);
+        let mut header = format!(
+            "{}) [id: {}] {when} {}",
+            index + 1,
+            hit.global_session_id,
+            hit.label
+        );
+        if let Some(session) = session {
+            header.push_str(&format!(" ({}", session.backend));
+            if let Some(cwd) = session.cwd.as_ref() {
+                header.push_str(&format!(", {}", cwd.display()));
+            }
+            header.push(')');
+        }
+        lines.push(header);
+        lines.push(format!("   {}", hit.snippet));
+    }
+    lines.push(
+        "Use `xsfire-camp log show <id>` to read a session, or open it from the client's thread list."
+            .to_string(),
+    );
+    lines.join("\n")
+}
+
 fn format_mcp_tools_message(event: &codex_core::protocol::McpListToolsResponseEvent) -> String {
     let mut tool_names = event.tools.keys().cloned().collect::<Vec<_>>();
     tool_names.sort();
@@ -7330,6 +8527,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_search_results_message() {
+        assert_eq!(
+            format_search_results_message("retry", &[], &[]),
+            "No past session matches `retry`."
+        );
+
+        let hits = vec![SearchHit {
+            global_session_id: "abc".to_string(),
+            ts_ms: 0,
+            label: "agent".to_string(),
+            snippet: "The retry budget is shared.".to_string(),
+            matched_terms: 1,
+            score: 1.0,
+        }];
+        let sessions = vec![StoredSession {
+            global_session_id: "abc".to_string(),
+            backend: "claude-code".to_string(),
+            acp_session_id: "claude:abc".to_string(),
+            cwd: Some(PathBuf::from("/tmp/project")),
+            created_at_ms: 0,
+        }];
+        let message = format_search_results_message("retry", &hits, &sessions);
+        let lines = message.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Sessions matching `retry`:");
+        assert!(lines[1].starts_with("1) [id: abc] "), "{message}");
+        assert!(
+            lines[1].ends_with(" agent (claude-code, /tmp/project)"),
+            "{message}"
+        );
+        assert_eq!(lines[2], "   The retry budget is shared.");
+    }
+
     #[test]
     fn test_decode_utf8_streaming_preserves_split_multibyte_sequence() {
         let mut pending = Vec::new();
@@ -7589,52 +8819,233 @@ mod tests {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_compact() -> anyhow::Result<()> {
-        let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
-        let (prompt_response_tx, prompt_response_rx) = tokio::sync::oneshot::channel();
+    fn embedded_text(uri: &str, text: String) -> ContentBlock {
+        ContentBlock::Resource(EmbeddedResource::new(
+            EmbeddedResourceResource::TextResourceContents(TextResourceContents::new(text, uri)),
+        ))
+    }
 
-        message_tx.send(ThreadMessage::Prompt {
-            request: PromptRequest::new(session_id.clone(), vec!["/compact".into()]),
-            response_tx: prompt_response_tx,
-        })?;
+    #[test]
+    fn test_shed_embedded_context_trims_largest_resources_first() {
+        // 40 lines of 10 chars is 100 heuristic tokens; the small file is 10.
+        let large = "123456789\n".repeat(40);
+        let small = "x".repeat(40);
+        let prompt = vec![
+            "review these".into(),
+            embedded_text("file:///repo/large.rs", large.clone()),
+            embedded_text("file:///repo/small.rs", small),
+        ];
 
-        tokio::try_join!(
-            async {
-                let stop_reason = prompt_response_rx.await??.await??;
-                assert_eq!(stop_reason, StopReason::EndTurn);
-                drop(message_tx);
-                anyhow::Ok(())
-            },
-            async {
-                local_set.await;
-                anyhow::Ok(())
-            }
-        )?;
+        // Trimming large.rs alone saves the 50 tokens, marker included.
+        let mut trimmed = prompt.clone();
+        let changed = shed_embedded_context(&mut trimmed, 50, false, &HeuristicCounter);
+        assert_eq!(changed, vec!["file:///repo/large.rs"]);
+        let ContentBlock::Resource(EmbeddedResource {
+            resource: EmbeddedResourceResource::TextResourceContents(contents),
+            ..
+        }) = &trimmed[1]
+        else {
+            panic!("large.rs should stay embedded");
+        };
+        assert_eq!(
+            contents.text,
+            format!(
+                "{}[... 26 more lines trimmed to fit the context budget]",
+                "123456789\n".repeat(14)
+            )
+        );
+        assert!(HeuristicCounter.count(&contents.text) <= 50);
+        assert!(matches!(&trimmed[2], ContentBlock::Resource(_)));
 
-        let notifications = client.notifications.lock().unwrap();
-        assert_eq!(notifications.len(), 1);
+        let mut linked = prompt;
+        let changed = shed_embedded_context(&mut linked, 50, true, &HeuristicCounter);
+        assert_eq!(changed, vec!["file:///repo/large.rs"]);
         assert!(matches!(
-            &notifications[0].update,
-            SessionUpdate::AgentMessageChunk(ContentChunk {
-                content: ContentBlock::Text(TextContent { text, .. }),
-                ..
-            }) if text == "Compact task completed"
+            &linked[1],
+            ContentBlock::ResourceLink(link)
+                if link.name == "large.rs" && link.uri == "file:///repo/large.rs"
         ));
-        let ops = thread.ops.lock().unwrap();
-        assert_eq!(ops.as_slice(), &[Op::Compact]);
-
-        Ok(())
+        assert!(matches!(&linked[2], ContentBlock::Resource(_)));
     }
 
     #[tokio::test]
-    async fn test_undo() -> anyhow::Result<()> {
-        let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
-        let (prompt_response_tx, prompt_response_rx) = tokio::sync::oneshot::channel();
+    async fn test_prompt_over_budget_compacts_first_in_auto_mode() -> anyhow::Result<()> {
+        let client = Arc::new(StubClient::new());
+        let thread = Arc::new(StubCodexThread::new());
+        let session_client =
+            SessionClient::with_client(SessionId::new("test"), client, Arc::default(), None);
+        let config = Config::load_with_cli_overrides_and_harness_overrides(
+            vec![],
+            ConfigOverrides::default(),
+        )
+        .await?;
+        let (_message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut actor = ThreadActor::new(
+            StubAuth,
+            session_client,
+            thread.clone(),
+            Arc::new(StubModelsManager),
+            config,
+            message_rx,
+        );
+        actor.context_optimization.mode = ContextOptimizationMode::Auto;
+        actor.context_optimization.trigger_percent = 90;
+        actor.context_optimization.last_token_info = Some(codex_core::protocol::TokenUsageInfo {
+            total_token_usage: codex_core::protocol::TokenUsage {
+                total_tokens: 80_000,
+                ..Default::default()
+            },
+            last_token_usage: codex_core::protocol::TokenUsage::default(),
+            model_context_window: Some(100_000),
+        });
 
-        message_tx.send(ThreadMessage::Prompt {
-            request: PromptRequest::new(session_id.clone(), vec!["/undo".into()]),
-            response_tx: prompt_response_tx,
+        // ~20k tokens on top of 80k crosses the 90% trigger, but fits on its own.
+        let prompt = "word ".repeat(16_000);
+        let response_rx = actor
+            .handle_prompt(PromptRequest::new(
+                SessionId::new("test"),
+                vec![prompt.as_str().into()],
+            ))
+            .await?;
+        assert_eq!(thread.ops.lock().unwrap().as_slice(), &[Op::Compact]);
+        let compact_submission_id = actor
+            .deferred_prompt
+            .as_ref()
+            .map(|deferred| deferred.compact_submission_id.clone())
+            .unwrap();
+
+        // The prompt is held until the compaction's turn completes.
+        let released_by_compaction = loop {
+            let event = actor.thread.next_event().await?;
+            let completes_compaction =
+                event.id == compact_submission_id && matches!(event.msg, EventMsg::TurnComplete(_));
+            actor.handle_event(event).await;
+            if actor.deferred_prompt.is_none() {
+                break completes_compaction;
+            }
+        };
+        assert!(released_by_compaction);
+        let prompt_submission_id = match thread.ops.lock().unwrap().as_slice() {
+            [Op::Compact, Op::UserInput { .. }] => actor
+                .submissions
+                .keys()
+                .find(|id| **id != compact_submission_id)
+                .cloned()
+                .unwrap(),
+            ops => panic!("unexpected ops {ops:?}"),
+        };
+
+        // Then the released prompt runs its own turn.
+        loop {
+            let event = actor.thread.next_event().await?;
+            let done =
+                event.id == prompt_submission_id && matches!(event.msg, EventMsg::TurnComplete(_));
+            actor.handle_event(event).await;
+            if done {
+                break;
+            }
+        }
+        assert_eq!(response_rx.await??, StopReason::EndTurn);
+        assert_eq!(actor.context_optimization.auto_compact_count, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompt_over_budget_without_files_to_shed_is_sent() -> anyhow::Result<()> {
+        let client = Arc::new(StubClient::new());
+        let thread = Arc::new(StubCodexThread::new());
+        let session_client =
+            SessionClient::with_client(SessionId::new("test"), client, Arc::default(), None);
+        let config = Config::load_with_cli_overrides_and_harness_overrides(
+            vec![],
+            ConfigOverrides::default(),
+        )
+        .await?;
+        let (_message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut actor = ThreadActor::new(
+            StubAuth,
+            session_client,
+            thread.clone(),
+            Arc::new(StubModelsManager),
+            config,
+            message_rx,
+        );
+        actor.context_optimization.mode = ContextOptimizationMode::Monitor;
+        actor.context_optimization.trigger_percent = 90;
+        actor.context_optimization.last_token_info = Some(codex_core::protocol::TokenUsageInfo {
+            total_token_usage: codex_core::protocol::TokenUsage {
+                total_tokens: 80_000,
+                ..Default::default()
+            },
+            last_token_usage: codex_core::protocol::TokenUsage::default(),
+            model_context_window: Some(100_000),
+        });
+
+        // The stub answers permission requests with an unknown option, which would cancel the
+        // prompt; with no embedded files there is nothing to ask about.
+        let prompt = "word ".repeat(16_000);
+        actor
+            .handle_prompt(PromptRequest::new(
+                SessionId::new("test"),
+                vec![prompt.as_str().into()],
+            ))
+            .await?;
+        assert!(matches!(
+            thread.ops.lock().unwrap().as_slice(),
+            [Op::UserInput { .. }]
+        ));
+        assert!(actor.deferred_prompt.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compact() -> anyhow::Result<()> {
+        let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
+        let (prompt_response_tx, prompt_response_rx) = tokio::sync::oneshot::channel();
+
+        message_tx.send(ThreadMessage::Prompt {
+            request: PromptRequest::new(session_id.clone(), vec!["/compact".into()]),
+            response_tx: prompt_response_tx,
+        })?;
+
+        tokio::try_join!(
+            async {
+                let stop_reason = prompt_response_rx.await??.await??;
+                assert_eq!(stop_reason, StopReason::EndTurn);
+                drop(message_tx);
+                anyhow::Ok(())
+            },
+            async {
+                local_set.await;
+                anyhow::Ok(())
+            }
+        )?;
+
+        let notifications = client.notifications.lock().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert!(matches!(
+            &notifications[0].update,
+            SessionUpdate::AgentMessageChunk(ContentChunk {
+                content: ContentBlock::Text(TextContent { text, .. }),
+                ..
+            }) if text == "Compact task completed"
+        ));
+        let ops = thread.ops.lock().unwrap();
+        assert_eq!(ops.as_slice(), &[Op::Compact]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo() -> anyhow::Result<()> {
+        let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
+        let (prompt_response_tx, prompt_response_rx) = tokio::sync::oneshot::channel();
+
+        message_tx.send(ThreadMessage::Prompt {
+            request: PromptRequest::new(session_id.clone(), vec!["/undo".into()]),
+            response_tx: prompt_response_tx,
         })?;
 
         tokio::try_join!(
@@ -8218,6 +9629,286 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_elicitation_form_is_answered_in_next_prompt() -> anyhow::Result<()> {
+        let session_id = SessionId::new("elicitation-test");
+        let client = Arc::new(StubClient::new());
+        let thread = Arc::new(StubCodexThread::new());
+        let session_client =
+            SessionClient::with_client(session_id.clone(), client.clone(), Arc::default(), None);
+        let models_manager = Arc::new(StubModelsManager);
+        let config = Config::load_with_cli_overrides_and_harness_overrides(
+            vec![],
+            ConfigOverrides::default(),
+        )
+        .await?;
+        let (_message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut actor = ThreadActor::new(
+            StubAuth,
+            session_client,
+            thread.clone(),
+            models_manager,
+            config,
+            message_rx,
+        );
+        actor.task_monitoring.preempt_on_new_prompt = true;
+
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        actor.submissions.insert(
+            "turn".to_string(),
+            SubmissionState::Prompt(PromptState::new(
+                thread.clone(),
+                response_tx,
+                "turn".to_string(),
+            )),
+        );
+        actor
+            .handle_event(Event {
+                id: "turn".to_string(),
+                msg: EventMsg::ElicitationRequest(ElicitationRequestEvent {
+                    server_name: "github".to_string(),
+                    id: RequestId::Integer(7),
+                    message: "Pick a scope".to_string(),
+                    requested_schema: Some(json!({
+                        "type": "object",
+                        "properties": {
+                            "scope": { "type": "string", "enum": ["read", "write"] },
+                            "days": { "type": "integer", "minimum": 1 }
+                        },
+                        "required": ["scope"]
+                    })),
+                }),
+            })
+            .await;
+
+        // The client has no form UI, so the prompt ends with the form as a message.
+        assert_eq!(response_rx.await??, StopReason::EndTurn);
+        assert!(
+            client
+                .notifications
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|n| matches!(
+                    &n.update,
+                    SessionUpdate::AgentMessageChunk(ContentChunk {
+                        content: ContentBlock::Text(text),
+                        ..
+                    }) if text.text.contains("- `scope`: one of `read`, `write`, required")
+                ))
+        );
+
+        let retry_rx = actor
+            .handle_prompt(PromptRequest::new(
+                session_id.clone(),
+                vec!["days=0".into()],
+            ))
+            .await?;
+        assert_eq!(retry_rx.await??, StopReason::EndTurn);
+        assert!(
+            thread.ops.lock().unwrap().is_empty(),
+            "invalid answers must not resolve the request"
+        );
+
+        let mut answer_rx = actor
+            .handle_prompt(PromptRequest::new(
+                session_id,
+                vec!["scope=write\ndays=3".into()],
+            ))
+            .await?;
+        {
+            let ops = thread.ops.lock().unwrap();
+            assert!(
+                matches!(
+                    ops.as_slice(),
+                    [Op::ResolveElicitation {
+                        decision: ElicitationAction::Accept,
+                        content: Some(content),
+                        ..
+                    }] if *content == json!({"scope": "write", "days": 3})
+                ),
+                "expected only the resolved elicitation, without preemption. ops={ops:?}"
+            );
+        }
+        // The answer prompt now follows the resumed turn.
+        assert!(answer_rx.try_recv().is_err());
+        actor
+            .handle_event(Event {
+                id: "turn".to_string(),
+                msg: EventMsg::TurnComplete(TurnCompleteEvent {
+                    last_agent_message: None,
+                }),
+            })
+            .await;
+        assert_eq!(answer_rx.await??, StopReason::EndTurn);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_approval_rules_answer_requests_without_the_client() -> anyhow::Result<()> {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let root = std::env::temp_dir().join(format!("acp-approval-rules-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(
+            root.join("approvals.toml"),
+            r#"
+[[rule]]
+name = "notes"
+decision = "allow"
+patch_paths = ["*.md"]
+
+[[rule]]
+name = "no rm"
+decision = "deny"
+command_prefix = "rm"
+"#,
+        )?;
+        let _home_restore = EnvVarRestore::set("ACP_HOME", root.to_str());
+
+        let session_id = SessionId::new("approval-rules-test");
+        let client = Arc::new(StubClient::new());
+        let thread = Arc::new(StubCodexThread::new());
+        let session_client =
+            SessionClient::with_client(session_id, client.clone(), Arc::default(), None);
+        let config = Config::load_with_cli_overrides_and_harness_overrides(
+            vec![],
+            ConfigOverrides::default(),
+        )
+        .await?;
+        // Patches are only decided for files inside the session's repository.
+        let notes = crate::project_settings::repo_root(&config.cwd).join("notes.md");
+        let (_message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut actor = ThreadActor::new(
+            StubAuth,
+            session_client,
+            thread.clone(),
+            Arc::new(StubModelsManager),
+            config,
+            message_rx,
+        );
+        let (response_tx, _response_rx) = tokio::sync::oneshot::channel();
+        actor.submissions.insert(
+            "turn".to_string(),
+            SubmissionState::Prompt(PromptState::new(
+                thread.clone(),
+                response_tx,
+                "turn".to_string(),
+            )),
+        );
+
+        let changes = HashMap::from([(
+            notes,
+            FileChange::Add {
+                content: "hello\n".to_string(),
+            },
+        )]);
+        actor
+            .handle_event(Event {
+                id: "turn".to_string(),
+                msg: EventMsg::ApplyPatchApprovalRequest(ApplyPatchApprovalRequestEvent {
+                    call_id: "patch-1".to_string(),
+                    turn_id: "turn".to_string(),
+                    changes: changes.clone(),
+                    reason: None,
+                    grant_root: None,
+                }),
+            })
+            .await;
+        actor
+            .handle_event(Event {
+                id: "turn".to_string(),
+                msg: EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+                    call_id: "patch-1".to_string(),
+                    turn_id: "turn".to_string(),
+                    auto_approved: false,
+                    changes,
+                }),
+            })
+            .await;
+
+        let command = vec![
+            "bash".to_string(),
+            "-lc".to_string(),
+            "rm -rf build".to_string(),
+        ];
+        let parsed_cmd = vec![ParsedCommand::Unknown {
+            cmd: "rm -rf build".to_string(),
+        }];
+        thread.pending_exec.lock().unwrap().insert(
+            "turn".to_string(),
+            PendingExec {
+                call_id: "exec-1".to_string(),
+                command: command.clone(),
+                cwd: PathBuf::from("/tmp/repo"),
+                parsed_cmd: parsed_cmd.clone(),
+            },
+        );
+        actor
+            .handle_event(Event {
+                id: "turn".to_string(),
+                msg: EventMsg::ExecApprovalRequest(ExecApprovalRequestEvent {
+                    call_id: "exec-1".to_string(),
+                    turn_id: "turn".to_string(),
+                    command,
+                    cwd: PathBuf::from("/tmp/repo"),
+                    reason: None,
+                    proposed_execpolicy_amendment: None,
+                    parsed_cmd,
+                }),
+            })
+            .await;
+
+        {
+            let ops = thread.ops.lock().unwrap();
+            assert!(
+                matches!(
+                    ops.as_slice(),
+                    [
+                        Op::PatchApproval {
+                            decision: ReviewDecision::Approved,
+                            ..
+                        },
+                        Op::ExecApproval {
+                            decision: ReviewDecision::Denied,
+                            ..
+                        },
+                    ]
+                ),
+                "ops={ops:?}"
+            );
+        }
+        let tool_call_text = |call_id: &str| {
+            client
+                .notifications
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|n| match &n.update {
+                    SessionUpdate::ToolCall(tool_call)
+                        if tool_call.tool_call_id.0.as_ref() == call_id =>
+                    {
+                        Some(format!("{:?} {:?}", tool_call.status, tool_call.content))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert!(tool_call_text("patch-1").contains("Auto-approved by rule `notes`"));
+        let exec = tool_call_text("exec-1");
+        assert!(
+            exec.contains("Failed") && exec.contains("Denied by rule `no rm`"),
+            "{exec}"
+        );
+
+        drop(std::fs::remove_dir_all(&root));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_monitoring_auto_mode_shows_task_queue_with_active_task() -> anyhow::Result<()> {
         let session_id = SessionId::new("test-monitor-auto-active");
@@ -8350,16 +10041,23 @@ mod tests {
     async fn test_monitor_retrospective_command() -> anyhow::Result<()> {
         let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
         let (prompt_response_tx, prompt_response_rx) = tokio::sync::oneshot::channel();
+        let (korean_response_tx, korean_response_rx) = tokio::sync::oneshot::channel();
 
         message_tx.send(ThreadMessage::Prompt {
-            request: PromptRequest::new(session_id.clone(), vec!["/monitor retro".into()]),
+            request: PromptRequest::new(session_id.clone(), vec!["/monitor retro en".into()]),
             response_tx: prompt_response_tx,
         })?;
+        message_tx.send(ThreadMessage::Prompt {
+            request: PromptRequest::new(session_id.clone(), vec!["/monitor retro ko".into()]),
+            response_tx: korean_response_tx,
+        })?;
 
         tokio::try_join!(
             async {
                 let stop_reason = prompt_response_rx.await??.await??;
                 assert_eq!(stop_reason, StopReason::EndTurn);
+                let stop_reason = korean_response_rx.await??.await??;
+                assert_eq!(stop_reason, StopReason::EndTurn);
                 drop(message_tx);
                 anyhow::Ok(())
             },
@@ -8383,14 +10081,20 @@ mod tests {
         assert!(
             text_chunks
                 .iter()
-                .any(|text| text.contains("회고형 상태 보고서 (2026-02-14)")),
-            "monitor retrospective output should include report header. notifications={notifications:?}"
+                .any(|text| text.contains("Session retrospective")
+                    && text.contains("Plan: no plan updates received yet.")
+                    && text.contains("Next actions")),
+            "monitor retrospective output should be built from session state. notifications={notifications:?}"
         );
         assert!(
-            text_chunks.iter().any(|text| text.contains(
-                "1. brain job payload templates by type: 스펙 확정 + 예제 입력/출력 정의"
-            )),
-            "retrospective output should include item 1 title. notifications={notifications:?}"
+            text_chunks
+                .iter()
+                .any(|text| text.contains("세션 회고") && text.contains("다음 작업")),
+            "`/monitor retro ko` should render the report in Korean. notifications={notifications:?}"
+        );
+        assert!(
+            !text_chunks.iter().any(|text| text.contains("2026-02-14")),
+            "retrospective should not contain the old canned report. notifications={notifications:?}"
         );
 
         let ops = thread.ops.lock().unwrap();
@@ -8402,6 +10106,187 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_flow_vector_step_outcomes_track_plan_history() {
+        let plan = |statuses: [StepStatus; 2]| {
+            ["Write parser", "Add tests"]
+                .into_iter()
+                .zip(statuses)
+                .map(|(step, status)| PlanItemArg {
+                    step: step.to_string(),
+                    status,
+                })
+                .collect::<Vec<_>>()
+        };
+        let plan_items = |statuses: [StepStatus; 2]| {
+            plan(statuses)
+                .iter()
+                .map(|item| PlanItem {
+                    step: item.step.clone(),
+                    status: plan_step_status(&item.status),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut flow = FlowVectorState::default();
+        let now_ms = now_unix_ms();
+        flow.timeline.observe(
+            now_ms - 700_000,
+            &plan_items([StepStatus::InProgress, StepStatus::Pending]),
+        );
+        flow.timeline.observe(
+            now_ms - 660_000,
+            &plan_items([StepStatus::Completed, StepStatus::InProgress]),
+        );
+        let transitions =
+            flow.record_plan_update(None, &plan([StepStatus::Completed, StepStatus::InProgress]));
+        assert!(
+            transitions.is_empty(),
+            "an unchanged plan should not record transitions: {transitions:?}"
+        );
+
+        let outcomes = flow.step_outcomes();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].state, StepState::Completed);
+        assert_eq!(outcomes[0].elapsed, Some(Duration::from_secs(40)));
+        assert!(!outcomes[0].stalled);
+        assert_eq!(outcomes[1].state, StepState::InProgress);
+        assert!(outcomes[1].stalled, "{outcomes:?}");
+        assert_eq!(flow.plan_updates, 1);
+
+        let stall_signal = flow.render_stall_signal(1);
+        assert!(
+            stall_signal.contains("`Add tests` in progress for")
+                && stall_signal.contains("typical step 40s"),
+            "stall signal should name the step running past the typical step time. signal={stall_signal}"
+        );
+
+        let phases = flow.phase_durations();
+        assert_eq!(
+            phases.iter().map(|(phase, _)| *phase).collect::<String>(),
+            "AEVC"
+        );
+
+        let report = Retrospective {
+            steps: outcomes,
+            plan_updates: flow.plan_updates,
+            phase_time: phases,
+            repeated_action: None,
+            log: None,
+        }
+        .render(RetroLanguage::English);
+        assert!(
+            report.contains("- Stall: `Add tests` is not completing"),
+            "{report}"
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn test_plan_transitions_are_logged_and_restored_for_timeline() -> anyhow::Result<()> {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+
+        let root = std::env::temp_dir().join(format!("acp-plan-timeline-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root)?;
+        unsafe {
+            std::env::set_var("ACP_HOME", &root);
+        }
+
+        let mut idx = crate::session_store::GlobalSessionIndex::load()
+            .expect("ACP_HOME should be resolvable");
+        let global_id = idx.get_or_create("codex:test-plan-timeline").unwrap();
+        let init_store = || {
+            crate::session_store::SessionStore::init(
+                global_id.clone(),
+                "codex",
+                "acp-session-id",
+                "backend-session-id",
+                Some(Path::new("/tmp/repo")),
+            )
+            .expect("SessionStore should init")
+        };
+        let new_actor = async |store: crate::session_store::SessionStore| -> anyhow::Result<_> {
+            let session_client = SessionClient::with_client(
+                SessionId::new("test-plan-timeline"),
+                Arc::new(StubClient::new()),
+                Arc::default(),
+                Some(store),
+            );
+            let config = Config::load_with_cli_overrides_and_harness_overrides(
+                vec![],
+                ConfigOverrides::default(),
+            )
+            .await?;
+            let (_message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+            Ok(ThreadActor::new(
+                StubAuth,
+                session_client,
+                Arc::new(StubCodexThread::new()),
+                Arc::new(StubModelsManager),
+                config,
+                message_rx,
+            ))
+        };
+        let plan_update = |statuses: [StepStatus; 2]| Event {
+            id: "turn".to_string(),
+            msg: EventMsg::PlanUpdate(UpdatePlanArgs {
+                explanation: None,
+                plan: ["parse", "test"]
+                    .into_iter()
+                    .zip(statuses)
+                    .map(|(step, status)| PlanItemArg {
+                        step: step.to_string(),
+                        status,
+                    })
+                    .collect(),
+            }),
+        };
+
+        let mut actor = new_actor(init_store()).await?;
+        actor
+            .handle_event(plan_update([StepStatus::InProgress, StepStatus::Pending]))
+            .await;
+        actor
+            .handle_event(plan_update([StepStatus::Completed, StepStatus::InProgress]))
+            .await;
+
+        let timeline = actor.render_monitor_timeline();
+        assert!(
+            timeline.starts_with("```text\nPlan timeline")
+                && timeline.contains("1/2 steps completed, 4 transitions")
+                && timeline.contains("parse: in_progress -> completed"),
+            "timeline={timeline}"
+        );
+
+        let transitions = crate::session_store::SessionStore::read_canonical(&global_id)
+            .into_iter()
+            .filter(|record| record.kind == "acp.plan.step_transition")
+            .count();
+        assert_eq!(transitions, 4);
+
+        let restored = new_actor(init_store()).await?;
+        let steps = restored.flow_vector.timeline.steps();
+        assert_eq!(
+            steps
+                .iter()
+                .map(|span| (span.step.as_str(), span.status))
+                .collect::<Vec<_>>(),
+            vec![
+                ("parse", PlanStepStatus::Completed),
+                ("test", PlanStepStatus::InProgress),
+            ]
+        );
+
+        drop(std::fs::remove_dir_all(&root));
+        unsafe {
+            std::env::remove_var("ACP_HOME");
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_vector_command() -> anyhow::Result<()> {
         let (session_id, client, thread, message_tx, local_set) = setup(vec![]).await?;
@@ -9234,6 +11119,7 @@ mod tests {
         assert!(has_option(&options, "task_monitoring_enabled"));
         assert!(has_option(&options, "task_vector_check_enabled"));
         assert!(has_option(&options, "preempt_on_new_prompt"));
+        assert!(has_option(&options, "project_defaults"));
         assert!(
             options
                 .iter()
@@ -9243,6 +11129,57 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn test_saved_workspace_defaults_apply_to_new_threads() -> anyhow::Result<()> {
+        let _guard = crate::session_store::ENV_LOCK
+            .get_or_init(|| std::sync::Mutex::new(()))
+            .lock()
+            .unwrap();
+        let root =
+            std::env::temp_dir().join(format!("acp-thread-defaults-test-{}", Uuid::new_v4()));
+        let _home_restore = EnvVarRestore::set("ACP_HOME", root.to_str());
+
+        let mut actor = test_actor_for_config_options().await?;
+        actor
+            .handle_set_config_option(
+                SessionConfigId::new("context_optimization_mode"),
+                SessionConfigValueId::new("auto"),
+            )
+            .await?;
+        actor
+            .handle_set_config_option(
+                SessionConfigId::new("preempt_on_new_prompt"),
+                SessionConfigValueId::new("off"),
+            )
+            .await?;
+        actor
+            .handle_set_config_option(
+                SessionConfigId::new("project_defaults"),
+                SessionConfigValueId::new("save_workspace"),
+            )
+            .await?;
+
+        let fresh = test_actor_for_config_options().await?;
+        assert_eq!(
+            fresh.context_optimization.mode,
+            ContextOptimizationMode::Auto
+        );
+        assert!(!fresh.task_monitoring.preempt_on_new_prompt);
+
+        let err = actor
+            .handle_set_config_option(
+                SessionConfigId::new("project_defaults"),
+                SessionConfigValueId::new("everywhere"),
+            )
+            .await
+            .expect_err("unknown scope should fail");
+        assert!(format!("{err:?}").contains("Project Defaults values must be one of"));
+
+        drop(std::fs::remove_dir_all(&root));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_set_config_rejects_invalid_advanced_panel_value() -> anyhow::Result<()> {
         let mut actor = test_actor_for_config_options().await?;
@@ -11198,6 +13135,7 @@ mod tests {
                             .unwrap();
                     }
                 }
+                Op::ResolveElicitation { .. } | Op::PatchApproval { .. } => {}
                 _ => {
                     unimplemented!()
                 }
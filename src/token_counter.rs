@@ -0,0 +1,533 @@
+//! Prompt token counting for context-pressure monitoring.
+//!
+//! OpenAI models are counted with an offline byte-pair encoder that reads tiktoken vocabulary
+//! files (`o200k_base.tiktoken`, `cl100k_base.tiktoken`) from `XSFIRE_TOKENIZER_DIR` or
+//! `ACP_HOME/tokenizers`. Text is split with a hand-written equivalent of the encoding's
+//! pre-tokenizer pattern, then merged by rank, so counts match tiktoken's ordinary encoding for
+//! practically all text. Models without a known encoding, or without a vocabulary on disk, fall
+//! back to the ~4 chars/token heuristic.
+//!
+//! Image cost is derived from the dimensions in the PNG, JPEG, GIF or WebP header of the base64
+//! payload, using OpenAI's high-detail tiling.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use anyhow::{Context as _, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use tracing::{info, warn};
+
+use crate::session_store::AcpHome;
+
+/// Directory holding `*.tiktoken` vocabulary files; defaults to `ACP_HOME/tokenizers`.
+pub const TOKENIZER_DIR_ENV: &str = "XSFIRE_TOKENIZER_DIR";
+
+/// Assumed cost of an image whose dimensions cannot be read.
+pub const IMAGE_TOKENS_FALLBACK: i64 = 1024;
+
+/// Base64 characters decoded when looking for image dimensions (JPEG headers can follow a
+/// large EXIF block).
+const IMAGE_HEADER_BASE64_CHARS: usize = 256 * 1024;
+
+/// Pieces longer than this are counted in chunks to keep merging cheap.
+const MAX_PIECE_BYTES: usize = 512;
+
+/// Counts tokens of prompt text for one model family.
+pub trait TokenCounter: Send + Sync {
+    /// Tokenizer name shown in the monitor and the canonical log.
+    fn name(&self) -> &str;
+
+    fn count(&self, text: &str) -> i64;
+}
+
+/// The ~4 chars/token heuristic used when no vocabulary is available.
+pub struct HeuristicCounter;
+
+impl TokenCounter for HeuristicCounter {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn count(&self, text: &str) -> i64 {
+        ((text.chars().count() as f64) / 4.0).ceil() as i64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    O200k,
+    Cl100k,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::O200k => "o200k_base",
+            Self::Cl100k => "cl100k_base",
+        }
+    }
+
+    /// Encoding used by an OpenAI model, by slug prefix.
+    pub fn for_model(model: &str) -> Option<Self> {
+        let model = model.trim().to_ascii_lowercase();
+        let model = model.rsplit('/').next().unwrap_or_default();
+        if [
+            "gpt-5", "gpt-4.1", "gpt-4.5", "gpt-4o", "o1", "o3", "o4", "codex-", "gpt-oss",
+        ]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+        {
+            Some(Self::O200k)
+        } else if [
+            "gpt-4",
+            "gpt-3.5",
+            "text-embedding-3",
+            "text-embedding-ada-002",
+        ]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+        {
+            Some(Self::Cl100k)
+        } else {
+            None
+        }
+    }
+}
+
+/// Byte-pair encoder over a tiktoken vocabulary; only counts, never materializes token ids.
+pub struct BpeCounter {
+    encoding: Encoding,
+    ranks: HashMap<Vec<u8>, u32>,
+}
+
+impl BpeCounter {
+    /// Parse a `.tiktoken` file: one `<base64 token> <rank>` pair per line.
+    pub fn from_tiktoken(encoding: Encoding, data: &str) -> anyhow::Result<Self> {
+        let mut ranks = HashMap::new();
+        for (index, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((token, rank)) = line.split_once(' ') else {
+                bail!("line {}: expected `<token> <rank>`", index + 1);
+            };
+            let token = STANDARD
+                .decode(token)
+                .with_context(|| format!("line {}: invalid base64 token", index + 1))?;
+            let rank = rank
+                .trim()
+                .parse::<u32>()
+                .with_context(|| format!("line {}: invalid rank", index + 1))?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            bail!("vocabulary is empty");
+        }
+        Ok(Self { encoding, ranks })
+    }
+
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if piece.is_empty() {
+            return 0;
+        }
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+        if piece.len() > MAX_PIECE_BYTES {
+            return piece
+                .chunks(MAX_PIECE_BYTES)
+                .map(|chunk| self.count_piece(chunk))
+                .sum();
+        }
+
+        // Start from single bytes and repeatedly merge the adjacent pair with the lowest rank.
+        let mut bounds = (0..=piece.len()).collect::<Vec<_>>();
+        loop {
+            let best = bounds
+                .windows(3)
+                .enumerate()
+                .filter_map(|(index, window)| {
+                    let rank = self.ranks.get(&piece[window[0]..window[2]])?;
+                    Some((*rank, index))
+                })
+                .min();
+            match best {
+                Some((_, index)) => {
+                    bounds.remove(index + 1);
+                }
+                None => break,
+            }
+        }
+        bounds.len() - 1
+    }
+}
+
+impl TokenCounter for BpeCounter {
+    fn name(&self) -> &str {
+        self.encoding.name()
+    }
+
+    fn count(&self, text: &str) -> i64 {
+        split_pieces(text, self.encoding)
+            .into_iter()
+            .map(|piece| self.count_piece(piece.as_bytes()))
+            .sum::<usize>() as i64
+    }
+}
+
+/// Vocabularies loaded so far; a missing or invalid file is not cached, so it is picked up once
+/// it is installed.
+static LOADED: LazyLock<Mutex<HashMap<Encoding, Arc<BpeCounter>>>> = LazyLock::new(Mutex::default);
+
+fn tokenizer_dir() -> Option<PathBuf> {
+    match std::env::var_os(TOKENIZER_DIR_ENV) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => AcpHome::resolve().map(|home| home.join("tokenizers")),
+    }
+}
+
+fn load(encoding: Encoding) -> Option<Arc<BpeCounter>> {
+    let path = tokenizer_dir()?.join(format!("{}.tiktoken", encoding.name()));
+    let data = match std::fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Failed to read tokenizer {}: {e}", path.display());
+            return None;
+        }
+    };
+    match BpeCounter::from_tiktoken(encoding, &data) {
+        Ok(counter) => {
+            info!("Loaded tokenizer {}", path.display());
+            Some(Arc::new(counter))
+        }
+        Err(e) => {
+            warn!("Ignoring invalid tokenizer {}: {e:#}", path.display());
+            None
+        }
+    }
+}
+
+/// The counter for `model`: its BPE encoding when the vocabulary is on disk, else the heuristic.
+/// A vocabulary is loaded once per process; until one loads, every call looks for it again.
+pub fn for_model(model: &str) -> Arc<dyn TokenCounter> {
+    let Some(encoding) = Encoding::for_model(model) else {
+        return Arc::new(HeuristicCounter);
+    };
+    let counter = match LOADED.lock() {
+        Ok(mut loaded) => match loaded.get(&encoding) {
+            Some(counter) => Some(counter.clone()),
+            None => load(encoding).inspect(|counter| {
+                loaded.insert(encoding, counter.clone());
+            }),
+        },
+        Err(_) => None,
+    };
+    match counter {
+        Some(counter) => counter,
+        None => Arc::new(HeuristicCounter),
+    }
+}
+
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+fn is_number(c: char) -> bool {
+    c.is_numeric()
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+/// Letters allowed in the leading run of an o200k word (`\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}`).
+fn is_upperish(c: char) -> bool {
+    c.is_alphabetic() && !c.is_lowercase()
+}
+
+/// Letters allowed in the trailing run of an o200k word (`\p{Ll}\p{Lm}\p{Lo}\p{M}`).
+fn is_lowerish(c: char) -> bool {
+    c.is_alphabetic() && !c.is_uppercase()
+}
+
+/// Split `text` the way the encoding's pre-tokenizer regex does.
+fn split_pieces(text: &str, encoding: Encoding) -> Vec<&str> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut pieces = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let len = match_at(&chars, index, encoding).max(1);
+        let start = chars[index].0;
+        let end = chars
+            .get(index + len)
+            .map_or(text.len(), |(offset, _)| *offset);
+        pieces.push(&text[start..end]);
+        index += len;
+    }
+    pieces
+}
+
+fn run(chars: &[(usize, char)], from: usize, matches: impl Fn(char) -> bool) -> usize {
+    chars[from.min(chars.len())..]
+        .iter()
+        .take_while(|(_, c)| matches(*c))
+        .count()
+}
+
+/// Length of `(?i:'s|'t|'re|'ve|'m|'ll|'d)` at `at`, if it matches.
+fn contraction(chars: &[(usize, char)], at: usize) -> Option<usize> {
+    if chars.get(at).map(|(_, c)| *c) != Some('\'') {
+        return None;
+    }
+    let next = |offset: usize| chars.get(at + offset).map(|(_, c)| c.to_ascii_lowercase());
+    match (next(1)?, next(2)) {
+        ('r', Some('e')) | ('v', Some('e')) | ('l', Some('l')) => Some(3),
+        ('s' | 't' | 'm' | 'd', _) => Some(2),
+        _ => None,
+    }
+}
+
+/// Length in chars of the pre-tokenizer match starting at `at`.
+fn match_at(chars: &[(usize, char)], at: usize, encoding: Encoding) -> usize {
+    let c = chars[at].1;
+    let prefixed = !is_newline(c) && !is_letter(c) && !is_number(c);
+    let word_start = if prefixed { at + 1 } else { at };
+
+    match encoding {
+        Encoding::Cl100k => {
+            if let Some(len) = contraction(chars, at) {
+                return len;
+            }
+            // [^\r\n\p{L}\p{N}]?\p{L}+
+            let letters = run(chars, word_start, is_letter);
+            if letters > 0 {
+                return word_start - at + letters;
+            }
+        }
+        Encoding::O200k => {
+            // [^\r\n\p{L}\p{N}]?[upper]*[lower]+('s...)? | [^\r\n\p{L}\p{N}]?[upper]+[lower]*('s...)?
+            let upper = run(chars, word_start, is_upperish);
+            let lower = run(chars, word_start + upper, is_lowerish);
+            let word = if lower > 0 {
+                upper + lower
+            } else if upper > 0 {
+                // Either the leading run gives back a letter that is also "lower" (the first
+                // alternative), or the second alternative matches the run alone.
+                upper
+            } else {
+                0
+            };
+            if word > 0 {
+                let end = word_start + word;
+                return end - at + contraction(chars, end).unwrap_or(0);
+            }
+        }
+    }
+
+    // \p{N}{1,3}
+    let digits = run(chars, at, is_number);
+    if digits > 0 {
+        return digits.min(3);
+    }
+
+    // ' ?[^\s\p{L}\p{N}]+[\r\n]*' (o200k also absorbs trailing '/')
+    let punct_start = if c == ' ' { at + 1 } else { at };
+    let punct = run(chars, punct_start, |c| {
+        !c.is_whitespace() && !is_letter(c) && !is_number(c)
+    });
+    if punct > 0 {
+        let end = punct_start + punct;
+        let trailing = match encoding {
+            Encoding::Cl100k => run(chars, end, is_newline),
+            Encoding::O200k => run(chars, end, |c| is_newline(c) || c == '/'),
+        };
+        return end + trailing - at;
+    }
+
+    let spaces = run(chars, at, char::is_whitespace);
+    if spaces > 0 {
+        // \s*[\r\n]+ ends at the last newline of the whitespace run.
+        if let Some(last_newline) = (at..at + spaces).rev().find(|&i| is_newline(chars[i].1)) {
+            return last_newline + 1 - at;
+        }
+        // \s+(?!\S) leaves the last space to prefix the next word; \s+ otherwise.
+        if at + spaces < chars.len() && spaces > 1 {
+            return spaces - 1;
+        }
+        return spaces;
+    }
+    1
+}
+
+/// Width and height from the header of a base64 image (optionally a `data:` URL).
+pub fn image_dimensions(data: &str) -> Option<(u32, u32)> {
+    let data = data.trim();
+    let data = match data.split_once(";base64,") {
+        Some((prefix, payload)) if prefix.starts_with("data:") => payload,
+        _ => data,
+    };
+    let prefix_len = data.len().min(IMAGE_HEADER_BASE64_CHARS) / 4 * 4;
+    let bytes = STANDARD.decode(data.get(..prefix_len)?).ok()?;
+
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let le24 = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+    if bytes.starts_with(b"GIF8") {
+        return Some((u32::from(le16(6)?), u32::from(le16(8)?)));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((u32::from(le16(26)? & 0x3fff), u32::from(le16(28)? & 0x3fff))),
+            b"VP8L" => {
+                let b = bytes.get(21..25)?;
+                let bits = u32::from_le_bytes(b.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        let mut at = 2;
+        while at + 4 <= bytes.len() {
+            if bytes[at] != 0xff {
+                at += 1;
+                continue;
+            }
+            let marker = bytes[at + 1];
+            match marker {
+                0xff => at += 1,
+                0x01 | 0xd0..=0xd9 => at += 2,
+                0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                    return Some((u32::from(be16(at + 7)?), u32::from(be16(at + 5)?)));
+                }
+                _ => at += 2 + usize::from(be16(at + 2)?),
+            }
+        }
+    }
+    None
+}
+
+/// High-detail image cost: fit within 2048x2048, scale the short side to 768, then 170 tokens
+/// per 512px tile plus 85.
+pub fn image_tokens(width: u32, height: u32) -> i64 {
+    if width == 0 || height == 0 {
+        return 85;
+    }
+    let (mut width, mut height) = (f64::from(width), f64::from(height));
+    let fit = (2048.0 / width.max(height)).min(1.0);
+    width *= fit;
+    height *= fit;
+    let short_side = (768.0 / width.min(height)).min(1.0);
+    width *= short_side;
+    height *= short_side;
+    let tiles = (width / 512.0).ceil() * (height / 512.0).ceil();
+    85 + 170 * tiles as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(text: &str, encoding: Encoding) -> Vec<&str> {
+        split_pieces(text, encoding)
+    }
+
+    #[test]
+    fn test_split_pieces_follows_tiktoken_patterns() {
+        assert_eq!(
+            pieces("Hello world, it's 12345!\n\n  fn x()", Encoding::Cl100k),
+            vec![
+                "Hello", " world", ",", " it", "'s", " ", "123", "45", "!\n\n", " ", " fn", " x",
+                "()"
+            ]
+        );
+        assert_eq!(
+            pieces("parseHTTPRequest don't path/to", Encoding::O200k),
+            vec!["parse", "HTTPRequest", " don't", " path", "/to"]
+        );
+        assert_eq!(
+            pieces("안녕하세요 세계", Encoding::O200k),
+            vec!["안녕하세요", " 세계"]
+        );
+        assert_eq!(
+            pieces("a  \n\tb   ", Encoding::Cl100k),
+            vec!["a", "  \n", "\tb", "   "]
+        );
+    }
+
+    #[test]
+    fn test_bpe_counter_merges_by_rank() {
+        let vocab = ["a", "b", "c", " ", "ab", "bc", "abc", " a", " ab"]
+            .iter()
+            .enumerate()
+            .map(|(rank, token)| format!("{} {rank}", STANDARD.encode(token)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let counter = BpeCounter::from_tiktoken(Encoding::Cl100k, &vocab).unwrap();
+
+        assert_eq!(counter.count("abc"), 1);
+        // " abcab" is one piece: " ab" + "c" + "ab" beats any split through "bc".
+        assert_eq!(counter.count(" abcab"), 3);
+        assert_eq!(counter.count("abc abc"), 3);
+        assert_eq!(counter.name(), "cl100k_base");
+        assert!(BpeCounter::from_tiktoken(Encoding::Cl100k, "not-base64!! x").is_err());
+    }
+
+    #[test]
+    fn test_encoding_for_model_and_fallback() {
+        assert_eq!(Encoding::for_model("gpt-5-codex"), Some(Encoding::O200k));
+        assert_eq!(
+            Encoding::for_model("openai/gpt-4o-mini"),
+            Some(Encoding::O200k)
+        );
+        assert_eq!(Encoding::for_model("gpt-4-turbo"), Some(Encoding::Cl100k));
+        assert_eq!(Encoding::for_model("llama3.2"), None);
+        assert_eq!(for_model("llama3.2").name(), "heuristic");
+        assert_eq!(HeuristicCounter.count("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_image_dimensions_and_cost() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(1024u32.to_be_bytes());
+        png.extend(768u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        let png = STANDARD.encode(&png);
+        assert_eq!(image_dimensions(&png), Some((1024, 768)));
+        assert_eq!(
+            image_dimensions(&format!("data:image/png;base64,{png}")),
+            Some((1024, 768))
+        );
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend(320u16.to_le_bytes());
+        gif.extend(200u16.to_le_bytes());
+        assert_eq!(image_dimensions(&STANDARD.encode(&gif)), Some((320, 200)));
+
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend([0xff, 0xc0, 0x00, 0x11, 0x08]);
+        jpeg.extend(600u16.to_be_bytes());
+        jpeg.extend(800u16.to_be_bytes());
+        jpeg.extend([0x03, 0x01, 0x22, 0x00]);
+        assert_eq!(image_dimensions(&STANDARD.encode(&jpeg)), Some((800, 600)));
+
+        assert_eq!(image_dimensions("not an image"), None);
+        assert_eq!(image_tokens(1024, 1024), 765);
+        assert_eq!(image_tokens(512, 512), 255);
+        assert_eq!(image_tokens(4096, 8192), 1105);
+    }
+}